@@ -1,12 +1,85 @@
-use color_eyre::Result;
+use color_eyre::{eyre::eyre, Result};
+use devdoc::{
+    clipboard,
+    config::{Config, ConfigOrigin, EmptyQueryView, ManagerSort, OpenAction, SyncOnStartup},
+    diff,
+    docset_manager::{
+        self, doc_family, About, CachedRegistry, DownloadTooLarge, IndexDiff, Manifest, Outdated,
+        RegistryFetch, SyncFailure, SyncReport, SyncSummary,
+    },
+    external::{
+        edit_temporary, editor_command, extension_for, open_dir, open_url, pager_command, run_pager,
+    },
+    fulltext::{self, find_ignore_case, FullTextIndex},
+    history::{LastScope, RecentEntry, RecentList, SearchHistory},
+    html::{html_to_text, Document, Heading, RenderOptions, TextStats},
+    index::{load_doc_set, DocType, Index, IndexEntry},
+    keymap::{Keymap, PendingKeys, Resolution},
+    link::{looks_like_devdocs_url, parse_devdocs_url, DevdocsLink, ListFormat, ShareFormat},
+    net::{self, HttpClient},
+    notification::{Notifications, Severity},
+    output,
+    path::{canonical_url, image_url, split_fragment},
+    query::{parse_query, split_scope, CaseMode, Query},
+    search::{
+        admits_type, compare_versions, group_rows, in_scope, merge_versions, slug_hint,
+        sort_results, Fuzzy, Matcher, ResultRow, SearchResult, Searcher, SortMode,
+    },
+    stats::UsageStats,
+    storage::Storage,
+    theme::{apply_high_contrast, blink_supported, gradient_line, BuiltInTheme, ColorSupport},
+    toml,
+};
+use lru::LruCache;
 use ratatui::{
-    crossterm::event::{self, Event, KeyCode, KeyEventKind},
-    layout::{Constraint, Flex, Layout, Position, Rect},
+    crossterm::{
+        event::{
+            self, DisableMouseCapture, EnableMouseCapture, Event as TerminalEvent, KeyCode,
+            KeyEvent, KeyEventKind, KeyModifiers, MouseEventKind,
+        },
+        execute,
+        terminal::{enable_raw_mode, EnterAlternateScreen},
+    },
+    layout::{Constraint, Flex, Layout, Margin, Position, Rect},
     style::{Color, Modifier, Style, Stylize},
-    text::{Line, Text},
-    widgets::{Block, Paragraph},
+    text::{Line, Span, Text},
+    widgets::{Block, BorderType, Clear, List, ListItem, ListState, Padding, Paragraph, Wrap},
     DefaultTerminal, Frame,
 };
+use std::cmp::Reverse;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::io::Write;
+use std::num::NonZeroUsize;
+use std::ops::{ControlFlow, Range};
+use std::panic::PanicHookInfo;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+use url::Url;
+
+const USAGE: &str =
+    "usage: rtftui [--config <path>] [--watch-config] [--pager] [--max-download-mb <n>]
+              [--proxy <url>] [--json] [<command>]
+
+commands:
+    open <devdocs url>               open an entry by its devdocs.io URL
+    search <query> [--export markdown|text] [--output <file>]
+                                     list the entries matching a query, or export them as
+                                     a list of links to share
+    get <doc set>/<path>             print an entry as plain text
+    docs list                        list the installed doc sets
+    docs add <doc set>...            download doc sets from devdocs, or update them
+    docs size                        show the disk space each doc set takes up
+    verify [<doc set>...]            check doc sets for entries without content
+    docs clean                       remove what interrupted downloads left behind
+    docs index [<doc set>...]        build the full-text index of installed doc sets
+    index rebuild [<doc set>...]     rebuild the abstracts and full-text indexes of doc sets
+    docs add-local <path> --slug <name>
+                                     install a doc set from a local directory
+    paths                            show where data, doc sets and the config file are kept";
 
 const DEVDOCS_ASCII_BANNER: &str = r#"
 ______          ______               
@@ -25,197 +98,7071 @@ fn center(area: Rect, horizontal: Constraint, vertical: Constraint) -> Rect {
     area
 }
 
+/// A list of search results, highlighting the selected one
+fn result_list(results: &[SearchResult], config: &Config, colors: ColorSupport) -> List<'static> {
+    list(
+        results
+            .iter()
+            .map(|result| result_item(result, config, colors))
+            .collect(),
+        config,
+        colors,
+    )
+}
+
+fn result_item(result: &SearchResult, config: &Config, colors: ColorSupport) -> ListItem<'static> {
+    ListItem::new(result_line(result, config, colors))
+}
+
+fn result_line(result: &SearchResult, config: &Config, colors: ColorSupport) -> Line<'static> {
+    let mut spans = Vec::with_capacity(6);
+    if let Some(glyph) = config.type_glyphs.glyph(&result.entry.type_name) {
+        spans.push(format!("{glyph} ").magenta());
+    }
+    spans.push(result.entry.name.clone().bold());
+    if let Some(namespace) = result.entry.namespace().filter(|_| config.qualified_names) {
+        spans.push(format!(" {namespace}").dark_gray());
+    }
+    spans.extend([
+        Span::styled(
+            format!("  {}", result.entry.slug),
+            config.doc_set_colors.style(&result.entry.slug, colors),
+        ),
+        format!("  {}", result.entry.type_name).dark_gray(),
+    ]);
+    if !result.other_versions.is_empty() {
+        let versions = result.other_versions.len();
+        spans.push(
+            format!(
+                "  +{versions} other version{}",
+                if versions == 1 { "" } else { "s" }
+            )
+            .yellow(),
+        );
+    }
+    if config.detect_deprecated && result.entry.looks_deprecated() {
+        spans.push(DEPRECATED_BADGE.red());
+    }
+    Line::from(spans)
+}
+
+/// A full-text match's snippet, indented under its result, with the query's words
+/// highlighted
+fn snippet_line(snippet: &str, query: &Query) -> Line<'static> {
+    let mut matches: Vec<Range<usize>> = Vec::new();
+    for word in fulltext::words(&query.full_text()) {
+        let mut start = 0;
+        while let Some(found) = find_ignore_case(&snippet[start..], &word) {
+            matches.push(start + found.start..start + found.end);
+            start += found.end;
+        }
+    }
+    matches.sort_by_key(|found| found.start);
+
+    let mut spans = vec![Span::raw("    ")];
+    let mut end = 0;
+    for found in matches {
+        if found.start < end {
+            continue;
+        }
+        spans.push(snippet[end..found.start].to_owned().dark_gray());
+        spans.push(snippet[found.clone()].to_owned().yellow().bold());
+        end = found.end;
+    }
+    spans.push(snippet[end..].to_owned().dark_gray());
+    Line::from(spans)
+}
+
+const DEPRECATED_BADGE: &str = "  deprecated";
+
+const SEEN_BADGE: &str = "  seen";
+
+/// The entries opened before, going by the recently viewed list and the stats
+fn seen_entries(recent: &RecentList, stats: &UsageStats) -> HashSet<(String, String)> {
+    let recent = recent
+        .entries()
+        .iter()
+        .map(|entry| (&entry.slug, &entry.path));
+    let opened = stats.entries().map(|entry| (&entry.slug, &entry.path));
+    recent
+        .chain(opened)
+        .map(|(slug, path)| (slug.clone(), path.clone()))
+        .collect()
+}
+
+/// A list highlighting its selected row as the `[selection]` table says
+fn list(items: Vec<ListItem<'static>>, config: &Config, colors: ColorSupport) -> List<'static> {
+    let selection = &config.selection;
+    let list = List::new(items).highlight_style(selection.style(colors));
+    match selection.symbol(colors) {
+        Some(symbol) => list.highlight_symbol(symbol),
+        None => list,
+    }
+}
+
+/// Word count and estimated reading time, e.g. "1,204 words, ~7 min read"
+fn reading_time(stats: TextStats) -> String {
+    let digits = stats.words.to_string();
+    let mut words = String::with_capacity(digits.len() * 4 / 3);
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            words.push(',');
+        }
+        words.push(digit);
+    }
+    let mut summary = format!(
+        "{words} word{}, ~{} min read",
+        if stats.words == 1 { "" } else { "s" },
+        stats.reading_minutes()
+    );
+    if stats.code_lines > 0 {
+        summary.push_str(&format!(" + {} lines of code", stats.code_lines));
+    }
+    summary
+}
+
+/// The formats the open entry's address can be copied in, with their keys
+fn draw_copy_menu(frame: &mut Frame, area: Rect, entry: &OpenEntry) {
+    let keys = ['p', 'u', 'm'];
+    let mut lines: Vec<Line> = ShareFormat::ALL
+        .iter()
+        .zip(keys)
+        .map(|(format, key)| {
+            Line::from(vec![
+                format!(" {key} ").bold(),
+                format!("{:<14}", format.label()).into(),
+                format
+                    .format(&entry.slug, &entry.path, &entry.title)
+                    .dark_gray(),
+            ])
+        })
+        .collect();
+    let blocks = entry.document.code_blocks.len();
+    lines.push(Line::from(vec![
+        " c ".bold(),
+        format!("{:<14}", "Code blocks").into(),
+        format!("{blocks} in this entry").dark_gray(),
+    ]));
+
+    let width = lines.iter().map(Line::width).max().unwrap_or_default() as u16 + 3;
+    let area = center(
+        area,
+        Constraint::Length(width.min(area.width)),
+        Constraint::Length(lines.len() as u16 + 2),
+    );
+    frame.render_widget(Clear, area);
+    frame.render_widget(
+        Paragraph::new(lines).block(Block::bordered().title(" Copy ")),
+        area,
+    );
+}
+
+/// The headings of an entry, indented by level, to pick a section to show from
+fn draw_toc(
+    frame: &mut Frame,
+    area: Rect,
+    entry: &OpenEntry,
+    selected: usize,
+    config: &Config,
+    colors: ColorSupport,
+) {
+    let mut items = vec![ListItem::new("Whole entry".italic())];
+    items.extend(entry.document.headings.iter().map(|heading| {
+        let indent = "  ".repeat(usize::from(heading.level.saturating_sub(1)));
+        ListItem::new(format!("{indent}{}", heading.title))
+    }));
+    let width = items.iter().map(ListItem::width).max().unwrap_or_default() as u16 + 2;
+    let area = center(
+        area,
+        Constraint::Length(width.max(24).min(area.width)),
+        Constraint::Length((items.len() as u16 + 2).min(area.height)),
+    );
+    let list = list(items, config, colors).block(Block::bordered().title(" Sections "));
+    let mut state = ListState::default().with_selected(Some(selected));
+    frame.render_widget(Clear, area);
+    frame.render_stateful_widget(list, area, &mut state);
+}
+
+/// Where a doc set comes from and under what license, as devdocs credits it
+fn draw_about(frame: &mut Frame, area: Rect, row: &ManagerRow) {
+    let mut lines = vec![Line::from(vec![
+        row.name.clone().bold(),
+        format!("  {}", row.slug).cyan(),
+    ])];
+    if !row.version.is_empty() {
+        lines.push(Line::from(format!("Version {}", row.version).dark_gray()));
+    }
+    lines.push(Line::default());
+    if row.about.links.is_empty() {
+        lines.push(Line::from(
+            "No links to the original source are known.".dark_gray(),
+        ));
+    }
+    for (kind, url) in &row.about.links {
+        let label = match kind.as_str() {
+            "home" => "Home",
+            "code" => "Source",
+            other => other,
+        };
+        lines.push(Line::from(vec![
+            format!("{label}: ").bold(),
+            url.clone().underlined(),
+        ]));
+    }
+    lines.push(Line::default());
+    if row.about.attribution.is_empty() {
+        lines.push(Line::from(
+            "No attribution or license is known, check the original source before sharing."
+                .dark_gray(),
+        ));
+    } else {
+        let attribution = html_to_text(&row.about.attribution, &RenderOptions::default());
+        lines.extend(
+            attribution
+                .text
+                .lines
+                .into_iter()
+                .filter(|line| line.width() > 0),
+        );
+    }
+
+    let width = area.width.saturating_sub(4).min(80);
+    let height = lines.len() as u16 + 4;
+    let area = center(
+        area,
+        Constraint::Length(width),
+        Constraint::Length(height.min(area.height)),
+    );
+    let dialog = Paragraph::new(lines).wrap(Wrap { trim: true }).block(
+        Block::bordered()
+            .title(" About ")
+            .padding(Padding::horizontal(1)),
+    );
+    frame.render_widget(Clear, area);
+    frame.render_widget(dialog, area);
+}
+
+/// A dialog asking whether to go ahead with an action that loses data
+fn draw_confirmation(frame: &mut Frame, area: Rect, question: &str) {
+    let lines = vec![
+        Line::from(question.to_owned()),
+        Line::default(),
+        Line::from(vec!["y".bold(), " yes / ".into(), "n".bold(), " no".into()]),
+    ];
+    let width = lines.iter().map(Line::width).max().unwrap_or_default() as u16 + 4;
+    let area = center(
+        area,
+        Constraint::Length(width.min(area.width)),
+        Constraint::Length(lines.len() as u16 + 2),
+    );
+    let dialog = Paragraph::new(lines).wrap(Wrap { trim: true }).block(
+        Block::bordered()
+            .title(" Confirm ")
+            .padding(Padding::horizontal(1)),
+    );
+    frame.render_widget(Clear, area);
+    frame.render_widget(dialog, area);
+}
+
+/// A dialog asking whether to download a doc set, e.g. the one a link points into
+fn draw_sync_prompt(frame: &mut Frame, area: Rect, prompt: &SyncPrompt) {
+    let (state, question) = match prompt.reason {
+        SyncReason::NotInstalled => (" doc set is not installed.", "Download it now? "),
+        SyncReason::OutOfSync => (" doc set is out of sync.", "Download it again? "),
+        SyncReason::Update => (" doc set is installed.", "Update it now? "),
+    };
+    let mut lines = vec![
+        Line::from(vec![
+            "The ".into(),
+            prompt.slug.clone().bold(),
+            state.into(),
+        ]),
+        Line::default(),
+    ];
+    let downloading = prompt.task.is_some();
+    if let Some(too_large) = prompt.oversized.as_ref().filter(|_| !downloading) {
+        lines.push(Line::from(format!("{too_large}.").yellow()));
+        lines.push(Line::from(vec![
+            "Download it anyway? ".into(),
+            "y".bold(),
+            "/".into(),
+            "n".bold(),
+        ]));
+    } else {
+        lines.push(match (&prompt.error, downloading) {
+            (_, true) => Line::from("Downloading…".yellow()),
+            (Some(error), _) => Line::from(format!("Download failed: {error}").red()),
+            (None, _) if prompt.cancelled => Line::from(vec![
+                "Download cancelled. ".yellow(),
+                "Try again? ".into(),
+                "y".bold(),
+                "/".into(),
+                "n".bold(),
+            ]),
+            (None, _) => Line::from(vec![question.into(), "y".bold(), "/".into(), "n".bold()]),
+        });
+    }
+
+    let width = lines.iter().map(Line::width).max().unwrap_or_default() as u16 + 4;
+    let area = center(
+        area,
+        Constraint::Length(width.min(area.width)),
+        Constraint::Length(lines.len() as u16 + 2),
+    );
+    let title = if prompt.link.is_some() {
+        " Open link "
+    } else {
+        " Download "
+    };
+    let dialog = Paragraph::new(lines)
+        .wrap(Wrap { trim: true })
+        .block(Block::bordered().title(title));
+    frame.render_widget(Clear, area);
+    frame.render_widget(dialog, area);
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     // let _ = devdoc::sync_repo().await;
-    color_eyre::install()?;
+    install_hooks()?;
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    let json = take_flag(&mut args, "--json");
+    match run(args, json).await {
+        Err(e) if json => {
+            eprintln!("{}", output::error(&e.to_string()));
+            std::process::exit(1);
+        }
+        result => result,
+    }
+}
+
+/// Run the command named by the arguments, or the TUI if there is none
+///
+/// With `json`, commands print their output as described in [`output`].
+async fn run(mut args: Vec<String>, json: bool) -> Result<()> {
+    let storage = Storage::new().map_err(|e| eyre!("{e}"))?;
+    let open_in_pager = take_flag(&mut args, "--pager");
+    let slug = take_option(&mut args, "--slug")?;
+    let config_flag = take_option(&mut args, "--config")?;
+    let watch_config = take_flag(&mut args, "--watch-config");
+    let max_download_mb = take_option(&mut args, "--max-download-mb")?
+        .map(|megabytes| {
+            megabytes
+                .parse::<u64>()
+                .ok()
+                .filter(|&megabytes| megabytes > 0)
+                .ok_or_else(|| eyre!("--max-download-mb must be a positive number"))
+        })
+        .transpose()?;
+    let export = take_option(&mut args, "--export")?
+        .map(|name| {
+            ListFormat::from_name(&name).ok_or_else(|| eyre!("--export must be markdown or text"))
+        })
+        .transpose()?;
+    let output_file = take_option(&mut args, "--output")?.map(PathBuf::from);
+    if json && (export.is_some() || output_file.is_some()) {
+        return Err(eyre!("--json can't be combined with --export or --output"));
+    }
+    let proxy = take_option(&mut args, "--proxy")?
+        .map(|proxy| net::parse_proxy(&proxy).map_err(|e| eyre!("--proxy: {e}")))
+        .transpose()?;
+
+    let located = Config::locate(
+        config_flag.map(PathBuf::from),
+        std::env::var_os("RTFTUI_CONFIG").map(PathBuf::from),
+        Config::default_path(),
+    );
+    let mut config = match &located {
+        Some((path, origin)) => {
+            log::info!(
+                "Loading config from {} (found via {origin})",
+                path.display()
+            );
+            Config::load(path).map_err(|e| eyre!("{}: {e}", path.display()))?
+        }
+        None => {
+            log::info!("No config file found, using the defaults");
+            Config::default()
+        }
+    };
+    if max_download_mb.is_some() {
+        config.max_download_mb = max_download_mb;
+    }
+    if proxy.is_some() {
+        config.proxy = proxy.clone();
+    }
+    let storage = storage
+        .with_history_files(
+            config.recent_file.clone(),
+            config.search_history_file.clone(),
+        )
+        .with_kept_archives(config.keep_archive);
+    let http =
+        HttpClient::new(config.proxy.as_ref(), config.max_connections).map_err(|e| eyre!("{e}"))?;
+    // Settings changed in the app go to the loaded file, or start one in the config directory
+    let config_origin = located.as_ref().map(|&(_, origin)| origin);
+    let config_path = located.map(|(path, _)| path).or_else(Config::default_path);
+    let link = match args.as_slice() {
+        [] if json => return Err(eyre!("--json needs a command")),
+        [] => None,
+        [command, url] if command == "open" && json => {
+            let link = parse_devdocs_url(url).map_err(|e| eyre!("{e}"))?;
+            let entry = render_entry(&storage, &config, &link.slug, &link.entry_path())?;
+            println!("{}", entry.to_json());
+            return Ok(());
+        }
+        [command, url] if command == "open" => {
+            let link = parse_devdocs_url(url).map_err(|e| eyre!("{e}"))?;
+            if !docset_manager::installed_doc_sets(&storage).contains(&link.slug) {
+                if !confirm(&format!(
+                    "The '{}' doc set is not installed. Download it now?",
+                    link.slug
+                ))? {
+                    return Ok(());
+                }
+                println!("Downloading {}...", link.slug);
+                docset_manager::sync_doc(&storage, &http, &link.slug, config.max_download_bytes())
+                    .await
+                    .map_err(|e| eyre!("could not download {}: {e}", link.slug))?;
+                if config.full_text_index {
+                    docset_manager::index_full_text(&storage, &link.slug)
+                        .map_err(|e| eyre!("could not index {}: {e}", link.slug))?;
+                }
+            }
+            Some(link)
+        }
+        [command, query @ ..] if command == "search" && !query.is_empty() => {
+            return search_command(
+                &storage,
+                &config,
+                &query.join(" "),
+                json,
+                export,
+                output_file.as_deref(),
+            );
+        }
+        [command, address] if command == "get" => {
+            let (slug, path) = address
+                .split_once('/')
+                .filter(|(slug, path)| !slug.is_empty() && !path.is_empty())
+                .ok_or_else(|| eyre!("get needs an entry as <doc set>/<path>"))?;
+            let entry = render_entry(&storage, &config, slug, path)?;
+            if json {
+                println!("{}", entry.to_json());
+            } else {
+                print!("{}", entry.text);
+            }
+            return Ok(());
+        }
+        [command, subcommand] if command == "docs" && subcommand == "list" => {
+            return list_doc_sets(&storage, json);
+        }
+        [command, subcommand] if command == "docs" && subcommand == "size" => {
+            return doc_set_sizes(&storage, json);
+        }
+        [command, subcommand] if command == "docs" && subcommand == "clean" => {
+            return clean(&storage, &config, json);
+        }
+        [command, subcommand, dir] if command == "docs" && subcommand == "add-local" => {
+            let slug = slug.ok_or_else(|| eyre!("add-local needs a --slug for the doc set"))?;
+            let manifest = docset_manager::add_local(&storage, Path::new(dir), &slug)
+                .map_err(|e| eyre!("could not add {dir}: {e}"))?;
+            if config.full_text_index {
+                docset_manager::index_full_text(&storage, &manifest.slug)
+                    .map_err(|e| eyre!("could not index {}: {e}", manifest.slug))?;
+            }
+            let entries = load_doc_set(&storage, &manifest.slug)
+                .map_err(|e| eyre!("{e}"))?
+                .len();
+            if json {
+                println!("{}", output::added_local(&manifest.slug, entries));
+            } else {
+                println!("Added {} with {entries} entries", manifest.slug);
+            }
+            return Ok(());
+        }
+        [command, subcommand, slugs @ ..]
+            if command == "docs" && subcommand == "add" && !slugs.is_empty() =>
+        {
+            return add_doc_sets(&storage, &http, &config, slugs, json).await;
+        }
+        [command, subcommand, slugs @ ..] if command == "docs" && subcommand == "index" => {
+            let slugs = if slugs.is_empty() {
+                docset_manager::installed_doc_sets(&storage)
+            } else {
+                slugs.to_vec()
+            };
+            return index_full_text(&storage, &slugs, json);
+        }
+        [command, subcommand, slugs @ ..] if command == "index" && subcommand == "rebuild" => {
+            return rebuild_index(&storage, &config, slugs, json);
+        }
+        [command] if command == "paths" => {
+            return print_paths(&storage, config_path.as_deref(), json);
+        }
+        [command, slugs @ ..] if command == "verify" => {
+            let slugs = if slugs.is_empty() {
+                docset_manager::installed_doc_sets(&storage)
+            } else {
+                slugs.to_vec()
+            };
+            return verify(&storage, &slugs, json);
+        }
+        _ => return Err(eyre!(USAGE)),
+    };
+
+    let watch = (watch_config || config.watch_config).then(|| ConfigWatch {
+        modified: config_path.as_deref().and_then(modified_time),
+        max_download_mb,
+        proxy: proxy.clone(),
+    });
+    let mut app = App::new(storage, http, config);
+    app.open_in_pager = open_in_pager;
+    app.config_path = config_path;
+    app.config_origin = config_origin;
+    app.config_watch = watch;
+    app.start_update_check();
+    app.start_index_check();
+    app.start_startup_sync();
+    if let Some(link) = link {
+        app.open_link(link);
+    }
     let terminal = ratatui::init();
-    let app_result = App::new().run(terminal);
-    ratatui::restore();
+    if app.config.mouse {
+        execute!(std::io::stdout(), EnableMouseCapture)?;
+    }
+    let app_result = app.run(terminal);
+    restore();
     app_result
 }
 
-/// App holds the state of the application
-struct App {
-    /// Current value of the input box
-    input: String,
-    /// Position of cursor in the editor area.
-    character_index: usize,
-    /// Current input mode
-    input_mode: InputMode,
+/// Give the terminal back in the state it was found in
+fn restore() {
+    let _ = execute!(std::io::stdout(), DisableMouseCapture);
+    ratatui::restore();
 }
 
-enum InputMode {
-    Normal,
-    Searching,
+/// Report what interrupted syncs left in the docs directory and remove it once confirmed
+///
+/// JSON output only lists what there is to clean up, as it can't ask for confirmation.
+fn clean(storage: &Storage, config: &Config, json: bool) -> Result<()> {
+    let leftovers = docset_manager::find_leftovers(storage)?;
+    if json {
+        println!("{}", output::leftovers(&leftovers));
+        return Ok(());
+    }
+    if leftovers.is_empty() {
+        println!("Nothing to clean up");
+        return Ok(());
+    }
+    for leftover in &leftovers {
+        println!("{leftover}");
+    }
+    if !config.confirm_destructive
+        || confirm(&format!("Remove these {} item(s)?", leftovers.len()))?
+    {
+        docset_manager::remove_leftovers(storage, &leftovers)?;
+        println!("Removed {} item(s)", leftovers.len());
+    }
+    Ok(())
 }
 
-impl App {
-    const fn new() -> Self {
-        Self {
-            input: String::new(),
-            input_mode: InputMode::Searching,
-            character_index: 0,
+/// Report index entries missing from the content of each doc set
+fn verify(storage: &Storage, slugs: &[String], json: bool) -> Result<()> {
+    let mut reports = Vec::with_capacity(slugs.len());
+    for slug in slugs {
+        let report = docset_manager::verify(storage, slug).map_err(|e| eyre!("{slug}: {e}"))?;
+        if !json {
+            println!(
+                "{slug}: {} entries, {} missing from db.json",
+                report.entries,
+                report.missing.len()
+            );
+            for path in &report.missing {
+                println!("  {path}");
+            }
         }
+        reports.push((slug.clone(), report));
     }
+    if json {
+        println!("{}", output::verify_reports(&reports));
+    }
+    let mismatched = reports.iter().filter(|(_, report)| !report.is_ok()).count();
+    if mismatched > 0 {
+        return Err(eyre!(
+            "{mismatched} doc set(s) have entries without content, re-sync them to fix this"
+        ));
+    }
+    Ok(())
+}
 
-    fn move_cursor_left(&mut self) {
-        let cursor_moved_left = self.character_index.saturating_sub(1);
-        self.character_index = self.clamp_cursor(cursor_moved_left);
+/// Download doc sets from devdocs, replacing any installed versions
+async fn add_doc_sets(
+    storage: &Storage,
+    http: &HttpClient,
+    config: &Config,
+    slugs: &[String],
+    json: bool,
+) -> Result<()> {
+    if !json {
+        for slug in slugs {
+            println!("Downloading {slug}...");
+        }
+    }
+    let mut added = Vec::with_capacity(slugs.len());
+    let mut failed = Vec::new();
+    let add = |slug: &str, result: &Result<_, SyncFailure>| -> Result<usize> {
+        if let Err(e) = result {
+            return Err(eyre!("could not download {slug}: {e}"));
+        }
+        if config.full_text_index {
+            docset_manager::index_full_text(storage, slug)
+                .map_err(|e| eyre!("could not index {slug}: {e}"))?;
+        }
+        Ok(load_doc_set(storage, slug).map_err(|e| eyre!("{e}"))?.len())
+    };
+    let summary = docset_manager::sync_many(
+        storage,
+        http,
+        slugs,
+        config.max_download_bytes(),
+        config.max_connections,
+        |report| match add(&report.slug, &report.result) {
+            Ok(entries) => {
+                if !json {
+                    println!("Added {} with {entries} entries", report.slug);
+                }
+                added.push((report.slug.clone(), entries));
+            }
+            Err(e) => failed.push(e.to_string()),
+        },
+    )
+    .await;
+    if !json && slugs.len() > 1 {
+        println!("{}", summary.summary());
     }
+    // Listed in the order asked for, rather than the order the downloads finished in
+    added.sort_by_key(|(slug, _)| slugs.iter().position(|s| s == slug));
+    if json {
+        println!("{}", output::added(&added));
+    }
+    match failed.as_slice() {
+        [] => Ok(()),
+        failed => Err(eyre!("{}", failed.join("\n"))),
+    }
+}
 
-    fn move_cursor_right(&mut self) {
-        let cursor_moved_right = self.character_index.saturating_add(1);
-        self.character_index = self.clamp_cursor(cursor_moved_right);
+/// Build the full-text index of doc sets, e.g. ones installed before the config asked for it
+fn index_full_text(storage: &Storage, slugs: &[String], json: bool) -> Result<()> {
+    let mut indexed = Vec::with_capacity(slugs.len());
+    for slug in slugs {
+        let entries =
+            docset_manager::index_full_text(storage, slug).map_err(|e| eyre!("{slug}: {e}"))?;
+        if !json {
+            println!("{slug}: indexed {entries} entries");
+        }
+        indexed.push((slug.clone(), entries));
+    }
+    if json {
+        println!("{}", output::full_text_indexed(&indexed));
     }
+    Ok(())
+}
 
-    fn enter_char(&mut self, new_char: char) {
-        let index = self.byte_index();
-        self.input.insert(index, new_char);
-        self.move_cursor_right();
+/// Rebuild the index of doc sets from their files, of every installed one and dropping
+/// removed ones from it if none are given
+fn rebuild_index(storage: &Storage, config: &Config, slugs: &[String], json: bool) -> Result<()> {
+    let slugs = if slugs.is_empty() {
+        let removed = docset_manager::check_index(storage)
+            .map_err(|e| eyre!("{e}"))?
+            .removed;
+        docset_manager::forget_indexed(storage, &removed).map_err(|e| eyre!("{e}"))?;
+        docset_manager::installed_doc_sets(storage)
+    } else {
+        slugs.to_vec()
+    };
+    let mut rebuilt = Vec::with_capacity(slugs.len());
+    let mut failed = Vec::new();
+    for slug in slugs {
+        match docset_manager::rebuild_index(storage, &slug, config.full_text_index) {
+            Ok(()) => {
+                if !json {
+                    println!("{slug}: rebuilt");
+                }
+                rebuilt.push(slug);
+            }
+            Err(e) => failed.push(format!("{slug}: {e}")),
+        }
     }
+    if json {
+        println!("{}", output::index_rebuilt(&rebuilt));
+    }
+    match failed.as_slice() {
+        [] => Ok(()),
+        failed => Err(eyre!("{}", failed.join("\n"))),
+    }
+}
 
-    /// Returns the byte index based on the character position.
-    ///
-    /// Since each character in a string can be contain multiple bytes, it's necessary to calculate
-    /// the byte index based on the index of the character.
-    fn byte_index(&self) -> usize {
-        self.input
-            .char_indices()
-            .map(|(i, _)| i)
-            .nth(self.character_index)
-            .unwrap_or(self.input.len())
+/// List the entries matching a query, scoped like searches in the TUI
+///
+/// With `export`, the results are written as a list to share instead, to `output` if given.
+fn search_command(
+    storage: &Storage,
+    config: &Config,
+    query: &str,
+    json: bool,
+    export: Option<ListFormat>,
+    output: Option<&Path>,
+) -> Result<()> {
+    let installed = docset_manager::installed_doc_sets(storage);
+    let parsed = Query {
+        case: config.search_case,
+        limit: Some(config.max_results),
+        ..parse_query(query)
+    };
+    if let Some(error) = parsed.errors.first() {
+        return Err(eyre!("{error}"));
     }
+    let scope = match parsed.resolve_scopes(&installed) {
+        Ok(scope) if scope.is_empty() => config.default_scope.clone(),
+        Ok(scope) => scope,
+        Err(scope) => return Err(eyre!("unknown doc set '{scope}'")),
+    };
+    let mut results = config
+        .matcher
+        .searcher()
+        .search_in(&parsed, &Index::load(storage), &scope);
+    sort_results(&mut results, config.sort_mode, &config.doc_set_order);
 
-    fn delete_char(&mut self) {
-        let is_not_cursor_leftmost = self.character_index != 0;
-        if is_not_cursor_leftmost {
-            // Method "remove" is not used on the saved text for deleting the selected char.
-            // Reason: Using remove on String works on bytes instead of the chars.
-            // Using remove would require special care because of char boundaries.
+    if let Some(format) = export.or(output.map(|_| ListFormat::Markdown)) {
+        let list = format.format(query, &scope, &results);
+        return match output {
+            Some(path) => std::fs::write(path, list)
+                .map_err(|e| eyre!("could not write {}: {e}", path.display())),
+            None => {
+                print!("{list}");
+                Ok(())
+            }
+        };
+    }
+    if json {
+        println!("{}", output::search_results(query, &results));
+        return Ok(());
+    }
+    for result in &results {
+        let entry = &result.entry;
+        println!(
+            "{}  {}/{}  {}",
+            entry.name, entry.slug, entry.path, entry.type_name
+        );
+    }
+    Ok(())
+}
 
-            let current_index = self.character_index;
-            let from_left_to_current_index = current_index - 1;
+/// Render an installed entry as plain text
+fn render_entry(
+    storage: &Storage,
+    config: &Config,
+    slug: &str,
+    path: &str,
+) -> Result<output::EntryOutput> {
+    if !docset_manager::installed_doc_sets(storage)
+        .iter()
+        .any(|s| s == slug)
+    {
+        return Err(eyre!("the '{slug}' doc set is not installed"));
+    }
+    let html = docset_manager::read_entry(storage, slug, path)
+        .map_err(|e| eyre!("could not read {slug}/{path}: {e}"))?
+        .ok_or_else(|| eyre!("{slug} has no entry '{path}'"))?;
+    let document = html_to_text(&html, &config.render_options());
+    Ok(output::EntryOutput {
+        slug: slug.to_owned(),
+        path: path.to_owned(),
+        anchor_line: split_fragment(path)
+            .1
+            .and_then(|fragment| document.anchors.get(fragment).copied()),
+        text: document.plain_text(),
+    })
+}
 
-            // Getting all characters before the selected character.
-            let before_char_to_delete = self.input.chars().take(from_left_to_current_index);
-            // Getting all characters after selected character.
-            let after_char_to_delete = self.input.chars().skip(current_index);
+/// Where rtftui keeps its data, doc sets and config file, whether or not they exist yet
+fn file_paths(storage: &Storage, config_path: Option<&Path>) -> Vec<(&'static str, PathBuf)> {
+    let mut paths = vec![
+        ("data", storage.root().to_owned()),
+        ("docs", storage.docs_dir()),
+    ];
+    if let Some(path) = config_path {
+        paths.push(("config", path.to_owned()));
+    }
+    paths
+}
 
-            // Put all characters together except the selected one.
-            // By leaving the selected one out, it is forgotten and therefore deleted.
-            self.input = before_char_to_delete.chain(after_char_to_delete).collect();
-            self.move_cursor_left();
-        }
+/// Print where rtftui keeps its files, noting the ones that don't exist yet
+fn print_paths(storage: &Storage, config_path: Option<&Path>, json: bool) -> Result<()> {
+    let paths = file_paths(storage, config_path);
+    if json {
+        println!("{}", output::paths(&paths));
+        return Ok(());
+    }
+    for (name, path) in &paths {
+        let missing = if path.exists() {
+            ""
+        } else {
+            " (does not exist yet)"
+        };
+        println!("{name:<8}{}{missing}", path.display());
     }
+    Ok(())
+}
 
-    fn clamp_cursor(&self, new_cursor_pos: usize) -> usize {
-        new_cursor_pos.clamp(0, self.input.chars().count())
+fn list_doc_sets(storage: &Storage, json: bool) -> Result<()> {
+    let mut doc_sets = Vec::new();
+    for slug in docset_manager::installed_doc_sets(storage) {
+        let entries = load_doc_set(storage, &slug)
+            .map_err(|e| eyre!("{slug}: {e}"))?
+            .len();
+        let manifest = docset_manager::read_manifest(storage, &slug);
+        doc_sets.push((slug, manifest, entries));
     }
 
-    fn reset_cursor(&mut self) {
-        self.character_index = 0;
+    if json {
+        println!("{}", output::doc_set_list(&doc_sets));
+        return Ok(());
     }
+    for (slug, manifest, entries) in &doc_sets {
+        let mut line = slug.clone();
+        if let Some(version) = manifest
+            .as_ref()
+            .map(Manifest::display_version)
+            .filter(|version| !version.is_empty())
+        {
+            line.push_str(&format!("  {version}"));
+        }
+        line.push_str(&format!("  {entries} entries"));
+        if manifest.as_ref().is_some_and(|m| m.local) {
+            line.push_str("  (local)");
+        }
+        println!("{line}");
+    }
+    Ok(())
+}
 
-    fn submit_message(&mut self) {
-        self.input.clear();
-        self.reset_cursor();
+fn doc_set_sizes(storage: &Storage, json: bool) -> Result<()> {
+    let mut sizes = Vec::new();
+    for slug in docset_manager::installed_doc_sets(storage) {
+        let size =
+            docset_manager::doc_set_size(storage, &slug).map_err(|e| eyre!("{slug}: {e}"))?;
+        sizes.push((slug, size));
     }
 
-    fn run(mut self, mut terminal: DefaultTerminal) -> Result<()> {
-        loop {
-            terminal.draw(|frame| self.draw(frame))?;
+    if json {
+        println!("{}", output::doc_set_sizes(&sizes));
+        return Ok(());
+    }
+    let megabytes = |bytes: u64| bytes as f64 / 1_000_000.0;
+    for (slug, bytes) in &sizes {
+        println!("{slug}  {:.1} MB", megabytes(*bytes));
+    }
+    let total: u64 = sizes.iter().map(|(_, bytes)| bytes).sum();
+    println!("total  {:.1} MB", megabytes(total));
+    Ok(())
+}
 
-            if let Event::Key(key) = event::read()? {
-                match self.input_mode {
-                    InputMode::Normal => match key.code {
-                        KeyCode::Char('i') | KeyCode::Char('/') => {
-                            self.input_mode = InputMode::Searching;
-                        }
-                        KeyCode::Char('q') => {
-                            return Ok(());
-                        }
-                        _ => {}
-                    },
-                    InputMode::Searching if key.kind == KeyEventKind::Press => match key.code {
-                        KeyCode::Enter => self.submit_message(),
-                        KeyCode::Char(to_insert) => self.enter_char(to_insert),
-                        KeyCode::Backspace => self.delete_char(),
-                        KeyCode::Left => self.move_cursor_left(),
-                        KeyCode::Right => self.move_cursor_right(),
-                        KeyCode::Esc => self.input_mode = InputMode::Normal,
-                        _ => {}
-                    },
-                    InputMode::Searching => {}
+/// Make sure the terminal is restored however the app ends
+///
+/// The panic hook restores the terminal before color_eyre prints the report, so the report
+/// isn't swallowed by the alternate screen. SIGINT and SIGTERM restore it before exiting.
+fn install_hooks() -> Result<()> {
+    let (panic_hook, eyre_hook) = color_eyre::config::HookBuilder::default().into_hooks();
+    eyre_hook.install()?;
+    std::panic::set_hook(restore_first(restore, move |info| {
+        eprintln!("{}", panic_hook.panic_report(info));
+    }));
+
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        for (kind, code) in [
+            (SignalKind::interrupt(), 130),
+            (SignalKind::terminate(), 143),
+        ] {
+            let mut signals = signal(kind)?;
+            tokio::spawn(async move {
+                if signals.recv().await.is_some() {
+                    restore();
+                    std::process::exit(code);
                 }
-            }
+            });
         }
     }
 
-    fn draw(&self, frame: &mut Frame) {
-        let vertical = Layout::vertical([
-            Constraint::Min(12),
-            Constraint::Length(3),
-            Constraint::Length(1),
-        ]);
-        let [content_area, input_area, help_area] = vertical.areas(frame.area());
+    Ok(())
+}
 
-        let (msg, style) = match self.input_mode {
-            InputMode::Normal => (
-                vec![
-                    "q".bold(),
-                    " exit | ".into(),
-                    "i".bold(),
-                    " or ".into(),
-                    "/".bold(),
-                    " enter search".into(),
-                ],
-                Style::default().add_modifier(Modifier::RAPID_BLINK),
-            ),
-            InputMode::Searching => (
-                vec![
-                    "Return".bold(),
-                    " run search | ".into(),
-                    "Esc".bold(),
-                    " leave search mode".into(),
-                ],
-                Style::default(),
-            ),
-        };
-        let text = Text::from(Line::from(msg)).patch_style(style);
-        let help_message = Paragraph::new(text);
-        frame.render_widget(help_message, help_area);
+/// A panic hook calling `restore` to restore the terminal, and only then `report`
+fn restore_first(
+    restore: impl Fn() + Send + Sync + 'static,
+    report: impl Fn(&PanicHookInfo) + Send + Sync + 'static,
+) -> Box<dyn Fn(&PanicHookInfo) + Send + Sync> {
+    Box::new(move |info| {
+        restore();
+        report(info);
+    })
+}
 
-        let input = Paragraph::new(self.input.as_str())
-            .style(match self.input_mode {
-                InputMode::Normal => Style::default(),
-                InputMode::Searching => Style::default().fg(Color::Yellow),
-            })
-            .block(Block::bordered().title("Search"));
-        frame.render_widget(input, input_area);
-        match self.input_mode {
-            // Hide the cursor. `Frame` does this by default, so we don't need to do anything here
-            InputMode::Normal => {}
+fn read_manifests(storage: &Storage, installed: &[String]) -> HashMap<String, Manifest> {
+    installed
+        .iter()
+        .filter_map(|slug| Some((slug.clone(), docset_manager::read_manifest(storage, slug)?)))
+        .collect()
+}
 
-            // Make the cursor visible and ask ratatui to put it at the specified coordinates after
-            #[allow(clippy::cast_possible_truncation)]
-            InputMode::Searching => frame.set_cursor_position(Position::new(
-                // Draw the cursor at the current position in the input field.
-                // This position is can be controlled via the left and right arrow key
-                input_area.x + self.character_index as u16 + 1,
-                // Move one line down, from the border to the input line
-                input_area.y + 1,
-            )),
-        }
+/// Remove a flag from the command line arguments, returning whether it was given
+fn take_flag(args: &mut Vec<String>, flag: &str) -> bool {
+    let given = args.iter().any(|arg| arg == flag);
+    args.retain(|arg| arg != flag);
+    given
+}
 
-        //  TODO: Bordered on first search, otherwise unbordered
+/// Remove an option and its value from the command line arguments, returning the value
+fn take_option(args: &mut Vec<String>, option: &str) -> Result<Option<String>> {
+    let Some(i) = args.iter().position(|arg| arg == option) else {
+        return Ok(None);
+    };
+    if i + 1 == args.len() {
+        return Err(eyre!("{option} needs a value"));
+    }
+    let value = args.remove(i + 1);
+    args.remove(i);
+    Ok(Some(value))
+}
 
-        let text = Text::raw(DEVDOCS_ASCII_BANNER);
-        let centered_content_area = center(
-            content_area,
-            Constraint::Length(text.width() as u16),
-            Constraint::Length(text.height() as u16),
-        );
-        frame.render_widget(text, centered_content_area);
+/// Ask a yes/no question on the terminal, defaulting to no
+fn confirm(question: &str) -> Result<bool> {
+    print!("{question} [y/N] ");
+    std::io::stdout().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
+/// Something the event loop reacts to
+enum Event {
+    Key(KeyEvent),
+    /// The mouse wheel turned by some steps, negative going up
+    Scroll(i32),
+    /// The terminal was resized and needs to be redrawn
+    Resize,
+    /// Emitted every `tick_rate` while no other event arrives
+    Tick,
+    /// Emitted between ticks while a search runs in the background, to pick up its results
+    /// without waiting for the next tick
+    SearchPoll,
+}
+
+/// App holds the state of the application
+struct App {
+    /// Current value of the input box
+    input: String,
+    /// Position of cursor in the editor area.
+    character_index: usize,
+    /// Current input mode
+    input_mode: InputMode,
+    /// Where doc sets and user state live on disk
+    storage: Storage,
+    /// Client doc sets and the registry are downloaded with
+    http: HttpClient,
+    /// Entries opened in the viewer, most recent first
+    recent: RecentList,
+    /// How often entries were opened and searches run, for the stats screen
+    stats: UsageStats,
+    /// Slugs and paths of the entries opened before, as the recently viewed list and the
+    /// stats remember them
+    seen: HashSet<(String, String)>,
+    /// Entry currently shown in the viewer
+    entry: Option<OpenEntry>,
+    /// Number of lines the viewer is scrolled down by
+    scroll: u16,
+    /// Number of columns the viewer is scrolled right by, while lines aren't wrapped
+    hscroll: u16,
+    config: Config,
+    /// Slugs of the installed doc sets
+    installed: Vec<String>,
+    /// Manifests of the installed doc sets that have one, by slug
+    manifests: HashMap<String, Manifest>,
+    /// Entries of every installed doc set, shared with searches running in the background
+    index: Arc<Index>,
+    /// Matches for the current input, best first
+    results: Vec<SearchResult>,
+    /// Position of the highlighted result
+    selected: usize,
+    /// Whether the default scope is ignored for the current query
+    scope_cleared: bool,
+    /// Entry that could not be found, with likely replacements
+    missing: Option<MissingEntry>,
+    /// Colors the terminal can display
+    color_support: ColorSupport,
+    /// Whether the terminal can blink text, see `blink_supported`
+    blink_supported: bool,
+    /// Number of ticks since the app started, the frame counter for animations
+    ticks: u64,
+    /// Offer to download the doc set of a link that isn't installed
+    sync_prompt: Option<SyncPrompt>,
+    /// Why the devdocs URL in the search box could not be opened
+    url_error: Option<String>,
+    /// Open entries in the pager whatever the config says, as `--pager` asks
+    open_in_pager: bool,
+    /// Show the open entry in the pager once the event loop gets to it
+    page_requested: bool,
+    /// Open the code block in view in an editor before the next frame
+    edit_requested: bool,
+    /// Outcomes of recent actions, shown over the UI for a while
+    notifications: Notifications,
+    /// Action waiting to be confirmed, in a dialog that takes every key until answered
+    confirmation: Option<Destructive>,
+    /// Where settings changed from within the app are saved
+    config_path: Option<PathBuf>,
+    /// How the config file was found, `None` if there was none and the defaults apply
+    config_origin: Option<ConfigOrigin>,
+    /// Show the debug overlay of where the config and data come from
+    debug_open: bool,
+    /// Reloading the config when it changes, if asked to
+    config_watch: Option<ConfigWatch>,
+    /// Recently rendered entries by slug and path, so reopening them needs no parsing
+    documents: LruCache<(String, String), Document>,
+    /// Sorted results of recent searches, cleared whenever the index or doc set order changes
+    searches: LruCache<SearchKey, Vec<SearchResult>>,
+    /// The search for the latest query, while it runs in the background
+    search_task: Option<SearchTask>,
+    /// When to search for what was typed, while typing hasn't paused for
+    /// `search_debounce` yet
+    search_due: Option<Instant>,
+    /// Bumped whenever a search starts or is cancelled, so a search running in the
+    /// background can tell it was superseded
+    search_generation: Arc<AtomicU64>,
+    /// Searching the content of entries rather than their names
+    full_text: bool,
+    /// Full-text indexes of the doc sets searched by content so far, `None` for those that
+    /// have none
+    full_text_indexes: HashMap<String, Option<FullTextIndex>>,
+    /// Doc sets the last full-text search couldn't search, for lack of an index
+    unindexed: usize,
+    /// The results as listed, which can leave some out when they are grouped by doc set
+    rows: Vec<ResultRow>,
+    /// Doc sets whose group of results is shown in full
+    expanded_groups: HashSet<String>,
+    scroll_animation: Option<ScrollAnimation>,
+    /// The registry as last fetched, loaded when the management view opens
+    registry: Option<CachedRegistry>,
+    manager: Option<Manager>,
+    browser: Option<Browser>,
+    /// The selected result shown next to the results
+    preview: Option<Preview>,
+    comparison: Option<Comparison>,
+    /// The selected result's content in a popup, while it is open
+    quick_look: Option<QuickLook>,
+    /// The preview or the outline, if one of them has focus rather than the panel it is next
+    /// to
+    side_focus: Option<Panel>,
+    keymaps: Keymaps,
+    /// Row of the table of contents picked, where 0 is the whole entry
+    toc_selected: usize,
+    /// Lines picked in the viewer, while selecting them
+    selection: Option<Selection>,
+    /// Auto-scrolling of the open entry, once started and until it reaches the end
+    auto_scroll: Option<AutoScroll>,
+    /// Row selected in the list shown while the search box is empty, if it shows one
+    home_selected: usize,
+    /// Keys typed towards a key sequence that isn't complete yet
+    pending_keys: PendingKeys,
+    /// The last action `.` can repeat, see `Action::repeatable`
+    last_action: Option<Action>,
+    search_history: SearchHistory,
+    /// The config's own `default_scope`, which the scope last switched to stands in for
+    configured_scope: Vec<String>,
+    /// Installed doc sets with updates available, once the check after launch finished
+    updates: Vec<Outdated>,
+    update_check: Option<oneshot::Receiver<Result<Vec<Outdated>, String>>>,
+    /// Progress of the doc sets synced after launch, while they are
+    startup_sync: Option<mpsc::UnboundedReceiver<StartupSync>>,
+    /// Doc sets whose index could not be rebuilt, once the rebuild after launch finished
+    index_rebuild: Option<oneshot::Receiver<Vec<String>>>,
+}
+
+/// What a key sequence can be bound to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Action {
+    SelectFirst,
+    SelectLast,
+    Browse,
+    DocSets,
+    Sort,
+    TogglePreview,
+    ScrollTop,
+    ScrollBottom,
+    EditCode,
+    Copy,
+    CopyQuery,
+    CopyCode,
+    ToggleReadingTime,
+    ToggleWrap,
+    ToggleOutline,
+    /// Hide or show the help line, search box and banner
+    ToggleMinimal,
+    /// Search the next installed doc set, or all of them after the last one
+    NextScope,
+    PreviousScope,
+    /// Copy the results to the clipboard as a Markdown list of links
+    CopyResults,
+    /// Copy the results to the clipboard as plain text, a name and URL per line
+    CopyResultsText,
+    /// Open the data directory in the file manager
+    OpenDataDir,
+    /// Open the directory of the config file in the file manager
+    OpenConfigDir,
+    /// Show or hide which config file was loaded and where the data is kept
+    ToggleDebug,
+    /// Run the last repeatable action again
+    Repeat,
+}
+
+impl Action {
+    /// Whether `.` can run the action again: cycling the sort, toggling the preview,
+    /// reading time, wrapping or outline, and copying or editing code, but not moving
+    /// around or opening another view
+    fn repeatable(self) -> bool {
+        matches!(
+            self,
+            Action::Sort
+                | Action::NextScope
+                | Action::PreviousScope
+                | Action::TogglePreview
+                | Action::EditCode
+                | Action::CopyQuery
+                | Action::CopyResults
+                | Action::CopyResultsText
+                | Action::CopyCode
+                | Action::ToggleReadingTime
+                | Action::ToggleWrap
+                | Action::ToggleOutline
+        )
+    }
+
+    /// Whether the action is one of the viewer's rather than of the results'
+    fn in_viewer(self) -> bool {
+        matches!(
+            self,
+            Action::ScrollTop
+                | Action::ScrollBottom
+                | Action::EditCode
+                | Action::Copy
+                | Action::CopyCode
+                | Action::ToggleReadingTime
+                | Action::ToggleWrap
+                | Action::ToggleOutline
+        )
+    }
+}
+
+/// Key sequences of the modes that have them
+struct Keymaps {
+    normal: Keymap<Action>,
+    viewer: Keymap<Action>,
+}
+
+impl Keymaps {
+    fn new(config: &Config) -> Self {
+        let leader = config.leader;
+        Self {
+            normal: Keymap::new(
+                leader,
+                &[
+                    ("gg", Action::SelectFirst),
+                    ("G", Action::SelectLast),
+                    ("<leader>b", Action::Browse),
+                    ("<leader>d", Action::DocSets),
+                    ("<leader>s", Action::Sort),
+                    ("<leader>p", Action::TogglePreview),
+                    ("<leader>y", Action::CopyQuery),
+                    ("Y", Action::CopyResults),
+                    ("<leader>Y", Action::CopyResultsText),
+                    ("<leader>o", Action::OpenDataDir),
+                    ("<leader>O", Action::OpenConfigDir),
+                    ("<leader>D", Action::ToggleDebug),
+                    (config.next_scope_key.as_str(), Action::NextScope),
+                    (config.previous_scope_key.as_str(), Action::PreviousScope),
+                    ("z", Action::ToggleMinimal),
+                    (".", Action::Repeat),
+                ],
+            ),
+            viewer: Keymap::new(
+                leader,
+                &[
+                    ("gg", Action::ScrollTop),
+                    ("G", Action::ScrollBottom),
+                    ("<leader>e", Action::EditCode),
+                    ("<leader>y", Action::Copy),
+                    ("<leader>c", Action::CopyCode),
+                    ("<leader>D", Action::ToggleDebug),
+                    ("z", Action::ToggleMinimal),
+                    (".", Action::Repeat),
+                ],
+            ),
+        }
+    }
+}
+
+/// The result shown in the preview pane
+struct Preview {
+    entry: IndexEntry,
+    /// Tick at which the result was selected
+    since: u64,
+    /// Lines scrolled past while the preview has focus
+    scroll: u16,
+    /// Its content once loaded, or why it couldn't be
+    document: Option<Result<Document, String>>,
+}
+
+/// The parts of the screen keys can go to, moved between with Tab and Shift-Tab
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Panel {
+    Search,
+    Results,
+    /// The selected result's content, next to the results
+    Preview,
+    Viewer,
+    /// The open entry's headings, next to the viewer
+    Outline,
+}
+
+/// Watching the config file for changes, to reload it while the app runs
+struct ConfigWatch {
+    /// When the file was last changed, as of the last check
+    modified: Option<SystemTime>,
+    /// Settings given on the command line, which keep overriding the file's
+    max_download_mb: Option<u64>,
+    proxy: Option<Url>,
+}
+
+/// The scope last switched to with the scope keys, unless the config's `default_scope`
+/// changed since
+fn last_scope(storage: &Storage, configured: &[String]) -> Option<Vec<String>> {
+    match LastScope::load(&storage.last_scope_path()) {
+        Ok(last) => last?.scope_for(configured).map(<[String]>::to_vec),
+        Err(e) => {
+            log::warn!("Could not read the last scope: {e}");
+            None
+        }
+    }
+}
+
+/// When a file was last changed, or `None` if that can't be told, e.g. as it doesn't exist
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+/// The open entry next to the same entry of another version of its doc family
+struct Comparison {
+    /// Slug and lines of the older version, going by the version in the slug
+    old: (String, Vec<Line<'static>>),
+    new: (String, Vec<Line<'static>>),
+    rows: Vec<diff::Row>,
+    /// Rows scrolled past
+    scroll: usize,
+}
+
+/// A result's rendered content, shown in a popup over the results
+struct QuickLook {
+    entry: IndexEntry,
+    document: Document,
+    scroll: u16,
+}
+
+/// Largest size of the quick look popup, in columns and rows
+const QUICK_LOOK_MAX: (u16, u16) = (100, 40);
+
+/// Four fifths of the space there is, up to a maximum
+fn quick_look_size(available: u16, max: u16) -> u16 {
+    (available * 4 / 5).min(max)
+}
+
+/// Columns the quick look popup wraps its entry at, inside its border
+fn quick_look_width() -> usize {
+    let columns = ratatui::crossterm::terminal::size().map_or(80, |(columns, _)| columns);
+    usize::from(quick_look_size(columns, QUICK_LOOK_MAX.0).saturating_sub(2)).max(1)
+}
+
+/// Lines of the open entry picked to copy, between the line selecting started at and the
+/// one moved to, both lines of the document
+#[derive(Debug, Clone, Copy)]
+struct Selection {
+    anchor: usize,
+    cursor: usize,
+}
+
+impl Selection {
+    fn lines(self) -> std::ops::Range<usize> {
+        self.anchor.min(self.cursor)..self.anchor.max(self.cursor) + 1
+    }
+}
+
+/// Scrolling the viewer a line at a time by itself, for reading hands-free
+#[derive(Debug, Clone, Copy)]
+struct AutoScroll {
+    /// Tick of the last line scrolled, or of starting or resuming
+    since: u64,
+    paused: bool,
+}
+
+/// How much `+` and `-` change the time auto-scrolling waits before each line
+const AUTO_SCROLL_STEP: Duration = Duration::from_millis(250);
+
+/// A smooth scroll of the viewer in progress
+#[derive(Clone, Copy)]
+struct ScrollAnimation {
+    target: u16,
+    /// Lines moved per tick
+    step: u16,
+}
+
+/// Ticks a smooth scroll takes to reach its target
+const SMOOTH_SCROLL_TICKS: u16 = 3;
+
+enum InputMode {
+    Normal,
+    Searching,
+    Viewing,
+    EntryNotFound,
+    ConfirmSync,
+    /// Choosing the format to copy the open entry's address in
+    CopyMenu,
+    /// Browsing the doc sets devdocs offers, to install or update them
+    Manage,
+    /// Browsing a doc set's entries by type
+    Browse,
+    /// Picking a section of the open entry from its table of contents
+    Toc,
+    /// Selecting lines of the open entry to copy
+    Visual,
+    /// Comparing the open entry with another version of it
+    Compare,
+    /// Looking at which entries and doc sets were used most
+    Stats,
+}
+
+/// An action that loses data, which is confirmed first unless the config says not to
+enum Destructive {
+    RemoveDocSet(String),
+    ClearRecent,
+    ClearStats,
+}
+
+impl Destructive {
+    fn question(&self) -> String {
+        match self {
+            Destructive::RemoveDocSet(slug) => format!("Remove the {slug} doc set?"),
+            Destructive::ClearRecent => "Clear the recently viewed entries?".to_owned(),
+            Destructive::ClearStats => "Clear the usage stats?".to_owned(),
+        }
+    }
+}
+
+/// State of browsing a doc set by type
+struct Browser {
+    slug: String,
+    types: Vec<DocType>,
+    selected_type: usize,
+    /// Entries of the chosen type, once one is chosen
+    entries: Option<Vec<SearchResult>>,
+    selected: usize,
+}
+
+/// State of the doc set management view
+#[derive(Default)]
+struct Manager {
+    /// Doc sets of the registry and any installed ones it doesn't list, by name
+    rows: Vec<ManagerRow>,
+    selected: usize,
+    /// Number of doc sets in the registry
+    available: usize,
+    /// Slugs of doc sets past searches suggest installing
+    recommended: BTreeSet<String>,
+    /// Showing where the selected doc set comes from and its license
+    about_open: bool,
+}
+
+struct ManagerRow {
+    slug: String,
+    name: String,
+    version: String,
+    installed: bool,
+    /// Download size in bytes, 0 if unknown
+    db_size: u64,
+    /// Bytes the doc set takes up on disk, if it is installed
+    disk_size: Option<u64>,
+    about: About,
+}
+
+/// A doc set to download, e.g. before a link into it can be opened
+struct SyncPrompt {
+    slug: String,
+    /// Entry to open once the download finishes
+    link: Option<DevdocsLink>,
+    /// The download in progress, once the user agreed to it
+    task: Option<SyncTask>,
+    /// The last download was cancelled, so the prompt offers to try again
+    cancelled: bool,
+    error: Option<String>,
+    reason: SyncReason,
+    /// The download went over the size cap, asking whether to download it regardless
+    oversized: Option<DownloadTooLarge>,
+}
+
+/// A doc set download running in the background
+struct SyncTask {
+    handle: JoinHandle<()>,
+    result: oneshot::Receiver<Result<Option<IndexDiff>, SyncError>>,
+}
+
+/// Why a doc set download failed, sendable from the task it ran in
+enum SyncError {
+    TooLarge(DownloadTooLarge),
+    Failed(String),
+}
+
+/// How far the doc sets synced after launch got, sent one step at a time
+enum StartupSync {
+    /// These doc sets are about to be synced
+    Started(Vec<String>),
+    Synced(String, Option<IndexDiff>),
+    Failed(String, String),
+    /// All of them were synced or failed to be
+    Finished(SyncSummary),
+    /// Which doc sets to sync could not be found out
+    Unavailable(String),
+}
+
+/// Why a doc set is about to be downloaded
+#[derive(Clone, Copy)]
+enum SyncReason {
+    NotInstalled,
+    /// The doc set is installed but its files are out of step with each other
+    OutOfSync,
+    /// The doc set is installed and is downloaded again to update it
+    Update,
+}
+
+/// An entry loaded from a doc set, ready to display
+struct OpenEntry {
+    slug: String,
+    path: String,
+    title: String,
+    document: Document,
+    /// Marked deprecated by its index entry or its content
+    deprecated: bool,
+    /// The heading whose section alone is shown, by position in the document's headings
+    section: Option<usize>,
+    /// Its doc set was synced since it was rendered, so it may be out of date
+    stale: bool,
+}
+
+impl OpenEntry {
+    /// The lines of the document the viewer shows
+    fn lines(&self) -> &[Line<'static>] {
+        let lines = &self.document.text.lines;
+        match self.section.and_then(|i| self.document.headings.get(i)) {
+            Some(heading) => &lines[self.document.section(heading)],
+            None => lines,
+        }
+    }
+
+    /// Line of the document the viewer's lines start at
+    fn first_line(&self) -> usize {
+        self.section
+            .and_then(|i| self.document.headings.get(i))
+            .map_or(0, |heading| heading.line)
+    }
+
+    /// Headings of the lines the viewer shows, i.e. of the section shown if there is one
+    fn shown_headings(&self) -> &[Heading] {
+        let headings = &self.document.headings;
+        let shown = self.first_line()..self.first_line() + self.lines().len();
+        let start = headings.partition_point(|heading| heading.line < shown.start);
+        let end = headings.partition_point(|heading| heading.line < shown.end);
+        &headings[start..end]
+    }
+}
+
+/// An entry whose path no longer exists in its doc set, e.g. after the doc set was updated
+struct MissingEntry {
+    slug: String,
+    path: String,
+    title: String,
+    /// Existing entries of the same doc set with a similar name
+    suggestions: Vec<SearchResult>,
+    selected: usize,
+    /// The index still lists the entry but `db.json` has no content for it, so the doc set
+    /// needs re-syncing rather than the entry having moved
+    mismatch: bool,
+}
+
+/// How many replacements are suggested for an entry that could not be found
+const SUGGESTIONS_SHOWN: usize = 10;
+
+/// How many recent entries are offered on the empty search screen, one per digit key
+const RECENT_SHOWN: usize = 9;
+
+/// Smallest terminal the layout fits in: the content's minimum height, the search box and
+/// the help line
+const MIN_WIDTH: u16 = 40;
+const MIN_HEIGHT: u16 = 12 + 3 + 1;
+
+/// How many notifications show at once, the most recent ones
+const NOTIFICATIONS_SHOWN: usize = 3;
+
+/// Columns a notification is at most wide, cutting off longer messages
+const NOTIFICATION_WIDTH: u16 = 60;
+
+/// Columns the viewer keeps when the outline panel is shown next to it
+const MIN_VIEWER_WIDTH: u16 = 40;
+
+/// How many rendered entries are kept for reopening
+const DOCUMENT_CACHE_SIZE: NonZeroUsize = NonZeroUsize::new(16).unwrap();
+
+/// How many recent searches are kept, e.g. for retyping a query after backspacing
+const SEARCH_CACHE_SIZE: NonZeroUsize = NonZeroUsize::new(32).unwrap();
+
+/// Indexes with more entries than this are searched in the background, so typing never
+/// waits on a search; smaller ones are searched right away
+const BACKGROUND_SEARCH_ENTRIES: usize = 20_000;
+
+/// How often the results of a search running in the background are looked for
+const SEARCH_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// A search of entry names running in the background
+struct SearchTask {
+    key: SearchKey,
+    /// The results, sorted, or `None` if the search was superseded before it finished
+    result: oneshot::Receiver<Option<Vec<SearchResult>>>,
+    /// Enter was pressed before the search finished, so its top result opens once it does
+    open_top: bool,
+}
+
+/// What the results of a search depend on, besides the index
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct SearchKey {
+    query: Query,
+    scope: Vec<String>,
+    matcher: Matcher,
+    sort_mode: SortMode,
+    full_text: bool,
+}
+
+impl App {
+    fn new(storage: Storage, http: HttpClient, mut config: Config) -> Self {
+        let configured_scope = config.default_scope.clone();
+        if let Some(scope) = last_scope(&storage, &configured_scope) {
+            config.default_scope = scope;
+        }
+        let recent = RecentList::load(&storage.recent_path()).unwrap_or_default();
+        let stats = UsageStats::load(&storage.stats_path()).unwrap_or_default();
+        let search_history =
+            SearchHistory::load(&storage.search_history_path()).unwrap_or_default();
+        let installed = docset_manager::installed_doc_sets(&storage);
+        let keymaps = Keymaps::new(&config);
+        let manifests = read_manifests(&storage, &installed);
+        let index = Arc::new(Index::load(&storage));
+        for scope in &config.default_scope {
+            if !installed.iter().any(|slug| in_scope(slug, scope)) {
+                log::warn!("Default scope '{scope}' is not an installed doc set");
+            }
+        }
+        Self {
+            input: String::new(),
+            input_mode: InputMode::Searching,
+            character_index: 0,
+            storage,
+            http,
+            seen: seen_entries(&recent, &stats),
+            recent,
+            stats,
+            entry: None,
+            scroll: 0,
+            hscroll: 0,
+            config,
+            installed,
+            manifests,
+            index,
+            results: Vec::new(),
+            selected: 0,
+            scope_cleared: false,
+            missing: None,
+            color_support: ColorSupport::detect(),
+            blink_supported: blink_supported(|name| std::env::var(name).ok()),
+            ticks: 0,
+            sync_prompt: None,
+            url_error: None,
+            open_in_pager: false,
+            page_requested: false,
+            edit_requested: false,
+            notifications: Notifications::default(),
+            confirmation: None,
+            config_path: None,
+            config_origin: None,
+            debug_open: false,
+            config_watch: None,
+            documents: LruCache::new(DOCUMENT_CACHE_SIZE),
+            searches: LruCache::new(SEARCH_CACHE_SIZE),
+            search_task: None,
+            search_due: None,
+            search_generation: Arc::new(AtomicU64::new(0)),
+            full_text: false,
+            full_text_indexes: HashMap::new(),
+            unindexed: 0,
+            rows: Vec::new(),
+            expanded_groups: HashSet::new(),
+            scroll_animation: None,
+            registry: None,
+            manager: None,
+            browser: None,
+            preview: None,
+            side_focus: None,
+            comparison: None,
+            quick_look: None,
+            keymaps,
+            toc_selected: 0,
+            selection: None,
+            auto_scroll: None,
+            home_selected: 0,
+            pending_keys: PendingKeys::default(),
+            last_action: None,
+            search_history,
+            configured_scope,
+            updates: Vec::new(),
+            update_check: None,
+            startup_sync: None,
+            index_rebuild: None,
+        }
+    }
+
+    fn move_cursor_left(&mut self) {
+        let cursor_moved_left = self.character_index.saturating_sub(1);
+        self.character_index = self.clamp_cursor(cursor_moved_left);
+    }
+
+    fn move_cursor_right(&mut self) {
+        let cursor_moved_right = self.character_index.saturating_add(1);
+        self.character_index = self.clamp_cursor(cursor_moved_right);
+    }
+
+    fn enter_char(&mut self, new_char: char) {
+        let index = self.byte_index();
+        self.input.insert(index, new_char);
+        self.move_cursor_right();
+        self.search_as_typed();
+    }
+
+    /// Returns the byte index based on the character position.
+    ///
+    /// Since each character in a string can be contain multiple bytes, it's necessary to calculate
+    /// the byte index based on the index of the character.
+    fn byte_index(&self) -> usize {
+        self.input
+            .char_indices()
+            .map(|(i, _)| i)
+            .nth(self.character_index)
+            .unwrap_or(self.input.len())
+    }
+
+    fn delete_char(&mut self) {
+        let is_not_cursor_leftmost = self.character_index != 0;
+        if is_not_cursor_leftmost {
+            // Method "remove" is not used on the saved text for deleting the selected char.
+            // Reason: Using remove on String works on bytes instead of the chars.
+            // Using remove would require special care because of char boundaries.
+
+            let current_index = self.character_index;
+            let from_left_to_current_index = current_index - 1;
+
+            // Getting all characters before the selected character.
+            let before_char_to_delete = self.input.chars().take(from_left_to_current_index);
+            // Getting all characters after selected character.
+            let after_char_to_delete = self.input.chars().skip(current_index);
+
+            // Put all characters together except the selected one.
+            // By leaving the selected one out, it is forgotten and therefore deleted.
+            self.input = before_char_to_delete.chain(after_char_to_delete).collect();
+            self.move_cursor_left();
+            self.search_as_typed();
+        }
+    }
+
+    fn clamp_cursor(&self, new_cursor_pos: usize) -> usize {
+        new_cursor_pos.clamp(0, self.input.chars().count())
+    }
+
+    fn reset_cursor(&mut self) {
+        self.character_index = 0;
+    }
+
+    fn submit_message(&mut self) {
+        self.record_query();
+        self.input.clear();
+        self.reset_cursor();
+        self.scope_cleared = false;
+        self.update_results();
+    }
+
+    /// Open the best match for the query, keeping the query to refine it on coming back
+    ///
+    /// A search still running in the background opens its best match once it finishes.
+    fn open_top_result(&mut self) {
+        if self.search_due.is_some() {
+            self.update_results();
+        }
+        if let Some(task) = self.search_task.as_mut() {
+            task.open_top = true;
+            return;
+        }
+        if self.input.trim().is_empty() {
+            return;
+        }
+        let top = self
+            .rows
+            .iter()
+            .position(|row| matches!(row, ResultRow::Result(_)));
+        match top {
+            Some(top) => {
+                self.selected = top;
+                self.open_selected(self.open_action());
+            }
+            None => self.notify(Severity::Info, "No matches"),
+        }
+    }
+
+    /// Remember the current query in the search history
+    fn record_query(&mut self) {
+        if !self.input.trim().is_empty() {
+            self.stats.record_search();
+            if let Err(e) = self.stats.save(&self.storage.stats_path()) {
+                log::warn!("Could not save the usage stats: {e}");
+            }
+        }
+        self.search_history.record(&self.input);
+        if let Err(e) = self
+            .search_history
+            .save(&self.storage.search_history_path())
+        {
+            log::warn!("Could not save the search history: {e}");
+        }
+    }
+
+    /// Doc sets the current query searches, empty meaning all of them
+    ///
+    /// A `slug:` prefix in the query takes precedence over `in:`, which takes precedence
+    /// over the default scope. A prefix or `in:` doc set that doesn't name an installed doc
+    /// set is returned as the error.
+    fn active_scope(&self) -> Result<Vec<String>, String> {
+        match self.query().resolve_scopes(&self.installed)? {
+            scope if !scope.is_empty() => Ok(scope),
+            _ if self.scope_cleared => Ok(Vec::new()),
+            _ => Ok(self.config.default_scope.clone()),
+        }
+    }
+
+    /// The current query, with the case mode as toggled
+    fn query(&self) -> Query {
+        Query {
+            case: self.config.search_case,
+            limit: Some(self.config.max_results),
+            ..parse_query(&self.input)
+        }
+    }
+
+    fn update_results(&mut self) {
+        self.search_due = None;
+        self.cancel_search();
+        let results = match self.active_scope() {
+            Ok(scope) => {
+                let key = SearchKey {
+                    query: self.query(),
+                    scope,
+                    matcher: self.config.matcher,
+                    sort_mode: self.config.sort_mode,
+                    full_text: self.full_text,
+                };
+                match self.searches.get(&key) {
+                    Some(results) => results.clone(),
+                    None if !key.full_text
+                        && self.index.entries.len() > BACKGROUND_SEARCH_ENTRIES =>
+                    {
+                        // The results so far stay up until the new ones are in
+                        self.start_search(key);
+                        return;
+                    }
+                    None => {
+                        let mut results = if key.full_text {
+                            self.search_full_text(&key.query, &key.scope)
+                        } else {
+                            key.matcher
+                                .searcher()
+                                .search_in(&key.query, &self.index, &key.scope)
+                        };
+                        sort_results(
+                            &mut results,
+                            self.config.sort_mode,
+                            &self.config.doc_set_order,
+                        );
+                        self.searches.put(key, results.clone());
+                        results
+                    }
+                }
+            }
+            Err(_) => Vec::new(),
+        };
+        self.show_results(results);
+    }
+
+    /// Search for the query as it was edited, once typing pauses for `search_debounce`
+    fn search_as_typed(&mut self) {
+        if self.config.search_debounce.is_zero() {
+            self.update_results();
+        } else {
+            self.search_due = Some(Instant::now() + self.config.search_debounce);
+        }
+    }
+
+    /// Run the search held back while typing once it is due, returning whether it was
+    fn poll_debounce(&mut self, now: Instant) -> bool {
+        match self.search_due {
+            Some(due) if due <= now => {
+                self.update_results();
+                self.update_preview();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether the results are yet to follow the query, as its search is held back or
+    /// still running
+    fn search_pending(&self) -> bool {
+        self.search_due.is_some() || self.search_task.is_some()
+    }
+
+    /// Replace the results with those of a new search
+    fn show_results(&mut self, results: Vec<SearchResult>) {
+        self.results = results;
+        if self.config.merge_versions {
+            merge_versions(&mut self.results);
+        }
+        self.expanded_groups.clear();
+        self.refresh_rows();
+        self.selected = 0;
+        self.url_error = None;
+    }
+
+    /// Search entry names on a blocking thread, leaving the results for `poll_search`
+    fn start_search(&mut self, key: SearchKey) {
+        let generation = Arc::clone(&self.search_generation);
+        let started = generation.load(Ordering::Relaxed);
+        let index = Arc::clone(&self.index);
+        let (sort_mode, order) = (self.config.sort_mode, self.config.doc_set_order.clone());
+        let (query, scope) = (key.query.clone(), key.scope.clone());
+        let searcher = key.matcher.searcher();
+        let (sender, receiver) = oneshot::channel();
+        tokio::task::spawn_blocking(move || {
+            let superseded = || generation.load(Ordering::Relaxed) != started;
+            let results = searcher.search_until(&query, &index, &scope, &superseded);
+            let results = results.map(|mut results| {
+                sort_results(&mut results, sort_mode, &order);
+                results
+            });
+            let _ = sender.send(results);
+        });
+        self.search_task = Some(SearchTask {
+            key,
+            result: receiver,
+            open_top: false,
+        });
+    }
+
+    /// Stop waiting for the search running in the background, telling it to give up
+    fn cancel_search(&mut self) {
+        if self.search_task.take().is_some() {
+            self.search_generation.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Show the results of the search running in the background once it is done, returning
+    /// whether it was
+    fn poll_search(&mut self) -> bool {
+        let Some(task) = &mut self.search_task else {
+            return false;
+        };
+        let results = match task.result.try_recv() {
+            Ok(Some(results)) => results,
+            Ok(None) | Err(oneshot::error::TryRecvError::Closed) => {
+                self.search_task = None;
+                return false;
+            }
+            Err(oneshot::error::TryRecvError::Empty) => return false,
+        };
+        let Some(task) = self.search_task.take() else {
+            return false;
+        };
+        self.searches.put(task.key, results.clone());
+        self.show_results(results);
+        self.update_preview();
+        if task.open_top {
+            self.open_top_result();
+        }
+        true
+    }
+
+    /// Lay the results out again after they or the groups shown in full changed
+    fn refresh_rows(&mut self) {
+        self.rows = if self.config.sort_mode == SortMode::DocSet {
+            group_rows(
+                &self.results,
+                self.config.max_per_group,
+                &self.expanded_groups,
+            )
+        } else {
+            (0..self.results.len()).map(ResultRow::Result).collect()
+        };
+    }
+
+    /// The result shown in the selected row, if it is not a group's "more" row
+    fn selected_result(&self) -> Option<&SearchResult> {
+        match self.rows.get(self.selected)? {
+            ResultRow::Result(i) => self.results.get(*i),
+            ResultRow::More { .. } => None,
+        }
+    }
+
+    /// Show every result of a doc set, including those the search cut off
+    fn expand_group(&mut self, slug: String) {
+        let mut rest = self.config.matcher.searcher().search_in(
+            &self.query(),
+            &self.index,
+            std::slice::from_ref(&slug),
+        );
+        rest.retain(|result| result.entry.slug == slug);
+        self.results.retain(|result| result.entry.slug != slug);
+        self.results.extend(rest);
+        sort_results(
+            &mut self.results,
+            self.config.sort_mode,
+            &self.config.doc_set_order,
+        );
+        if self.config.merge_versions {
+            merge_versions(&mut self.results);
+        }
+        self.expanded_groups.insert(slug);
+        self.refresh_rows();
+    }
+
+    /// Ignore the default scope until the current query is submitted
+    fn clear_scope(&mut self) {
+        if !self.config.default_scope.is_empty() && !self.scope_cleared {
+            self.scope_cleared = true;
+            self.update_results();
+        }
+    }
+
+    /// Search the content of the installed doc sets in scope that have a full-text index
+    ///
+    /// The query's text and phrases are looked for in the content, and its operators keep
+    /// the entries they are listed as.
+    fn search_full_text(&mut self, query: &Query, scope: &[String]) -> Vec<SearchResult> {
+        let slugs: Vec<String> = self
+            .installed
+            .iter()
+            .filter(|slug| scope.is_empty() || scope.iter().any(|scope| in_scope(slug, scope)))
+            .cloned()
+            .collect();
+        self.unindexed = 0;
+        let mut results = Vec::new();
+        for slug in slugs {
+            let index = self
+                .full_text_indexes
+                .entry(slug.clone())
+                .or_insert_with(|| {
+                    docset_manager::full_text_index(&self.storage, &slug).unwrap_or_else(|e| {
+                        log::warn!("Could not read the full-text index of {slug}: {e}");
+                        None
+                    })
+                });
+            let Some(index) = index else {
+                self.unindexed += 1;
+                continue;
+            };
+            // Matches are whole documents, listed as the entry for the top of the document
+            let mut entries: HashMap<&str, &IndexEntry> = HashMap::new();
+            for entry in self.index.entries_of(&slug) {
+                let (path, fragment) = split_fragment(&entry.path);
+                if fragment.is_none() || !entries.contains_key(path) {
+                    entries.insert(path, entry);
+                }
+            }
+            results.extend(
+                index
+                    .search(&query.full_text(), self.config.max_results)
+                    .into_iter()
+                    .filter_map(|found| {
+                        let entry = (*entries.get(found.path.as_str())?).clone();
+                        if !admits_type(query, &entry.type_name) {
+                            return None;
+                        }
+                        Some(SearchResult {
+                            score: found.score,
+                            snippet: Some(found.snippet_or(&entry.name)),
+                            entry,
+                            other_versions: Vec::new(),
+                        })
+                    }),
+            );
+        }
+        sort_results(
+            &mut results,
+            SortMode::Relevance,
+            &self.config.doc_set_order,
+        );
+        results.truncate(self.config.max_results);
+        results
+    }
+
+    /// Switch between searching entry names and entry content
+    fn toggle_full_text(&mut self) {
+        self.full_text = !self.full_text;
+        self.update_results();
+    }
+
+    /// Switch to the next way of matching entry names, keeping it for later sessions
+    fn cycle_matcher(&mut self) {
+        let matcher = self.config.matcher.next();
+        self.config.matcher = matcher;
+        self.update_results();
+        self.notify(
+            Severity::Info,
+            format!("Matching entry names by {}", matcher.name()),
+        );
+        self.save_setting("matcher", &toml::quote(matcher.name()));
+    }
+
+    /// Switch to the next case mode, keeping it for later sessions
+    fn cycle_case_mode(&mut self) {
+        let mode = self.config.search_case.next();
+        self.config.search_case = mode;
+        self.update_results();
+        self.notify(Severity::Info, format!("Searching {}", mode.label()));
+        self.save_setting("search_case", &toml::quote(mode.name()));
+    }
+
+    /// Switch between searching the default scope and every installed doc set
+    fn toggle_global(&mut self) {
+        if !self.config.default_scope.is_empty() {
+            self.scope_cleared = !self.scope_cleared;
+            self.update_results();
+        }
+    }
+
+    fn leave_search(&mut self) {
+        self.input_mode = InputMode::Normal;
+        if self.scope_cleared {
+            self.scope_cleared = false;
+            self.update_results();
+        }
+    }
+
+    /// Make the next or previous installed doc set the default scope, going through all of
+    /// them between the last and the first, and search it
+    ///
+    /// A scope typed as a prefix is dropped so the one switched to applies. The scope is
+    /// remembered for the next run with the history, leaving the config's `default_scope`
+    /// as it was written.
+    fn cycle_scope(&mut self, forward: bool) {
+        let slug = match self.installed.as_slice() {
+            [] => {
+                self.notify(Severity::Warning, "No doc sets are installed");
+                return;
+            }
+            [slug] => {
+                self.notify(
+                    Severity::Info,
+                    format!("{slug} is the only doc set installed"),
+                );
+                return;
+            }
+            installed => {
+                // All of them sit at the end, after the last doc set
+                let positions = installed.len() + 1;
+                let current = match self.config.default_scope.as_slice() {
+                    [] => Some(installed.len()),
+                    [scope] => installed.iter().position(|slug| slug == scope),
+                    _ => None,
+                };
+                let next = match (current, forward) {
+                    (Some(current), true) => (current + 1) % positions,
+                    (Some(current), false) => (current + positions - 1) % positions,
+                    (None, true) => 0,
+                    (None, false) => installed.len() - 1,
+                };
+                installed.get(next).cloned()
+            }
+        };
+
+        if let (Some(_), query) = split_scope(&self.input) {
+            self.input = query.trim_start().to_owned();
+            self.character_index = self.input.chars().count();
+        }
+        self.scope_cleared = false;
+        self.config.default_scope = slug.iter().cloned().collect();
+        self.update_results();
+        let last = LastScope {
+            scope: self.config.default_scope.clone(),
+            configured: self.configured_scope.clone(),
+        };
+        if let Err(e) = last.save(&self.storage.last_scope_path()) {
+            log::warn!("Could not save the scope: {e}");
+        }
+        match &slug {
+            Some(slug) => self.notify(Severity::Info, format!("Searching {slug}")),
+            None => self.notify(Severity::Info, "Searching all installed doc sets"),
+        }
+    }
+
+    /// Switch to the next sort mode, keeping the selected result selected
+    fn cycle_sort_mode(&mut self) {
+        let mode = self.config.sort_mode.next();
+        self.config.sort_mode = mode;
+        let selected = self.selected_result().map(|result| result.entry.clone());
+        sort_results(&mut self.results, mode, &self.config.doc_set_order);
+        self.refresh_rows();
+        self.selected = selected
+            .and_then(|entry| {
+                self.rows.iter().position(
+                    |row| matches!(row, ResultRow::Result(i) if self.results[*i].entry == entry),
+                )
+            })
+            .unwrap_or_default();
+        // A search still running would sort its results the old way
+        if self.search_task.is_some() {
+            self.update_results();
+        }
+
+        self.notify(
+            Severity::Info,
+            format!("Sorting results by {}", mode.label()),
+        );
+        self.save_setting("sort_mode", &toml::quote(mode.name()));
+    }
+
+    fn toggle_reading_time(&mut self) {
+        self.config.reading_time = !self.config.reading_time;
+        self.save_setting("reading_time", &self.config.reading_time.to_string());
+    }
+
+    /// Show a notification for a while
+    fn notify(&mut self, severity: Severity, message: impl Into<String>) {
+        self.notifications.push(severity, message, self.ticks);
+    }
+
+    /// Ticks notifications show for before they go away by themselves
+    fn notification_ticks(&self) -> u64 {
+        let tick = self.config.tick_rate.as_millis().max(1);
+        self.config.notification_duration.as_millis().div_ceil(tick) as u64
+    }
+
+    /// Persist a setting changed from within the app, reporting a failure in a notification
+    fn save_setting(&mut self, key: &str, literal: &str) {
+        let Some(path) = &self.config_path else {
+            return;
+        };
+        let saved = Config::save_setting(path, key, literal);
+        // The app's own change needs no reloading
+        if let Some(watch) = self.config_watch.as_mut() {
+            watch.modified = modified_time(path);
+        }
+        if let Err(e) = saved {
+            self.notify(Severity::Error, format!("Could not save {key}: {e}"));
+        }
+    }
+
+    /// Reload the config if its file changed since the last check, returning whether it did
+    ///
+    /// A config that fails to load is reported and the one in use is kept. Entries already
+    /// open keep the look they were rendered with until they are opened again.
+    fn poll_config(&mut self) -> bool {
+        let (Some(watch), Some(path)) = (self.config_watch.as_mut(), &self.config_path) else {
+            return false;
+        };
+        let modified = modified_time(path);
+        if modified.is_none() || modified == watch.modified {
+            return false;
+        }
+        watch.modified = modified;
+
+        let mut config = match Config::load(path) {
+            Ok(config) => config,
+            Err(e) => {
+                let message = format!("{}: {e}, keeping the previous config", path.display());
+                self.notify(Severity::Error, message);
+                return true;
+            }
+        };
+        if watch.max_download_mb.is_some() {
+            config.max_download_mb = watch.max_download_mb;
+        }
+        if watch.proxy.is_some() {
+            config.proxy = watch.proxy.clone();
+        }
+        if config.proxy != self.config.proxy
+            || config.max_connections != self.config.max_connections
+        {
+            match HttpClient::new(config.proxy.as_ref(), config.max_connections) {
+                Ok(http) => self.http = http,
+                Err(e) => {
+                    self.notify(Severity::Error, format!("Could not use the proxy: {e}"));
+                    return true;
+                }
+            }
+        }
+        if config.mouse != self.config.mouse {
+            let _ = if config.mouse {
+                execute!(std::io::stdout(), EnableMouseCapture)
+            } else {
+                execute!(std::io::stdout(), DisableMouseCapture)
+            };
+        }
+        self.keymaps = Keymaps::new(&config);
+        self.configured_scope = config.default_scope.clone();
+        if let Some(scope) = last_scope(&self.storage, &self.configured_scope) {
+            config.default_scope = scope;
+        }
+        self.config = config;
+        self.documents.clear();
+        self.searches.clear();
+        self.update_results();
+        self.notify(Severity::Success, "Reloaded the config");
+        true
+    }
+
+    fn select_next(&mut self) {
+        if self.selected + 1 < self.rows.len() {
+            self.selected += 1;
+        }
+    }
+
+    fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    /// Open the selected result the way `action` says, or expand the group of the selected
+    /// "more" row
+    fn open_selected(&mut self, action: OpenAction) {
+        match self.rows.get(self.selected).cloned() {
+            Some(ResultRow::Result(i)) => {
+                self.record_query();
+                let entry = self.results[i].entry.clone();
+                self.open_entry(&entry.slug, &entry.path, &entry.name, action);
+            }
+            Some(ResultRow::More { slug, .. }) => self.expand_group(slug),
+            None => {}
+        }
+    }
+
+    /// Show the selected result's content in a popup over the results
+    fn open_quick_look(&mut self) {
+        let Some(entry) = self.selected_result().map(|result| result.entry.clone()) else {
+            return;
+        };
+        match self.document(&entry.slug, &entry.path) {
+            Ok(Some(document)) => {
+                let line = split_fragment(&entry.path)
+                    .1
+                    .and_then(|fragment| document.anchors.get(fragment).copied())
+                    .unwrap_or(0);
+                let width = quick_look_width();
+                let rows: usize = document.text.lines[..line]
+                    .iter()
+                    .map(|line| self.rows_of(line, width))
+                    .sum();
+                self.quick_look = Some(QuickLook {
+                    entry,
+                    document,
+                    scroll: u16::try_from(rows).unwrap_or(u16::MAX),
+                });
+            }
+            Ok(None) => self.notify(Severity::Warning, "No content for this entry"),
+            Err(e) => self.notify(Severity::Error, format!("Could not read this entry: {e}")),
+        }
+    }
+
+    /// Scroll the quick look popup, close it, or open its entry in the viewer
+    fn quick_look_key(&mut self, code: KeyCode) {
+        let Some(lines) = self.quick_look.as_ref().map(|q| &q.document.text.lines) else {
+            return;
+        };
+        let width = quick_look_width();
+        let rows: usize = lines.iter().map(|line| self.rows_of(line, width)).sum();
+        let last = u16::try_from(rows.saturating_sub(1)).unwrap_or(u16::MAX);
+        let Some(quick_look) = self.quick_look.as_mut() else {
+            return;
+        };
+        let scroll = |quick_look: &mut QuickLook, rows: i32| {
+            quick_look.scroll = quick_look
+                .scroll
+                .saturating_add_signed(rows as i16)
+                .min(last);
+        };
+        match code {
+            KeyCode::Char('j') | KeyCode::Down => scroll(quick_look, 1),
+            KeyCode::Char('k') | KeyCode::Up => scroll(quick_look, -1),
+            KeyCode::PageDown | KeyCode::Char(' ') => scroll(quick_look, 20),
+            KeyCode::PageUp => scroll(quick_look, -20),
+            KeyCode::Char('g') => quick_look.scroll = 0,
+            KeyCode::Char('G') => quick_look.scroll = last,
+            KeyCode::Enter => {
+                self.quick_look = None;
+                self.open_selected(self.open_action());
+            }
+            KeyCode::Esc | KeyCode::Char('q') => self.quick_look = None,
+            _ => {}
+        }
+    }
+
+    /// What opening an entry does without a modifier key: the `open_action` of the config,
+    /// unless `--pager` was given
+    fn open_action(&self) -> OpenAction {
+        if self.open_in_pager {
+            OpenAction::Pager
+        } else {
+            self.config.open_action
+        }
+    }
+
+    /// Open an entry in the viewer, the pager or the browser
+    fn open_entry(&mut self, slug: &str, path: &str, title: &str, action: OpenAction) {
+        match action {
+            OpenAction::Viewer => self.load_entry(slug, path, title),
+            OpenAction::Pager => {
+                self.load_entry(slug, path, title);
+                self.page_requested = matches!(self.input_mode, InputMode::Viewing);
+            }
+            OpenAction::Browser => {
+                let url = canonical_url(slug, path);
+                match open_url(&url) {
+                    Ok(()) => {
+                        self.record_view(slug, path, title);
+                        self.notify(Severity::Info, format!("Opened {url}"));
+                    }
+                    Err(e) => self.notify(Severity::Error, format!("Could not open {url}: {e}")),
+                }
+            }
+        }
+    }
+
+    /// Open an entry in the viewer and remember it as recently viewed
+    fn load_entry(&mut self, slug: &str, path: &str, title: &str) {
+        self.side_focus = None;
+        let document = match self.document(slug, path) {
+            Ok(Some(document)) => document,
+            Ok(None) => {
+                log::warn!("No entry {slug}/{path}");
+                self.show_missing(slug, path, title);
+                return;
+            }
+            Err(e) => {
+                log::warn!("Could not open {slug}/{path}: {e}");
+                return;
+            }
+        };
+
+        let deprecated = self.config.detect_deprecated
+            && (document.deprecated
+                || self
+                    .index
+                    .find(slug, path)
+                    .is_some_and(IndexEntry::looks_deprecated));
+        // The page's own heading tends to say more than its terse index name, unless the entry
+        // is only a part of the page
+        let title = match &document.title {
+            Some(page_title) if split_fragment(path).1.is_none() => page_title.clone(),
+            _ => title.to_owned(),
+        };
+        self.entry = Some(OpenEntry {
+            slug: slug.to_owned(),
+            path: path.to_owned(),
+            title: title.clone(),
+            document,
+            deprecated,
+            section: None,
+            stale: false,
+        });
+        self.scroll_animation = None;
+        self.auto_scroll = None;
+        self.hscroll = 0;
+        self.scroll = match split_fragment(path) {
+            (_, Some(fragment)) => self.anchor_scroll(fragment),
+            (_, None) => 0,
+        };
+        self.input_mode = InputMode::Viewing;
+        let headings = self
+            .entry
+            .as_ref()
+            .map_or(0, |entry| entry.document.headings.len());
+        if self.config.toc_min_headings > 0
+            && headings >= self.config.toc_min_headings
+            && split_fragment(path).1.is_none()
+        {
+            self.open_toc();
+        }
+
+        self.record_view(slug, path, &title);
+    }
+
+    /// Read the open entry from storage and render it again, keeping the viewer at the
+    /// same place: as far below the same anchor, or as far through the entry if that anchor
+    /// is gone
+    ///
+    /// An entry its doc set no longer has is handled as on opening it.
+    fn reload_entry(&mut self) {
+        let top = self.top_line();
+        let Some(entry) = &self.entry else {
+            return;
+        };
+        let (slug, path, title) = (entry.slug.clone(), entry.path.clone(), entry.title.clone());
+        let anchor = entry
+            .document
+            .anchors
+            .iter()
+            .filter(|&(_, &line)| line <= top)
+            .max_by_key(|&(_, &line)| line)
+            .map(|(anchor, &line)| (anchor.clone(), top - line));
+        let through = top as f64 / entry.document.text.lines.len().max(1) as f64;
+
+        self.documents
+            .pop(&(slug.clone(), split_fragment(&path).0.to_owned()));
+        let document = match self.document(&slug, &path) {
+            Ok(Some(document)) => document,
+            Ok(None) => {
+                log::warn!("No entry {slug}/{path} after reloading");
+                self.show_missing(&slug, &path, &title);
+                return;
+            }
+            Err(e) => {
+                self.notify(Severity::Error, format!("Could not reload {title}: {e}"));
+                return;
+            }
+        };
+        let Some(entry) = self.entry.as_mut() else {
+            return;
+        };
+        let lines = document.text.lines.len();
+        let top =
+            match anchor.and_then(|(anchor, below)| Some(document.anchors.get(&anchor)? + below)) {
+                Some(line) => line,
+                None => (through * lines as f64) as usize,
+            };
+        if entry.section.is_some_and(|i| i >= document.headings.len()) {
+            entry.section = None;
+        }
+        entry.document = document;
+        entry.stale = false;
+        let Some(entry) = &self.entry else {
+            return;
+        };
+        let lines = entry.lines();
+        let line = top.saturating_sub(entry.first_line()).min(lines.len());
+        self.scroll = self.rows_before(&lines[..line]);
+        self.scroll_animation = None;
+        self.notify(Severity::Info, format!("Reloaded {title}"));
+    }
+
+    /// Mark the open entry as out of date after its doc set was synced, offering to reload it
+    fn offer_reload(&mut self, slug: &str) {
+        let Some(entry) = self.entry.as_mut().filter(|entry| entry.slug == slug) else {
+            return;
+        };
+        entry.stale = true;
+        let title = entry.title.clone();
+        self.notify(
+            Severity::Info,
+            format!("{slug} was updated, press R to reload {title}"),
+        );
+    }
+
+    /// Remember an entry as recently viewed, and count it as opened once more
+    fn record_view(&mut self, slug: &str, path: &str, title: &str) {
+        self.recent.record(RecentEntry::now(slug, path, title));
+        if let Err(e) = self.recent.save(&self.storage.recent_path()) {
+            log::warn!("Could not save recently viewed entries: {e}");
+        }
+        self.stats.record_open(slug, path, title);
+        self.seen.insert((slug.to_owned(), path.to_owned()));
+        if let Err(e) = self.stats.save(&self.storage.stats_path()) {
+            log::warn!("Could not save the usage stats: {e}");
+        }
+    }
+
+    /// The rendered content of an entry, from the cache if it was rendered recently, or
+    /// `None` if the doc set has no content for it
+    fn document(
+        &mut self,
+        slug: &str,
+        path: &str,
+    ) -> Result<Option<Document>, Box<dyn std::error::Error>> {
+        // Anchors of an entry share its content, so they share a cache slot too
+        let key = (slug.to_owned(), split_fragment(path).0.to_owned());
+        if let Some(document) = self.documents.get(&key) {
+            return Ok(Some(document.clone()));
+        }
+        let Some(html) = docset_manager::read_entry(&self.storage, slug, path)? else {
+            return Ok(None);
+        };
+        let document = html_to_text(&html, &self.render_options());
+        self.documents.put(key, document.clone());
+        Ok(Some(document))
+    }
+
+    /// Keep the preview pane on the selected result, loading it once the selection has
+    /// rested for the preview delay, and return whether the preview changed
+    fn update_preview(&mut self) -> bool {
+        if !self.config.preview {
+            return self.preview.take().is_some();
+        }
+        let Some(selected) = self.selected_result().map(|result| result.entry.clone()) else {
+            return self.preview.take().is_some();
+        };
+        match &self.preview {
+            Some(preview) if preview.entry == selected => {
+                let rested = self.ticks.wrapping_sub(preview.since) >= self.preview_delay_ticks();
+                if preview.document.is_some() || !rested {
+                    return false;
+                }
+                let document = match self.document(&selected.slug, &selected.path) {
+                    Ok(Some(document)) => Ok(document),
+                    Ok(None) => Err("No content for this entry".to_owned()),
+                    Err(e) => Err(format!("Could not read this entry: {e}")),
+                };
+                if let Some(preview) = self.preview.as_mut() {
+                    preview.document = Some(document);
+                }
+                true
+            }
+            _ => {
+                self.preview = Some(Preview {
+                    entry: selected,
+                    since: self.ticks,
+                    scroll: 0,
+                    document: None,
+                });
+                true
+            }
+        }
+    }
+
+    /// Ticks the selection has to rest on a result before it is previewed
+    fn preview_delay_ticks(&self) -> u64 {
+        let tick = self.config.tick_rate.as_millis().max(1);
+        self.config.preview_delay.as_millis().div_ceil(tick) as u64
+    }
+
+    fn toggle_preview(&mut self) {
+        self.config.preview = !self.config.preview;
+        self.update_preview();
+        self.save_setting("preview", &self.config.preview.to_string());
+    }
+
+    /// Offer entries of the same doc set named like the one that could not be found
+    fn show_missing(&mut self, slug: &str, path: &str, title: &str) {
+        let base_path = |path: &str| split_fragment(path).0.to_owned();
+        let mismatch = self
+            .index
+            .entries_of(slug)
+            .any(|entry| base_path(&entry.path) == base_path(path));
+        if mismatch {
+            log::warn!("{slug} lists {path} in index.json but db.json has no content for it");
+        }
+
+        let scope = [slug.to_owned()];
+        let mut suggestions = Fuzzy.search_in(&Query::text(title), &self.index, &scope);
+        if suggestions.is_empty() {
+            // The name may have changed too, so fall back to the last part of the path
+            let path = split_fragment(path).0;
+            let last = path.rsplit('/').next().unwrap_or(path);
+            suggestions = Fuzzy.search_in(&Query::text(last), &self.index, &scope);
+        }
+        // Other anchors of an entry without content would be just as empty
+        suggestions.retain(|result| base_path(&result.entry.path) != base_path(path));
+        suggestions.truncate(SUGGESTIONS_SHOWN);
+
+        self.missing = Some(MissingEntry {
+            slug: slug.to_owned(),
+            path: path.to_owned(),
+            title: title.to_owned(),
+            suggestions,
+            selected: 0,
+            mismatch,
+        });
+        self.input_mode = InputMode::EntryNotFound;
+    }
+
+    /// Download the doc set of an entry without content again, then retry opening it
+    fn resync_missing(&mut self) {
+        let Some(missing) = self.missing.take_if(|missing| missing.mismatch) else {
+            return;
+        };
+        let (path, fragment) = split_fragment(&missing.path);
+        let (path, fragment) = (path.to_owned(), fragment.map(str::to_owned));
+        self.sync_prompt = Some(SyncPrompt {
+            slug: missing.slug.clone(),
+            link: Some(DevdocsLink {
+                slug: missing.slug,
+                path,
+                fragment,
+            }),
+            task: None,
+            cancelled: false,
+            error: None,
+            oversized: None,
+            reason: SyncReason::OutOfSync,
+        });
+        self.input_mode = InputMode::ConfirmSync;
+        self.start_sync();
+    }
+
+    fn select_suggestion(&mut self, forward: bool) {
+        if let Some(missing) = &mut self.missing {
+            missing.selected = if forward {
+                (missing.selected + 1).min(missing.suggestions.len().saturating_sub(1))
+            } else {
+                missing.selected.saturating_sub(1)
+            };
+        }
+    }
+
+    fn open_suggestion(&mut self) {
+        let Some(missing) = self.missing.take() else {
+            return;
+        };
+        match missing.suggestions.get(missing.selected) {
+            Some(result) => {
+                let entry = result.entry.clone();
+                self.open_entry(&entry.slug, &entry.path, &entry.name, self.open_action());
+            }
+            None => self.missing = Some(missing),
+        }
+    }
+
+    fn render_options(&self) -> RenderOptions {
+        self.config.render_options()
+    }
+
+    /// How far to scroll the open entry for an anchor to be at the top of the viewer
+    ///
+    /// The viewer scrolls by wrapped rows, so this estimates how many rows the lines before
+    /// the anchor wrap into at the current terminal width.
+    fn anchor_scroll(&self, anchor: &str) -> u16 {
+        let Some(entry) = &self.entry else {
+            return 0;
+        };
+        let Some(&line) = entry.document.anchors.get(anchor) else {
+            return 0;
+        };
+        self.rows_before(&entry.document.text.lines[..line])
+    }
+
+    /// Columns the viewer wraps the open entry at
+    fn viewer_width(&self) -> usize {
+        let columns = ratatui::crossterm::terminal::size().map_or(80, |(columns, _)| columns);
+        let outline = if self.outline_shown(columns) {
+            self.config.outline_width
+        } else {
+            0
+        };
+        columns.saturating_sub(outline).saturating_sub(2).max(1) as usize
+    }
+
+    /// Rows the viewer shows, inside its border, below which the search box and help line
+    /// take the rest of the terminal unless the layout is minimal
+    fn viewer_height(&self) -> u16 {
+        let rows = ratatui::crossterm::terminal::size().map_or(24, |(_, rows)| rows);
+        let chrome = if self.config.minimal { 0 } else { 3 + 1 };
+        rows.saturating_sub(chrome).saturating_sub(2).max(1)
+    }
+
+    /// The panels on screen that can have focus, in the order Tab moves through them
+    fn panels(&self) -> Vec<Panel> {
+        let columns = ratatui::crossterm::terminal::size().map_or(80, |(columns, _)| columns);
+        match self.input_mode {
+            InputMode::Normal | InputMode::Searching => {
+                let mut panels = vec![Panel::Search, Panel::Results];
+                if self.preview.is_some() && !self.input.is_empty() && columns >= 2 * MIN_WIDTH {
+                    panels.push(Panel::Preview);
+                }
+                panels
+            }
+            InputMode::Viewing if self.entry.is_some() => {
+                let mut panels = vec![Panel::Viewer];
+                if self.outline_shown(columns) {
+                    panels.push(Panel::Outline);
+                }
+                panels
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// The panel keys go to, if the screen has any
+    fn focused_panel(&self) -> Option<Panel> {
+        let panels = self.panels();
+        let focused = match self.input_mode {
+            InputMode::Searching => Panel::Search,
+            InputMode::Normal => self.side_focus.unwrap_or(Panel::Results),
+            InputMode::Viewing => self.side_focus.unwrap_or(Panel::Viewer),
+            _ => return None,
+        };
+        // The side panel may have gone away since it got focus
+        match focused {
+            Panel::Preview | Panel::Outline if !panels.contains(&focused) => panels.get(1).copied(),
+            _ => Some(focused),
+        }
+    }
+
+    fn focus(&mut self, panel: Panel) {
+        self.side_focus = None;
+        match panel {
+            Panel::Search => self.input_mode = InputMode::Searching,
+            Panel::Results => self.input_mode = InputMode::Normal,
+            Panel::Preview => {
+                self.input_mode = InputMode::Normal;
+                self.side_focus = Some(panel);
+            }
+            Panel::Viewer => self.input_mode = InputMode::Viewing,
+            Panel::Outline => {
+                self.input_mode = InputMode::Viewing;
+                self.side_focus = Some(panel);
+            }
+        }
+    }
+
+    /// Move focus to the next panel on screen, or the previous one, wrapping around
+    fn cycle_focus(&mut self, forward: bool) {
+        let panels = self.panels();
+        let Some(focused) = self.focused_panel() else {
+            return;
+        };
+        let i = panels
+            .iter()
+            .position(|&panel| panel == focused)
+            .unwrap_or_default();
+        let next = if forward {
+            (i + 1) % panels.len()
+        } else {
+            (i + panels.len() - 1) % panels.len()
+        };
+        if focused == Panel::Search {
+            self.leave_search();
+        }
+        self.focus(panels[next]);
+    }
+
+    /// Handle a key for the preview or outline while it has focus, returning whether it did
+    ///
+    /// Keys it doesn't handle go on to the panel it is next to.
+    fn side_panel_key(&mut self, key: KeyEvent) -> bool {
+        match (self.focused_panel(), key.code) {
+            (Some(Panel::Preview), KeyCode::Char('j') | KeyCode::Down) => self.scroll_preview(1),
+            (Some(Panel::Preview), KeyCode::Char('k') | KeyCode::Up) => self.scroll_preview(-1),
+            (Some(Panel::Preview), KeyCode::PageDown | KeyCode::Char(' ')) => {
+                self.scroll_preview(20)
+            }
+            (Some(Panel::Preview), KeyCode::PageUp) => self.scroll_preview(-20),
+            (Some(Panel::Preview), KeyCode::Esc) => self.focus(Panel::Results),
+            (Some(Panel::Outline), KeyCode::Char('j') | KeyCode::Down) => {
+                self.jump_to_heading(true)
+            }
+            (Some(Panel::Outline), KeyCode::Char('k') | KeyCode::Up) => self.jump_to_heading(false),
+            (Some(Panel::Outline), KeyCode::Esc | KeyCode::Enter) => self.focus(Panel::Viewer),
+            _ => return false,
+        }
+        true
+    }
+
+    /// A bordered block for a panel, its border highlighted and thick while it has focus
+    /// among others
+    fn panel_block(&self, panel: Panel) -> Block<'static> {
+        let block = Block::bordered();
+        if self.focused_panel() == Some(panel) && self.panels().len() > 1 {
+            block
+                .border_style(self.config.selection.border_style(self.color_support))
+                .border_type(BorderType::Thick)
+        } else {
+            block
+        }
+    }
+
+    fn scroll_preview(&mut self, lines: i32) {
+        if let Some(preview) = self.preview.as_mut() {
+            preview.scroll =
+                (i32::from(preview.scroll) + lines).clamp(0, i32::from(u16::MAX)) as u16;
+        }
+    }
+
+    /// Whether the outline panel is shown next to a viewer this many columns wide
+    fn outline_shown(&self, columns: u16) -> bool {
+        self.config.outline
+            && columns >= self.config.outline_width + MIN_VIEWER_WIDTH
+            && self
+                .entry
+                .as_ref()
+                .is_some_and(|entry| !entry.shown_headings().is_empty())
+    }
+
+    fn toggle_outline(&mut self) {
+        self.config.outline = !self.config.outline;
+        self.save_setting("outline", &self.config.outline.to_string());
+    }
+
+    /// Scroll to the start of the next or previous heading of the open entry
+    fn jump_to_heading(&mut self, forward: bool) {
+        let top = self.top_line();
+        let Some(entry) = &self.entry else {
+            return;
+        };
+        let headings = entry.shown_headings();
+        if headings.is_empty() {
+            self.notify(Severity::Warning, "This entry has no headings");
+            return;
+        }
+        let heading = if forward {
+            headings.iter().find(|heading| heading.line > top)
+        } else {
+            headings.iter().rfind(|heading| heading.line < top)
+        };
+        let Some(heading) = heading else {
+            return;
+        };
+        let first_line = entry.first_line();
+        let rows = self.rows_before(&entry.lines()[..heading.line - first_line]);
+        let from = self
+            .scroll_animation
+            .map_or(self.scroll, |animation| animation.target);
+        self.scroll_by(i32::from(rows) - i32::from(from));
+    }
+
+    /// Rows some lines wrap into in the viewer
+    fn rows_before(&self, lines: &[Line]) -> u16 {
+        let width = self.viewer_width();
+        let rows: usize = lines.iter().map(|line| self.rows_of(line, width)).sum();
+        // Scrolling stops at the last row it can reach rather than wrapping around
+        u16::try_from(rows).unwrap_or(u16::MAX)
+    }
+
+    /// Rows a line takes up in a viewer this many columns wide
+    fn rows_of(&self, line: &Line, width: usize) -> usize {
+        if self.config.wrap {
+            line.width().div_ceil(width).max(1)
+        } else {
+            1
+        }
+    }
+
+    /// Line of the document at the top of the viewer, estimated like [`Self::anchor_scroll`]
+    fn top_line(&self) -> usize {
+        let Some(entry) = &self.entry else {
+            return 0;
+        };
+        let width = self.viewer_width();
+        let mut rows = 0;
+        let lines = entry.lines();
+        let line = lines
+            .iter()
+            .position(|line| {
+                rows += self.rows_of(line, width);
+                rows > usize::from(self.scroll)
+            })
+            .unwrap_or(lines.len());
+        entry.first_line() + line
+    }
+
+    /// Open the first image at or below the top of the viewer in the browser
+    fn open_image(&mut self) {
+        let top = self.top_line();
+        let Some(entry) = &self.entry else {
+            return;
+        };
+        if entry.document.images.is_empty() {
+            self.notify(Severity::Warning, "This entry has no images");
+            return;
+        }
+        let shown = entry.first_line()..entry.first_line() + entry.lines().len();
+        let Some(image) = entry
+            .document
+            .image_at(top)
+            .filter(|image| shown.contains(&image.line))
+        else {
+            self.notify(Severity::Warning, "No image below the top of the viewer");
+            return;
+        };
+        let Some(url) = image_url(&entry.slug, &entry.path, &image.src) else {
+            self.notify(
+                Severity::Warning,
+                "This image is part of the entry and has no address to open",
+            );
+            return;
+        };
+        match open_url(&url) {
+            Ok(()) => self.notify(Severity::Info, format!("Opened {url}")),
+            Err(e) => self.notify(Severity::Error, format!("Could not open {url}: {e}")),
+        }
+    }
+
+    /// Open a directory in the file manager, or say where to create it if it doesn't exist
+    fn open_dir(&mut self, dir: Option<PathBuf>) {
+        let Some(dir) = dir else {
+            self.notify(
+                Severity::Warning,
+                "Could not determine the config directory",
+            );
+            return;
+        };
+        let shown = dir.display();
+        if !dir.is_dir() {
+            self.notify(
+                Severity::Warning,
+                format!("{shown} does not exist yet; create it to keep files there"),
+            );
+            return;
+        }
+        match open_dir(&dir) {
+            Ok(()) => self.notify(Severity::Info, format!("Opened {shown}")),
+            Err(e) => self.notify(Severity::Error, format!("Could not open {shown}: {e}")),
+        }
+    }
+
+    /// List the open entry's headings to pick a section from, starting at the one above
+    /// the top of the viewer
+    fn open_toc(&mut self) {
+        let top = self.top_line();
+        let Some(entry) = &self.entry else {
+            return;
+        };
+        if entry.document.headings.is_empty() {
+            self.notify(Severity::Warning, "This entry has no headings");
+            return;
+        }
+        // The first row stands for the whole entry
+        self.toc_selected = match entry.section {
+            Some(i) => i + 1,
+            None => entry
+                .document
+                .headings
+                .iter()
+                .rposition(|heading| heading.line <= top)
+                .map_or(0, |i| i + 1),
+        };
+        self.input_mode = InputMode::Toc;
+    }
+
+    fn select_toc_row(&mut self, forward: bool) {
+        let rows = self
+            .entry
+            .as_ref()
+            .map_or(0, |entry| entry.document.headings.len() + 1);
+        if forward {
+            if self.toc_selected + 1 < rows {
+                self.toc_selected += 1;
+            }
+        } else {
+            self.toc_selected = self.toc_selected.saturating_sub(1);
+        }
+    }
+
+    /// Show only the section picked from the table of contents, or the whole entry
+    fn open_toc_row(&mut self) {
+        if let Some(entry) = self.entry.as_mut() {
+            entry.section = self.toc_selected.checked_sub(1);
+        }
+        self.scroll = 0;
+        self.scroll_animation = None;
+        self.input_mode = InputMode::Viewing;
+    }
+
+    /// Open the entry a devdocs URL points at, offering to download its doc set if needed
+    fn open_link(&mut self, link: DevdocsLink) {
+        if !self.installed.contains(&link.slug) {
+            self.sync_prompt = Some(SyncPrompt {
+                slug: link.slug.clone(),
+                link: Some(link),
+                task: None,
+                cancelled: false,
+                error: None,
+                oversized: None,
+                reason: SyncReason::NotInstalled,
+            });
+            self.input_mode = InputMode::ConfirmSync;
+            return;
+        }
+
+        let path = link.entry_path();
+        let title = self
+            .index
+            .find(&link.slug, &path)
+            .or_else(|| self.index.find(&link.slug, &link.path))
+            .map_or_else(|| link.path.clone(), |entry| entry.name.clone());
+        self.open_entry(&link.slug, &path, &title, self.open_action());
+    }
+
+    /// Open the devdocs URL typed or pasted into the search box
+    fn open_input_url(&mut self) {
+        match parse_devdocs_url(&self.input) {
+            Ok(link) => {
+                self.submit_message();
+                self.open_link(link);
+            }
+            Err(e) => self.url_error = Some(e.to_string()),
+        }
+    }
+
+    /// Drop the cached documents of a doc set, whose content may have changed
+    fn forget_documents(&mut self, slug: &str) {
+        let stale: Vec<_> = self
+            .documents
+            .iter()
+            .map(|(key, _)| key)
+            .filter(|(cached_slug, _)| cached_slug == slug)
+            .cloned()
+            .collect();
+        for key in stale {
+            self.documents.pop(&key);
+        }
+    }
+
+    /// Pick up doc sets that were installed, updated or removed
+    fn reload_doc_sets(&mut self) {
+        self.installed = docset_manager::installed_doc_sets(&self.storage);
+        self.manifests = read_manifests(&self.storage, &self.installed);
+        self.index = Arc::new(Index::load(&self.storage));
+        self.full_text_indexes.clear();
+        self.searches.clear();
+        self.cancel_search();
+    }
+
+    /// Other installed versions of the open entry's doc family, e.g. `python~3.9` for
+    /// `python~3.12`
+    fn other_versions(&self) -> Vec<&str> {
+        let Some(entry) = &self.entry else {
+            return Vec::new();
+        };
+        let family = doc_family(&entry.slug);
+        self.installed
+            .iter()
+            .filter(|slug| doc_family(slug) == family && **slug != entry.slug)
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// Show the open entry next to the same entry of the closest other installed version,
+    /// the one before it if there is one
+    fn open_comparison(&mut self) {
+        let Some(entry) = &self.entry else {
+            return;
+        };
+        let mut versions = self.other_versions();
+        if versions.is_empty() {
+            let family = doc_family(&entry.slug).to_owned();
+            self.notify(
+                Severity::Info,
+                format!("Install another version of {family} to compare with"),
+            );
+            return;
+        }
+        versions.push(&entry.slug);
+        versions.sort_by(|a, b| compare_versions(a, b));
+        let i = versions
+            .iter()
+            .position(|slug| *slug == entry.slug)
+            .unwrap_or_default();
+        let other = versions[if i > 0 { i - 1 } else { i + 1 }].to_owned();
+
+        let slug = entry.slug.clone();
+        let path = entry.path.clone();
+        let lines = entry.document.text.lines.clone();
+        let other_lines = match self.document(&other, &path) {
+            Ok(Some(document)) => document.text.lines,
+            Ok(None) => {
+                self.notify(Severity::Warning, format!("{other} has no {path}"));
+                return;
+            }
+            Err(e) => {
+                self.notify(Severity::Error, format!("Could not read {other}: {e}"));
+                return;
+            }
+        };
+        let (old, new) = if compare_versions(&other, &slug).is_lt() {
+            ((other, other_lines), (slug, lines))
+        } else {
+            ((slug, lines), (other, other_lines))
+        };
+        let text = |lines: &[Line]| -> Vec<String> {
+            lines
+                .iter()
+                .map(|line| {
+                    line.spans
+                        .iter()
+                        .map(|span| span.content.as_ref())
+                        .collect()
+                })
+                .collect()
+        };
+        let rows = diff::side_by_side(&diff::diff_lines(&text(&old.1), &text(&new.1)));
+        if !rows.iter().any(|row| row.changed) {
+            self.notify(
+                Severity::Info,
+                format!("{path} is the same in {} and {}", old.0, new.0),
+            );
+        }
+        self.comparison = Some(Comparison {
+            old,
+            new,
+            rows,
+            scroll: 0,
+        });
+        self.input_mode = InputMode::Compare;
+    }
+
+    fn scroll_comparison(&mut self, rows: i32) {
+        if let Some(comparison) = self.comparison.as_mut() {
+            let last = comparison.rows.len().saturating_sub(1);
+            comparison.scroll = comparison
+                .scroll
+                .saturating_add_signed(rows as isize)
+                .min(last);
+        }
+    }
+
+    /// Scroll to where the next run of changed rows starts, or the previous one
+    fn jump_to_change(&mut self, forward: bool) {
+        let Some(comparison) = self.comparison.as_mut() else {
+            return;
+        };
+        let rows = &comparison.rows;
+        let starts = |i: usize| rows[i].changed && (i == 0 || !rows[i - 1].changed);
+        let target = if forward {
+            (comparison.scroll + 1..rows.len()).find(|&i| starts(i))
+        } else {
+            (0..comparison.scroll).rev().find(|&i| starts(i))
+        };
+        match target {
+            Some(target) => comparison.scroll = target,
+            None => self.notify(Severity::Info, "No more changes"),
+        }
+    }
+
+    /// Open the same entry in the next installed version of its doc family
+    fn switch_version(&mut self) {
+        let Some(entry) = &self.entry else {
+            return;
+        };
+        let family = doc_family(&entry.slug);
+        let versions: Vec<&String> = self
+            .installed
+            .iter()
+            .filter(|slug| doc_family(slug) == family)
+            .collect();
+        if versions.len() < 2 {
+            return;
+        }
+        let current = versions.iter().position(|slug| **slug == entry.slug);
+        let next = versions[current.map_or(0, |i| (i + 1) % versions.len())].clone();
+
+        let (path, title) = (entry.path.clone(), entry.title.clone());
+        self.load_entry(&next, &path, &title);
+    }
+
+    /// Copy every code block of the open entry to the clipboard, fenced as Markdown
+    fn copy_code_blocks(&mut self) {
+        self.input_mode = InputMode::Viewing;
+        let Some(entry) = &self.entry else {
+            return;
+        };
+        let blocks = entry.document.code_blocks.len();
+        if blocks == 0 {
+            self.notify(Severity::Info, "This entry has no code blocks to copy");
+            return;
+        }
+        match clipboard::copy(&entry.document.fenced_code()) {
+            Ok(()) => self.notify(
+                Severity::Success,
+                format!(
+                    "Copied {blocks} code block{}",
+                    if blocks == 1 { "" } else { "s" }
+                ),
+            ),
+            Err(e) => self.notify(
+                Severity::Error,
+                format!("Could not copy to the clipboard: {e}"),
+            ),
+        }
+    }
+
+    /// Start selecting lines to copy at the top line of the viewer
+    fn start_selection(&mut self) {
+        let line = self.top_line();
+        let Some(entry) = &self.entry else {
+            return;
+        };
+        if entry.lines().is_empty() {
+            return;
+        }
+        let last = entry.first_line() + entry.lines().len() - 1;
+        let line = line.min(last);
+        self.selection = Some(Selection {
+            anchor: line,
+            cursor: line,
+        });
+        self.input_mode = InputMode::Visual;
+    }
+
+    /// Move the end of the selection by some lines, negative going up, and scroll it into
+    /// view
+    fn move_selection(&mut self, lines: i32) {
+        let (Some(entry), Some(selection)) = (&self.entry, self.selection.as_mut()) else {
+            return;
+        };
+        let first_line = entry.first_line();
+        let last = first_line + entry.lines().len() - 1;
+        selection.cursor = selection
+            .cursor
+            .saturating_add_signed(lines as isize)
+            .clamp(first_line, last);
+
+        // Scroll just enough for every row the line wraps into to show
+        let cursor = selection.cursor - first_line;
+        let start = self.rows_before(&entry.lines()[..cursor]);
+        let end = self.rows_before(&entry.lines()[..=cursor]);
+        let height = self.viewer_height();
+        self.scroll_animation = None;
+        if start < self.scroll {
+            self.scroll = start;
+        } else if end > self.scroll + height {
+            self.scroll = end - height;
+        }
+    }
+
+    /// Copy the selected lines of the open entry, without styling, and stop selecting
+    fn copy_selection(&mut self) {
+        self.input_mode = InputMode::Viewing;
+        let (Some(entry), Some(selection)) = (&self.entry, self.selection.take()) else {
+            return;
+        };
+        let lines = selection.lines();
+        let count = lines.len();
+        match clipboard::copy(&entry.document.plain_lines(lines)) {
+            Ok(()) => self.notify(
+                Severity::Success,
+                format!("Copied {count} line{}", if count == 1 { "" } else { "s" }),
+            ),
+            Err(e) => self.notify(
+                Severity::Error,
+                format!("Could not copy to the clipboard: {e}"),
+            ),
+        }
+    }
+
+    /// Copy the open entry's address to the clipboard
+    fn copy_entry(&mut self, format: ShareFormat) {
+        self.input_mode = InputMode::Viewing;
+        let Some(entry) = &self.entry else {
+            return;
+        };
+        let text = format.format(&entry.slug, &entry.path, &entry.title);
+        match clipboard::copy(&text) {
+            Ok(()) => self.notify(
+                Severity::Success,
+                format!("Copied {}: {text}", format.label()),
+            ),
+            Err(e) => self.notify(
+                Severity::Error,
+                format!("Could not copy to the clipboard: {e}"),
+            ),
+        }
+    }
+
+    /// Copy the search query to the clipboard, e.g. to search the web for it too
+    fn copy_query(&mut self) {
+        if self.input.is_empty() {
+            self.notify(Severity::Info, "There is no search query to copy");
+            return;
+        }
+        match clipboard::copy(&self.input) {
+            Ok(()) => self.notify(Severity::Success, format!("Copied {}", self.input)),
+            Err(e) => self.notify(
+                Severity::Error,
+                format!("Could not copy to the clipboard: {e}"),
+            ),
+        }
+    }
+
+    /// Copy the results listed for the query, headed by the query and the doc sets searched
+    fn copy_results(&mut self, format: ListFormat) {
+        if self.results.is_empty() {
+            self.notify(Severity::Info, "There are no results to copy");
+            return;
+        }
+        let scope = self.active_scope().unwrap_or_default();
+        let list = format.format(&self.input, &scope, &self.results);
+        match clipboard::copy(&list) {
+            Ok(()) => self.notify(
+                Severity::Success,
+                format!("Copied {} results", self.results.len()),
+            ),
+            Err(e) => self.notify(
+                Severity::Error,
+                format!("Could not copy to the clipboard: {e}"),
+            ),
+        }
+    }
+
+    /// Read the open entry in the pager, suspending the TUI until the pager exits
+    ///
+    /// Without a pager, the entry simply stays open in the built-in viewer.
+    fn page_entry(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        self.page_requested = false;
+        let (Some(entry), Some(command)) = (&self.entry, pager_command()) else {
+            return Ok(());
+        };
+
+        let text = entry.document.ansi_text();
+        let result = self.suspended(terminal, || run_pager(&command, &text))?;
+        if let Err(e) = result {
+            log::warn!("Could not run the pager '{}': {e}", command.join(" "));
+        }
+        Ok(())
+    }
+
+    /// Open the code block at the top of the viewer, or the next one below it, in `$EDITOR`
+    fn edit_code_block(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        self.edit_requested = false;
+        let Some(entry) = &self.entry else {
+            return Ok(());
+        };
+        let Some(block) = entry.document.code_block_at(self.top_line()).cloned() else {
+            self.notify(Severity::Warning, "No code block here or below");
+            return Ok(());
+        };
+        let Some(command) = editor_command() else {
+            self.notify(
+                Severity::Warning,
+                "Set $EDITOR to open code blocks in an editor",
+            );
+            return Ok(());
+        };
+
+        let extension = extension_for(block.language.as_deref(), &self.config.editor_extensions);
+        let result = self.suspended(terminal, || {
+            edit_temporary(&command, &block.code, extension)
+        })?;
+        if let Err(e) = result {
+            self.notify(
+                Severity::Error,
+                format!("Could not run '{}': {e}", command.join(" ")),
+            );
+        }
+        Ok(())
+    }
+
+    /// Hand the terminal to another program while `run` runs, then take it back
+    fn suspended<T>(&self, terminal: &mut DefaultTerminal, run: impl FnOnce() -> T) -> Result<T> {
+        restore();
+        let result = run();
+        enable_raw_mode()?;
+        execute!(std::io::stdout(), EnterAlternateScreen)?;
+        if self.config.mouse {
+            execute!(std::io::stdout(), EnableMouseCapture)?;
+        }
+        terminal.clear()?;
+        Ok(result)
+    }
+
+    /// Start downloading the doc set the sync prompt is for in the background
+    fn start_sync(&mut self) {
+        let Some(prompt) = self.sync_prompt.as_mut() else {
+            return;
+        };
+        if prompt.task.is_some() {
+            return;
+        }
+        prompt.error = None;
+        prompt.cancelled = false;
+        if self.config.offline {
+            prompt.error = Some("offline mode is on".to_owned());
+            return;
+        }
+
+        // Agreeing to an oversized download lifts the cap for it
+        let limit = match prompt.oversized.take() {
+            Some(_) => None,
+            None => self.config.max_download_bytes(),
+        };
+        let (sender, receiver) = oneshot::channel();
+        let storage = self.storage.clone();
+        let http = self.http.clone();
+        let slug = prompt.slug.clone();
+        let full_text_index = self.config.full_text_index;
+        let handle = tokio::spawn(async move {
+            let result = docset_manager::sync_doc(&storage, &http, &slug, limit)
+                .await
+                .map_err(|e| match e.downcast::<DownloadTooLarge>() {
+                    Ok(too_large) => SyncError::TooLarge(*too_large),
+                    Err(e) => SyncError::Failed(e.to_string()),
+                });
+            // Without its full-text index the doc set is still usable, only not by content
+            if full_text_index && result.is_ok() {
+                if let Err(e) = docset_manager::index_full_text(&storage, &slug) {
+                    log::warn!("Could not build the full-text index of {slug}: {e}");
+                }
+            }
+            let _ = sender.send(result);
+        });
+        prompt.task = Some(SyncTask {
+            handle,
+            result: receiver,
+        });
+    }
+
+    /// Abort the download in progress, leaving any installed version of the doc set as it was
+    fn cancel_sync(&mut self) {
+        let Some(prompt) = self.sync_prompt.as_mut() else {
+            return;
+        };
+        if let Some(task) = prompt.task.take() {
+            task.handle.abort();
+            prompt.cancelled = true;
+        }
+    }
+
+    /// Pick up the result of the download once it is done, returning whether it was
+    ///
+    /// A finished download closes the prompt and opens its link if it has one.
+    fn poll_sync(&mut self) -> bool {
+        let Some(prompt) = self.sync_prompt.as_mut() else {
+            return false;
+        };
+        let Some(task) = prompt.task.as_mut() else {
+            return false;
+        };
+        let result = match task.result.try_recv() {
+            Ok(result) => result,
+            Err(oneshot::error::TryRecvError::Empty) => return false,
+            Err(oneshot::error::TryRecvError::Closed) => Err(SyncError::Failed(
+                "the download stopped unexpectedly".to_owned(),
+            )),
+        };
+        prompt.task = None;
+        let changes = match result {
+            Ok(changes) => changes,
+            Err(SyncError::TooLarge(too_large)) => {
+                prompt.oversized = Some(too_large);
+                return true;
+            }
+            Err(SyncError::Failed(e)) => {
+                prompt.error = Some(e);
+                return true;
+            }
+        };
+
+        let slug = prompt.slug.clone();
+        self.forget_documents(&slug);
+        self.updates.retain(|update| update.installed.slug != slug);
+        self.reload_doc_sets();
+        self.offer_reload(&slug);
+        self.close_sync_prompt();
+        if let Some(changes) = changes {
+            self.notify(
+                Severity::Success,
+                format!("Updated {slug}: {}", changes.summary()),
+            );
+        }
+        true
+    }
+
+    /// Return from the sync prompt to where it was opened from, opening its link if it has one
+    fn close_sync_prompt(&mut self) {
+        match self.sync_prompt.take().and_then(|prompt| prompt.link) {
+            Some(link) => {
+                self.input_mode = InputMode::Normal;
+                self.open_link(link);
+            }
+            None if self.manager.is_some() => {
+                self.refresh_manager();
+                self.input_mode = InputMode::Manage;
+            }
+            None => self.input_mode = InputMode::Normal,
+        }
+    }
+
+    /// Look for doc set updates without holding up the UI, if the config allows it
+    ///
+    /// Offline, only the cached registry is consulted.
+    fn start_update_check(&mut self) {
+        if !self.config.auto_check_updates {
+            return;
+        }
+        if self.config.offline {
+            let Some(registry) = docset_manager::cached_registry(&self.storage) else {
+                return;
+            };
+            let installed: Vec<Manifest> = self.manifests.values().cloned().collect();
+            self.updates = docset_manager::outdated_in(&registry.entries, &installed);
+            return;
+        }
+
+        let (sender, receiver) = oneshot::channel();
+        let storage = self.storage.clone();
+        let http = self.http.clone();
+        tokio::spawn(async move {
+            let result = docset_manager::outdated_doc_sets(&storage, &http)
+                .await
+                .map_err(|e| e.to_string());
+            let _ = sender.send(result);
+        });
+        self.update_check = Some(receiver);
+    }
+
+    /// Sync doc sets in the background after launch, as `sync_on_startup` asks, reporting on
+    /// them through [`StartupSync`] so the UI never waits on them
+    fn start_startup_sync(&mut self) {
+        let mode = self.config.sync_on_startup;
+        if mode == SyncOnStartup::Never {
+            return;
+        }
+        if self.config.offline {
+            log::info!("Not syncing doc sets at launch, offline mode is on");
+            return;
+        }
+
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let storage = self.storage.clone();
+        let http = self.http.clone();
+        let limit = self.config.max_download_bytes();
+        let full_text_index = self.config.full_text_index;
+        let concurrency = self.config.max_connections;
+        tokio::spawn(async move {
+            let slugs: Vec<String> = match mode {
+                SyncOnStartup::Never => return,
+                SyncOnStartup::IfStale => {
+                    match docset_manager::outdated_doc_sets(&storage, &http).await {
+                        Ok(outdated) => outdated
+                            .into_iter()
+                            .map(|outdated| outdated.installed.slug)
+                            .collect(),
+                        Err(e) => {
+                            let _ = sender.send(StartupSync::Unavailable(e.to_string()));
+                            return;
+                        }
+                    }
+                }
+                // Doc sets added from a local directory have nowhere to be synced from
+                SyncOnStartup::Always => docset_manager::installed_doc_sets(&storage)
+                    .into_iter()
+                    .filter(|slug| {
+                        !docset_manager::read_manifest(&storage, slug)
+                            .is_some_and(|manifest| manifest.local)
+                    })
+                    .collect(),
+            };
+            let _ = sender.send(StartupSync::Started(slugs.clone()));
+            let synced = |report: &SyncReport| {
+                let slug = &report.slug;
+                let progress = match &report.result {
+                    Ok(changes) => {
+                        if full_text_index {
+                            if let Err(e) = docset_manager::index_full_text(&storage, slug) {
+                                log::warn!("Could not build the full-text index of {slug}: {e}");
+                            }
+                        }
+                        StartupSync::Synced(slug.clone(), changes.clone())
+                    }
+                    Err(e) => StartupSync::Failed(slug.clone(), e.to_string()),
+                };
+                let _ = sender.send(progress);
+            };
+            let summary =
+                docset_manager::sync_many(&storage, &http, &slugs, limit, concurrency, synced)
+                    .await;
+            let _ = sender.send(StartupSync::Finished(summary));
+        });
+        self.startup_sync = Some(receiver);
+    }
+
+    /// Rebuild the index in the background if the doc sets changed since it was built, e.g.
+    /// because their files were changed or removed by hand
+    fn start_index_check(&mut self) {
+        let stale = match docset_manager::check_index(&self.storage) {
+            Ok(stale) if stale.is_empty() => return,
+            Ok(stale) => stale,
+            Err(e) => {
+                log::warn!("Could not check whether the index is up to date: {e}");
+                return;
+            }
+        };
+        log::info!(
+            "Rebuilding the index, changed: {:?}, removed: {:?}",
+            stale.changed,
+            stale.removed
+        );
+        self.notify(Severity::Info, "Rebuilding the index…");
+
+        let (sender, receiver) = oneshot::channel();
+        let storage = self.storage.clone();
+        let full_text_index = self.config.full_text_index;
+        tokio::task::spawn_blocking(move || {
+            let mut failed = Vec::new();
+            for slug in &stale.changed {
+                if let Err(e) = docset_manager::rebuild_index(&storage, slug, full_text_index) {
+                    log::warn!("Could not rebuild the index of {slug}: {e}");
+                    failed.push(slug.clone());
+                }
+            }
+            if let Err(e) = docset_manager::forget_indexed(&storage, &stale.removed) {
+                log::warn!("Could not drop removed doc sets from the record of the index: {e}");
+            }
+            let _ = sender.send(failed);
+        });
+        self.index_rebuild = Some(receiver);
+    }
+
+    /// Search the rebuilt index once the rebuild after launch finished, returning whether it
+    /// did
+    fn poll_index_rebuild(&mut self) -> bool {
+        let Some(receiver) = &mut self.index_rebuild else {
+            return false;
+        };
+        let failed = match receiver.try_recv() {
+            Ok(failed) => failed,
+            Err(oneshot::error::TryRecvError::Empty) => return false,
+            Err(oneshot::error::TryRecvError::Closed) => Vec::new(),
+        };
+        self.index_rebuild = None;
+        // Content changed by hand may be cached as it was
+        self.documents.clear();
+        self.reload_doc_sets();
+        self.update_results();
+        match failed.as_slice() {
+            [] => self.notify(Severity::Success, "Rebuilt the index"),
+            failed => self.notify(
+                Severity::Warning,
+                format!("Could not rebuild the index of {}", failed.join(", ")),
+            ),
+        }
+        true
+    }
+
+    /// Pick up how far the sync after launch got, returning whether it got any further
+    fn poll_startup_sync(&mut self) -> bool {
+        let Some(receiver) = &mut self.startup_sync else {
+            return false;
+        };
+        let mut progress = Vec::new();
+        let finished = loop {
+            match receiver.try_recv() {
+                Ok(step) => progress.push(step),
+                Err(mpsc::error::TryRecvError::Empty) => break false,
+                Err(mpsc::error::TryRecvError::Disconnected) => break true,
+            }
+        };
+        if finished {
+            self.startup_sync = None;
+        }
+        let changed = !progress.is_empty();
+        for step in progress {
+            match step {
+                StartupSync::Started(slugs) => match slugs.as_slice() {
+                    [] => {}
+                    [slug] => self.notify(Severity::Info, format!("Syncing {slug}…")),
+                    slugs => self.notify(
+                        Severity::Info,
+                        format!("Syncing {} doc sets in the background…", slugs.len()),
+                    ),
+                },
+                StartupSync::Synced(slug, changes) => {
+                    self.forget_documents(&slug);
+                    self.updates.retain(|update| update.installed.slug != slug);
+                    self.reload_doc_sets();
+                    self.offer_reload(&slug);
+                    if self.manager.is_some() {
+                        self.refresh_manager();
+                    }
+                    let message = match changes {
+                        Some(changes) => format!("Synced {slug}: {}", changes.summary()),
+                        None => format!("Synced {slug}"),
+                    };
+                    self.notify(Severity::Success, message);
+                }
+                StartupSync::Failed(slug, e) => {
+                    self.notify(Severity::Error, format!("Could not sync {slug}: {e}"))
+                }
+                // A single doc set's toast already says all there is to say
+                StartupSync::Finished(summary) if summary.reports.len() > 1 => {
+                    let severity = if summary.failed() == 0 {
+                        Severity::Success
+                    } else {
+                        Severity::Warning
+                    };
+                    self.notify(severity, summary.summary());
+                }
+                StartupSync::Finished(_) => {}
+                StartupSync::Unavailable(e) => self.notify(
+                    Severity::Warning,
+                    format!("Could not check which doc sets to sync: {e}"),
+                ),
+            }
+        }
+        changed
+    }
+
+    /// Pick up the result of the update check once it is done, returning whether it was
+    fn poll_update_check(&mut self) -> bool {
+        let Some(receiver) = &mut self.update_check else {
+            return false;
+        };
+        let result = match receiver.try_recv() {
+            Ok(result) => result,
+            Err(oneshot::error::TryRecvError::Empty) => return false,
+            Err(oneshot::error::TryRecvError::Closed) => Err("the check was cancelled".into()),
+        };
+        self.update_check = None;
+        match result {
+            Ok(updates) => self.updates = updates,
+            Err(e) => log::warn!("Could not check for doc set updates: {e}"),
+        }
+        if self.manager.is_some() {
+            self.refresh_manager();
+        }
+        true
+    }
+
+    /// Show the doc sets devdocs offers, as far as the cached registry knows them
+    fn open_manager(&mut self) {
+        self.registry = docset_manager::cached_registry(&self.storage);
+        self.manager = Some(Manager::default());
+        self.refresh_manager();
+        self.input_mode = InputMode::Manage;
+    }
+
+    /// Rebuild the doc set list from the registry and what is installed
+    fn refresh_manager(&mut self) {
+        let registry = self
+            .registry
+            .as_ref()
+            .map_or(&[][..], |registry| &registry.entries);
+        let mut rows: Vec<ManagerRow> = registry
+            .iter()
+            .map(|entry| ManagerRow {
+                slug: entry.slug.clone(),
+                name: entry.name.clone(),
+                version: if entry.release.is_empty() {
+                    entry.version.clone()
+                } else {
+                    entry.release.clone()
+                },
+                installed: self.installed.contains(&entry.slug),
+                db_size: entry.db_size,
+                disk_size: None,
+                about: match self.manifests.get(&entry.slug) {
+                    Some(manifest) if entry.about.is_empty() => manifest.about.clone(),
+                    _ => entry.about.clone(),
+                },
+            })
+            .collect();
+        // Doc sets added from a local directory, or installed before the registry was cached
+        for slug in &self.installed {
+            if !rows.iter().any(|row| row.slug == *slug) {
+                let manifest = self.manifests.get(slug);
+                rows.push(ManagerRow {
+                    slug: slug.clone(),
+                    name: manifest.map_or_else(|| slug.clone(), |m| m.name.clone()),
+                    version: manifest
+                        .map(Manifest::display_version)
+                        .unwrap_or_default()
+                        .to_owned(),
+                    installed: true,
+                    db_size: 0,
+                    disk_size: None,
+                    about: manifest.map(|m| m.about.clone()).unwrap_or_default(),
+                });
+            }
+        }
+        for row in rows.iter_mut().filter(|row| row.installed) {
+            row.disk_size = docset_manager::doc_set_size(&self.storage, &row.slug).ok();
+        }
+
+        let order = &self.config.doc_set_order;
+        let rank = |row: &ManagerRow| {
+            if !row.installed {
+                return usize::MAX;
+            }
+            order
+                .iter()
+                .position(|scope| in_scope(&row.slug, scope))
+                .unwrap_or(order.len())
+        };
+        let outdated = |row: &ManagerRow| {
+            self.updates
+                .iter()
+                .any(|update| update.installed.slug == row.slug)
+        };
+        // Ties fall back to the name and then the slug, which are unique
+        match self.config.manager_sort {
+            ManagerSort::Installed => {
+                rows.sort_by_cached_key(|row| (rank(row), row.name.clone(), row.slug.clone()))
+            }
+            ManagerSort::Name => {
+                rows.sort_by_cached_key(|row| (row.name.to_lowercase(), row.slug.clone()))
+            }
+            ManagerSort::Size => rows.sort_by_cached_key(|row| {
+                (
+                    !row.installed,
+                    Reverse(row.disk_size.unwrap_or(row.db_size)),
+                    row.name.clone(),
+                    row.slug.clone(),
+                )
+            }),
+            ManagerSort::Updates => rows.sort_by_cached_key(|row| {
+                (
+                    !outdated(row),
+                    !row.installed,
+                    row.name.clone(),
+                    row.slug.clone(),
+                )
+            }),
+        }
+
+        // Recommend what past searches and viewed entries suggest is missing
+        let hints = self
+            .search_history
+            .queries()
+            .iter()
+            .map(String::as_str)
+            .chain(
+                self.recent
+                    .entries()
+                    .iter()
+                    .map(|entry| doc_family(&entry.slug)),
+            );
+        let recommended = docset_manager::recommended(registry, &self.installed, hints);
+
+        if let Some(manager) = &mut self.manager {
+            manager.selected = manager.selected.min(rows.len().saturating_sub(1));
+            manager.rows = rows;
+            manager.recommended = recommended;
+            manager.available = registry.len();
+        }
+    }
+
+    /// Fetch the registry again, e.g. to see newly added doc sets
+    fn refresh_registry(&mut self) {
+        if self.config.offline {
+            self.notify(
+                Severity::Warning,
+                "Offline mode is on, using the cached list",
+            );
+            return;
+        }
+        let result = tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current()
+                .block_on(docset_manager::fetch_registry_or_cached(
+                    &self.storage,
+                    &self.http,
+                ))
+                .map_err(|e| e.to_string())
+        });
+        match result {
+            Ok(RegistryFetch::Fetched(_)) => {
+                self.registry = docset_manager::cached_registry(&self.storage);
+                self.refresh_manager();
+            }
+            Ok(RegistryFetch::Cached { registry, error }) => {
+                self.registry = Some(registry);
+                self.refresh_manager();
+                self.notify(
+                    Severity::Warning,
+                    format!("{error}, showing the cached list"),
+                );
+            }
+            Err(e) => {
+                self.notify(
+                    Severity::Error,
+                    format!("Could not fetch the registry: {e}"),
+                );
+            }
+        }
+    }
+
+    fn select_doc_set(&mut self, forward: bool) {
+        if let Some(manager) = &mut self.manager {
+            manager.selected = if forward {
+                (manager.selected + 1).min(manager.rows.len().saturating_sub(1))
+            } else {
+                manager.selected.saturating_sub(1)
+            };
+        }
+    }
+
+    /// Move the selected installed doc set up or down in search priority
+    fn move_doc_set(&mut self, up: bool) {
+        let Some(manager) = &self.manager else {
+            return;
+        };
+        if self.config.manager_sort != ManagerSort::Installed {
+            self.notify(
+                Severity::Info,
+                "Doc sets can only be reordered while sorted installed first",
+            );
+            return;
+        }
+        // Installed doc sets are listed first, in priority order
+        let order: Vec<String> = manager
+            .rows
+            .iter()
+            .take_while(|row| row.installed)
+            .map(|row| row.slug.clone())
+            .collect();
+        let selected = manager.selected;
+        let target = if up {
+            selected.checked_sub(1)
+        } else {
+            Some(selected + 1)
+        };
+        let Some(target) = target.filter(|&target| selected < order.len() && target < order.len())
+        else {
+            return;
+        };
+
+        let mut order = order;
+        order.swap(selected, target);
+        let literal = format!(
+            "[{}]",
+            order
+                .iter()
+                .map(|slug| toml::quote(slug))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        self.config.doc_set_order = order;
+        self.searches.clear();
+        self.save_setting("doc_set_order", &literal);
+        self.refresh_manager();
+        if let Some(manager) = &mut self.manager {
+            manager.selected = target;
+        }
+        self.update_results();
+    }
+
+    /// Ask whether to go ahead with an action that loses data, or go ahead right away if the
+    /// config turned confirmations off
+    fn confirm_then(&mut self, action: Destructive) {
+        if self.config.confirm_destructive {
+            self.confirmation = Some(action);
+        } else {
+            self.run_destructive(action);
+        }
+    }
+
+    fn run_destructive(&mut self, action: Destructive) {
+        match action {
+            Destructive::RemoveDocSet(slug) => {
+                if let Err(e) = docset_manager::remove_doc_set(&self.storage, &slug) {
+                    self.notify(Severity::Error, format!("Could not remove {slug}: {e}"));
+                    return;
+                }
+                self.forget_documents(&slug);
+                self.updates.retain(|update| update.installed.slug != slug);
+                if self.entry.as_ref().is_some_and(|entry| entry.slug == slug) {
+                    self.entry = None;
+                }
+                if self
+                    .browser
+                    .as_ref()
+                    .is_some_and(|browser| browser.slug == slug)
+                {
+                    self.browser = None;
+                }
+                self.reload_doc_sets();
+                if self.manager.is_some() {
+                    self.refresh_manager();
+                }
+                self.notify(Severity::Success, format!("Removed {slug}"));
+            }
+            Destructive::ClearRecent => {
+                self.recent.clear();
+                if let Err(e) = self.recent.save(&self.storage.recent_path()) {
+                    self.notify(Severity::Error, format!("Could not clear the history: {e}"));
+                    return;
+                }
+                self.home_selected = 0;
+                self.seen = seen_entries(&self.recent, &self.stats);
+                self.notify(Severity::Success, "Cleared the recently viewed entries");
+            }
+            Destructive::ClearStats => {
+                self.stats.clear();
+                if let Err(e) = self.stats.save(&self.storage.stats_path()) {
+                    self.notify(Severity::Error, format!("Could not clear the stats: {e}"));
+                    return;
+                }
+                self.seen = seen_entries(&self.recent, &self.stats);
+                self.notify(Severity::Success, "Cleared the usage stats");
+            }
+        }
+    }
+
+    /// List the doc sets in the next order, keeping the selected one selected
+    fn cycle_manager_sort(&mut self) {
+        let sort = self.config.manager_sort.next();
+        self.config.manager_sort = sort;
+        let selected = self
+            .manager
+            .as_ref()
+            .and_then(|manager| manager.rows.get(manager.selected))
+            .map(|row| row.slug.clone());
+        self.refresh_manager();
+        if let Some(manager) = self.manager.as_mut() {
+            manager.selected = selected
+                .and_then(|slug| manager.rows.iter().position(|row| row.slug == slug))
+                .unwrap_or_default();
+        }
+        self.save_setting("manager_sort", &toml::quote(sort.name()));
+    }
+
+    /// Show or hide where the selected doc set comes from
+    fn toggle_about(&mut self) {
+        if let Some(manager) = self.manager.as_mut() {
+            manager.about_open = !manager.about_open && !manager.rows.is_empty();
+        }
+    }
+
+    /// Open the upstream source of the selected doc set in the browser
+    fn open_selected_source(&mut self) {
+        let Some(row) = self
+            .manager
+            .as_ref()
+            .and_then(|manager| manager.rows.get(manager.selected))
+        else {
+            return;
+        };
+        let Some(url) = row.about.source_url().map(str::to_owned) else {
+            self.notify(
+                Severity::Warning,
+                format!("{} doesn't link to its source", row.slug),
+            );
+            return;
+        };
+        match open_url(&url) {
+            Ok(()) => self.notify(Severity::Info, format!("Opened {url}")),
+            Err(e) => self.notify(Severity::Error, format!("Could not open {url}: {e}")),
+        }
+    }
+
+    /// Offer to remove the selected doc set, if it is installed
+    fn remove_selected_doc_set(&mut self) {
+        let Some(row) = self
+            .manager
+            .as_ref()
+            .and_then(|manager| manager.rows.get(manager.selected))
+        else {
+            return;
+        };
+        if !row.installed {
+            self.notify(Severity::Warning, format!("{} is not installed", row.slug));
+            return;
+        }
+        let slug = row.slug.clone();
+        self.confirm_then(Destructive::RemoveDocSet(slug));
+    }
+
+    /// Offer to install the selected doc set, or to update it if it is installed
+    fn sync_selected_doc_set(&mut self) {
+        let Some(row) = self
+            .manager
+            .as_ref()
+            .and_then(|manager| manager.rows.get(manager.selected))
+        else {
+            return;
+        };
+        if self
+            .manifests
+            .get(&row.slug)
+            .is_some_and(|manifest| manifest.local)
+        {
+            self.notify(
+                Severity::Info,
+                format!(
+                    "{} is a local doc set, update it with docs add-local",
+                    row.slug
+                ),
+            );
+            return;
+        }
+        self.sync_prompt = Some(SyncPrompt {
+            slug: row.slug.clone(),
+            link: None,
+            task: None,
+            cancelled: false,
+            error: None,
+            oversized: None,
+            reason: if row.installed {
+                SyncReason::Update
+            } else {
+                SyncReason::NotInstalled
+            },
+        });
+        self.input_mode = InputMode::ConfirmSync;
+    }
+
+    /// List the types of a doc set's entries to pick from
+    fn open_browser(&mut self, slug: &str) {
+        let types = self.index.types_of(slug).to_vec();
+        if types.is_empty() {
+            self.notify(
+                Severity::Warning,
+                format!("{slug} doesn't sort its entries by type"),
+            );
+            return;
+        }
+        self.browser = Some(Browser {
+            slug: slug.to_owned(),
+            types,
+            selected_type: 0,
+            entries: None,
+            selected: 0,
+        });
+        self.input_mode = InputMode::Browse;
+    }
+
+    /// Browse the doc set of the selected result
+    fn browse_selected(&mut self) {
+        if let Some(slug) = self
+            .selected_result()
+            .map(|result| result.entry.slug.clone())
+        {
+            self.open_browser(&slug);
+        }
+    }
+
+    /// Browse the selected doc set of the management view, if it is installed
+    fn browse_selected_doc_set(&mut self) {
+        let Some(row) = self
+            .manager
+            .as_ref()
+            .and_then(|manager| manager.rows.get(manager.selected))
+        else {
+            return;
+        };
+        if row.installed {
+            let slug = row.slug.clone();
+            self.open_browser(&slug);
+        } else {
+            self.notify(Severity::Warning, format!("{} is not installed", row.slug));
+        }
+    }
+
+    fn browse_select(&mut self, forward: bool) {
+        let Some(browser) = self.browser.as_mut() else {
+            return;
+        };
+        let (selected, len) = match &browser.entries {
+            Some(entries) => (&mut browser.selected, entries.len()),
+            None => (&mut browser.selected_type, browser.types.len()),
+        };
+        if forward {
+            if *selected + 1 < len {
+                *selected += 1;
+            }
+        } else {
+            *selected = selected.saturating_sub(1);
+        }
+    }
+
+    /// Drill into the selected type, or open the selected entry
+    fn browse_open(&mut self) {
+        let Some(browser) = self.browser.as_mut() else {
+            return;
+        };
+        match &browser.entries {
+            Some(entries) => {
+                if let Some(entry) = entries.get(browser.selected).map(|r| r.entry.clone()) {
+                    self.open_entry(&entry.slug, &entry.path, &entry.name, self.open_action());
+                }
+            }
+            None => {
+                let Some(doc_type) = browser.types.get(browser.selected_type) else {
+                    return;
+                };
+                browser.entries = Some(
+                    self.index
+                        .entries_of(&browser.slug)
+                        .filter(|entry| entry.type_name == doc_type.name)
+                        .map(|entry| SearchResult {
+                            entry: entry.clone(),
+                            score: 0,
+                            other_versions: Vec::new(),
+                            snippet: None,
+                        })
+                        .collect(),
+                );
+                browser.selected = 0;
+            }
+        }
+    }
+
+    /// Go back from a type's entries to the types, or leave browsing
+    fn browse_back(&mut self) {
+        match self.browser.as_mut() {
+            Some(browser) if browser.entries.is_some() => browser.entries = None,
+            _ => {
+                self.browser = None;
+                self.input_mode = if self.manager.is_some() {
+                    InputMode::Manage
+                } else {
+                    InputMode::Normal
+                };
+            }
+        }
+    }
+
+    /// Rows of the list shown in place of results while the search box is empty, 0 when the
+    /// banner shows instead
+    fn home_rows(&self) -> usize {
+        if !self.input.is_empty() {
+            return 0;
+        }
+        match self.config.empty_query_view {
+            EmptyQueryView::Banner => 0,
+            EmptyQueryView::Recent => self.recent.entries().len(),
+            EmptyQueryView::Browse => self.installed.len(),
+        }
+    }
+
+    fn select_home_row(&mut self, forward: bool) {
+        let last = self.home_rows().saturating_sub(1);
+        self.home_selected = if forward {
+            (self.home_selected + 1).min(last)
+        } else {
+            self.home_selected.saturating_sub(1).min(last)
+        };
+    }
+
+    /// Open the recent entry or browse the doc set selected while the search box is empty
+    fn open_home_row(&mut self) {
+        let row = self.home_selected.min(self.home_rows().saturating_sub(1));
+        match self.config.empty_query_view {
+            EmptyQueryView::Banner => {}
+            EmptyQueryView::Recent => self.open_recent(row),
+            EmptyQueryView::Browse => {
+                if let Some(slug) = self.installed.get(row).cloned() {
+                    self.open_browser(&slug);
+                }
+            }
+        }
+    }
+
+    /// Reopen one of the entries listed on the empty search screen
+    fn open_recent(&mut self, index: usize) {
+        if let Some(entry) = self.recent.entries().get(index).cloned() {
+            self.open_entry(&entry.slug, &entry.path, &entry.title, self.open_action());
+        }
+    }
+
+    /// Scroll the viewer by some lines, negative going up, without going past either end
+    ///
+    /// With smooth scrolling, jumps of more than a line are animated by `on_tick`.
+    fn scroll_by(&mut self, lines: i32) {
+        let max = self
+            .entry
+            .as_ref()
+            .map_or(0, |entry| self.rows_before(entry.lines()).saturating_sub(1));
+        let from = self
+            .scroll_animation
+            .map_or(self.scroll, |animation| animation.target);
+        let target = (i32::from(from) + lines).clamp(0, i32::from(max)) as u16;
+
+        if self.config.smooth_scroll && !self.config.reduced_motion && lines.abs() > 1 {
+            let step = target
+                .abs_diff(self.scroll)
+                .div_ceil(SMOOTH_SCROLL_TICKS)
+                .max(1);
+            self.scroll_animation = Some(ScrollAnimation { target, step });
+        } else {
+            self.scroll = target;
+            self.scroll_animation = None;
+        }
+    }
+
+    /// Scroll unwrapped lines sideways by some columns, negative going left, as far as the
+    /// longest line needs
+    fn scroll_sideways(&mut self, columns: i32) {
+        if self.config.wrap {
+            return;
+        }
+        let longest = self.entry.as_ref().map_or(0, |entry| {
+            entry
+                .lines()
+                .iter()
+                .map(Line::width)
+                .max()
+                .unwrap_or_default()
+        });
+        let max = longest.saturating_sub(self.viewer_width()) as i32;
+        self.hscroll = (i32::from(self.hscroll) + columns).clamp(0, max.max(0)) as u16;
+    }
+
+    /// Switch between wrapping long lines and scrolling sideways, keeping the same line at
+    /// the top of the viewer
+    fn toggle_wrap(&mut self) {
+        let top = self.top_line();
+        self.config.wrap = !self.config.wrap;
+        self.hscroll = 0;
+        self.scroll_animation = None;
+        if let Some(entry) = &self.entry {
+            let first_line = entry.first_line();
+            self.scroll = self.rows_before(&entry.lines()[..top - first_line]);
+        }
+        self.save_setting("wrap", &self.config.wrap.to_string());
+    }
+
+    /// Start auto-scrolling the open entry, or pause or resume it
+    fn toggle_auto_scroll(&mut self) {
+        match self.auto_scroll.as_mut() {
+            Some(auto_scroll) if !auto_scroll.paused => auto_scroll.paused = true,
+            _ => {
+                self.auto_scroll = Some(AutoScroll {
+                    since: self.ticks,
+                    paused: false,
+                })
+            }
+        }
+    }
+
+    /// Make auto-scrolling wait longer or less long before each line
+    fn change_auto_scroll_interval(&mut self, slower: bool) {
+        let interval = self.config.auto_scroll_interval;
+        self.config.auto_scroll_interval = if slower {
+            interval + AUTO_SCROLL_STEP
+        } else {
+            interval
+                .saturating_sub(AUTO_SCROLL_STEP)
+                .max(AUTO_SCROLL_STEP)
+        };
+        let millis = self.config.auto_scroll_interval.as_millis();
+        self.notify(
+            Severity::Info,
+            format!(
+                "Auto-scrolling a line every {:.2} s",
+                millis as f64 / 1000.0
+            ),
+        );
+        self.save_setting("auto_scroll_interval_ms", &millis.to_string());
+    }
+
+    /// Ticks auto-scrolling waits before each line, at least one
+    fn auto_scroll_ticks(&self) -> u64 {
+        let tick = self.config.tick_rate.as_millis().max(1);
+        (self.config.auto_scroll_interval.as_millis().div_ceil(tick) as u64).max(1)
+    }
+
+    /// Scroll a line once auto-scrolling has waited long enough, stopping once the last line
+    /// shows, and return whether it scrolled
+    fn auto_scroll_step(&mut self) -> bool {
+        let Some(auto_scroll) = self.auto_scroll.filter(|auto_scroll| !auto_scroll.paused) else {
+            return false;
+        };
+        if !matches!(self.input_mode, InputMode::Viewing)
+            || self.ticks.wrapping_sub(auto_scroll.since) < self.auto_scroll_ticks()
+        {
+            return false;
+        }
+        let rows = self
+            .entry
+            .as_ref()
+            .map_or(0, |entry| self.rows_before(entry.lines()));
+        if self.scroll + self.viewer_height() >= rows {
+            self.auto_scroll = None;
+            self.notify(
+                Severity::Info,
+                "Auto-scrolling reached the end of the entry",
+            );
+            return true;
+        }
+        self.scroll_by(1);
+        self.auto_scroll = Some(AutoScroll {
+            since: self.ticks,
+            paused: false,
+        });
+        true
+    }
+
+    /// Move one tick's worth towards the target of a smooth scroll
+    fn animate_scroll(&mut self) -> bool {
+        let Some(animation) = self.scroll_animation else {
+            return false;
+        };
+        self.scroll = if self.scroll < animation.target {
+            self.scroll
+                .saturating_add(animation.step)
+                .min(animation.target)
+        } else {
+            self.scroll
+                .saturating_sub(animation.step)
+                .max(animation.target)
+        };
+        if self.scroll == animation.target {
+            self.scroll_animation = None;
+        }
+        true
+    }
+
+    /// Wait for the next event, emitting a tick once `tick_rate` has passed since the last one
+    fn next_event(&self, last_tick: &mut Instant) -> Result<Event> {
+        loop {
+            let mut timeout = self.config.tick_rate.saturating_sub(last_tick.elapsed());
+            let searching = self.search_pending() && timeout > SEARCH_POLL_INTERVAL;
+            if searching {
+                timeout = SEARCH_POLL_INTERVAL;
+            }
+            if !event::poll(timeout)? {
+                if searching {
+                    return Ok(Event::SearchPoll);
+                }
+                *last_tick = Instant::now();
+                return Ok(Event::Tick);
+            }
+            match event::read()? {
+                TerminalEvent::Key(key) => return Ok(Event::Key(key)),
+                TerminalEvent::Mouse(mouse) => match mouse.kind {
+                    MouseEventKind::ScrollDown => return Ok(Event::Scroll(1)),
+                    MouseEventKind::ScrollUp => return Ok(Event::Scroll(-1)),
+                    _ => {}
+                },
+                TerminalEvent::Resize(_, _) => return Ok(Event::Resize),
+                _ => {}
+            }
+        }
+    }
+
+    /// Advance time-based state by one tick, returning whether anything visible changed
+    ///
+    /// Features that animate or wait on a timer hook in here, reading `self.ticks` as their
+    /// frame counter, and report whether they need a redraw. The event loop only redraws on
+    /// input, resizes, and ticks that changed something, so an idle app stays asleep.
+    fn on_tick(&mut self) -> bool {
+        self.ticks = self.ticks.wrapping_add(1);
+        let scrolled = self.animate_scroll() | self.auto_scroll_step();
+        let previewed = self.update_preview();
+        let synced = self.poll_sync();
+        let reloaded = self.poll_config();
+        let searched = self.poll_debounce(Instant::now()) | self.poll_search();
+        let startup_synced = self.poll_startup_sync();
+        let rebuilt = self.poll_index_rebuild();
+        let expired = self
+            .notifications
+            .expire(self.ticks, self.notification_ticks());
+        self.poll_update_check()
+            || synced
+            || reloaded
+            || searched
+            || startup_synced
+            || rebuilt
+            || expired
+            || scrolled
+            || previewed
+    }
+
+    fn run(mut self, mut terminal: DefaultTerminal) -> Result<()> {
+        let mut last_tick = Instant::now();
+        let mut redraw = true;
+        loop {
+            if redraw {
+                terminal.draw(|frame| self.draw(frame))?;
+            }
+            if self.page_requested {
+                self.page_entry(&mut terminal)?;
+                redraw = true;
+                continue;
+            }
+            if self.edit_requested {
+                self.edit_code_block(&mut terminal)?;
+                redraw = true;
+                continue;
+            }
+            redraw = match self.next_event(&mut last_tick)? {
+                Event::Key(key) => {
+                    if self.handle_key(key).is_break() {
+                        return Ok(());
+                    }
+                    // Follow the selection right away, so a stale preview never shows
+                    self.update_preview();
+                    true
+                }
+                Event::Scroll(steps) => {
+                    let scrolled = self.handle_scroll(steps);
+                    self.update_preview() || scrolled
+                }
+                Event::Resize => true,
+                Event::SearchPoll => self.poll_debounce(Instant::now()) | self.poll_search(),
+                Event::Tick => match self.expire_pending_keys() {
+                    ControlFlow::Break(()) => return Ok(()),
+                    ControlFlow::Continue(expired) => self.on_tick() || expired,
+                },
+            };
+        }
+    }
+
+    /// Scroll by steps of the mouse wheel, returning whether anything visible changed
+    fn handle_scroll(&mut self, steps: i32) -> bool {
+        match self.input_mode {
+            InputMode::Viewing => {
+                self.scroll_by(steps * i32::from(self.config.scroll_lines));
+                true
+            }
+            InputMode::Normal | InputMode::Searching if steps > 0 => {
+                self.select_next();
+                true
+            }
+            InputMode::Normal | InputMode::Searching => {
+                self.select_previous();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) -> ControlFlow<()> {
+        // Raw mode turns Ctrl-C into a key press instead of SIGINT
+        if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+            return ControlFlow::Break(());
+        }
+        if key.code == KeyCode::Char('x') && key.modifiers.contains(KeyModifiers::CONTROL) {
+            self.notifications.dismiss();
+            return ControlFlow::Continue(());
+        }
+        if let Some(action) = self.confirmation.take() {
+            match key.code {
+                KeyCode::Char('y') | KeyCode::Enter => self.run_destructive(action),
+                KeyCode::Char('n') | KeyCode::Esc | KeyCode::Char('q') => {}
+                _ => self.confirmation = Some(action),
+            }
+            return ControlFlow::Continue(());
+        }
+        if self.quick_look.is_some() {
+            if key.kind == KeyEventKind::Press {
+                self.quick_look_key(key.code);
+            }
+            return ControlFlow::Continue(());
+        }
+        if key.kind == KeyEventKind::Press
+            && !matches!(key.code, KeyCode::Char('a' | '+' | '=' | '-'))
+        {
+            // Any other key pauses auto-scrolling, which `a` resumes
+            if let Some(auto_scroll) = self.auto_scroll.as_mut() {
+                auto_scroll.paused = true;
+            }
+        }
+        if key.kind == KeyEventKind::Press {
+            if let KeyCode::Tab | KeyCode::BackTab = key.code {
+                if self.panels().len() > 1 {
+                    self.pending_keys = PendingKeys::default();
+                    self.cycle_focus(key.code == KeyCode::Tab);
+                    return ControlFlow::Continue(());
+                }
+            }
+            if self.side_focus.is_some() && self.side_panel_key(key) {
+                return ControlFlow::Continue(());
+            }
+        }
+
+        let keymap = match self.input_mode {
+            InputMode::Normal => Some(&self.keymaps.normal),
+            InputMode::Viewing => Some(&self.keymaps.viewer),
+            _ => None,
+        };
+        if let Some(keymap) = keymap.filter(|_| key.kind == KeyEventKind::Press) {
+            let modified = key
+                .modifiers
+                .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT);
+            match key.code {
+                KeyCode::Char(c) if !modified => {
+                    let resolution = self.pending_keys.push(keymap, c, Instant::now());
+                    return self.resolve_keys(resolution);
+                }
+                _ if !self.pending_keys.is_empty() => {
+                    let resolution = self.pending_keys.flush(keymap);
+                    self.resolve_keys(resolution)?;
+                }
+                _ => {}
+            }
+        }
+        self.dispatch_key(key)
+    }
+
+    /// Act on what the keys typed towards a sequence resolved to
+    fn resolve_keys(&mut self, resolution: Resolution<Action>) -> ControlFlow<()> {
+        match resolution {
+            Resolution::Pending => {}
+            Resolution::Action(action) => self.run_action(action),
+            Resolution::Fallback(keys) => {
+                for key in keys {
+                    self.dispatch_key(KeyEvent::new(KeyCode::Char(key), KeyModifiers::NONE))?;
+                }
+            }
+        }
+        ControlFlow::Continue(())
+    }
+
+    /// Resolve the keys typed towards a sequence once no more followed in time
+    ///
+    /// Continues with whether anything happened.
+    fn expire_pending_keys(&mut self) -> ControlFlow<(), bool> {
+        let keymap = match self.input_mode {
+            InputMode::Normal => &self.keymaps.normal,
+            InputMode::Viewing => &self.keymaps.viewer,
+            _ => return ControlFlow::Continue(false),
+        };
+        let expired =
+            self.pending_keys
+                .expire(keymap, Instant::now(), self.config.sequence_timeout);
+        match expired {
+            Some(resolution) => {
+                self.resolve_keys(resolution)?;
+                ControlFlow::Continue(true)
+            }
+            None => ControlFlow::Continue(false),
+        }
+    }
+
+    fn run_action(&mut self, action: Action) {
+        if action.repeatable() {
+            self.last_action = Some(action);
+        }
+        match action {
+            Action::SelectFirst if self.home_rows() > 0 => self.home_selected = 0,
+            Action::SelectLast if self.home_rows() > 0 => self.home_selected = self.home_rows() - 1,
+            Action::SelectFirst => self.selected = 0,
+            Action::SelectLast => self.selected = self.rows.len().saturating_sub(1),
+            Action::Browse => self.browse_selected(),
+            Action::DocSets => self.open_manager(),
+            Action::Sort => self.cycle_sort_mode(),
+            Action::NextScope => self.cycle_scope(true),
+            Action::PreviousScope => self.cycle_scope(false),
+            Action::TogglePreview => self.toggle_preview(),
+            Action::ScrollTop => self.scroll_by(-i32::from(u16::MAX)),
+            Action::ScrollBottom => self.scroll_by(i32::from(u16::MAX)),
+            Action::EditCode => self.edit_requested = true,
+            Action::Copy => self.input_mode = InputMode::CopyMenu,
+            Action::CopyQuery => self.copy_query(),
+            Action::CopyResults => self.copy_results(ListFormat::Markdown),
+            Action::CopyResultsText => self.copy_results(ListFormat::Text),
+            Action::CopyCode => self.copy_code_blocks(),
+            Action::ToggleReadingTime => self.toggle_reading_time(),
+            Action::ToggleWrap => self.toggle_wrap(),
+            Action::ToggleOutline => self.toggle_outline(),
+            Action::ToggleMinimal => {
+                self.config.minimal = !self.config.minimal;
+                self.save_setting("minimal", &self.config.minimal.to_string());
+            }
+            Action::OpenDataDir => self.open_dir(Some(self.storage.root().to_owned())),
+            Action::OpenConfigDir => self.open_dir(
+                self.config_path
+                    .as_deref()
+                    .and_then(Path::parent)
+                    .map(Path::to_owned),
+            ),
+            Action::ToggleDebug => self.debug_open = !self.debug_open,
+            Action::Repeat => {
+                let viewing = matches!(self.input_mode, InputMode::Viewing);
+                match self.last_action {
+                    Some(action) if action.in_viewer() == viewing => self.run_action(action),
+                    _ => self.notify(Severity::Info, "Nothing to repeat here"),
+                }
+            }
+        }
+    }
+
+    /// Go back one step from the search box, the results or the viewer
+    ///
+    /// Esc goes back along one chain, a step per press:
+    ///
+    /// 1. a section of an entry shown alone goes back to the whole entry,
+    /// 2. the viewer goes back to the doc set browser it was opened from, or else the results,
+    /// 3. the search box goes to the results, and the results back to the search box, or
+    ///    quit with `esc_quits` while the query is empty.
+    ///
+    /// Before any of these, Esc dismisses dialogs, popups and menus, and gives focus back from
+    /// a side panel to the panel it is next to; those handle it themselves.
+    fn escape(&mut self) -> ControlFlow<()> {
+        match self.input_mode {
+            InputMode::Viewing => match &self.entry {
+                Some(entry) if entry.section.is_some() => {
+                    let top = self.top_line();
+                    if let Some(entry) = self.entry.as_mut() {
+                        entry.section = None;
+                    }
+                    let lines = self.entry.as_ref().map_or(&[][..], OpenEntry::lines);
+                    self.scroll = self.rows_before(&lines[..top.min(lines.len())]);
+                    self.scroll_animation = None;
+                }
+                _ if self.browser.is_some() => self.input_mode = InputMode::Browse,
+                _ => self.input_mode = InputMode::Normal,
+            },
+            InputMode::Searching => self.leave_search(),
+            _ if self.config.esc_quits && self.input.is_empty() => return ControlFlow::Break(()),
+            _ => self.focus(Panel::Search),
+        }
+        ControlFlow::Continue(())
+    }
+
+    /// Handle a key on its own, outside of any key sequence
+    fn dispatch_key(&mut self, key: KeyEvent) -> ControlFlow<()> {
+        match self.input_mode {
+            InputMode::Normal => match key.code {
+                KeyCode::Char('i') | KeyCode::Char('/') => {
+                    self.input_mode = InputMode::Searching;
+                }
+                KeyCode::Char('q') => {
+                    return ControlFlow::Break(());
+                }
+                KeyCode::Char(digit @ '1'..='9') if self.input.is_empty() => {
+                    self.open_recent(digit as usize - '1' as usize);
+                }
+                KeyCode::Char('X') if self.input.is_empty() && !self.recent.is_empty() => {
+                    self.confirm_then(Destructive::ClearRecent)
+                }
+                KeyCode::Char('j') | KeyCode::Down if self.home_rows() > 0 => {
+                    self.select_home_row(true)
+                }
+                KeyCode::Char('k') | KeyCode::Up if self.home_rows() > 0 => {
+                    self.select_home_row(false)
+                }
+                KeyCode::Enter if self.home_rows() > 0 => self.open_home_row(),
+                KeyCode::Char('j') | KeyCode::Down => self.select_next(),
+                KeyCode::Char('k') | KeyCode::Up => self.select_previous(),
+                KeyCode::Char('s') => self.run_action(Action::Sort),
+                KeyCode::Char('d') | KeyCode::Char('u') => self.run_action(Action::DocSets),
+                KeyCode::Char('b') => self.run_action(Action::Browse),
+                KeyCode::Char('p') => self.run_action(Action::TogglePreview),
+                KeyCode::Char('y') => self.run_action(Action::CopyQuery),
+                KeyCode::Char('S') => self.input_mode = InputMode::Stats,
+                KeyCode::Char(' ') => self.open_quick_look(),
+                // Terminals that report modifiers on Return let them pick where it opens
+                KeyCode::Enter if key.modifiers.contains(KeyModifiers::ALT) => {
+                    self.open_selected(OpenAction::Browser)
+                }
+                KeyCode::Enter if key.modifiers.contains(KeyModifiers::SHIFT) => {
+                    self.open_selected(OpenAction::Pager)
+                }
+                KeyCode::Enter if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.open_selected(OpenAction::Viewer)
+                }
+                KeyCode::Enter => self.open_selected(self.open_action()),
+                KeyCode::Esc => return self.escape(),
+                _ => {}
+            },
+            InputMode::Searching if key.kind == KeyEventKind::Press => match key.code {
+                KeyCode::Enter if looks_like_devdocs_url(&self.input) => self.open_input_url(),
+                KeyCode::Enter => self.open_top_result(),
+                KeyCode::Char('g') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.toggle_global()
+                }
+                KeyCode::Char('y') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.copy_query()
+                }
+                KeyCode::Char('f') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.toggle_full_text()
+                }
+                KeyCode::Char('r') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.cycle_matcher()
+                }
+                KeyCode::Char('t') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.cycle_case_mode()
+                }
+                KeyCode::Char(to_insert) => self.enter_char(to_insert),
+                KeyCode::Backspace if self.input.is_empty() => self.clear_scope(),
+                KeyCode::Backspace => self.delete_char(),
+                KeyCode::Left => self.move_cursor_left(),
+                KeyCode::Right => self.move_cursor_right(),
+                KeyCode::Down => self.select_next(),
+                KeyCode::Up => self.select_previous(),
+                KeyCode::Esc => return self.escape(),
+                _ => {}
+            },
+            InputMode::Searching => {}
+            InputMode::Viewing if key.kind == KeyEventKind::Press => match key.code {
+                KeyCode::Char('j') => self.scroll_by(1),
+                KeyCode::Char('k') => self.scroll_by(-1),
+                KeyCode::Down => self.scroll_by(i32::from(self.config.scroll_lines)),
+                KeyCode::Up => self.scroll_by(-i32::from(self.config.scroll_lines)),
+                KeyCode::PageDown | KeyCode::Char(' ') => self.scroll_by(20),
+                KeyCode::PageUp => self.scroll_by(-20),
+                KeyCode::Char('v') => self.switch_version(),
+                KeyCode::Char('V') => self.start_selection(),
+                KeyCode::Char('a') => self.toggle_auto_scroll(),
+                KeyCode::Char('+' | '=') => self.change_auto_scroll_interval(false),
+                KeyCode::Char('-') => self.change_auto_scroll_interval(true),
+                KeyCode::Char('D') => self.open_comparison(),
+                KeyCode::Char('R') => self.reload_entry(),
+                KeyCode::Char('p') => self.page_requested = true,
+                KeyCode::Char('e') => self.run_action(Action::EditCode),
+                KeyCode::Char('y') => self.run_action(Action::Copy),
+                KeyCode::Char('w') => self.run_action(Action::ToggleReadingTime),
+                KeyCode::Char('t') => self.open_toc(),
+                KeyCode::Char('I') => self.open_image(),
+                KeyCode::Char('o') => self.run_action(Action::ToggleOutline),
+                KeyCode::Char('u') => self.run_action(Action::ToggleWrap),
+                KeyCode::Char('h') | KeyCode::Left => self.scroll_sideways(-4),
+                KeyCode::Char('l') | KeyCode::Right => self.scroll_sideways(4),
+                KeyCode::Char(']') => self.jump_to_heading(true),
+                KeyCode::Char('[') => self.jump_to_heading(false),
+                KeyCode::Esc => return self.escape(),
+                KeyCode::Char('q') if self.browser.is_some() => self.input_mode = InputMode::Browse,
+                KeyCode::Char('q') => self.input_mode = InputMode::Normal,
+                _ => {}
+            },
+            InputMode::Viewing => {}
+            InputMode::EntryNotFound if key.kind == KeyEventKind::Press => match key.code {
+                KeyCode::Char('j') | KeyCode::Down => self.select_suggestion(true),
+                KeyCode::Char('k') | KeyCode::Up => self.select_suggestion(false),
+                KeyCode::Enter => self.open_suggestion(),
+                KeyCode::Char('r') => self.resync_missing(),
+                KeyCode::Esc | KeyCode::Char('q') => {
+                    self.missing = None;
+                    self.input_mode = InputMode::Normal;
+                }
+                _ => {}
+            },
+            InputMode::EntryNotFound => {}
+            InputMode::ConfirmSync
+                if key.kind == KeyEventKind::Press
+                    && self
+                        .sync_prompt
+                        .as_ref()
+                        .is_some_and(|prompt| prompt.task.is_some()) =>
+            {
+                if let KeyCode::Esc | KeyCode::Char('q') = key.code {
+                    self.cancel_sync();
+                }
+            }
+            InputMode::ConfirmSync if key.kind == KeyEventKind::Press => match key.code {
+                KeyCode::Char('y') | KeyCode::Enter => self.start_sync(),
+                KeyCode::Char('n') | KeyCode::Esc => {
+                    self.sync_prompt = None;
+                    self.input_mode = if self.manager.is_some() {
+                        InputMode::Manage
+                    } else {
+                        InputMode::Normal
+                    };
+                }
+                _ => {}
+            },
+            InputMode::ConfirmSync => {}
+            InputMode::Manage
+                if key.kind == KeyEventKind::Press
+                    && self.manager.as_ref().is_some_and(|m| m.about_open) =>
+            {
+                match key.code {
+                    KeyCode::Char('o') => self.open_selected_source(),
+                    KeyCode::Char('i') | KeyCode::Esc | KeyCode::Char('q') => self.toggle_about(),
+                    _ => {}
+                }
+            }
+            InputMode::Manage if key.kind == KeyEventKind::Press => match key.code {
+                KeyCode::Char('j') | KeyCode::Down => self.select_doc_set(true),
+                KeyCode::Char('k') | KeyCode::Up => self.select_doc_set(false),
+                KeyCode::Char('K') => self.move_doc_set(true),
+                KeyCode::Char('J') => self.move_doc_set(false),
+                KeyCode::Enter => self.sync_selected_doc_set(),
+                KeyCode::Char('b') => self.browse_selected_doc_set(),
+                KeyCode::Char('r') => self.refresh_registry(),
+                KeyCode::Char('x') | KeyCode::Delete => self.remove_selected_doc_set(),
+                KeyCode::Char('i') => self.toggle_about(),
+                KeyCode::Char('s') => self.cycle_manager_sort(),
+                KeyCode::Esc | KeyCode::Char('q') => {
+                    self.manager = None;
+                    self.input_mode = InputMode::Normal;
+                }
+                _ => {}
+            },
+            InputMode::Manage => {}
+            InputMode::Browse if key.kind == KeyEventKind::Press => match key.code {
+                KeyCode::Char('j') | KeyCode::Down => self.browse_select(true),
+                KeyCode::Char('k') | KeyCode::Up => self.browse_select(false),
+                KeyCode::Enter | KeyCode::Char('l') | KeyCode::Right => self.browse_open(),
+                KeyCode::Esc | KeyCode::Char('q' | 'h') | KeyCode::Left => self.browse_back(),
+                _ => {}
+            },
+            InputMode::Browse => {}
+            InputMode::Toc if key.kind == KeyEventKind::Press => match key.code {
+                KeyCode::Char('j') | KeyCode::Down => self.select_toc_row(true),
+                KeyCode::Char('k') | KeyCode::Up => self.select_toc_row(false),
+                KeyCode::Enter => self.open_toc_row(),
+                KeyCode::Esc | KeyCode::Char('q' | 't') => self.input_mode = InputMode::Viewing,
+                _ => {}
+            },
+            InputMode::Toc => {}
+            InputMode::Visual if key.kind == KeyEventKind::Press => match key.code {
+                KeyCode::Char('j') | KeyCode::Down => self.move_selection(1),
+                KeyCode::Char('k') | KeyCode::Up => self.move_selection(-1),
+                KeyCode::PageDown | KeyCode::Char(' ') => self.move_selection(20),
+                KeyCode::PageUp => self.move_selection(-20),
+                KeyCode::Char('g') => self.move_selection(i32::MIN),
+                KeyCode::Char('G') => self.move_selection(i32::MAX),
+                KeyCode::Char('y') | KeyCode::Enter => self.copy_selection(),
+                KeyCode::Esc | KeyCode::Char('q' | 'V') => {
+                    self.selection = None;
+                    self.input_mode = InputMode::Viewing;
+                }
+                _ => {}
+            },
+            InputMode::Visual => {}
+            InputMode::Compare if key.kind == KeyEventKind::Press => match key.code {
+                KeyCode::Char('j') => self.scroll_comparison(1),
+                KeyCode::Char('k') => self.scroll_comparison(-1),
+                KeyCode::Down => self.scroll_comparison(i32::from(self.config.scroll_lines)),
+                KeyCode::Up => self.scroll_comparison(-i32::from(self.config.scroll_lines)),
+                KeyCode::PageDown | KeyCode::Char(' ') => self.scroll_comparison(20),
+                KeyCode::PageUp => self.scroll_comparison(-20),
+                KeyCode::Char('g') => self.scroll_comparison(i32::MIN),
+                KeyCode::Char('G') => self.scroll_comparison(i32::MAX),
+                KeyCode::Char(']') => self.jump_to_change(true),
+                KeyCode::Char('[') => self.jump_to_change(false),
+                KeyCode::Esc | KeyCode::Char('q' | 'D') => {
+                    self.comparison = None;
+                    self.input_mode = InputMode::Viewing;
+                }
+                _ => {}
+            },
+            InputMode::Compare => {}
+            InputMode::Stats if key.kind == KeyEventKind::Press => match key.code {
+                KeyCode::Char('X') if !self.stats.is_empty() => {
+                    self.confirm_then(Destructive::ClearStats)
+                }
+                KeyCode::Esc | KeyCode::Char('q' | 'S') => self.input_mode = InputMode::Normal,
+                _ => {}
+            },
+            InputMode::Stats => {}
+            InputMode::CopyMenu if key.kind == KeyEventKind::Press => match key.code {
+                KeyCode::Char('p' | '1') => self.copy_entry(ShareFormat::Path),
+                KeyCode::Char('u' | '2') => self.copy_entry(ShareFormat::Url),
+                KeyCode::Char('m' | '3') => self.copy_entry(ShareFormat::Markdown),
+                KeyCode::Char('c' | '4') => self.copy_code_blocks(),
+                KeyCode::Esc | KeyCode::Char('q') => self.input_mode = InputMode::Viewing,
+                _ => {}
+            },
+            InputMode::CopyMenu => {}
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        self.draw_screen(frame);
+        if self.config.theme == BuiltInTheme::HighContrast {
+            apply_high_contrast(frame.buffer_mut());
+        }
+    }
+
+    fn draw_screen(&self, frame: &mut Frame) {
+        let area = frame.area();
+        if area.width < MIN_WIDTH || area.height < MIN_HEIGHT {
+            let message = Paragraph::new(format!(
+                "Terminal too small (need at least {MIN_WIDTH}x{MIN_HEIGHT}, have {}x{})",
+                area.width, area.height
+            ))
+            .wrap(Wrap { trim: true })
+            .centered();
+            let [line] = Layout::vertical([Constraint::Length(2)])
+                .flex(Flex::Center)
+                .areas(area);
+            frame.render_widget(message, line);
+            return;
+        }
+
+        // The minimal layout keeps the search box only while typing in it
+        let minimal = self.config.minimal;
+        let searching = matches!(self.input_mode, InputMode::Searching);
+        let vertical = Layout::vertical([
+            Constraint::Min(12),
+            Constraint::Length(if minimal && !searching { 0 } else { 3 }),
+            Constraint::Length(if minimal { 0 } else { 1 }),
+        ]);
+        let [content_area, input_area, help_area] = vertical.areas(frame.area());
+
+        let (msg, style) = match self.input_mode {
+            _ if self.confirmation.is_some() => (
+                vec![
+                    "y".bold(),
+                    " confirm | ".into(),
+                    "n".bold(),
+                    " cancel".into(),
+                ],
+                Style::default(),
+            ),
+            _ if self.focused_panel() == Some(Panel::Preview) => (
+                vec![
+                    "j/k".bold(),
+                    " scroll preview | ".into(),
+                    "Tab".bold(),
+                    " next panel | ".into(),
+                    "Esc".bold(),
+                    " back to results".into(),
+                ],
+                Style::default(),
+            ),
+            _ if self.focused_panel() == Some(Panel::Outline) => (
+                vec![
+                    "j/k".bold(),
+                    " next/previous heading | ".into(),
+                    "Tab".bold(),
+                    " next panel | ".into(),
+                    "Esc".bold(),
+                    " back to the entry".into(),
+                ],
+                Style::default(),
+            ),
+            InputMode::Normal => (
+                [
+                    vec![
+                        "q".bold(),
+                        " exit | ".into(),
+                        "i".bold(),
+                        " or ".into(),
+                        "/".bold(),
+                        " enter search | ".into(),
+                        "Return".bold(),
+                        " open result | ".into(),
+                        "s".bold(),
+                        " sort | ".into(),
+                        format!(
+                            "{}/{}",
+                            self.config.next_scope_key, self.config.previous_scope_key
+                        )
+                        .bold(),
+                        " scope | ".into(),
+                        "b".bold(),
+                        " browse | ".into(),
+                        "p".bold(),
+                        " preview | ".into(),
+                        "y".bold(),
+                        " copy query | ".into(),
+                        "d".bold(),
+                        " doc sets | ".into(),
+                        "1-9".bold(),
+                        " reopen recent".into(),
+                    ],
+                    self.update_badge(),
+                ]
+                .concat(),
+                Style::default(),
+            ),
+            InputMode::Searching => (
+                vec![
+                    "Return".bold(),
+                    " open top result | ".into(),
+                    "Tab".bold(),
+                    " results | ".into(),
+                    "Ctrl-y".bold(),
+                    " copy query | ".into(),
+                    "Ctrl-f".bold(),
+                    if self.full_text {
+                        " search names | ".into()
+                    } else {
+                        " search content | ".into()
+                    },
+                    "Ctrl-r".bold(),
+                    " matching | ".into(),
+                    "Ctrl-t".bold(),
+                    " case | ".into(),
+                    "Esc".bold(),
+                    " leave search mode".into(),
+                ],
+                Style::default(),
+            ),
+            InputMode::Viewing => {
+                let mut msg = vec!["j/k".bold(), " scroll | ".into()];
+                if self.entry.as_ref().is_some_and(|entry| entry.stale) {
+                    msg.extend(["R".bold(), " reload | ".into()]);
+                }
+                if !self.other_versions().is_empty() {
+                    msg.extend([
+                        "v".bold(),
+                        " switch version | ".into(),
+                        "D".bold(),
+                        " compare | ".into(),
+                    ]);
+                }
+                msg.extend(["p".bold(), " pager | ".into()]);
+                if self
+                    .entry
+                    .as_ref()
+                    .is_some_and(|entry| !entry.document.code_blocks.is_empty())
+                {
+                    msg.extend(["e".bold(), " edit code | ".into()]);
+                }
+                msg.extend(["y".bold(), " copy | ".into()]);
+                msg.extend(["V".bold(), " select lines | ".into()]);
+                msg.extend(["a".bold(), " auto-scroll | ".into()]);
+                if self.auto_scroll.is_some() {
+                    msg.extend(["+/-".bold(), " speed | ".into()]);
+                }
+                if self
+                    .entry
+                    .as_ref()
+                    .is_some_and(|entry| !entry.document.images.is_empty())
+                {
+                    msg.extend(["I".bold(), " open image | ".into()]);
+                }
+                msg.extend(["w".bold(), " reading time | ".into()]);
+                if self
+                    .entry
+                    .as_ref()
+                    .is_some_and(|entry| !entry.document.headings.is_empty())
+                {
+                    msg.extend(["t".bold(), " sections | ".into()]);
+                    msg.extend(["[/]".bold(), " headings | ".into()]);
+                    msg.extend(["o".bold(), " outline | ".into()]);
+                }
+                if self.config.wrap {
+                    msg.extend(["u".bold(), " unwrap | ".into()]);
+                } else {
+                    msg.extend(["h/l".bold(), " scroll sideways | ".into()]);
+                    msg.extend(["u".bold(), " wrap | ".into()]);
+                }
+                msg.extend(["z".bold(), " minimal | ".into()]);
+                msg.extend(["Esc".bold(), " close entry".into()]);
+                if let Some(entry) = self.entry.as_ref().filter(|_| self.config.reading_time) {
+                    msg.push(format!(" | {}", reading_time(entry.document.stats)).dark_gray());
+                }
+                (msg, Style::default())
+            }
+            InputMode::Stats => (
+                vec![
+                    "X".bold(),
+                    " clear stats | ".into(),
+                    "Esc".bold(),
+                    " back".into(),
+                ],
+                Style::default(),
+            ),
+            InputMode::Compare => (
+                vec![
+                    "j/k".bold(),
+                    " scroll | ".into(),
+                    "]/[".bold(),
+                    " next/previous change | ".into(),
+                    "Esc".bold(),
+                    " back to the entry".into(),
+                ],
+                Style::default(),
+            ),
+            InputMode::Toc => (
+                vec![
+                    "j/k".bold(),
+                    " select | ".into(),
+                    "Return".bold(),
+                    " show section | ".into(),
+                    "Esc".bold(),
+                    " back".into(),
+                ],
+                Style::default(),
+            ),
+            InputMode::Visual => (
+                vec![
+                    "j/k".bold(),
+                    " extend selection | ".into(),
+                    "y".bold(),
+                    " copy | ".into(),
+                    "Esc".bold(),
+                    " cancel".into(),
+                ],
+                Style::default(),
+            ),
+            InputMode::CopyMenu => (
+                vec![
+                    "p/u/m".bold(),
+                    " copy format | ".into(),
+                    "c".bold(),
+                    " code blocks | ".into(),
+                    "Esc".bold(),
+                    " cancel".into(),
+                ],
+                Style::default(),
+            ),
+            InputMode::ConfirmSync
+                if self
+                    .sync_prompt
+                    .as_ref()
+                    .is_some_and(|prompt| prompt.task.is_some()) =>
+            {
+                (
+                    vec!["Esc".bold(), " cancel the download".into()],
+                    Style::default(),
+                )
+            }
+            InputMode::ConfirmSync => (
+                vec![
+                    "y".bold(),
+                    " download | ".into(),
+                    "n".bold(),
+                    " cancel".into(),
+                ],
+                Style::default(),
+            ),
+            InputMode::EntryNotFound => (
+                vec![
+                    "j/k".bold(),
+                    " select | ".into(),
+                    "Return".bold(),
+                    " open suggestion | ".into(),
+                    "Esc".bold(),
+                    " dismiss".into(),
+                ],
+                Style::default(),
+            ),
+            InputMode::Manage if self.manager.as_ref().is_some_and(|m| m.about_open) => (
+                vec![
+                    "o".bold(),
+                    " open source in browser | ".into(),
+                    "Esc".bold(),
+                    " back".into(),
+                ],
+                Style::default(),
+            ),
+            InputMode::Manage => (
+                vec![
+                    "j/k".bold(),
+                    " select | ".into(),
+                    "Return".bold(),
+                    " install or update | ".into(),
+                    "J/K".bold(),
+                    " reorder | ".into(),
+                    "s".bold(),
+                    " sort | ".into(),
+                    "b".bold(),
+                    " browse | ".into(),
+                    "r".bold(),
+                    " refresh list | ".into(),
+                    "x".bold(),
+                    " remove | ".into(),
+                    "i".bold(),
+                    " about | ".into(),
+                    "Esc".bold(),
+                    " back".into(),
+                ],
+                Style::default(),
+            ),
+            InputMode::Browse => (
+                vec![
+                    "j/k".bold(),
+                    " select | ".into(),
+                    "Return".bold(),
+                    " open | ".into(),
+                    "Esc".bold(),
+                    " back".into(),
+                ],
+                Style::default(),
+            ),
+        };
+        let text = if self.pending_keys.is_empty() {
+            Text::from(Line::from(msg)).patch_style(style)
+        } else {
+            Text::from(Line::from(vec![
+                self.keymaps
+                    .normal
+                    .notation(self.pending_keys.keys())
+                    .bold(),
+                "…".dark_gray(),
+            ]))
+        };
+        let help_message = Paragraph::new(text);
+        frame.render_widget(help_message, help_area);
+
+        let input = Paragraph::new(self.input.as_str())
+            .style(match self.input_mode {
+                InputMode::Normal => Style::default(),
+                InputMode::Searching => Style::default().fg(Color::Yellow),
+                InputMode::Viewing
+                | InputMode::EntryNotFound
+                | InputMode::ConfirmSync
+                | InputMode::CopyMenu
+                | InputMode::Manage
+                | InputMode::Browse
+                | InputMode::Toc
+                | InputMode::Visual
+                | InputMode::Compare
+                | InputMode::Stats => Style::default(),
+            })
+            .block(self.panel_block(Panel::Search).title(self.search_title()));
+        frame.render_widget(input, input_area);
+        match self.input_mode {
+            // Hide the cursor. `Frame` does this by default, so we don't need to do anything here
+            InputMode::Normal
+            | InputMode::Viewing
+            | InputMode::EntryNotFound
+            | InputMode::ConfirmSync
+            | InputMode::CopyMenu
+            | InputMode::Manage
+            | InputMode::Browse
+            | InputMode::Toc
+            | InputMode::Visual
+            | InputMode::Compare
+            | InputMode::Stats => {}
+
+            // Make the cursor visible and ask ratatui to put it at the specified coordinates after
+            #[allow(clippy::cast_possible_truncation)]
+            InputMode::Searching => frame.set_cursor_position(Position::new(
+                // Draw the cursor at the current position in the input field.
+                // This position is can be controlled via the left and right arrow key
+                input_area.x + self.character_index as u16 + 1,
+                // Move one line down, from the border to the input line
+                input_area.y + 1,
+            )),
+        }
+
+        //  TODO: Bordered on first search, otherwise unbordered
+
+        match (&self.input_mode, &self.entry) {
+            (
+                InputMode::Viewing | InputMode::CopyMenu | InputMode::Toc | InputMode::Visual,
+                Some(entry),
+            ) => self.draw_entry(frame, content_area, entry),
+            (InputMode::EntryNotFound, _) if self.missing.is_some() => {
+                self.draw_missing(frame, content_area)
+            }
+            (InputMode::Compare, _) if self.comparison.is_some() => {
+                self.draw_comparison(frame, content_area)
+            }
+            (InputMode::Stats, _) => self.draw_stats(frame, content_area),
+            (InputMode::Manage | InputMode::ConfirmSync, _) if self.manager.is_some() => {
+                self.draw_manager(frame, content_area)
+            }
+            (InputMode::Browse, _) if self.browser.is_some() => {
+                self.draw_browser(frame, content_area)
+            }
+            _ if !self.input.is_empty() => self.draw_results(frame, content_area),
+            _ => self.draw_home(frame, content_area),
+        }
+
+        if let Some(prompt) = &self.sync_prompt {
+            draw_sync_prompt(frame, content_area, prompt);
+        }
+        if let (InputMode::CopyMenu, Some(entry)) = (&self.input_mode, &self.entry) {
+            draw_copy_menu(frame, content_area, entry);
+        }
+        if let (InputMode::Toc, Some(entry)) = (&self.input_mode, &self.entry) {
+            draw_toc(
+                frame,
+                content_area,
+                entry,
+                self.toc_selected,
+                &self.config,
+                self.color_support,
+            );
+        }
+        if let Some(quick_look) = &self.quick_look {
+            self.draw_quick_look(frame, content_area, quick_look);
+        }
+        if let Some(action) = &self.confirmation {
+            draw_confirmation(frame, content_area, &action.question());
+        }
+        if self.debug_open {
+            self.draw_debug(frame, content_area);
+        }
+        self.draw_notifications(frame, content_area);
+    }
+
+    /// Search box title, showing the default scope unless the query overrides it
+    fn search_title(&self) -> Line<'static> {
+        let mut title = vec!["Search".into()];
+        if self.full_text {
+            title.push(" [full text]".yellow());
+            if self.unindexed > 0 {
+                title.push(
+                    format!(" {} doc set(s) not indexed, see docs index", self.unindexed)
+                        .dark_gray(),
+                );
+            }
+        }
+        if let Some(error) = &self.url_error {
+            title.push(format!(" {error}").red());
+            return Line::from(title);
+        }
+        let query = self.query();
+        if self.config.matcher != Matcher::Fuzzy {
+            title.push(format!(" [{}]", self.config.matcher.name()).yellow());
+        }
+        if self.config.search_case != CaseMode::Insensitive {
+            title.push(format!(" [{}]", self.config.search_case.label()).yellow());
+        }
+        if let Some(error) = query.errors.first() {
+            title.push(format!(" {error}").red());
+        }
+        if let Err(error) = self.config.matcher.searcher().check(&query) {
+            title.push(format!(" {error}").red());
+        }
+        if let Err(prefix) = self.active_scope() {
+            title.push(format!(" unknown doc set '{prefix}'").red());
+            if let Some(hint) = slug_hint(&prefix, &self.installed) {
+                title.push(format!(", {hint}").dark_gray());
+            }
+            return Line::from(title);
+        }
+        if self.config.default_scope.is_empty()
+            || query.prefix.is_some()
+            || !query.scopes.is_empty()
+        {
+            return Line::from(title);
+        }
+
+        if self.scope_cleared {
+            title.push(" [all installed]".yellow());
+            title.push(" Ctrl-G to search ".dark_gray());
+            title.push(self.config.default_scope.join(", ").dark_gray());
+            return Line::from(title);
+        }
+        title.push(" [".cyan());
+        for (i, scope) in self.config.default_scope.iter().enumerate() {
+            if i > 0 {
+                title.push(", ".cyan());
+            }
+            if self.installed.iter().any(|slug| in_scope(slug, scope)) {
+                title.push(Span::styled(
+                    scope.clone(),
+                    self.config.doc_set_colors.style(scope, self.color_support),
+                ));
+            } else {
+                title.push(format!("{scope} (not installed)").red());
+            }
+        }
+        title.push("]".cyan());
+        if matches!(self.input_mode, InputMode::Searching) {
+            title.push(" Ctrl-G to search all".dark_gray());
+        }
+        Line::from(title)
+    }
+
+    fn draw_results(&self, frame: &mut Frame, area: Rect) {
+        let area = match &self.preview {
+            Some(preview) if area.width >= 2 * MIN_WIDTH => {
+                let [results, preview_area] =
+                    Layout::horizontal([Constraint::Fill(1), Constraint::Fill(1)]).areas(area);
+                self.draw_preview(frame, preview_area, preview);
+                results
+            }
+            _ => area,
+        };
+        let mut title = Line::from(format!(
+            " {} results, by {} ",
+            self.results.len(),
+            self.config.sort_mode.label()
+        ));
+        if self.search_pending() {
+            title.push_span("searching… ".dark_gray());
+        }
+        let query = self.query();
+        let items = self
+            .rows
+            .iter()
+            .map(|row| match row {
+                ResultRow::Result(i) => {
+                    let result = &self.results[*i];
+                    let mut line = result_line(result, &self.config, self.color_support);
+                    let entry = &result.entry;
+                    if self.config.mark_seen
+                        && self
+                            .seen
+                            .contains(&(entry.slug.clone(), entry.path.clone()))
+                    {
+                        line = line.patch_style(Style::default().add_modifier(Modifier::DIM));
+                        line.push_span(SEEN_BADGE.dark_gray());
+                    }
+                    let abstract_text = self
+                        .index
+                        .abstract_of(entry)
+                        .filter(|_| self.config.abstracts);
+                    match (&result.snippet, abstract_text) {
+                        (Some(snippet), _) => {
+                            ListItem::new(Text::from(vec![line, snippet_line(snippet, &query)]))
+                        }
+                        (None, Some(text)) => ListItem::new(Text::from(vec![
+                            line,
+                            Line::from(format!("    {text}").dark_gray()),
+                        ])),
+                        (None, None) => ListItem::new(line),
+                    }
+                }
+                ResultRow::More { slug, hidden } => ListItem::new(Line::from(vec![
+                    format!("  …{hidden} more from ").dark_gray(),
+                    Span::styled(
+                        slug.clone(),
+                        self.config.doc_set_colors.style(slug, self.color_support),
+                    ),
+                ])),
+            })
+            .collect();
+        let list = list(items, &self.config, self.color_support)
+            .block(self.panel_block(Panel::Results).title(title));
+        let mut state = ListState::default().with_selected(Some(self.selected));
+        frame.render_stateful_widget(list, area, &mut state);
+    }
+
+    /// The quick look popup, centered over the results and no larger than
+    /// [`QUICK_LOOK_MAX`]
+    fn draw_quick_look(&self, frame: &mut Frame, area: Rect, quick_look: &QuickLook) {
+        let area = center(
+            area,
+            Constraint::Length(quick_look_size(area.width, QUICK_LOOK_MAX.0)),
+            Constraint::Length(quick_look_size(area.height, QUICK_LOOK_MAX.1)),
+        );
+        let title = Line::from(vec![
+            format!(" {} ", quick_look.entry.name).bold(),
+            format!("{} ", quick_look.entry.slug).dark_gray(),
+        ]);
+        let hint = Line::from(vec![
+            " Return".bold(),
+            " open | ".into(),
+            "Esc".bold(),
+            " close ".into(),
+        ]);
+        let mut pane = Paragraph::new(quick_look.document.text.clone())
+            .scroll((quick_look.scroll, 0))
+            .block(
+                Block::bordered()
+                    .border_style(Style::default().fg(Color::Cyan))
+                    .title(title)
+                    .title_bottom(hint.right_aligned()),
+            );
+        if self.config.wrap {
+            pane = pane.wrap(Wrap { trim: false });
+        }
+        frame.render_widget(Clear, area);
+        frame.render_widget(pane, area);
+    }
+
+    fn draw_preview(&self, frame: &mut Frame, area: Rect, preview: &Preview) {
+        let title = Line::from(vec![
+            format!(" {} ", preview.entry.name).bold(),
+            format!("{} ", preview.entry.slug).dark_gray(),
+        ]);
+        let text = match &preview.document {
+            None => Text::from("Loading…".dark_gray()),
+            Some(Ok(document)) => document.text.clone(),
+            Some(Err(e)) => Text::from(e.clone().red()),
+        };
+        let pane = Paragraph::new(text)
+            .wrap(Wrap { trim: false })
+            .scroll((preview.scroll, 0))
+            .block(self.panel_block(Panel::Preview).title(title));
+        frame.render_widget(pane, area);
+    }
+
+    /// The hint that doc set updates are available, blinking if the config asks for it and
+    /// the terminal can
+    fn update_badge(&self) -> Vec<Span<'static>> {
+        let hint = match self.updates.len() {
+            0 => return Vec::new(),
+            1 => "1 update available (u)".to_owned(),
+            n => format!("{n} updates available (u)"),
+        };
+        let mut hint = hint.yellow();
+        if self.config.blink && !self.config.reduced_motion && self.blink_supported {
+            hint = hint.add_modifier(Modifier::SLOW_BLINK);
+        }
+        vec![" | ".into(), hint]
+    }
+
+    fn draw_browser(&self, frame: &mut Frame, area: Rect) {
+        let Some(browser) = &self.browser else {
+            return;
+        };
+        let (list, selected) = match &browser.entries {
+            Some(entries) => {
+                let title = format!(
+                    " {} › {} ({}) ",
+                    browser.slug,
+                    browser.types[browser.selected_type].name,
+                    entries.len()
+                );
+                let list = result_list(entries, &self.config, self.color_support);
+                (list.block(Block::bordered().title(title)), browser.selected)
+            }
+            None => {
+                let items = browser
+                    .types
+                    .iter()
+                    .map(|doc_type| {
+                        ListItem::new(Line::from(vec![
+                            doc_type.name.clone().bold(),
+                            format!("  {}", doc_type.count).dark_gray(),
+                        ]))
+                    })
+                    .collect();
+                let title = format!(" {} types of {} ", browser.types.len(), browser.slug);
+                (
+                    list(items, &self.config, self.color_support)
+                        .block(Block::bordered().title(title)),
+                    browser.selected_type,
+                )
+            }
+        };
+        let mut state = ListState::default().with_selected(Some(selected));
+        frame.render_stateful_widget(list, area, &mut state);
+    }
+
+    fn draw_manager(&self, frame: &mut Frame, area: Rect) {
+        let Some(manager) = &self.manager else {
+            return;
+        };
+        let mut header = vec![
+            " Doc sets ".bold(),
+            format!(
+                "Installed {} / {} available ",
+                self.installed.len(),
+                manager.available
+            )
+            .cyan(),
+            format!("by {} ", self.config.manager_sort.label()).dark_gray(),
+        ];
+        if !self.updates.is_empty() {
+            header.push(format!("{} with updates ", self.updates.len()).magenta());
+        }
+        if !manager.recommended.is_empty() {
+            header.push(format!("{} recommended ", manager.recommended.len()).yellow());
+        }
+        if self.registry.is_none() {
+            header.push("r fetches the list of available doc sets ".dark_gray());
+        }
+
+        let items = manager
+            .rows
+            .iter()
+            .map(|row| {
+                let outdated = self
+                    .updates
+                    .iter()
+                    .any(|update| update.installed.slug == row.slug);
+                let state = if outdated {
+                    "update      ".magenta()
+                } else if row.installed {
+                    "installed   ".green()
+                } else if manager.recommended.contains(&row.slug) {
+                    "recommended ".yellow()
+                } else {
+                    "            ".into()
+                };
+                let mut spans = vec![
+                    state,
+                    row.name.clone().bold(),
+                    format!("  {}", row.slug).cyan(),
+                ];
+                if !row.version.is_empty() {
+                    spans.push(format!("  {}", row.version).dark_gray());
+                }
+                // Installed doc sets show what they take up, others what they would download
+                let size = row.disk_size.unwrap_or(row.db_size);
+                if size > 0 {
+                    let megabytes = size as f64 / 1_000_000.0;
+                    spans.push(format!("  {megabytes:.1} MB").dark_gray());
+                }
+                ListItem::new(Line::from(spans))
+            })
+            .collect();
+        let list = list(items, &self.config, self.color_support)
+            .block(Block::bordered().title(Line::from(header)));
+        let mut state = ListState::default().with_selected(Some(manager.selected));
+        frame.render_stateful_widget(list, area, &mut state);
+
+        if let Some(row) = manager
+            .rows
+            .get(manager.selected)
+            .filter(|_| manager.about_open)
+        {
+            draw_about(frame, area, row);
+        }
+    }
+
+    /// The most opened entries next to the most used doc sets, with the totals above them
+    fn draw_stats(&self, frame: &mut Frame, area: Rect) {
+        let block = Block::bordered().title(" Usage stats ".bold());
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+        if self.stats.is_empty() {
+            frame.render_widget(
+                Paragraph::new("Nothing opened or searched yet".dark_gray()).centered(),
+                inner,
+            );
+            return;
+        }
+
+        let [totals_area, lists_area] =
+            Layout::vertical([Constraint::Length(2), Constraint::Min(0)]).areas(inner);
+        frame.render_widget(
+            Paragraph::new(Line::from(vec![
+                " Entries opened: ".into(),
+                self.stats.opens().to_string().bold(),
+                ", searches run: ".into(),
+                self.stats.searches().to_string().bold(),
+                ", kept on this machine only".dark_gray(),
+            ])),
+            totals_area,
+        );
+
+        let rows = usize::from(lists_area.height.saturating_sub(2));
+        let [entries_area, doc_sets_area] =
+            Layout::horizontal([Constraint::Fill(2), Constraint::Fill(1)]).areas(lists_area);
+        let entries: Vec<Line> = self
+            .stats
+            .top_entries(rows)
+            .into_iter()
+            .map(|entry| {
+                Line::from(vec![
+                    format!("{:>5} ", entry.count).yellow(),
+                    entry.title.clone().bold(),
+                    format!("  {}", entry.slug).cyan(),
+                ])
+            })
+            .collect();
+        frame.render_widget(
+            Paragraph::new(entries).block(Block::bordered().title(" Most opened entries ")),
+            entries_area,
+        );
+        let installed = |slug: &str| self.installed.iter().any(|installed| installed == slug);
+        let doc_sets: Vec<Line> = self
+            .stats
+            .top_doc_sets(rows)
+            .into_iter()
+            .map(|(slug, count)| {
+                let mut line = vec![format!("{count:>5} ").yellow(), slug.to_owned().cyan()];
+                if !installed(slug) {
+                    line.push("  removed".dark_gray());
+                }
+                Line::from(line)
+            })
+            .collect();
+        frame.render_widget(
+            Paragraph::new(doc_sets).block(Block::bordered().title(" Most used doc sets ")),
+            doc_sets_area,
+        );
+    }
+
+    fn draw_missing(&self, frame: &mut Frame, area: Rect) {
+        let Some(missing) = &self.missing else {
+            return;
+        };
+        let title = if missing.mismatch {
+            " Content unavailable "
+        } else {
+            " Entry not found "
+        };
+        let block = Block::bordered().title(title.red().bold());
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+
+        let [message_area, suggestions_area] =
+            Layout::vertical([Constraint::Length(3), Constraint::Min(0)]).areas(inner);
+        let name = vec![
+            missing.title.clone().bold(),
+            format!(" ({}/{})", missing.slug, missing.path).dark_gray(),
+        ];
+        let message = Text::from(if missing.mismatch {
+            vec![
+                Line::from(name),
+                Line::from(vec![
+                    "Content unavailable for this entry (index/db mismatch — try re-syncing). "
+                        .into(),
+                    "r".bold(),
+                    " re-syncs ".into(),
+                    missing.slug.clone().bold(),
+                    ".".into(),
+                ]),
+            ]
+        } else {
+            vec![
+                Line::from([name, vec![" no longer exists.".into()]].concat()),
+                Line::from(if missing.suggestions.is_empty() {
+                    "No similarly named entries were found."
+                } else {
+                    "It may have moved. Similarly named entries:"
+                }),
+            ]
+        });
+        frame.render_widget(Paragraph::new(message), message_area);
+
+        let list = result_list(&missing.suggestions, &self.config, self.color_support);
+        let mut state = ListState::default().with_selected(Some(missing.selected));
+        frame.render_stateful_widget(list, suggestions_area, &mut state);
+    }
+
+    fn draw_entry(&self, frame: &mut Frame, area: Rect, entry: &OpenEntry) {
+        let mut title = vec![
+            format!(" {} ", entry.title).bold(),
+            Span::styled(
+                entry.slug.clone(),
+                self.config
+                    .doc_set_colors
+                    .style(&entry.slug, self.color_support),
+            ),
+            format!("/{} ", entry.path).dark_gray(),
+        ];
+        let version = self
+            .manifests
+            .get(&entry.slug)
+            .map(Manifest::display_version)
+            .filter(|version| !version.is_empty());
+        if let Some(version) = version {
+            title.push(format!("v{version} ").yellow());
+        }
+        if entry.deprecated {
+            title.push("deprecated ".red().bold());
+        }
+        if let Some(heading) = entry.section.and_then(|i| entry.document.headings.get(i)) {
+            title.push(format!("§ {} ", heading.title).magenta());
+        }
+        if entry.stale {
+            title.push("updated, R reloads ".yellow());
+        }
+        match self.auto_scroll {
+            Some(AutoScroll { paused: true, .. }) => {
+                title.push("auto-scroll paused, a resumes ".dark_gray())
+            }
+            Some(_) => title.push("auto-scrolling ".green()),
+            None => {}
+        }
+        let title = Line::from(title);
+
+        let area = if self.outline_shown(frame.area().width) {
+            let [viewer_area, outline_area] = Layout::horizontal([
+                Constraint::Min(0),
+                Constraint::Length(self.config.outline_width),
+            ])
+            .areas(area);
+            self.draw_outline(frame, outline_area, entry);
+            viewer_area
+        } else {
+            area
+        };
+        let mut lines = entry.lines().to_vec();
+        let selection = self
+            .selection
+            .filter(|_| matches!(self.input_mode, InputMode::Visual));
+        if let Some(selection) = selection {
+            let style = self.config.selection.style(self.color_support);
+            let selected = selection.lines();
+            let first_line = entry.first_line();
+            for line in &mut lines[selected.start - first_line..selected.end - first_line] {
+                *line = std::mem::take(line).patch_style(style);
+            }
+        }
+        let mut viewer = Paragraph::new(Text::from(lines))
+            .scroll((self.scroll, self.hscroll))
+            .block(self.panel_block(Panel::Viewer).title(title));
+        if self.config.wrap {
+            viewer = viewer.wrap(Wrap { trim: false });
+        }
+        frame.render_widget(viewer, area);
+    }
+
+    /// Which config file was loaded and how it was found, and where the data is kept, in the
+    /// bottom right corner
+    fn draw_debug(&self, frame: &mut Frame, area: Rect) {
+        let config = match (&self.config_path, self.config_origin) {
+            (Some(path), Some(origin)) => format!("{} (via {origin})", path.display()),
+            _ => "none, using the defaults".to_owned(),
+        };
+        let lines = vec![
+            Line::from(vec!["config ".bold(), config.into()]),
+            Line::from(vec![
+                "data   ".bold(),
+                self.storage.root().display().to_string().into(),
+            ]),
+        ];
+        let width =
+            (lines.iter().map(Line::width).max().unwrap_or_default() as u16 + 4).min(area.width);
+        let height = (lines.len() as u16 + 2).min(area.height);
+        let overlay = Rect::new(
+            area.right().saturating_sub(width),
+            area.bottom().saturating_sub(height),
+            width,
+            height,
+        );
+        frame.render_widget(Clear, overlay);
+        frame.render_widget(
+            Paragraph::new(lines).block(
+                Block::bordered()
+                    .title(" Debug ")
+                    .padding(Padding::horizontal(1)),
+            ),
+            overlay,
+        );
+    }
+
+    /// The most recent notifications, stacked in the top right corner, newest at the bottom
+    fn draw_notifications(&self, frame: &mut Frame, area: Rect) {
+        let area = area.inner(Margin::new(1, 1));
+        let mut y = area.y;
+        for notification in self.notifications.visible(NOTIFICATIONS_SHOWN) {
+            if y + 3 > area.bottom() {
+                break;
+            }
+            let color = self.config.notifications.color(notification.severity);
+            let width = (Span::raw(notification.message.as_str()).width() as u16 + 4)
+                .min(area.width)
+                .min(NOTIFICATION_WIDTH);
+            let toast = Rect::new(area.right().saturating_sub(width), y, width, 3);
+            let text = Paragraph::new(notification.message.as_str()).block(
+                Block::bordered()
+                    .border_style(Style::default().fg(color))
+                    .padding(Padding::horizontal(1)),
+            );
+            frame.render_widget(Clear, toast);
+            frame.render_widget(text, toast);
+            y += 3;
+        }
+    }
+
+    /// The two versions of an entry side by side, older on the left, with the lines removed
+    /// and added between them colored
+    fn draw_comparison(&self, frame: &mut Frame, area: Rect) {
+        let Some(comparison) = &self.comparison else {
+            return;
+        };
+        let theme = self.config.diff;
+        let side = |(slug, lines): &(String, Vec<Line<'static>>),
+                    line_of: fn(&diff::Row) -> Option<usize>,
+                    (marker, label): (&'static str, &str),
+                    color: Color| {
+            let shown: Vec<Line> = comparison.rows[comparison.scroll..]
+                .iter()
+                .take(usize::from(area.height))
+                .map(|row| match line_of(row).and_then(|i| lines.get(i)) {
+                    Some(line) if row.changed => {
+                        let mut line = line.clone().patch_style(Style::default().fg(color));
+                        line.spans.insert(0, Span::raw(marker).fg(color));
+                        line
+                    }
+                    Some(line) => {
+                        let mut line = line.clone();
+                        line.spans.insert(0, Span::raw("  "));
+                        line
+                    }
+                    None => Line::default(),
+                })
+                .collect();
+            let changed = comparison
+                .rows
+                .iter()
+                .filter(|row| row.changed && line_of(row).is_some())
+                .count();
+            let title = Line::from(vec![
+                format!(" {slug} ").bold(),
+                format!("{changed} {label} ").fg(color),
+            ]);
+            Paragraph::new(shown).block(Block::bordered().title(title))
+        };
+        let [old_area, new_area] =
+            Layout::horizontal([Constraint::Fill(1), Constraint::Fill(1)]).areas(area);
+        frame.render_widget(
+            side(
+                &comparison.old,
+                |row| row.old,
+                ("- ", "removed"),
+                theme.removed,
+            ),
+            old_area,
+        );
+        frame.render_widget(
+            side(&comparison.new, |row| row.new, ("+ ", "added"), theme.added),
+            new_area,
+        );
+    }
+
+    /// The headings of the open entry, with the one of the section at the top of the viewer
+    /// highlighted
+    fn draw_outline(&self, frame: &mut Frame, area: Rect, entry: &OpenEntry) {
+        let headings = entry.shown_headings();
+        let top = self.top_line();
+        let current = headings.iter().rposition(|heading| heading.line <= top);
+        let items: Vec<ListItem> = headings
+            .iter()
+            .map(|heading| {
+                let indent = "  ".repeat(usize::from(heading.level.saturating_sub(1)));
+                ListItem::new(format!("{indent}{}", heading.title))
+            })
+            .collect();
+        let list = list(items, &self.config, self.color_support)
+            .block(self.panel_block(Panel::Outline).title(" Outline "));
+        let mut state = ListState::default().with_selected(current);
+        frame.render_stateful_widget(list, area, &mut state);
+    }
+
+    /// The banner, colored as configured and as far as the terminal allows
+    fn banner(&self) -> Text<'static> {
+        let banner = &self.config.banner;
+        match self.color_support {
+            ColorSupport::None => Text::raw(DEVDOCS_ASCII_BANNER),
+            ColorSupport::TrueColor if banner.gradient => {
+                let width = Text::raw(DEVDOCS_ASCII_BANNER).width();
+                Text::from(
+                    DEVDOCS_ASCII_BANNER
+                        .lines()
+                        .map(|line| {
+                            gradient_line(line, width, banner.gradient_start, banner.gradient_end)
+                        })
+                        .collect::<Vec<_>>(),
+                )
+            }
+            _ if banner.gradient => {
+                Text::raw(DEVDOCS_ASCII_BANNER).style(Style::default().fg(banner.accent))
+            }
+            _ => Text::raw(DEVDOCS_ASCII_BANNER),
+        }
+    }
+
+    /// What the config has fill the screen while the search box is empty, falling back to
+    /// the banner while that would be an empty list
+    fn draw_home(&self, frame: &mut Frame, area: Rect) {
+        if self.home_rows() == 0 {
+            return self.draw_banner(frame, area);
+        }
+        let selected = self.home_selected.min(self.home_rows() - 1);
+        let (items, title): (Vec<ListItem>, _) = match self.config.empty_query_view {
+            EmptyQueryView::Banner => return self.draw_banner(frame, area),
+            EmptyQueryView::Recent => (
+                self.recent
+                    .entries()
+                    .iter()
+                    .enumerate()
+                    .map(|(i, entry)| {
+                        let key = if i < RECENT_SHOWN {
+                            format!("{} ", i + 1)
+                        } else {
+                            "  ".to_owned()
+                        };
+                        ListItem::new(Line::from(vec![
+                            key.bold(),
+                            "◷ ".cyan(),
+                            entry.title.clone().into(),
+                            format!("  {}", entry.slug).dark_gray(),
+                        ]))
+                    })
+                    .collect(),
+                " Recently viewed ".to_owned(),
+            ),
+            EmptyQueryView::Browse => (
+                self.installed
+                    .iter()
+                    .map(|slug| {
+                        let manifest = self.manifests.get(slug);
+                        let name = manifest.map_or(slug.as_str(), |manifest| &manifest.name);
+                        let version = manifest.map_or("", Manifest::display_version);
+                        ListItem::new(Line::from(vec![
+                            name.to_owned().bold(),
+                            format!("  {slug}").dark_gray(),
+                            format!("  {version}").yellow(),
+                        ]))
+                    })
+                    .collect(),
+                format!(" Browse {} doc sets ", self.installed.len()),
+            ),
+        };
+        let list = list(items, &self.config, self.color_support)
+            .block(Block::bordered().title(title.cyan()));
+        let mut state = ListState::default().with_selected(Some(selected));
+        frame.render_stateful_widget(list, area, &mut state);
+    }
+
+    /// The banner, followed by the recently viewed entries while the search box is empty
+    fn draw_banner(&self, frame: &mut Frame, area: Rect) {
+        let banner = self.banner();
+
+        let recent: Vec<Line> = if self.input.is_empty() {
+            self.recent
+                .entries()
+                .iter()
+                .take(RECENT_SHOWN)
+                .enumerate()
+                .map(|(i, entry)| {
+                    Line::from(vec![
+                        format!("{} ", i + 1).bold(),
+                        "◷ ".cyan(),
+                        entry.title.clone().into(),
+                        format!("  {}", entry.slug).dark_gray(),
+                    ])
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        let recent_height = if recent.is_empty() {
+            0
+        } else {
+            recent.len() as u16 + 2
+        };
+        // The minimal layout leaves out the banner, keeping the recent entries
+        let banner_height = if self.config.minimal {
+            0
+        } else {
+            banner.height() as u16
+        };
+        let [banner_area, recent_area] = Layout::vertical([
+            Constraint::Length(banner_height),
+            Constraint::Length(recent_height),
+        ])
+        .flex(Flex::Center)
+        .areas(area);
+
+        if !self.config.minimal {
+            let centered_banner_area = center(
+                banner_area,
+                Constraint::Length(banner.width() as u16),
+                Constraint::Length(banner_height),
+            );
+            frame.render_widget(banner, centered_banner_area);
+        }
+
+        if !recent.is_empty() {
+            let width = recent.iter().map(Line::width).max().unwrap_or_default() as u16 + 2;
+            let recent_area = center(
+                recent_area,
+                Constraint::Length(width.max(20)),
+                Constraint::Length(recent_height),
+            );
+            let recent = Paragraph::new(recent).block(
+                Block::bordered()
+                    .title(" Recently viewed ".cyan())
+                    .border_style(Style::default().fg(Color::DarkGray)),
+            );
+            frame.render_widget(recent, recent_area);
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "../testing.rs"]
+mod testing;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+    use ratatui::{backend::TestBackend, buffer::Buffer, Terminal};
+    use std::ops::{Deref, DerefMut};
+
+    /// An app whose doc sets are kept in a directory removed again along with the app
+    struct TestApp {
+        app: App,
+        _dir: TempDir,
+    }
+
+    impl Deref for TestApp {
+        type Target = App;
+
+        fn deref(&self) -> &App {
+            &self.app
+        }
+    }
+
+    impl DerefMut for TestApp {
+        fn deref_mut(&mut self) -> &mut App {
+            &mut self.app
+        }
+    }
+
+    /// An app over doc sets written to a fresh directory, each with the entries `a`, `b#x`
+    /// and `c` and the given content for `a`
+    fn app(name: &str, doc_sets: &[(&str, &str)]) -> TestApp {
+        let dir = TempDir::new(name);
+        let storage = Storage::at(&*dir);
+        for (slug, content) in doc_sets {
+            let dir = storage.doc_set_dir(slug);
+            std::fs::create_dir_all(&dir).unwrap();
+            let index = serde_json::json!({ "entries": [
+                { "name": "a", "path": "a", "type": "t" },
+                { "name": "b", "path": "b#x", "type": "t" },
+                { "name": "c", "path": "c", "type": "t" },
+            ] });
+            std::fs::write(dir.join("index.json"), index.to_string()).unwrap();
+            let db = serde_json::json!({ "a": content, "b": "<p id=\"x\">b</p>", "c": "<p>c</p>" });
+            std::fs::write(dir.join("db.json"), db.to_string()).unwrap();
+        }
+        TestApp {
+            app: App::new(
+                storage,
+                HttpClient::new(None, 1).unwrap(),
+                Config::default(),
+            ),
+            _dir: dir,
+        }
+    }
+
+    fn press(app: &mut App, code: KeyCode) {
+        let _ = app.handle_key(KeyEvent::new(code, KeyModifiers::NONE));
+    }
+
+    fn type_text(app: &mut App, text: &str) {
+        for c in text.chars() {
+            press(app, KeyCode::Char(c));
+        }
+    }
+
+    fn render(app: &App) -> Buffer {
+        let mut terminal = Terminal::new(TestBackend::new(100, 30)).unwrap();
+        terminal.draw(|frame| app.draw(frame)).unwrap();
+        terminal.backend().buffer().clone()
+    }
+
+    fn row(buffer: &Buffer, y: u16) -> String {
+        (0..buffer.area.width)
+            .map(|x| buffer[(x, y)].symbol())
+            .collect()
+    }
+
+    /// The row whose text contains `text`, if any
+    fn find_row(buffer: &Buffer, text: &str) -> Option<u16> {
+        (0..buffer.area.height).find(|&y| row(buffer, y).contains(text))
+    }
+
+    #[test]
+    fn searching_shows_the_search_box_and_its_help() {
+        let mut app = app("searching", &[("mydocs", "<p>a</p>")]);
+        type_text(&mut app, "a");
+        let buffer = render(&app);
+
+        let input = find_row(&buffer, "Search").expect("the search box is titled");
+        assert!(row(&buffer, input + 1).contains("┃a "));
+        assert!(row(&buffer, 29).starts_with("Return open top result"));
+        assert!(find_row(&buffer, "a  mydocs").is_some());
+    }
+
+    #[test]
+    fn normal_mode_highlights_the_selected_result() {
+        let mut app = app("normal", &[("mydocs", "<p>a</p>")]);
+        type_text(&mut app, "a");
+        press(&mut app, KeyCode::Esc);
+        assert!(matches!(app.input_mode, InputMode::Normal));
+        let buffer = render(&app);
+
+        assert!(row(&buffer, 29).starts_with("q exit"));
+        let selected = find_row(&buffer, "a  mydocs").expect("the result is listed");
+        let x = row(&buffer, selected).find("a  mydocs").unwrap();
+        let x = row(&buffer, selected)[..x].chars().count() as u16;
+        assert!(buffer[(x, selected)].modifier.contains(Modifier::REVERSED));
+    }
+
+    #[tokio::test]
+    async fn removing_a_doc_sets_files_rebuilds_the_index() {
+        let mut app = app("index-rebuild", &[("one", "<p>a</p>"), ("two", "<p>a</p>")]);
+        // The first check starts the record of what the index was built from
+        assert!(docset_manager::check_index(&app.storage)
+            .unwrap()
+            .is_empty());
+        app.start_index_check();
+        assert!(app.index_rebuild.is_none());
+
+        std::fs::remove_dir_all(app.storage.doc_set_dir("two")).unwrap();
+        let stale = docset_manager::check_index(&app.storage).unwrap();
+        assert_eq!(stale.removed, ["two"]);
+        app.start_index_check();
+        assert!(app.index_rebuild.is_some());
+        while !app.poll_index_rebuild() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        assert_eq!(app.installed, ["one"]);
+        assert!(app.index.entries.iter().all(|entry| entry.slug == "one"));
+        assert!(docset_manager::check_index(&app.storage)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn typing_searches_once_it_pauses_for_the_debounce() {
+        let mut app = app("debounce", &[("mydocs", "<p>a</p>")]);
+        app.config.search_debounce = Duration::from_millis(200);
+        let start = Instant::now();
+        type_text(&mut app, "a");
+        type_text(&mut app, "b");
+        let due = app
+            .search_due
+            .expect("the search waits for typing to pause");
+        assert!(due >= start + Duration::from_millis(200));
+        assert!(app.results.is_empty());
+        assert!(find_row(&render(&app), "searching…").is_some());
+
+        // Each key pushes the search back, so none runs before the interval passes
+        assert!(!app.poll_debounce(due - Duration::from_millis(1)));
+        press(&mut app, KeyCode::Backspace);
+        let pushed_back = app.search_due.unwrap();
+        assert!(pushed_back >= due);
+        assert!(!app.poll_debounce(due));
+        assert!(app.results.is_empty());
+
+        assert!(app.poll_debounce(pushed_back));
+        assert_eq!(app.search_due, None);
+        assert_eq!(app.results.len(), 1);
+        assert!(find_row(&render(&app), "searching…").is_none());
+    }
+
+    #[test]
+    fn max_results_caps_the_results() {
+        let mut app = app("max-results", &[("one", "<p>a</p>"), ("two", "<p>a</p>")]);
+        type_text(&mut app, "a");
+        assert_eq!(app.results.len(), 2);
+        app.config.max_results = 1;
+        app.update_results();
+        assert_eq!(app.results.len(), 1);
+    }
+
+    #[test]
+    fn search_results_export_as_a_list_of_links() {
+        let app = app("export", &[("one", "<p>a</p>"), ("two", "<p>a</p>")]);
+        let file = app.storage.root().join("results.md");
+        let config = Config {
+            max_results: 1,
+            ..Config::default()
+        };
+        search_command(&app.storage, &config, "a in:one", false, None, Some(&file)).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&file).unwrap(),
+            "Results for `a in:one` in one\n\n- [a](https://devdocs.io/one/a) (one)\n"
+        );
+
+        search_command(
+            &app.storage,
+            &Config::default(),
+            "a",
+            false,
+            Some(ListFormat::Text),
+            Some(&file),
+        )
+        .unwrap();
+        let list = std::fs::read_to_string(&file).unwrap();
+        assert!(list.starts_with("Results for \"a\" in all doc sets\n\n"));
+        assert_eq!(list.matches("https://devdocs.io/").count(), 2);
+    }
+
+    #[test]
+    fn reloading_an_updated_entry_keeps_the_place_in_it() {
+        let entry = |before: usize| {
+            let paragraphs = |n: usize| "<p>text</p>".repeat(n);
+            format!(
+                "<h2 id=\"intro\">Intro</h2>{}<h2 id=\"later\">Later</h2>{}",
+                paragraphs(before),
+                paragraphs(40)
+            )
+        };
+        let mut app = app("reload", &[("mydocs", &entry(10))]);
+        app.config.toc_min_headings = 0;
+        app.load_entry("mydocs", "a", "a");
+        app.scroll = app.anchor_scroll("later") + 2;
+        let before = app.entry.as_ref().unwrap().document.anchors["later"];
+        let db_path = app.storage.doc_set_dir("mydocs").join("db.json");
+        let db = |a: &str| serde_json::json!({ "a": a, "c": "<p>c</p>" }).to_string();
+        std::fs::write(&db_path, db(&entry(30))).unwrap();
+
+        app.offer_reload("other");
+        assert!(!app.entry.as_ref().unwrap().stale);
+        app.offer_reload("mydocs");
+        assert!(find_row(&render(&app), "updated, R reloads").is_some());
+        press(&mut app, KeyCode::Char('R'));
+        let entry = app.entry.as_ref().unwrap();
+        assert!(!entry.stale);
+        assert!(entry.document.anchors["later"] > before);
+        assert_eq!(app.scroll, app.anchor_scroll("later") + 2);
+
+        // An entry the update removed is looked for like a broken link
+        std::fs::write(&db_path, serde_json::json!({ "c": "<p>c</p>" }).to_string()).unwrap();
+        press(&mut app, KeyCode::Char('R'));
+        assert!(matches!(app.input_mode, InputMode::EntryNotFound));
+    }
+
+    #[test]
+    fn selected_lines_are_highlighted_across_their_wrapped_rows() {
+        let long = "word ".repeat(40);
+        let entry = format!("<p>first</p><p>{long}</p><p>last</p>");
+        let mut app = app("visual", &[("mydocs", &entry)]);
+        type_text(&mut app, "a");
+        press(&mut app, KeyCode::Enter);
+        press(&mut app, KeyCode::Char('V'));
+        assert!(matches!(app.input_mode, InputMode::Visual));
+        press(&mut app, KeyCode::Char('j'));
+        press(&mut app, KeyCode::Char('j'));
+        let lines = app.selection.unwrap().lines();
+        assert_eq!(lines, 0..3);
+        let copied = app.entry.as_ref().unwrap().document.plain_lines(lines);
+        assert!(copied.starts_with("first\n\nword word"), "{copied:?}");
+
+        // Every row the long paragraph wraps into is highlighted
+        let buffer = render(&app);
+        let first = find_row(&buffer, "word").unwrap();
+        for y in [first, first + 1] {
+            assert!(buffer[(1, y)].modifier.contains(Modifier::REVERSED));
+        }
+        press(&mut app, KeyCode::Char('G'));
+        press(&mut app, KeyCode::Char('g'));
+        assert_eq!(app.selection.unwrap().lines(), 0..1);
+        press(&mut app, KeyCode::Esc);
+        assert!(matches!(app.input_mode, InputMode::Viewing));
+        assert!(!render(&app)[(1, 1)].modifier.contains(Modifier::REVERSED));
+    }
+
+    #[test]
+    fn auto_scrolling_pauses_on_any_key_and_stops_at_the_end() {
+        let entry = "<p>line</p>".repeat(40);
+        let mut app = app("auto-scroll", &[("mydocs", &entry)]);
+        app.config.tick_rate = Duration::from_millis(250);
+        app.config.auto_scroll_interval = Duration::from_millis(500);
+        type_text(&mut app, "a");
+        press(&mut app, KeyCode::Enter);
+        press(&mut app, KeyCode::Char('a'));
+        app.on_tick();
+        assert_eq!(app.scroll, 0);
+        app.on_tick();
+        assert_eq!(app.scroll, 1);
+
+        // Any other key pauses it, and `a` resumes it
+        press(&mut app, KeyCode::Char('j'));
+        assert_eq!(app.scroll, 2);
+        for _ in 0..4 {
+            app.on_tick();
+        }
+        assert_eq!(app.scroll, 2);
+        press(&mut app, KeyCode::Char('-'));
+        assert_eq!(app.config.auto_scroll_interval, Duration::from_millis(750));
+        press(&mut app, KeyCode::Char('a'));
+        for _ in 0..3 {
+            app.on_tick();
+        }
+        assert_eq!(app.scroll, 3);
+
+        let rows = app.rows_before(app.entry.as_ref().unwrap().lines());
+        app.scroll = rows - app.viewer_height() - 1;
+        for _ in 0..6 {
+            app.on_tick();
+        }
+        assert_eq!(app.scroll, rows - app.viewer_height());
+        assert!(app.auto_scroll.is_none());
+    }
+
+    #[test]
+    fn the_minimal_layout_leaves_the_screen_to_the_content() {
+        let mut app = app("minimal", &[("mydocs", "<p>first</p>")]);
+        type_text(&mut app, "a");
+        press(&mut app, KeyCode::Esc);
+        let buffer = render(&app);
+        assert_eq!(find_row(&buffer, "enter search"), Some(29));
+
+        press(&mut app, KeyCode::Char('z'));
+        assert!(app.config.minimal);
+        let buffer = render(&app);
+        assert_eq!(find_row(&buffer, "enter search"), None);
+        assert!(row(&buffer, 29).starts_with('┗'), "{}", row(&buffer, 29));
+
+        // The search box comes back while typing in it
+        press(&mut app, KeyCode::Char('i'));
+        let buffer = render(&app);
+        assert!(row(&buffer, 26).starts_with('└'), "{}", row(&buffer, 26));
+        assert!(row(&buffer, 29).starts_with('┗'), "{}", row(&buffer, 29));
+
+        press(&mut app, KeyCode::Enter);
+        assert!(matches!(app.input_mode, InputMode::Viewing));
+        let buffer = render(&app);
+        assert_eq!(find_row(&buffer, "first"), Some(1));
+        assert!(row(&buffer, 29).starts_with('└'), "{}", row(&buffer, 29));
+        press(&mut app, KeyCode::Char('z'));
+        assert!(!app.config.minimal);
+        assert_eq!(find_row(&render(&app), "pager"), Some(29));
+    }
+
+    #[test]
+    fn esc_steps_back_one_level_at_a_time() {
+        let entry = "<h2 id=\"one\">One</h2><p>1</p><h2 id=\"two\">Two</h2><p>2</p>";
+        let mut app = app("escape", &[("mydocs", entry)]);
+        app.config.toc_min_headings = 0;
+        let esc = |app: &mut App| app.handle_key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
+
+        // Between the search box and the results, which quit only if asked to
+        type_text(&mut app, "a");
+        assert!(esc(&mut app).is_continue());
+        assert!(matches!(app.input_mode, InputMode::Normal));
+        assert!(esc(&mut app).is_continue());
+        assert!(matches!(app.input_mode, InputMode::Searching));
+        app.config.esc_quits = true;
+        press(&mut app, KeyCode::Esc);
+        assert!(esc(&mut app).is_continue(), "the query isn't empty");
+        press(&mut app, KeyCode::Backspace);
+        press(&mut app, KeyCode::Esc);
+        assert!(esc(&mut app).is_break());
+
+        // From a section to the whole entry, then back to the results
+        type_text(&mut app, "ia");
+        press(&mut app, KeyCode::Enter);
+        assert!(matches!(app.input_mode, InputMode::Viewing));
+        press(&mut app, KeyCode::Char('t'));
+        assert!(matches!(app.input_mode, InputMode::Toc));
+        press(&mut app, KeyCode::Esc);
+        assert!(matches!(app.input_mode, InputMode::Viewing));
+        press(&mut app, KeyCode::Char('t'));
+        press(&mut app, KeyCode::Down);
+        press(&mut app, KeyCode::Down);
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.entry.as_ref().unwrap().section, Some(1));
+        press(&mut app, KeyCode::Esc);
+        assert_eq!(app.entry.as_ref().unwrap().section, None);
+        assert_eq!(
+            app.top_line(),
+            app.entry.as_ref().unwrap().document.anchors["two"]
+        );
+        assert!(matches!(app.input_mode, InputMode::Viewing));
+        press(&mut app, KeyCode::Esc);
+        assert!(matches!(app.input_mode, InputMode::Normal));
+
+        // Popups and menus are dismissed first
+        press(&mut app, KeyCode::Char('S'));
+        assert!(matches!(app.input_mode, InputMode::Stats));
+        press(&mut app, KeyCode::Esc);
+        assert!(matches!(app.input_mode, InputMode::Normal));
+        press(&mut app, KeyCode::Char(' '));
+        assert!(app.quick_look.is_some());
+        press(&mut app, KeyCode::Esc);
+        assert!(app.quick_look.is_none());
+        assert!(matches!(app.input_mode, InputMode::Normal));
+
+        // An entry opened from the doc set browser goes back to it
+        app.open_browser("mydocs");
+        press(&mut app, KeyCode::Enter);
+        press(&mut app, KeyCode::Enter);
+        assert!(matches!(app.input_mode, InputMode::Viewing));
+        press(&mut app, KeyCode::Esc);
+        assert!(matches!(app.input_mode, InputMode::Browse));
+    }
+
+    #[test]
+    fn paths_are_listed_before_they_exist() {
+        let app = app("paths", &[("mydocs", "<p>a</p>")]);
+        let config = app.storage.root().join("config").join("config.toml");
+        let paths = output::paths(&file_paths(&app.storage, Some(&config)));
+        let paths = paths["paths"].as_array().unwrap();
+        let names: Vec<&str> = paths.iter().filter_map(|p| p["name"].as_str()).collect();
+        assert_eq!(names, ["data", "docs", "config"]);
+        assert_eq!(paths[1]["exists"], true);
+        assert_eq!(paths[2]["path"], config.display().to_string());
+        assert_eq!(paths[2]["exists"], false);
+    }
+
+    #[test]
+    fn the_selection_is_marked_by_more_than_color() {
+        let mut app = app("selection", &[("mydocs", "<p>a</p>")]);
+        app.config.selection.background = Some(Color::Blue);
+        type_text(&mut app, "a");
+        press(&mut app, KeyCode::Esc);
+        let buffer = render(&app);
+        let selected = find_row(&buffer, "▎ a  mydocs").expect("the result has the bar");
+        let x = row(&buffer, selected).find("a  mydocs").unwrap();
+        let x = row(&buffer, selected)[..x].chars().count() as u16;
+        assert_eq!(buffer[(x, selected)].bg, Color::Blue);
+
+        // Without colors the row is reversed instead, and a bar that might not show is a `>`
+        app.color_support = ColorSupport::None;
+        app.config.selection.indicator = true;
+        let buffer = render(&app);
+        let selected = find_row(&buffer, "> a  mydocs").expect("the result has the marker");
+        let x = row(&buffer, selected).find("a  mydocs").unwrap();
+        let x = row(&buffer, selected)[..x].chars().count() as u16;
+        assert_eq!(buffer[(x, selected)].bg, Color::Reset);
+        assert!(buffer[(x, selected)].modifier.contains(Modifier::REVERSED));
+    }
+
+    #[test]
+    fn doc_sets_keep_their_color_across_the_screen() {
+        let mut app = app("doc-set-colors", &[("mydocs", "<p>a</p>")]);
+        app.color_support = ColorSupport::Ansi256;
+        type_text(&mut app, "a");
+        press(&mut app, KeyCode::Esc);
+        let slug_color = |app: &App, y: u16| {
+            let buffer = render(app);
+            let x = row(&buffer, y).find("mydocs").unwrap();
+            let x = row(&buffer, y)[..x].chars().count() as u16;
+            buffer[(x, y)].fg
+        };
+        let color = app.config.doc_set_colors.color("mydocs", app.color_support);
+        assert!(color.is_some());
+        assert_eq!(
+            color,
+            app.config
+                .doc_set_colors
+                .color("mydocs~2", app.color_support),
+            "versions share their family's color"
+        );
+        let result = find_row(&render(&app), "a  mydocs").unwrap();
+        assert_eq!(Some(slug_color(&app, result)), color);
+
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(Some(slug_color(&app, 0)), color);
+
+        app.config
+            .doc_set_colors
+            .custom
+            .push(("mydocs".to_owned(), Color::Red));
+        assert_eq!(slug_color(&app, 0), Color::Red);
+        app.color_support = ColorSupport::None;
+        assert_eq!(slug_color(&app, 0), Color::Reset);
+    }
+
+    #[test]
+    fn high_contrast_has_no_gray_text_and_underlines_the_selection() {
+        let mut app = app("high-contrast", &[("mydocs", "<p>a</p>")]);
+        app.config.theme = BuiltInTheme::HighContrast;
+        type_text(&mut app, "a");
+        press(&mut app, KeyCode::Esc);
+        let buffer = render(&app);
+
+        assert!(buffer.content.iter().all(|cell| {
+            !matches!(cell.fg, Color::DarkGray | Color::Gray)
+                && !cell.modifier.contains(Modifier::DIM)
+        }));
+        let selected = find_row(&buffer, "a  mydocs").expect("the result is listed");
+        let x = row(&buffer, selected).find("a  mydocs").unwrap();
+        let x = row(&buffer, selected)[..x].chars().count() as u16;
+        let modifier = buffer[(x, selected)].modifier;
+        assert!(modifier.contains(Modifier::REVERSED | Modifier::BOLD | Modifier::UNDERLINED));
+    }
+
+    #[test]
+    fn results_show_the_first_sentence_of_entries_with_prose() {
+        let mut app = app(
+            "abstracts",
+            &[
+                ("mydocs", "<p>Does one thing. Then another.</p>"),
+                ("other", "<pre>fn a() {}</pre>"),
+            ],
+        );
+        for slug in ["mydocs", "other"] {
+            docset_manager::write_abstracts(&app.storage, slug).unwrap();
+        }
+        app.reload_doc_sets();
+        type_text(&mut app, "a");
+        let buffer = render(&app);
+
+        let result = find_row(&buffer, "a  mydocs").expect("the prose entry is listed");
+        assert!(row(&buffer, result + 1).contains("    Does one thing. "));
+        assert!(!row(&buffer, result + 1).contains("Then"));
+        let result = find_row(&buffer, "a  other").expect("the code entry is listed");
+        assert!(!row(&buffer, result + 1).contains("fn a()"));
+    }
+
+    #[test]
+    fn compare_colors_the_changed_lines_of_each_version() {
+        let mut app = app(
+            "compare",
+            &[
+                ("mydocs~1", "<p>one</p><p>two</p>"),
+                ("mydocs~2", "<p>one</p><p>changed</p>"),
+            ],
+        );
+        app.load_entry("mydocs~2", "a", "a");
+        press(&mut app, KeyCode::Char('D'));
+        assert!(matches!(app.input_mode, InputMode::Compare));
+        let buffer = render(&app);
+
+        let title = find_row(&buffer, "mydocs~1").expect("both versions are titled");
+        assert!(row(&buffer, title).contains("mydocs~2"));
+        let removed = find_row(&buffer, "- two").expect("the removed line is marked");
+        let x = row(&buffer, removed).find("- two").unwrap() as u16;
+        assert_eq!(buffer[(x, removed)].fg, Color::Red);
+        let added = find_row(&buffer, "+ changed").expect("the added line is marked");
+        assert_eq!(added, removed);
+        assert!(row(&buffer, 29).starts_with("j/k scroll"));
+    }
+
+    #[test]
+    fn backspace_at_the_start_of_the_input_does_nothing() {
+        let mut app = app("backspace", &[("mydocs", "<p>a</p>")]);
+        type_text(&mut app, "ab");
+        press(&mut app, KeyCode::Left);
+        press(&mut app, KeyCode::Left);
+        press(&mut app, KeyCode::Backspace);
+        assert_eq!(app.input, "ab");
+        assert_eq!(app.character_index, 0);
+
+        press(&mut app, KeyCode::Right);
+        press(&mut app, KeyCode::Backspace);
+        assert_eq!(app.input, "b");
+        assert_eq!(app.character_index, 0);
+    }
+
+    #[test]
+    fn the_cursor_stays_within_the_input() {
+        let mut app = app("cursor", &[("mydocs", "<p>a</p>")]);
+        type_text(&mut app, "ab");
+        for _ in 0..3 {
+            press(&mut app, KeyCode::Right);
+        }
+        assert_eq!(app.character_index, 2);
+        for _ in 0..5 {
+            press(&mut app, KeyCode::Left);
+        }
+        assert_eq!(app.character_index, 0);
+        type_text(&mut app, "é");
+        assert_eq!(app.input, "éab");
+        assert_eq!(app.character_index, 1);
+    }
+
+    #[test]
+    fn submitting_opens_the_top_result_and_keeps_the_query() {
+        let mut app = app("submit", &[("mydocs", "<p>a</p>")]);
+        type_text(&mut app, "a");
+        assert_eq!(app.results.len(), 1);
+        press(&mut app, KeyCode::Enter);
+        assert!(matches!(app.input_mode, InputMode::Viewing));
+        assert_eq!(
+            app.entry.as_ref().map(|entry| entry.path.as_str()),
+            Some("a")
+        );
+        assert_eq!(app.input, "a");
+        assert_eq!(
+            app.search_history.queries().first().map(String::as_str),
+            Some("a")
+        );
+
+        press(&mut app, KeyCode::Esc);
+        press(&mut app, KeyCode::Char('i'));
+        type_text(&mut app, "zzz");
+        press(&mut app, KeyCode::Enter);
+        assert!(matches!(app.input_mode, InputMode::Searching));
+        assert_eq!(app.input, "azzz");
+    }
+
+    #[test]
+    fn the_selection_stays_within_the_results() {
+        let mut app = app(
+            "selection",
+            &[("mydocs", "<p>a</p>"), ("other", "<p>a</p>")],
+        );
+        type_text(&mut app, "a");
+        assert_eq!(app.rows.len(), 2);
+        press(&mut app, KeyCode::Up);
+        assert_eq!(app.selected, 0);
+        for _ in 0..3 {
+            press(&mut app, KeyCode::Down);
+        }
+        assert_eq!(app.selected, 1);
+
+        // A new query selects its best match
+        type_text(&mut app, "b");
+        assert_eq!(app.selected, 0);
+    }
+
+    #[test]
+    fn esc_steps_back_from_the_viewer_to_the_search_box() {
+        let mut app = app("esc", &[("mydocs", "<h2>One</h2><p>a</p>")]);
+        type_text(&mut app, "a");
+        press(&mut app, KeyCode::Esc);
+        assert!(matches!(app.input_mode, InputMode::Normal));
+        press(&mut app, KeyCode::Enter);
+        assert!(matches!(app.input_mode, InputMode::Viewing));
+        assert_eq!(
+            app.entry.as_ref().map(|entry| entry.path.as_str()),
+            Some("a")
+        );
+
+        press(&mut app, KeyCode::Char('t'));
+        assert!(matches!(app.input_mode, InputMode::Toc));
+        press(&mut app, KeyCode::Esc);
+        assert!(matches!(app.input_mode, InputMode::Viewing));
+        press(&mut app, KeyCode::Char('y'));
+        assert!(matches!(app.input_mode, InputMode::CopyMenu));
+        press(&mut app, KeyCode::Esc);
+        assert!(matches!(app.input_mode, InputMode::Viewing));
+
+        press(&mut app, KeyCode::Esc);
+        assert!(matches!(app.input_mode, InputMode::Normal));
+        press(&mut app, KeyCode::Char('i'));
+        assert!(matches!(app.input_mode, InputMode::Searching));
+        assert_eq!(app.input, "a");
+    }
+
+    #[test]
+    fn scope_keys_cycle_through_the_installed_doc_sets() {
+        let mut app = app("cycle-scope", &[("one", "<p>a</p>"), ("two", "<p>a</p>")]);
+        type_text(&mut app, "two:a");
+        press(&mut app, KeyCode::Esc);
+
+        press(&mut app, KeyCode::Char(']'));
+        assert_eq!(app.config.default_scope, ["one"]);
+        assert_eq!(app.input, "a");
+        assert!(app.results.iter().all(|result| result.entry.slug == "one"));
+        press(&mut app, KeyCode::Char(']'));
+        assert_eq!(app.config.default_scope, ["two"]);
+        assert!(find_row(&render(&app), "[two]").is_some());
+        press(&mut app, KeyCode::Char(']'));
+        assert!(app.config.default_scope.is_empty());
+        assert!(app.results.iter().any(|result| result.entry.slug == "one"));
+        press(&mut app, KeyCode::Char('['));
+        assert_eq!(app.config.default_scope, ["two"]);
+
+        // The next run searches the scope switched to, unless the config's own changed
+        let next_run = |default_scope: &[&str]| {
+            let config = Config {
+                default_scope: default_scope
+                    .iter()
+                    .map(|&scope| scope.to_owned())
+                    .collect(),
+                ..Config::default()
+            };
+            App::new(
+                app.storage.clone(),
+                HttpClient::new(None, 1).unwrap(),
+                config,
+            )
+        };
+        assert_eq!(next_run(&[]).config.default_scope, ["two"]);
+        assert_eq!(
+            next_run(&["one", "two"]).config.default_scope,
+            ["one", "two"]
+        );
+    }
+
+    #[test]
+    fn a_single_doc_set_has_no_scopes_to_cycle_through() {
+        let mut app = app("cycle-one-scope", &[("one", "<p>a</p>")]);
+        press(&mut app, KeyCode::Esc);
+        press(&mut app, KeyCode::Char(']'));
+        assert!(app.config.default_scope.is_empty());
+    }
+
+    #[test]
+    fn picking_a_section_shows_it_alone() {
+        let mut app = app(
+            "toc",
+            &[("mydocs", "<h2>One</h2><p>1</p><h2>Two</h2><p>2</p>")],
+        );
+        app.load_entry("mydocs", "a", "a");
+        press(&mut app, KeyCode::Char('t'));
+        press(&mut app, KeyCode::Down);
+        press(&mut app, KeyCode::Down);
+        press(&mut app, KeyCode::Enter);
+        assert!(matches!(app.input_mode, InputMode::Viewing));
+        assert_eq!(app.entry.as_ref().and_then(|entry| entry.section), Some(1));
+    }
+
+    #[test]
+    fn images_are_shown_as_placeholders() {
+        let content = "<p>See <img src=\"https://example.com/d.png\" alt=\" A  diagram \">, \
+                       <img src=\"/img/logo.svg?v=2\"> and <img src=\"data:image/png;base64,AA\">.</p>";
+        let mut app = app("images", &[("mydocs", content)]);
+        app.load_entry("mydocs", "a", "a");
+        let buffer = render(&app);
+        assert!(find_row(
+            &buffer,
+            "See [image: A diagram], [image: logo.svg] and [image]."
+        )
+        .is_some());
+
+        let images = &app.entry.as_ref().unwrap().document.images;
+        assert_eq!(images.len(), 3);
+        assert_eq!(images[0].alt.as_deref(), Some("A diagram"));
+        assert_eq!(images[1].alt, None);
+        assert!(find_row(&buffer, "I open image").is_some());
+    }
+
+    #[test]
+    fn an_entry_without_content_suggests_others() {
+        let mut app = app("missing", &[("mydocs", "<p>a</p>")]);
+        app.load_entry("mydocs", "gone", "c");
+        assert!(matches!(app.input_mode, InputMode::EntryNotFound));
+        assert_eq!(app.missing.as_ref().map(|m| m.suggestions.len()), Some(1));
+        press(&mut app, KeyCode::Enter);
+        assert!(matches!(app.input_mode, InputMode::Viewing));
+        assert_eq!(
+            app.entry.as_ref().map(|entry| entry.path.as_str()),
+            Some("c")
+        );
+    }
+
+    #[test]
+    fn a_link_to_a_missing_doc_set_offers_to_download_it() {
+        let mut app = app("link", &[("mydocs", "<p>a</p>")]);
+        type_text(&mut app, "https://devdocs.io/rust/std/index");
+        press(&mut app, KeyCode::Enter);
+        assert!(matches!(app.input_mode, InputMode::ConfirmSync));
+        assert_eq!(
+            app.sync_prompt.as_ref().map(|p| p.slug.as_str()),
+            Some("rust")
+        );
+        press(&mut app, KeyCode::Char('n'));
+        assert!(matches!(app.input_mode, InputMode::Normal));
+        assert!(app.sync_prompt.is_none());
+    }
+
+    #[test]
+    fn the_manager_and_browser_return_where_they_were_opened() {
+        let mut app = app("manage", &[("mydocs", "<p>a</p>")]);
+        type_text(&mut app, "a");
+        press(&mut app, KeyCode::Esc);
+        press(&mut app, KeyCode::Char('b'));
+        assert!(matches!(app.input_mode, InputMode::Browse));
+        press(&mut app, KeyCode::Enter);
+        assert!(app
+            .browser
+            .as_ref()
+            .is_some_and(|browser| browser.entries.is_some()));
+        press(&mut app, KeyCode::Esc);
+        press(&mut app, KeyCode::Esc);
+        assert!(matches!(app.input_mode, InputMode::Normal));
+
+        press(&mut app, KeyCode::Char('d'));
+        assert!(matches!(app.input_mode, InputMode::Manage));
+        press(&mut app, KeyCode::Char('b'));
+        assert!(matches!(app.input_mode, InputMode::Browse));
+        press(&mut app, KeyCode::Esc);
+        assert!(matches!(app.input_mode, InputMode::Manage));
+        press(&mut app, KeyCode::Esc);
+        assert!(matches!(app.input_mode, InputMode::Normal));
+        assert!(app.manager.is_none());
+    }
+
+    #[test]
+    fn compare_scrolls_within_its_rows_and_esc_returns_to_the_entry() {
+        let mut app = app(
+            "compare-keys",
+            &[
+                ("mydocs~1", "<p>1</p><p>2</p><p>3</p>"),
+                ("mydocs~2", "<p>1</p><p>two</p><p>3</p>"),
+            ],
+        );
+        app.load_entry("mydocs~1", "a", "a");
+        press(&mut app, KeyCode::Char('D'));
+        press(&mut app, KeyCode::Char(']'));
+        let comparison = app.comparison.as_ref().unwrap();
+        assert!(comparison.rows[comparison.scroll].changed);
+        assert!(comparison.rows[..comparison.scroll]
+            .iter()
+            .all(|row| !row.changed));
+        press(&mut app, KeyCode::Char('G'));
+        let last = app.comparison.as_ref().map(|c| c.rows.len() - 1);
+        assert_eq!(app.comparison.as_ref().map(|c| c.scroll), last);
+        press(&mut app, KeyCode::Char('g'));
+        assert_eq!(app.comparison.as_ref().map(|c| c.scroll), Some(0));
+
+        press(&mut app, KeyCode::Esc);
+        assert!(matches!(app.input_mode, InputMode::Viewing));
+        assert!(app.comparison.is_none());
+    }
+
+    #[test]
+    fn dot_repeats_the_last_repeatable_action_of_the_mode() {
+        let mut app = app("repeat", &[("mydocs", "<p>a</p>")]);
+        app.load_entry("mydocs", "a", "a");
+        let wrap = app.config.wrap;
+        press(&mut app, KeyCode::Char('u'));
+        assert_eq!(app.config.wrap, !wrap);
+        press(&mut app, KeyCode::Char('.'));
+        assert_eq!(app.config.wrap, wrap);
+
+        // Scrolling doesn't count, and the results don't repeat what the viewer did
+        press(&mut app, KeyCode::Char('G'));
+        press(&mut app, KeyCode::Char('.'));
+        assert_eq!(app.config.wrap, !wrap);
+        press(&mut app, KeyCode::Esc);
+        let preview = app.config.preview;
+        press(&mut app, KeyCode::Char('.'));
+        assert_eq!(app.config.wrap, !wrap);
+        assert_eq!(app.config.preview, preview);
+    }
+
+    #[test]
+    fn the_panic_hook_restores_the_terminal_before_reporting() {
+        let calls = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let (restored, reported) = (Arc::clone(&calls), Arc::clone(&calls));
+        let hook = restore_first(
+            move || restored.lock().unwrap().push("restore"),
+            move |_| reported.lock().unwrap().push("report"),
+        );
+
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(hook);
+        let result = std::panic::catch_unwind(|| panic!("boom"));
+        std::panic::set_hook(previous);
+        assert!(result.is_err());
+        assert_eq!(*calls.lock().unwrap(), ["restore", "report"]);
+    }
+
+    #[test]
+    fn the_debug_overlay_shows_which_config_file_was_loaded() {
+        let mut app = app("debug-overlay", &[("mydocs", "<p>a</p>")]);
+        press(&mut app, KeyCode::Esc);
+        type_text(&mut app, "\\D");
+        let buffer = render(&app);
+        let config = find_row(&buffer, "config ").expect("the overlay is shown");
+        assert!(row(&buffer, config).contains("none, using the defaults"));
+        let data = app.storage.root().display().to_string();
+        assert!(row(&buffer, config + 1).contains(&format!("data   {data}")));
+
+        app.config_path = Some(PathBuf::from("/etc/rtftui.toml"));
+        app.config_origin = Some(ConfigOrigin::Env);
+        let buffer = render(&app);
+        let config = find_row(&buffer, "config ").unwrap();
+        assert!(row(&buffer, config).contains("/etc/rtftui.toml (via RTFTUI_CONFIG)"));
+
+        type_text(&mut app, "\\D");
+        assert_eq!(find_row(&render(&app), "config "), None);
+    }
+
+    #[test]
+    fn searches_are_cached_until_the_doc_sets_change() {
+        let mut app = app("search-cache", &[("mydocs", "<p>a</p>")]);
+        let search = |app: &mut App, query: &str| {
+            app.input = query.to_owned();
+            app.update_results();
+            app.results
+                .iter()
+                .map(|result| result.entry.slug.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(search(&mut app, "a"), ["mydocs"]);
+
+        // With the index emptied behind its back, only a search made before still finds
+        // anything, from the cache
+        app.index = Arc::new(Index::default());
+        assert_eq!(search(&mut app, "a"), ["mydocs"]);
+        assert!(search(&mut app, "b").is_empty());
+
+        // Installing another doc set drops the cached results
+        let mydocs = app.storage.doc_set_dir("mydocs");
+        let otherdocs = app.storage.doc_set_dir("otherdocs");
+        std::fs::create_dir_all(&otherdocs).unwrap();
+        for file in ["index.json", "db.json"] {
+            std::fs::copy(mydocs.join(file), otherdocs.join(file)).unwrap();
+        }
+        app.reload_doc_sets();
+        assert_eq!(search(&mut app, "a"), ["mydocs", "otherdocs"]);
+
+        docset_manager::remove_doc_set(&app.storage, "mydocs").unwrap();
+        app.reload_doc_sets();
+        assert_eq!(search(&mut app, "a"), ["otherdocs"]);
+    }
+
+    #[test]
+    fn rows_past_the_scroll_limit_stop_at_it() {
+        let app = app("rows-before", &[("mydocs", "<p>a</p>")]);
+        let lines = vec![Line::from("x"); 70_000];
+        assert_eq!(app.rows_before(&lines[..100]), 100);
+        assert_eq!(app.rows_before(&lines), u16::MAX);
     }
 }
@@ -0,0 +1,1005 @@
+use crate::html::RenderOptions;
+use crate::net::parse_proxy;
+use crate::query::CaseMode;
+use crate::search::{Matcher, SortMode, MAX_RESULTS};
+use crate::storage::write_atomic;
+use crate::theme::{
+    BuiltInTheme, CalloutTheme, DiffTheme, DocSetColors, NotificationTheme, SelectionTheme,
+    TypeGlyphs,
+};
+use crate::toml;
+use directories::BaseDirs;
+use ratatui::style::Color;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use url::Url;
+
+/// User configuration, read from `config.toml`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    /// Doc set slugs searches are scoped to unless the query names its own scope.
+    /// Empty means every installed doc set is searched.
+    pub default_scope: Vec<String>,
+    pub banner: BannerConfig,
+    /// What fills the screen while the search box is empty
+    pub empty_query_view: EmptyQueryView,
+    /// What opening a search result does
+    pub open_action: OpenAction,
+    /// Interval between ticks of the event loop, which drive animations and timers
+    pub tick_rate: Duration,
+    /// Columns between tab stops in code blocks
+    pub tab_width: usize,
+    /// Order search results are listed in
+    pub sort_mode: SortMode,
+    /// Whether the case of letters counts when searching entry names
+    pub search_case: CaseMode,
+    /// How entry names are matched against the query
+    pub matcher: Matcher,
+    /// Order of the doc set management view
+    pub manager_sort: ManagerSort,
+    /// Doc sets whose results come first among equally good matches, most preferred first
+    pub doc_set_order: Vec<String>,
+    /// Results each doc set shows when results are grouped by doc set, 0 for all of them
+    pub max_per_group: usize,
+    /// Most results a search lists
+    pub max_results: usize,
+    /// How long typing has to pause before the results follow the query, zero to search
+    /// on every key
+    pub search_debounce: Duration,
+    /// Show the word count and reading time of the open entry
+    pub reading_time: bool,
+    /// Wrap long lines of the open entry, rather than scrolling sideways to see them
+    pub wrap: bool,
+    /// Hide the help line, the search box outside of searching and the banner, leaving
+    /// the screen to the content
+    pub minimal: bool,
+    /// Lines the viewer scrolls per press of Up/Down or step of the mouse wheel
+    pub scroll_lines: u16,
+    /// Animate jumps of more than one line over a few ticks
+    pub smooth_scroll: bool,
+    /// How long auto-scrolling waits before each line
+    pub auto_scroll_interval: Duration,
+    /// Blink the updates available hint in the help line, on terminals that can blink
+    pub blink: bool,
+    /// Never animate anything, overriding `smooth_scroll` and `blink`
+    pub reduced_motion: bool,
+    /// The look the theme tables adjust
+    pub theme: BuiltInTheme,
+    /// Capture the mouse for wheel scrolling, at the cost of the terminal's own text selection
+    pub mouse: bool,
+    /// Never touch the network, working only with what is downloaded and cached
+    pub offline: bool,
+    /// Reload the config whenever the file changes, checking it every tick
+    pub watch_config: bool,
+    /// Ask before removing doc sets, clearing history and the like
+    pub confirm_destructive: bool,
+    /// Quit when Esc is pressed in the results while the query is empty, where Esc has
+    /// nowhere further back to go
+    pub esc_quits: bool,
+    /// Check for doc set updates in the background after launch
+    pub auto_check_updates: bool,
+    /// Which doc sets are downloaded again in the background after launch
+    pub sync_on_startup: SyncOnStartup,
+    /// Proxy for every download instead of the one `HTTP_PROXY` or `HTTPS_PROXY` name, with
+    /// credentials in the URL if it needs any
+    pub proxy: Option<Url>,
+    /// Largest file a doc set download may fetch without asking, in megabytes
+    pub max_download_mb: Option<u64>,
+    /// Doc sets downloaded at once when syncing several, and connections kept open per host
+    pub max_connections: usize,
+    /// Keep downloaded doc sets in the data directory, to reinstall them from while devdocs
+    /// has no newer release
+    pub keep_archive: bool,
+    /// Where the recently viewed entries are kept instead of the data directory
+    pub recent_file: Option<PathBuf>,
+    /// Where past queries are kept instead of the data directory
+    pub search_history_file: Option<PathBuf>,
+    /// Shown once `use_nerd_font` is set or the theme has a `[type_glyphs]` table, unless
+    /// that table sets `enabled = false`
+    pub type_glyphs: TypeGlyphs,
+    /// Key that starts `<leader>` key sequences
+    pub leader: char,
+    /// Keys that switch the search to the next installed doc set, or to all of them after
+    /// the last, in the notation of key sequences
+    pub next_scope_key: String,
+    /// Keys that switch the search to the previous installed doc set
+    pub previous_scope_key: String,
+    /// How long a key sequence waits for its next key before settling for what was typed
+    pub sequence_timeout: Duration,
+    /// Open the table of contents of entries with at least this many headings, 0 for never
+    pub toc_min_headings: usize,
+    /// Show a panel listing the open entry's headings next to the viewer
+    pub outline: bool,
+    /// Columns the outline panel takes up
+    pub outline_width: u16,
+    /// Show the selected result's content next to the results
+    pub preview: bool,
+    /// How long the selection has to rest on a result before it is previewed
+    pub preview_delay: Duration,
+    /// Dim the results that were opened before, marking them as seen
+    pub mark_seen: bool,
+    /// Show the first sentence of each result under it, for entries with prose
+    pub abstracts: bool,
+    /// Show the module path of each result after its name, see `IndexEntry::namespace`
+    pub qualified_names: bool,
+    /// Mark entries as deprecated when their name, type or content say so
+    pub detect_deprecated: bool,
+    /// Show an entry found in several versions of a doc family once, from the newest version
+    pub merge_versions: bool,
+    /// Build a full-text index of each doc set when it is synced, for searching entry content
+    pub full_text_index: bool,
+    /// How notes and warnings in entries are set apart, from the `[callouts]` table
+    pub callouts: CalloutTheme,
+    /// How long notifications show before they go away by themselves
+    pub notification_duration: Duration,
+    /// Colors of notifications, from the `[notifications]` table
+    pub notifications: NotificationTheme,
+    /// Colors of lines added and removed between versions of an entry, from the `[diff]` table
+    pub diff: DiffTheme,
+    /// How the selected row and the focused panel stand out, from the `[selection]` table
+    pub selection: SelectionTheme,
+    /// Colors of doc set names, set by slug or family in the `[doc_set_colors]` table
+    pub doc_set_colors: DocSetColors,
+    /// File extensions for code blocks opened in an editor, by language, on top of the
+    /// built-in ones, from the `[editor_extensions]` table
+    pub editor_extensions: Vec<(String, String)>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            default_scope: Vec::new(),
+            banner: BannerConfig::default(),
+            empty_query_view: EmptyQueryView::default(),
+            open_action: OpenAction::default(),
+            tick_rate: Duration::from_millis(250),
+            tab_width: 4,
+            sort_mode: SortMode::default(),
+            search_case: CaseMode::default(),
+            matcher: Matcher::default(),
+            manager_sort: ManagerSort::default(),
+            doc_set_order: Vec::new(),
+            max_per_group: 10,
+            max_results: MAX_RESULTS,
+            search_debounce: Duration::ZERO,
+            reading_time: true,
+            wrap: true,
+            minimal: false,
+            scroll_lines: 1,
+            smooth_scroll: false,
+            auto_scroll_interval: Duration::from_millis(1500),
+            blink: false,
+            reduced_motion: false,
+            theme: BuiltInTheme::default(),
+            mouse: true,
+            watch_config: false,
+            offline: false,
+            auto_check_updates: true,
+            sync_on_startup: SyncOnStartup::default(),
+            confirm_destructive: true,
+            esc_quits: false,
+            proxy: None,
+            max_download_mb: None,
+            max_connections: 4,
+            keep_archive: false,
+            recent_file: None,
+            search_history_file: None,
+            type_glyphs: TypeGlyphs::default(),
+            callouts: CalloutTheme::default(),
+            notification_duration: Duration::from_millis(4000),
+            notifications: NotificationTheme::default(),
+            diff: DiffTheme::default(),
+            selection: SelectionTheme::default(),
+            doc_set_colors: DocSetColors::default(),
+            qualified_names: true,
+            mark_seen: false,
+            abstracts: true,
+            detect_deprecated: true,
+            merge_versions: false,
+            full_text_index: false,
+            leader: '\\',
+            next_scope_key: "]".to_owned(),
+            previous_scope_key: "[".to_owned(),
+            sequence_timeout: Duration::from_millis(1000),
+            toc_min_headings: 0,
+            outline: false,
+            outline_width: 30,
+            preview: true,
+            preview_delay: Duration::from_millis(150),
+            editor_extensions: Vec::new(),
+        }
+    }
+}
+
+/// What the screen shows while the search box is empty
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EmptyQueryView {
+    /// The banner, with the most recently viewed entries below it
+    #[default]
+    Banner,
+    /// Every recently viewed entry
+    Recent,
+    /// The installed doc sets, to browse one of them
+    Browse,
+}
+
+impl EmptyQueryView {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "banner" => Some(Self::Banner),
+            "recent" => Some(Self::Recent),
+            "browse" => Some(Self::Browse),
+            _ => None,
+        }
+    }
+}
+
+/// What opening a search result does, unless a modifier key says otherwise
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OpenAction {
+    /// Show it in the built-in viewer
+    #[default]
+    Viewer,
+    /// Open its page on devdocs.io in the browser
+    Browser,
+    /// Show it in the viewer and hand it to `$PAGER` right away
+    Pager,
+}
+
+impl OpenAction {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "viewer" => Some(Self::Viewer),
+            "browser" => Some(Self::Browser),
+            "pager" => Some(Self::Pager),
+            _ => None,
+        }
+    }
+}
+
+/// Which doc sets are synced in the background after launch
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SyncOnStartup {
+    /// None of them, leaving syncing to the user
+    #[default]
+    Never,
+    /// Those with updates available
+    IfStale,
+    /// Every installed doc set downloaded from devdocs
+    Always,
+}
+
+impl SyncOnStartup {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "never" => Some(Self::Never),
+            "if_stale" => Some(Self::IfStale),
+            "always" => Some(Self::Always),
+            _ => None,
+        }
+    }
+}
+
+/// Orders the doc set management view can list doc sets in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ManagerSort {
+    /// Installed doc sets first, in their search priority
+    #[default]
+    Installed,
+    /// Alphabetically by name
+    Name,
+    /// Largest on disk first, then those not installed by download size
+    Size,
+    /// Doc sets with updates first, then the other installed ones
+    Updates,
+}
+
+impl ManagerSort {
+    pub const ALL: [ManagerSort; 4] = [
+        ManagerSort::Installed,
+        ManagerSort::Name,
+        ManagerSort::Size,
+        ManagerSort::Updates,
+    ];
+
+    /// Name used for the order in the config file
+    pub fn name(self) -> &'static str {
+        match self {
+            ManagerSort::Installed => "installed",
+            ManagerSort::Name => "name",
+            ManagerSort::Size => "size",
+            ManagerSort::Updates => "updates",
+        }
+    }
+
+    /// How the order is described in the UI
+    pub fn label(self) -> &'static str {
+        match self {
+            ManagerSort::Installed => "installed first",
+            ManagerSort::Name => "name",
+            ManagerSort::Size => "size",
+            ManagerSort::Updates => "updates first",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|sort| sort.name() == name)
+    }
+
+    /// The order after this one, wrapping around
+    pub fn next(self) -> Self {
+        let i = Self::ALL
+            .iter()
+            .position(|&sort| sort == self)
+            .unwrap_or_default();
+        Self::ALL[(i + 1) % Self::ALL.len()]
+    }
+}
+
+/// How the splash screen banner is colored, from the `[banner]` table
+#[derive(Debug, Clone, PartialEq)]
+pub struct BannerConfig {
+    /// Color the banner with a horizontal gradient on truecolor terminals
+    pub gradient: bool,
+    pub gradient_start: Color,
+    pub gradient_end: Color,
+    /// Solid color used instead of the gradient on terminals with fewer colors
+    pub accent: Color,
+}
+
+impl Default for BannerConfig {
+    fn default() -> Self {
+        Self {
+            gradient: false,
+            gradient_start: Color::Rgb(0x4f, 0xac, 0xfe),
+            gradient_end: Color::Rgb(0xc4, 0x71, 0xed),
+            accent: Color::Cyan,
+        }
+    }
+}
+
+/// Where the path of the loaded config file came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigOrigin {
+    /// The `--config` command line flag
+    Flag,
+    /// The `RTFTUI_CONFIG` environment variable
+    Env,
+    /// The platform's config directory
+    Platform,
+}
+
+impl fmt::Display for ConfigOrigin {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            ConfigOrigin::Flag => "--config",
+            ConfigOrigin::Env => "RTFTUI_CONFIG",
+            ConfigOrigin::Platform => "the config directory",
+        })
+    }
+}
+
+impl Config {
+    /// Where the config file lives by default
+    pub fn default_path() -> Option<PathBuf> {
+        BaseDirs::new().map(|dirs| dirs.config_dir().join("rtftui").join("config.toml"))
+    }
+
+    /// The config file to load, searching in order of precedence: the `--config` flag, the
+    /// `RTFTUI_CONFIG` environment variable, then the platform's config directory
+    ///
+    /// The first of these that names an existing file wins. `None` means there is no config
+    /// file and the defaults apply.
+    pub fn locate(
+        flag: Option<PathBuf>,
+        env: Option<PathBuf>,
+        platform: Option<PathBuf>,
+    ) -> Option<(PathBuf, ConfigOrigin)> {
+        [
+            (flag, ConfigOrigin::Flag),
+            (env, ConfigOrigin::Env),
+            (platform, ConfigOrigin::Platform),
+        ]
+        .into_iter()
+        .filter_map(|(path, origin)| Some((path?, origin)))
+        .find(|(path, origin)| {
+            let exists = path.is_file();
+            if !exists && *origin != ConfigOrigin::Platform {
+                log::warn!(
+                    "Config file {} from {origin} does not exist",
+                    path.display()
+                );
+            }
+            exists
+        })
+    }
+
+    /// Load the config at `path`, using the defaults if the file does not exist
+    pub fn load(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    /// Persist a setting changed from within the app into the config file at `path`
+    ///
+    /// `literal` must be a valid TOML value. The rest of the file is left as it was.
+    pub fn save_setting(
+        path: &Path,
+        key: &str,
+        literal: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let source = if path.exists() {
+            std::fs::read_to_string(path)?
+        } else {
+            String::new()
+        };
+        write_atomic(path, toml::set_top_level(&source, key, literal).as_bytes())?;
+        Ok(())
+    }
+
+    /// How entries are rendered with these settings
+    pub fn render_options(&self) -> RenderOptions {
+        RenderOptions {
+            tab_width: self.tab_width,
+            callouts: self.callouts,
+        }
+    }
+
+    /// The download size cap in bytes, if there is one
+    pub fn max_download_bytes(&self) -> Option<u64> {
+        self.max_download_mb.map(|megabytes| megabytes * 1_000_000)
+    }
+
+    /// Parse a config file, using the defaults for any keys it leaves out
+    pub fn parse(source: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let table = toml::parse(source)?;
+        let mut config = Self::default();
+
+        // First, as the theme tables further down adjust the theme's colors
+        if let Some(value) = table.get("theme") {
+            config.theme = value
+                .as_str()
+                .and_then(BuiltInTheme::from_name)
+                .ok_or("theme must be one of default or high_contrast")?;
+        }
+        if config.theme == BuiltInTheme::HighContrast {
+            config.notifications = NotificationTheme::high_contrast();
+            config.diff = DiffTheme::high_contrast();
+            config.callouts = CalloutTheme::high_contrast();
+            config.selection = SelectionTheme::high_contrast();
+            config.banner.accent = Color::White;
+        }
+
+        if let Some(value) = table.get("default_scope") {
+            config.default_scope = value
+                .as_str_list()
+                .ok_or("default_scope must be a slug or a list of slugs")?;
+        }
+
+        if let Some(value) = table.get("tick_rate_ms") {
+            let millis = value
+                .as_integer()
+                .filter(|&millis| millis > 0)
+                .ok_or("tick_rate_ms must be a positive number of milliseconds")?;
+            config.tick_rate = Duration::from_millis(millis as u64);
+        }
+
+        if let Some(value) = table.get("tab_width") {
+            config.tab_width = value
+                .as_integer()
+                .filter(|width| (1..=16).contains(width))
+                .ok_or("tab_width must be a number from 1 to 16")?
+                as usize;
+        }
+
+        if let Some(value) = table.get("wrap") {
+            config.wrap = value.as_bool().ok_or("wrap must be true or false")?;
+        }
+        if let Some(value) = table.get("minimal") {
+            config.minimal = value.as_bool().ok_or("minimal must be true or false")?;
+        }
+        if let Some(value) = table.get("scroll_lines") {
+            config.scroll_lines = value
+                .as_integer()
+                .filter(|lines| (1..=100).contains(lines))
+                .ok_or("scroll_lines must be a number from 1 to 100")?
+                as u16;
+        }
+        if let Some(value) = table.get("smooth_scroll") {
+            config.smooth_scroll = value
+                .as_bool()
+                .ok_or("smooth_scroll must be true or false")?;
+        }
+        if let Some(value) = table.get("auto_scroll_interval_ms") {
+            let millis = value
+                .as_integer()
+                .filter(|&millis| millis > 0)
+                .ok_or("auto_scroll_interval_ms must be a positive number of milliseconds")?;
+            config.auto_scroll_interval = Duration::from_millis(millis as u64);
+        }
+        if let Some(value) = table.get("blink") {
+            config.blink = value.as_bool().ok_or("blink must be true or false")?;
+        }
+        if let Some(value) = table.get("reduced_motion") {
+            config.reduced_motion = value
+                .as_bool()
+                .ok_or("reduced_motion must be true or false")?;
+        }
+        if let Some(value) = table.get("mouse") {
+            config.mouse = value.as_bool().ok_or("mouse must be true or false")?;
+        }
+
+        if let Some(value) = table.get("offline") {
+            config.offline = value.as_bool().ok_or("offline must be true or false")?;
+        }
+        if let Some(value) = table.get("watch_config") {
+            config.watch_config = value
+                .as_bool()
+                .ok_or("watch_config must be true or false")?;
+        }
+        if let Some(value) = table.get("esc_quits") {
+            config.esc_quits = value.as_bool().ok_or("esc_quits must be true or false")?;
+        }
+
+        if let Some(value) = table.get("confirm_destructive") {
+            config.confirm_destructive = value
+                .as_bool()
+                .ok_or("confirm_destructive must be true or false")?;
+        }
+        if let Some(value) = table.get("auto_check_updates") {
+            config.auto_check_updates = value
+                .as_bool()
+                .ok_or("auto_check_updates must be true or false")?;
+        }
+        if let Some(value) = table.get("sync_on_startup") {
+            config.sync_on_startup = value
+                .as_str()
+                .and_then(SyncOnStartup::from_name)
+                .ok_or("sync_on_startup must be one of never, if_stale or always")?;
+        }
+
+        if let Some(value) = table.get("proxy") {
+            let proxy = value.as_str().ok_or("proxy must be a URL")?;
+            config.proxy = Some(parse_proxy(proxy)?);
+        }
+        if let Some(value) = table.get("max_download_mb") {
+            config.max_download_mb = Some(
+                value
+                    .as_integer()
+                    .filter(|&megabytes| megabytes > 0)
+                    .ok_or("max_download_mb must be a positive number of megabytes")?
+                    as u64,
+            );
+        }
+        if let Some(value) = table.get("max_connections") {
+            config.max_connections = value
+                .as_integer()
+                .filter(|&connections| connections > 0)
+                .ok_or("max_connections must be a positive number")?
+                as usize;
+        }
+        if let Some(value) = table.get("keep_archive") {
+            config.keep_archive = value
+                .as_bool()
+                .ok_or("keep_archive must be true or false")?;
+        }
+
+        if let Some(value) = table.get("recent_file") {
+            config.recent_file = Some(data_file(value, "recent_file")?);
+        }
+        if let Some(value) = table.get("search_history_file") {
+            config.search_history_file = Some(data_file(value, "search_history_file")?);
+        }
+
+        if let Some(value) = table.get("empty_query_view") {
+            config.empty_query_view = value
+                .as_str()
+                .and_then(EmptyQueryView::from_name)
+                .ok_or("empty_query_view must be one of banner, recent or browse")?;
+        }
+
+        if let Some(value) = table.get("open_action") {
+            config.open_action = value
+                .as_str()
+                .and_then(OpenAction::from_name)
+                .unwrap_or_else(|| {
+                    log::warn!(
+                        "open_action must be one of viewer, browser or pager, \
+                         opening results in the viewer"
+                    );
+                    OpenAction::Viewer
+                });
+        }
+
+        if let Some(value) = table.get("sort_mode") {
+            config.sort_mode = value
+                .as_str()
+                .and_then(SortMode::from_name)
+                .ok_or("sort_mode must be one of relevance, name, doc_set or type")?;
+        }
+        if let Some(value) = table.get("search_case") {
+            config.search_case = value
+                .as_str()
+                .and_then(CaseMode::from_name)
+                .ok_or("search_case must be one of insensitive, smart or sensitive")?;
+        }
+        if let Some(value) = table.get("matcher") {
+            config.matcher = value
+                .as_str()
+                .and_then(Matcher::from_name)
+                .ok_or("matcher must be one of fuzzy, substring or regex")?;
+        }
+        if let Some(value) = table.get("manager_sort") {
+            config.manager_sort = value
+                .as_str()
+                .and_then(ManagerSort::from_name)
+                .ok_or("manager_sort must be one of installed, name, size or updates")?;
+        }
+
+        if let Some(value) = table.get("doc_set_order") {
+            config.doc_set_order = value
+                .as_str_list()
+                .ok_or("doc_set_order must be a list of doc set slugs")?;
+        }
+
+        if let Some(value) = table.get("max_per_group") {
+            config.max_per_group = value
+                .as_integer()
+                .and_then(|max| usize::try_from(max).ok())
+                .ok_or("max_per_group must be a non-negative integer")?;
+        }
+
+        if let Some(value) = table.get("max_results") {
+            config.max_results = value
+                .as_integer()
+                .filter(|&max| max > 0)
+                .and_then(|max| usize::try_from(max).ok())
+                .ok_or("max_results must be a positive integer")?;
+        }
+
+        if let Some(value) = table.get("search_debounce_ms") {
+            let millis = value
+                .as_integer()
+                .and_then(|millis| u64::try_from(millis).ok())
+                .ok_or("search_debounce_ms must be a non-negative number of milliseconds")?;
+            config.search_debounce = Duration::from_millis(millis);
+        }
+
+        if let Some(value) = table.get("reading_time") {
+            config.reading_time = value
+                .as_bool()
+                .ok_or("reading_time must be true or false")?;
+        }
+
+        if let Some(value) = table.get("leader") {
+            let mut chars = value.as_str().unwrap_or_default().chars();
+            config.leader = match (chars.next(), chars.next()) {
+                (Some(leader), None) => leader,
+                _ => return Err("leader must be a single character".into()),
+            };
+        }
+        for (key, field) in [
+            ("next_scope_key", &mut config.next_scope_key),
+            ("previous_scope_key", &mut config.previous_scope_key),
+        ] {
+            if let Some(value) = table.get(key) {
+                *field = value
+                    .as_str()
+                    .filter(|keys| !keys.is_empty() && !keys.contains(char::is_whitespace))
+                    .ok_or_else(|| format!("{key} must be keys like \"]\" or \"<leader>n\""))?
+                    .to_owned();
+            }
+        }
+        if let Some(value) = table.get("sequence_timeout_ms") {
+            let millis = value
+                .as_integer()
+                .filter(|&millis| millis > 0)
+                .ok_or("sequence_timeout_ms must be a positive number of milliseconds")?;
+            config.sequence_timeout = Duration::from_millis(millis as u64);
+        }
+
+        if let Some(value) = table.get("toc_min_headings") {
+            config.toc_min_headings = value
+                .as_integer()
+                .and_then(|headings| usize::try_from(headings).ok())
+                .ok_or("toc_min_headings must be a non-negative integer")?;
+        }
+
+        if let Some(value) = table.get("outline") {
+            config.outline = value.as_bool().ok_or("outline must be true or false")?;
+        }
+        if let Some(value) = table.get("outline_width") {
+            config.outline_width = value
+                .as_integer()
+                .filter(|columns| (10..=100).contains(columns))
+                .ok_or("outline_width must be a number from 10 to 100")?
+                as u16;
+        }
+
+        if let Some(value) = table.get("preview") {
+            config.preview = value.as_bool().ok_or("preview must be true or false")?;
+        }
+        if let Some(value) = table.get("preview_delay_ms") {
+            let millis = value
+                .as_integer()
+                .filter(|&millis| millis >= 0)
+                .ok_or("preview_delay_ms must be a non-negative number of milliseconds")?;
+            config.preview_delay = Duration::from_millis(millis as u64);
+        }
+
+        if let Some(value) = table.get("mark_seen") {
+            config.mark_seen = value.as_bool().ok_or("mark_seen must be true or false")?;
+        }
+        if let Some(value) = table.get("abstracts") {
+            config.abstracts = value.as_bool().ok_or("abstracts must be true or false")?;
+        }
+
+        if let Some(value) = table.get("qualified_names") {
+            config.qualified_names = value
+                .as_bool()
+                .ok_or("qualified_names must be true or false")?;
+        }
+
+        if let Some(value) = table.get("detect_deprecated") {
+            config.detect_deprecated = value
+                .as_bool()
+                .ok_or("detect_deprecated must be true or false")?;
+        }
+
+        if let Some(value) = table.get("merge_versions") {
+            config.merge_versions = value
+                .as_bool()
+                .ok_or("merge_versions must be true or false")?;
+        }
+
+        if let Some(value) = table.get("full_text_index") {
+            config.full_text_index = value
+                .as_bool()
+                .ok_or("full_text_index must be true or false")?;
+        }
+
+        if let Some(value) = table.get("use_nerd_font") {
+            config.type_glyphs.use_nerd_font = value
+                .as_bool()
+                .ok_or("use_nerd_font must be true or false")?;
+            config.type_glyphs.enabled = config.type_glyphs.use_nerd_font;
+        }
+        if let Some(glyphs) = table.get("type_glyphs") {
+            let glyphs = glyphs.as_table().ok_or("type_glyphs must be a table")?;
+            let config = &mut config.type_glyphs;
+            config.enabled = true;
+            for (kind, value) in glyphs {
+                if kind == "enabled" {
+                    config.enabled = value
+                        .as_bool()
+                        .ok_or("type_glyphs.enabled must be true or false")?;
+                    continue;
+                }
+                let glyph = value
+                    .as_str()
+                    .ok_or_else(|| format!("type_glyphs.{kind} must be a string"))?;
+                config.custom.push((kind.to_lowercase(), glyph.to_owned()));
+            }
+        }
+
+        if let Some(extensions) = table.get("editor_extensions") {
+            let extensions = extensions
+                .as_table()
+                .ok_or("editor_extensions must be a table")?;
+            for (language, value) in extensions {
+                let extension = value
+                    .as_str()
+                    .map(|extension| extension.trim_start_matches('.'))
+                    .filter(|extension| !extension.is_empty())
+                    .ok_or_else(|| format!("editor_extensions.{language} must be an extension"))?;
+                config
+                    .editor_extensions
+                    .push((language.to_lowercase(), extension.to_owned()));
+            }
+        }
+
+        if let Some(callouts) = table.get("callouts") {
+            let callouts = callouts.as_table().ok_or("callouts must be a table")?;
+            let config = &mut config.callouts;
+            if let Some(value) = callouts.get("enabled") {
+                config.enabled = value
+                    .as_bool()
+                    .ok_or("callouts.enabled must be true or false")?;
+            }
+            for (key, color_of) in [
+                ("note", &mut config.note),
+                ("tip", &mut config.tip),
+                ("warning", &mut config.warning),
+                ("deprecated", &mut config.deprecated),
+            ] {
+                if let Some(value) = callouts.get(key) {
+                    *color_of = color(value, &format!("callouts.{key}"))?;
+                }
+            }
+        }
+
+        if let Some(value) = table.get("notification_duration_ms") {
+            let millis = value
+                .as_integer()
+                .filter(|&millis| millis > 0)
+                .ok_or("notification_duration_ms must be a positive number of milliseconds")?;
+            config.notification_duration = Duration::from_millis(millis as u64);
+        }
+        if let Some(notifications) = table.get("notifications") {
+            let notifications = notifications
+                .as_table()
+                .ok_or("notifications must be a table")?;
+            let config = &mut config.notifications;
+            for (key, color_of) in [
+                ("info", &mut config.info),
+                ("success", &mut config.success),
+                ("warning", &mut config.warning),
+                ("error", &mut config.error),
+            ] {
+                if let Some(value) = notifications.get(key) {
+                    *color_of = color(value, &format!("notifications.{key}"))?;
+                }
+            }
+        }
+        if let Some(diff) = table.get("diff") {
+            let diff = diff.as_table().ok_or("diff must be a table")?;
+            let config = &mut config.diff;
+            for (key, color_of) in [
+                ("added", &mut config.added),
+                ("removed", &mut config.removed),
+            ] {
+                if let Some(value) = diff.get(key) {
+                    *color_of = color(value, &format!("diff.{key}"))?;
+                }
+            }
+        }
+
+        if let Some(selection) = table.get("selection") {
+            let selection = selection.as_table().ok_or("selection must be a table")?;
+            let config = &mut config.selection;
+            if let Some(value) = selection.get("background") {
+                config.background = Some(color(value, "selection.background")?);
+            }
+            for (key, flag) in [
+                ("bold", &mut config.bold),
+                ("indicator", &mut config.indicator),
+            ] {
+                if let Some(value) = selection.get(key) {
+                    *flag = value
+                        .as_bool()
+                        .ok_or_else(|| format!("selection.{key} must be true or false"))?;
+                }
+            }
+            if let Some(value) = selection.get("border") {
+                config.border = color(value, "selection.border")?;
+            }
+        }
+
+        if let Some(doc_set_colors) = table.get("doc_set_colors") {
+            let doc_set_colors = doc_set_colors
+                .as_table()
+                .ok_or("doc_set_colors must be a table")?;
+            for (slug, value) in doc_set_colors {
+                let color = color(value, &format!("doc_set_colors.{slug}"))?;
+                config.doc_set_colors.custom.push((slug.clone(), color));
+            }
+        }
+
+        if let Some(banner) = table.get("banner") {
+            let banner = banner.as_table().ok_or("banner must be a table")?;
+            let config = &mut config.banner;
+            if let Some(value) = banner.get("gradient") {
+                config.gradient = value.as_bool().ok_or("banner.gradient must be a boolean")?;
+            }
+            if let Some(value) = banner.get("gradient_start") {
+                config.gradient_start = color(value, "banner.gradient_start")?;
+            }
+            if let Some(value) = banner.get("gradient_end") {
+                config.gradient_end = color(value, "banner.gradient_end")?;
+            }
+            if let Some(value) = banner.get("accent") {
+                config.accent = color(value, "banner.accent")?;
+            }
+        }
+
+        Ok(config)
+    }
+}
+
+/// A file rtftui writes to somewhere other than its data directory, given as an absolute
+/// path or one starting with `~/`
+///
+/// The directory it is in must already exist and be writable, so a typo is caught at load
+/// rather than when the file is first saved.
+fn data_file(value: &toml::Value, key: &str) -> Result<PathBuf, String> {
+    let path = value
+        .as_str()
+        .ok_or_else(|| format!("{key} must be a path"))?;
+    let path = match path.strip_prefix("~/") {
+        Some(rest) => BaseDirs::new()
+            .ok_or("could not determine the home directory")?
+            .home_dir()
+            .join(rest),
+        None => PathBuf::from(path),
+    };
+    if !path.is_absolute() {
+        return Err(format!("{key} must be an absolute path or start with ~/"));
+    }
+    let dir = path
+        .parent()
+        .ok_or_else(|| format!("{key} must name a file"))?;
+    match std::fs::metadata(dir) {
+        Ok(metadata) if !metadata.is_dir() => {
+            Err(format!("{key}: {} is not a directory", dir.display()))
+        }
+        Ok(metadata) if metadata.permissions().readonly() => {
+            Err(format!("{key}: {} is not writable", dir.display()))
+        }
+        Ok(_) => Ok(path),
+        Err(e) => Err(format!("{key}: {}: {e}", dir.display())),
+    }
+}
+
+/// A color given by name (`"cyan"`), index (`"42"`) or hex code (`"#4facfe"`)
+fn color(value: &toml::Value, key: &str) -> Result<Color, String> {
+    value
+        .as_str()
+        .and_then(|name| name.parse().ok())
+        .ok_or_else(|| format!("{key} must be a color name or #rrggbb code"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn the_first_existing_config_file_wins() {
+        let dir = TempDir::new("config-locate");
+        let [flag, env, platform] = ["flag.toml", "env.toml", "platform.toml"].map(|name| {
+            let path = dir.join(name);
+            std::fs::write(&path, "").unwrap();
+            path
+        });
+        let missing = dir.join("missing.toml");
+        let locate = |flag: &Path, env: &Path, platform: &Path| {
+            Config::locate(
+                Some(flag.to_owned()),
+                Some(env.to_owned()),
+                Some(platform.to_owned()),
+            )
+        };
+
+        assert_eq!(
+            locate(&flag, &env, &platform),
+            Some((flag.clone(), ConfigOrigin::Flag))
+        );
+        assert_eq!(
+            locate(&missing, &env, &platform),
+            Some((env.clone(), ConfigOrigin::Env))
+        );
+        assert_eq!(
+            locate(&missing, &missing, &platform),
+            Some((platform.clone(), ConfigOrigin::Platform))
+        );
+        assert_eq!(
+            locate(&flag, &missing, &platform),
+            Some((flag.clone(), ConfigOrigin::Flag))
+        );
+        assert_eq!(locate(&missing, &missing, &missing), None);
+
+        // Sources that aren't given are skipped
+        assert_eq!(
+            Config::locate(None, Some(env.clone()), Some(platform.clone())),
+            Some((env, ConfigOrigin::Env))
+        );
+        assert_eq!(
+            Config::locate(None, None, Some(platform.clone())),
+            Some((platform, ConfigOrigin::Platform))
+        );
+        assert_eq!(Config::locate(None, None, None), None);
+    }
+}
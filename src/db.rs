@@ -0,0 +1,156 @@
+use crate::path::split_fragment;
+use flate2::read::GzDecoder;
+use serde_json::{Map, Value};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+/// A doc set's content, as doc sets added from a local directory keep it
+pub const DB: &str = "db.json";
+/// A doc set's content compressed with gzip, as downloaded doc sets keep it
+pub const COMPRESSED_DB: &str = "db.json.gz";
+/// The bytes every gzip stream starts with
+pub const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// The file a doc set directory keeps its content in
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DbFile {
+    Plain(PathBuf),
+    Gzip(PathBuf),
+}
+
+impl DbFile {
+    /// The content of a doc set directory, preferring a plain `db.json` to a compressed one,
+    /// or `None` if it has neither
+    pub fn find(dir: &Path) -> Option<Self> {
+        let plain = dir.join(DB);
+        if plain.is_file() {
+            return Some(DbFile::Plain(plain));
+        }
+        let compressed = dir.join(COMPRESSED_DB);
+        compressed.is_file().then_some(DbFile::Gzip(compressed))
+    }
+
+    pub fn path(&self) -> &Path {
+        match self {
+            DbFile::Plain(path) | DbFile::Gzip(path) => path,
+        }
+    }
+}
+
+/// The HTML of each page of a doc set, by path, however the doc set stores it
+///
+/// devdocs writes `db.json` as one object mapping page paths to their HTML. Values that
+/// aren't strings are ignored.
+#[derive(Debug, Clone, Default)]
+pub struct DocDb {
+    pages: Map<String, Value>,
+}
+
+impl DocDb {
+    /// Read the content of a doc set directory
+    pub fn open(dir: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let file = DbFile::find(dir).ok_or_else(|| format!("{} has no {DB}", dir.display()))?;
+        let value = match &file {
+            DbFile::Plain(path) => serde_json::from_reader(BufReader::new(File::open(path)?))?,
+            DbFile::Gzip(path) => {
+                serde_json::from_reader(BufReader::new(GzDecoder::new(File::open(path)?)))?
+            }
+        };
+        Self::from_json(value)
+    }
+
+    pub fn from_json(value: Value) -> Result<Self, Box<dyn std::error::Error>> {
+        match value {
+            Value::Object(pages) => Ok(Self { pages }),
+            _ => Err(format!("{DB} is not an object of pages").into()),
+        }
+    }
+
+    /// The HTML of the page an entry path points into, ignoring its `#fragment`
+    pub fn get(&self, path: &str) -> Option<String> {
+        self.page(path).map(str::to_owned)
+    }
+
+    /// Whether there is content for the page an entry path points into
+    pub fn contains(&self, path: &str) -> bool {
+        self.page(path).is_some()
+    }
+
+    fn page(&self, path: &str) -> Option<&str> {
+        self.pages.get(split_fragment(path).0)?.as_str()
+    }
+
+    /// Every page with its HTML, in no particular order
+    pub fn pages(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.pages
+            .iter()
+            .filter_map(|(path, html)| Some((path.as_str(), html.as_str()?)))
+    }
+
+    pub fn len(&self) -> usize {
+        self.pages().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pages().next().is_none()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    const FIXTURE: &str = r#"{
+        "global_objects/array": "<h1>Array</h1><p>Lists of values.</p>",
+        "global_objects/array/at": "<h1>Array.prototype.at()</h1>",
+        "broken": 42
+    }"#;
+
+    #[test]
+    fn pages_are_looked_up_by_path() {
+        let db = DocDb::from_json(serde_json::from_str(FIXTURE).unwrap()).unwrap();
+        assert_eq!(
+            db.get("global_objects/array").as_deref(),
+            Some("<h1>Array</h1><p>Lists of values.</p>")
+        );
+        assert_eq!(
+            db.get("global_objects/array/at#syntax").as_deref(),
+            Some("<h1>Array.prototype.at()</h1>")
+        );
+        assert_eq!(db.get("global_objects/map"), None);
+        assert!(!db.contains("broken"));
+        assert_eq!(db.len(), 2);
+        assert!(DocDb::from_json(Value::Array(Vec::new())).is_err());
+    }
+
+    #[test]
+    fn plain_and_compressed_content_read_the_same() {
+        let plain = TempDir::new("db-plain");
+        std::fs::write(plain.join(DB), FIXTURE).unwrap();
+        let compressed = TempDir::new("db-gzip");
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(FIXTURE.as_bytes()).unwrap();
+        std::fs::write(compressed.join(COMPRESSED_DB), encoder.finish().unwrap()).unwrap();
+
+        assert!(matches!(DbFile::find(&plain), Some(DbFile::Plain(_))));
+        assert!(matches!(DbFile::find(&compressed), Some(DbFile::Gzip(_))));
+        for dir in [&plain, &compressed] {
+            let db = DocDb::open(dir).unwrap();
+            assert!(db.contains("global_objects/array"));
+            assert_eq!(db.len(), 2);
+        }
+
+        // A plain copy is read in place of the compressed one
+        std::fs::write(compressed.join(DB), r#"{"only": "<p>plain</p>"}"#).unwrap();
+        assert_eq!(DocDb::open(&compressed).unwrap().len(), 1);
+
+        std::fs::remove_file(plain.join(DB)).unwrap();
+        assert_eq!(DbFile::find(&plain), None);
+        assert!(DocDb::open(&plain).is_err());
+    }
+}
@@ -0,0 +1,219 @@
+/// A line of a diff between two texts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    /// In both texts, at these lines of the old and the new one
+    Same(usize, usize),
+    /// Only in the old text, at this line
+    Removed(usize),
+    /// Only in the new text, at this line
+    Added(usize),
+}
+
+/// Most cells the table of common subsequences may have, beyond which the differing middle
+/// of the texts is shown as removed and added as a whole
+const MAX_CELLS: usize = 4_000_000;
+
+/// The lines that stayed, were removed or were added going from `old` to `new`, in order
+///
+/// Lines are matched by a longest common subsequence, after setting aside the lines both
+/// texts start and end with.
+pub fn diff_lines<T: PartialEq>(old: &[T], new: &[T]) -> Vec<Change> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_middle = &old[prefix..old.len() - suffix];
+    let new_middle = &new[prefix..new.len() - suffix];
+
+    let mut changes: Vec<Change> = (0..prefix).map(|i| Change::Same(i, i)).collect();
+    let (n, m) = (old_middle.len(), new_middle.len());
+    if n * m > MAX_CELLS {
+        changes.extend((0..n).map(|i| Change::Removed(prefix + i)));
+        changes.extend((0..m).map(|j| Change::Added(prefix + j)));
+    } else {
+        // lengths[i][j] is the longest common subsequence of old_middle[i..] and new_middle[j..]
+        let width = m + 1;
+        let mut lengths = vec![0u32; (n + 1) * width];
+        for i in (0..n).rev() {
+            for j in (0..m).rev() {
+                lengths[i * width + j] = if old_middle[i] == new_middle[j] {
+                    lengths[(i + 1) * width + j + 1] + 1
+                } else {
+                    lengths[(i + 1) * width + j].max(lengths[i * width + j + 1])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < n || j < m {
+            if i < n && j < m && old_middle[i] == new_middle[j] {
+                changes.push(Change::Same(prefix + i, prefix + j));
+                i += 1;
+                j += 1;
+            } else if j == m
+                || (i < n && lengths[(i + 1) * width + j] >= lengths[i * width + j + 1])
+            {
+                changes.push(Change::Removed(prefix + i));
+                i += 1;
+            } else {
+                changes.push(Change::Added(prefix + j));
+                j += 1;
+            }
+        }
+    }
+    changes
+        .extend((0..suffix).map(|k| Change::Same(old.len() - suffix + k, new.len() - suffix + k)));
+    changes
+}
+
+/// A row of a side-by-side diff: a line of the old text on the left, of the new text on the
+/// right, or both
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Row {
+    pub old: Option<usize>,
+    pub new: Option<usize>,
+    /// The lines differ, rather than being the same line on both sides
+    pub changed: bool,
+}
+
+/// Lay out a diff side by side, pairing each run of removed lines with the added lines
+/// right after it
+pub fn side_by_side(changes: &[Change]) -> Vec<Row> {
+    let mut rows = Vec::with_capacity(changes.len());
+    let mut i = 0;
+    while i < changes.len() {
+        if let Change::Same(old, new) = changes[i] {
+            rows.push(Row {
+                old: Some(old),
+                new: Some(new),
+                changed: false,
+            });
+            i += 1;
+            continue;
+        }
+        let removed: Vec<usize> = changes[i..]
+            .iter()
+            .map_while(|change| match change {
+                Change::Removed(old) => Some(*old),
+                _ => None,
+            })
+            .collect();
+        i += removed.len();
+        let added: Vec<usize> = changes[i..]
+            .iter()
+            .map_while(|change| match change {
+                Change::Added(new) => Some(*new),
+                _ => None,
+            })
+            .collect();
+        i += added.len();
+        for k in 0..removed.len().max(added.len()) {
+            rows.push(Row {
+                old: removed.get(k).copied(),
+                new: added.get(k).copied(),
+                changed: true,
+            });
+        }
+    }
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Change::{Added, Removed, Same};
+
+    #[test]
+    fn lines_both_texts_start_and_end_with_are_set_aside() {
+        let old = ["a", "b", "c", "d", "e"];
+        let new = ["a", "b", "x", "d", "e"];
+        assert_eq!(
+            diff_lines(&old, &new),
+            [
+                Same(0, 0),
+                Same(1, 1),
+                Removed(2),
+                Added(2),
+                Same(3, 3),
+                Same(4, 4)
+            ]
+        );
+        assert_eq!(diff_lines(&old, &old).len(), 5);
+        assert_eq!(
+            diff_lines(&["a", "b"], &["a", "b", "c"]),
+            [Same(0, 0), Same(1, 1), Added(2)]
+        );
+        assert_eq!(diff_lines::<&str>(&[], &[]), []);
+    }
+
+    #[test]
+    fn the_middle_is_matched_by_its_longest_common_subsequence() {
+        let old = ["start", "a", "b", "c", "end"];
+        let new = ["start", "b", "x", "c", "y", "end"];
+        assert_eq!(
+            diff_lines(&old, &new),
+            [
+                Same(0, 0),
+                Removed(1),
+                Same(2, 1),
+                Added(2),
+                Same(3, 3),
+                Added(4),
+                Same(4, 5)
+            ]
+        );
+    }
+
+    #[test]
+    fn replaced_lines_are_paired_side_by_side() {
+        let changes = [
+            Same(0, 0),
+            Removed(1),
+            Removed(2),
+            Removed(3),
+            Added(1),
+            Added(2),
+            Same(4, 3),
+            Added(4),
+        ];
+        let row = |old, new, changed| Row { old, new, changed };
+        assert_eq!(
+            side_by_side(&changes),
+            [
+                row(Some(0), Some(0), false),
+                row(Some(1), Some(1), true),
+                row(Some(2), Some(2), true),
+                row(Some(3), None, true),
+                row(Some(4), Some(3), false),
+                row(None, Some(4), true),
+            ]
+        );
+    }
+
+    #[test]
+    fn a_middle_too_large_to_match_is_removed_and_added_whole() {
+        // The middles share the line 1_000, which a smaller diff would keep
+        let old: Vec<u32> = [0].into_iter().chain(1..2_002).chain([9_999]).collect();
+        let new: Vec<u32> = [0]
+            .into_iter()
+            .chain(1_000..1_001)
+            .chain(5_000..7_001)
+            .chain([9_999])
+            .collect();
+        let (n, m) = (old.len() - 2, new.len() - 2);
+        assert!(n * m > MAX_CELLS);
+
+        let changes = diff_lines(&old, &new);
+        let mut expected = vec![Same(0, 0)];
+        expected.extend((1..=n).map(Removed));
+        expected.extend((1..=m).map(Added));
+        expected.push(Same(n + 1, m + 1));
+        assert_eq!(changes, expected);
+
+        let rows = side_by_side(&changes);
+        assert_eq!(rows.len(), n.max(m) + 2);
+        assert!(rows[1..=n.max(m)].iter().all(|row| row.changed));
+    }
+}
@@ -0,0 +1,1856 @@
+use crate::db::{DbFile, DocDb, COMPRESSED_DB, DB, GZIP_MAGIC};
+use crate::fulltext::{FullTextIndex, FULL_TEXT_INDEX};
+use crate::html::{html_to_text, RenderOptions};
+use crate::index::{load_doc_set, load_index_file, IndexEntry};
+use crate::net::{HttpClient, NetworkError, ServerUnavailable};
+use crate::path::split_fragment;
+use crate::search::{expand_alias, slug_hint};
+use crate::storage::{read_versioned, write_atomic, write_versioned, Migration, Storage};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use log::{trace, warn};
+use serde_json::{json, Value};
+use sha1::{Digest, Sha1};
+use std::cmp::Reverse;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::fs::File;
+use std::io::{BufReader, Cursor, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+const DEVDOCS_DOCUMENTS: &str = "https://documents.devdocs.io";
+const DEVDOCS_REGISTRY: &str = "https://devdocs.io/docs.json";
+
+/// A doc set offered by devdocs, as listed in its registry
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegistryEntry {
+    pub name: String,
+    pub slug: String,
+    /// Version of the documented software, empty for unversioned doc sets
+    pub version: String,
+    /// Exact release the doc set was generated from, if known
+    pub release: String,
+    /// When the doc set was last generated, in seconds since the Unix epoch
+    pub mtime: u64,
+    /// Size of the doc set's `db.json` in bytes
+    pub db_size: u64,
+    pub about: About,
+}
+
+/// Where a doc set's content comes from and under what terms, as far as devdocs says
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct About {
+    /// Upstream links by what they point at, e.g. `home` or `code`
+    pub links: Vec<(String, String)>,
+    /// Copyright and license notice, as HTML
+    pub attribution: String,
+}
+
+impl About {
+    fn to_json(&self) -> Value {
+        let links: serde_json::Map<String, Value> = self
+            .links
+            .iter()
+            .map(|(kind, url)| (kind.clone(), Value::from(url.as_str())))
+            .collect();
+        json!({ "links": links, "attribution": self.attribution })
+    }
+
+    /// Read the `links` and `attribution` of a registry entry or manifest, either of which
+    /// may be missing
+    fn from_json(value: &Value) -> Self {
+        let links = value
+            .get("links")
+            .and_then(Value::as_object)
+            .map(|links| {
+                links
+                    .iter()
+                    .filter_map(|(kind, url)| Some((kind.clone(), url.as_str()?.to_owned())))
+                    .filter(|(_, url)| !url.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+        Self {
+            links,
+            attribution: value
+                .get("attribution")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_owned(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.links.is_empty() && self.attribution.is_empty()
+    }
+
+    /// The link to the original source: the code repository, or else the project's home
+    pub fn source_url(&self) -> Option<&str> {
+        ["code", "home"].iter().find_map(|kind| {
+            self.links
+                .iter()
+                .find(|(link_kind, _)| link_kind == kind)
+                .map(|(_, url)| url.as_str())
+        })
+    }
+}
+
+impl RegistryEntry {
+    fn to_json(&self) -> Value {
+        let mut value = json!({
+            "name": self.name,
+            "slug": self.slug,
+            "version": self.version,
+            "release": self.release,
+            "mtime": self.mtime,
+            "db_size": self.db_size,
+        });
+        merge(&mut value, self.about.to_json());
+        value
+    }
+
+    fn from_json(value: &Value) -> Option<Self> {
+        let string = |key| {
+            value
+                .get(key)
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_owned()
+        };
+        let number = |key| value.get(key).and_then(Value::as_u64).unwrap_or_default();
+        Some(Self {
+            name: value.get("name")?.as_str()?.to_owned(),
+            slug: value.get("slug")?.as_str()?.to_owned(),
+            version: string("version"),
+            release: string("release"),
+            mtime: number("mtime"),
+            db_size: number("db_size"),
+            about: About::from_json(value),
+        })
+    }
+}
+
+/// Add the fields of one JSON object to another
+fn merge(value: &mut Value, fields: Value) {
+    if let (Some(value), Value::Object(fields)) = (value.as_object_mut(), fields) {
+        value.extend(fields);
+    }
+}
+
+/// How long a fetched registry is used before it is fetched again
+pub const REGISTRY_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// The registry as last fetched, kept so browsing it needs no network
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CachedRegistry {
+    pub entries: Vec<RegistryEntry>,
+    /// When the registry was fetched, in seconds since the Unix epoch
+    pub fetched: u64,
+}
+
+impl CachedRegistry {
+    /// Whether the cache is younger than `REGISTRY_TTL`
+    pub fn is_fresh(&self) -> bool {
+        unix_now().saturating_sub(self.fetched) < REGISTRY_TTL.as_secs()
+    }
+}
+
+/// Upgrades of the cached registry and of manifests; format 2 only stamped them
+const MIGRATIONS: &[Migration] = &[Some];
+
+/// The registry as last fetched, if it was ever fetched
+pub fn cached_registry(storage: &Storage) -> Option<CachedRegistry> {
+    let value = read_versioned(&storage.registry_path(), MIGRATIONS).ok()??;
+    Some(CachedRegistry {
+        entries: value
+            .get("docs")?
+            .as_array()?
+            .iter()
+            .filter_map(RegistryEntry::from_json)
+            .collect(),
+        fetched: value.get("fetched")?.as_u64()?,
+    })
+}
+
+/// Fetch the list of doc sets devdocs offers, and cache it
+pub async fn fetch_registry(
+    storage: &Storage,
+    http: &HttpClient,
+) -> Result<Vec<RegistryEntry>, Box<dyn std::error::Error>> {
+    fetch_registry_from(storage, http, DEVDOCS_REGISTRY).await
+}
+
+async fn fetch_registry_from(
+    storage: &Storage,
+    http: &HttpClient,
+    url: &str,
+) -> Result<Vec<RegistryEntry>, Box<dyn std::error::Error>> {
+    trace!("Fetching the doc set registry");
+
+    let response = http.get(url).await?;
+    let registry: Value = response.json().await?;
+    let entries: Vec<RegistryEntry> = registry
+        .as_array()
+        .ok_or("the registry is not a list")?
+        .iter()
+        .filter_map(RegistryEntry::from_json)
+        .collect();
+
+    let cache = json!({
+        "fetched": unix_now(),
+        "docs": entries.iter().map(RegistryEntry::to_json).collect::<Vec<_>>(),
+    });
+    if let Err(e) = write_versioned(&storage.registry_path(), cache) {
+        log::warn!("Could not cache the registry: {e}");
+    }
+    Ok(entries)
+}
+
+/// The registry as fetched, or as cached when the server was unavailable
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RegistryFetch {
+    Fetched(Vec<RegistryEntry>),
+    /// The server kept failing or rate limiting the fetch, so the cached copy, however old,
+    /// stands in for it
+    Cached {
+        registry: CachedRegistry,
+        error: ServerUnavailable,
+    },
+}
+
+impl RegistryFetch {
+    pub fn into_entries(self) -> Vec<RegistryEntry> {
+        match self {
+            RegistryFetch::Fetched(entries) => entries,
+            RegistryFetch::Cached { registry, .. } => registry.entries,
+        }
+    }
+}
+
+/// Fetch the registry, falling back to the cached copy if the server is unavailable
+///
+/// Without a cached copy, or when the fetch fails for another reason, e.g. without a
+/// network, the error is returned.
+pub async fn fetch_registry_or_cached(
+    storage: &Storage,
+    http: &HttpClient,
+) -> Result<RegistryFetch, Box<dyn std::error::Error>> {
+    fetch_registry_or_cached_from(storage, http, DEVDOCS_REGISTRY).await
+}
+
+async fn fetch_registry_or_cached_from(
+    storage: &Storage,
+    http: &HttpClient,
+    url: &str,
+) -> Result<RegistryFetch, Box<dyn std::error::Error>> {
+    let e = match fetch_registry_from(storage, http, url).await {
+        Ok(entries) => return Ok(RegistryFetch::Fetched(entries)),
+        Err(e) => e,
+    };
+    let (Some(error), Some(registry)) = (
+        e.downcast_ref::<ServerUnavailable>(),
+        cached_registry(storage),
+    ) else {
+        return Err(e);
+    };
+    warn!("{error}, using the registry cached before");
+    Ok(RegistryFetch::Cached {
+        registry,
+        error: error.clone(),
+    })
+}
+
+/// The list of doc sets devdocs offers, fetched only if the cached copy is stale, and used
+/// stale while the server is unavailable
+pub async fn registry(
+    storage: &Storage,
+    http: &HttpClient,
+) -> Result<Vec<RegistryEntry>, Box<dyn std::error::Error>> {
+    match cached_registry(storage) {
+        Some(cache) if cache.is_fresh() => Ok(cache.entries),
+        _ => Ok(fetch_registry_or_cached(storage, http)
+            .await?
+            .into_entries()),
+    }
+}
+
+/// An installed doc set that devdocs has regenerated since it was downloaded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Outdated {
+    pub installed: Manifest,
+    pub available: RegistryEntry,
+}
+
+/// Installed doc sets the registry has newer content for, sorted by slug
+///
+/// Doc sets added from a local directory are never outdated.
+pub fn outdated_in(registry: &[RegistryEntry], installed: &[Manifest]) -> Vec<Outdated> {
+    let mut outdated: Vec<Outdated> = installed
+        .iter()
+        .filter(|manifest| !manifest.local)
+        .filter_map(|manifest| {
+            let available = registry.iter().find(|entry| entry.slug == manifest.slug)?;
+            (available.mtime > manifest.mtime).then(|| Outdated {
+                installed: manifest.clone(),
+                available: available.clone(),
+            })
+        })
+        .collect();
+    outdated.sort_by(|a, b| a.installed.slug.cmp(&b.installed.slug));
+    outdated
+}
+
+/// Installed doc sets with updates available, fetching the registry only if the cached copy
+/// is stale
+pub async fn outdated_doc_sets(
+    storage: &Storage,
+    http: &HttpClient,
+) -> Result<Vec<Outdated>, Box<dyn std::error::Error>> {
+    let registry = registry(storage, http).await?;
+    let installed: Vec<Manifest> = installed_doc_sets(storage)
+        .iter()
+        .filter_map(|slug| read_manifest(storage, slug))
+        .collect();
+    Ok(outdated_in(&registry, &installed))
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+/// Doc sets of the registry that are not installed but look relevant, by slug
+///
+/// A doc set is recommended when one of `hints`, such as the words of past queries, names its
+/// family or the first word of its name. Common aliases like `js` count as their family.
+pub fn recommended<'a>(
+    registry: &[RegistryEntry],
+    installed: &[String],
+    hints: impl IntoIterator<Item = &'a str>,
+) -> BTreeSet<String> {
+    let hints: BTreeSet<String> = hints
+        .into_iter()
+        .flat_map(|hint| hint.split(|c: char| !c.is_alphanumeric() && c != '.'))
+        .filter(|word| word.len() >= 2)
+        .map(expand_alias)
+        .collect();
+    registry
+        .iter()
+        .filter(|entry| !installed.contains(&entry.slug))
+        .filter(|entry| {
+            let name = entry.name.to_lowercase();
+            let first_word = name.split_whitespace().next().unwrap_or_default();
+            hints.contains(doc_family(&entry.slug)) || hints.contains(first_word)
+        })
+        .map(|entry| entry.slug.clone())
+        .collect()
+}
+
+/// What is known about an installed doc set, saved next to its content
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Manifest {
+    pub slug: String,
+    pub name: String,
+    pub version: String,
+    pub release: String,
+    /// Registry `mtime` of the installed content
+    pub mtime: u64,
+    /// Added from a local directory rather than downloaded from devdocs
+    pub local: bool,
+    pub about: About,
+}
+
+impl Manifest {
+    /// The most precise version known, or an empty string for unversioned doc sets
+    pub fn display_version(&self) -> &str {
+        if self.release.is_empty() {
+            &self.version
+        } else {
+            &self.release
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        let mut value = json!({
+            "slug": self.slug,
+            "name": self.name,
+            "version": self.version,
+            "release": self.release,
+            "mtime": self.mtime,
+            "local": self.local,
+        });
+        merge(&mut value, self.about.to_json());
+        value
+    }
+
+    fn from_json(value: &Value) -> Option<Self> {
+        Some(Self {
+            slug: value.get("slug")?.as_str()?.to_owned(),
+            name: value.get("name")?.as_str()?.to_owned(),
+            version: value.get("version")?.as_str()?.to_owned(),
+            release: value.get("release")?.as_str()?.to_owned(),
+            mtime: value.get("mtime")?.as_u64()?,
+            local: value
+                .get("local")
+                .and_then(Value::as_bool)
+                .unwrap_or_default(),
+            about: About::from_json(value),
+        })
+    }
+}
+
+impl From<&RegistryEntry> for Manifest {
+    fn from(entry: &RegistryEntry) -> Self {
+        Self {
+            slug: entry.slug.clone(),
+            name: entry.name.clone(),
+            version: entry.version.clone(),
+            release: entry.release.clone(),
+            mtime: entry.mtime,
+            local: false,
+            about: entry.about.clone(),
+        }
+    }
+}
+
+/// The manifest of an installed doc set, if it has a readable one
+pub fn read_manifest(storage: &Storage, slug: &str) -> Option<Manifest> {
+    let path = storage.doc_set_dir(slug).join("manifest.json");
+    Manifest::from_json(&read_versioned(&path, MIGRATIONS).ok()??)
+}
+
+fn write_manifest(
+    storage: &Storage,
+    manifest: &Manifest,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let path = storage.doc_set_dir(&manifest.slug).join("manifest.json");
+    write_versioned(&path, manifest.to_json())
+}
+
+/// The doc family a slug belongs to, which is the slug without its `~version` suffix
+pub fn doc_family(slug: &str) -> &str {
+    slug.split_once('~').map_or(slug, |(family, _)| family)
+}
+
+/// A download was refused or stopped because the file is bigger than the size cap
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DownloadTooLarge {
+    pub file: String,
+    /// Size the server reported, or `None` if the download was stopped at the cap
+    pub size: Option<u64>,
+    /// The cap, in bytes
+    pub limit: u64,
+}
+
+impl fmt::Display for DownloadTooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let limit = self.limit as f64 / 1_000_000.0;
+        match self.size {
+            Some(size) => write!(
+                f,
+                "{} is {:.1} MB, over the {limit:.0} MB download limit",
+                self.file,
+                size as f64 / 1_000_000.0
+            ),
+            None => write!(
+                f,
+                "{} grew past the {limit:.0} MB download limit",
+                self.file
+            ),
+        }
+    }
+}
+
+impl std::error::Error for DownloadTooLarge {}
+
+/// A doc set devdocs doesn't offer was asked for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownDocSet(pub String);
+
+impl fmt::Display for UnknownDocSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for UnknownDocSet {}
+
+/// Whether a download of `size` bytes goes over a cap of `limit` bytes, if there is one
+pub fn exceeds_cap(size: u64, limit: Option<u64>) -> bool {
+    limit.is_some_and(|limit| size > limit)
+}
+
+/// Download a file, refusing files over `limit` bytes
+///
+/// The size the server reports is checked before downloading anything, and the download is
+/// stopped once it passes the cap in case the server reports none.
+async fn download(
+    http: &HttpClient,
+    url: &str,
+    limit: Option<u64>,
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let too_large = |size| DownloadTooLarge {
+        file: url.rsplit('/').next().unwrap_or(url).to_owned(),
+        size,
+        limit: limit.unwrap_or_default(),
+    };
+    let mut response = http.get(url).await?;
+    if let Some(size) = response.content_length() {
+        if exceeds_cap(size, limit) {
+            return Err(too_large(Some(size)).into());
+        }
+    }
+
+    let mut bytes = Vec::with_capacity(response.content_length().unwrap_or_default() as usize);
+    while let Some(chunk) = response.chunk().await? {
+        bytes.extend_from_slice(&chunk);
+        if exceeds_cap(bytes.len() as u64, limit) {
+            return Err(too_large(None).into());
+        }
+    }
+    Ok(bytes)
+}
+
+/// Kept archives beyond these are removed, oldest first, though never the one just kept
+const MAX_ARCHIVE_BYTES: u64 = 500_000_000;
+const MAX_ARCHIVE_AGE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// File a release of a doc set is kept in, named after when devdocs generated it
+fn archive_name(entry: &RegistryEntry) -> String {
+    format!("{}-{}.zip", entry.slug, entry.mtime)
+}
+
+/// The checksum of a kept archive, in a file next to it
+fn checksum_path(archive: &Path) -> PathBuf {
+    let mut path = archive.as_os_str().to_owned();
+    path.push(".sha1");
+    PathBuf::from(path)
+}
+
+fn sha1_hex(bytes: &[u8]) -> String {
+    Sha1::digest(bytes)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// The content and index of the release the registry lists, if it was kept in `dir` and
+/// still matches the checksum recorded when it was
+fn kept_archive(dir: &Path, entry: &RegistryEntry) -> Option<(Vec<u8>, Vec<u8>)> {
+    let path = dir.join(archive_name(entry));
+    let bytes = std::fs::read(&path).ok()?;
+    let checksum = std::fs::read_to_string(checksum_path(&path)).ok()?;
+    if sha1_hex(&bytes) != checksum.trim() {
+        warn!(
+            "{} does not match its checksum, downloading it again",
+            path.display()
+        );
+        return None;
+    }
+    let mut archive = ZipArchive::new(Cursor::new(bytes)).ok()?;
+    let mut read = |name| {
+        let mut file = archive.by_name(name).ok()?;
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes).ok()?;
+        Some(bytes)
+    };
+    Some((read(COMPRESSED_DB)?, read("index.json")?))
+}
+
+/// Keep the content and index of a release in `dir` with their checksum, and clean up old
+/// archives
+fn keep_archive(
+    dir: &Path,
+    entry: &RegistryEntry,
+    db: &[u8],
+    index: &[u8],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    // The content is already compressed
+    let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+    zip.start_file(COMPRESSED_DB, stored)?;
+    zip.write_all(db)?;
+    zip.start_file("index.json", SimpleFileOptions::default())?;
+    zip.write_all(index)?;
+    let bytes = zip.finish()?.into_inner();
+
+    let name = archive_name(entry);
+    let path = dir.join(&name);
+    write_atomic(&path, &bytes)?;
+    write_atomic(&checksum_path(&path), sha1_hex(&bytes).as_bytes())?;
+    clean_archives(dir, &name)?;
+    Ok(())
+}
+
+/// Remove kept archives older than [`MAX_ARCHIVE_AGE`], then the oldest of the rest until
+/// they fit in [`MAX_ARCHIVE_BYTES`], sparing `latest`
+fn clean_archives(dir: &Path, latest: &str) -> std::io::Result<()> {
+    let mut archives: Vec<(SystemTime, u64, PathBuf)> = Vec::new();
+    for file in std::fs::read_dir(dir)? {
+        let file = file?;
+        let path = file.path();
+        let is_archive = path.extension().is_some_and(|extension| extension == "zip");
+        if !is_archive || file.file_name() == latest {
+            continue;
+        }
+        let metadata = file.metadata()?;
+        archives.push((metadata.modified()?, metadata.len(), path));
+    }
+    // Newest first, so whatever is over the cap is at the end
+    archives.sort_by_key(|&(modified, _, _)| Reverse(modified));
+    let mut total = std::fs::metadata(dir.join(latest))?.len();
+    for (modified, size, path) in archives {
+        total += size;
+        let expired = modified.elapsed().is_ok_and(|age| age > MAX_ARCHIVE_AGE);
+        if expired || total > MAX_ARCHIVE_BYTES {
+            trace!("Removing the old archive {}", path.display());
+            std::fs::remove_file(&path)?;
+            match std::fs::remove_file(checksum_path(&path)) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e),
+                _ => {}
+            }
+            total -= size;
+        }
+    }
+    Ok(())
+}
+
+/// Download a doc set's index and content into its directory, replacing any previous version
+///
+/// The content is stored gzipped as `db.json.gz`. Files over `max_download` bytes are
+/// refused with a [`DownloadTooLarge`] error. When this updates an installed doc set, its
+/// previous `index.json` is kept as `index.previous.json` and the entries added and removed
+/// by the update are returned.
+pub async fn sync_doc(
+    storage: &Storage,
+    http: &HttpClient,
+    slug: &str,
+    max_download: Option<u64>,
+) -> Result<Option<IndexDiff>, Box<dyn std::error::Error>> {
+    sync_doc_reported(storage, http, slug, max_download).await.0
+}
+
+/// The sort of problem a sync ran into, to sum up failed syncs by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncErrorKind {
+    /// Devdocs couldn't be reached or answered with an error
+    Network,
+    /// A file was over the download limit
+    TooLarge,
+    /// Devdocs has no such doc set
+    UnknownDocSet,
+    /// The doc set couldn't be written to disk
+    Disk,
+    /// Devdocs sent something that isn't a doc set
+    BadData,
+    Other,
+}
+
+impl SyncErrorKind {
+    /// The kind of an error syncing returned
+    pub fn of(error: &(dyn std::error::Error + 'static)) -> Self {
+        if error.is::<NetworkError>()
+            || error.is::<ServerUnavailable>()
+            || error.is::<reqwest::Error>()
+        {
+            Self::Network
+        } else if error.is::<DownloadTooLarge>() {
+            Self::TooLarge
+        } else if error.is::<UnknownDocSet>() {
+            Self::UnknownDocSet
+        } else if error.is::<std::io::Error>() {
+            Self::Disk
+        } else if error.is::<serde_json::Error>() {
+            Self::BadData
+        } else {
+            Self::Other
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Network => "network",
+            Self::TooLarge => "too large",
+            Self::UnknownDocSet => "unknown doc set",
+            Self::Disk => "disk",
+            Self::BadData => "bad data",
+            Self::Other => "other",
+        }
+    }
+}
+
+/// Why a sync failed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyncFailure {
+    pub kind: SyncErrorKind,
+    pub message: String,
+}
+
+impl From<&(dyn std::error::Error + 'static)> for SyncFailure {
+    fn from(error: &(dyn std::error::Error + 'static)) -> Self {
+        Self {
+            kind: SyncErrorKind::of(error),
+            message: error.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for SyncFailure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// What syncing one doc set did
+#[derive(Debug, Clone)]
+pub struct SyncReport {
+    pub slug: String,
+    /// Bytes of the files downloaded in full
+    pub bytes: u64,
+    pub duration: Duration,
+    /// Entries of the doc set once synced, 0 if the sync failed
+    pub entries: usize,
+    pub result: Result<Option<IndexDiff>, SyncFailure>,
+}
+
+impl SyncReport {
+    /// Log the report as one line of `key=value` pairs, failures as warnings
+    fn log(&self) {
+        let fields = format!(
+            "slug={} bytes={} duration_ms={} entries={}",
+            self.slug,
+            self.bytes,
+            self.duration.as_millis(),
+            self.entries
+        );
+        match &self.result {
+            Ok(_) => log::info!("sync {fields} result=ok"),
+            Err(failure) => warn!(
+                "sync {fields} result=failed kind={} error={:?}",
+                failure.kind.label(),
+                failure.message
+            ),
+        }
+    }
+}
+
+/// What syncing several doc sets did, one report per doc set in the order they finished
+#[derive(Debug, Clone, Default)]
+pub struct SyncSummary {
+    pub reports: Vec<SyncReport>,
+    /// How long syncing them all took, less than the sum of their durations as they ran
+    /// side by side
+    pub elapsed: Duration,
+}
+
+impl SyncSummary {
+    /// A one-line summary, such as "Synced 3 doc sets, 42.0 MB, 2.1s; 1 failed: cpp (network)"
+    pub fn summary(&self) -> String {
+        let (synced, failed): (Vec<&SyncReport>, Vec<&SyncReport>) = self
+            .reports
+            .iter()
+            .partition(|report| report.result.is_ok());
+        let bytes: u64 = self.reports.iter().map(|report| report.bytes).sum();
+        let mut summary = format!(
+            "Synced {} doc set{}, {:.1} MB, {:.1}s",
+            synced.len(),
+            if synced.len() == 1 { "" } else { "s" },
+            bytes as f64 / 1_000_000.0,
+            self.elapsed.as_secs_f64()
+        );
+        if !failed.is_empty() {
+            let failures: Vec<String> = failed
+                .iter()
+                .filter_map(|report| {
+                    let failure = report.result.as_ref().err()?;
+                    Some(format!("{} ({})", report.slug, failure.kind.label()))
+                })
+                .collect();
+            summary.push_str(&format!(
+                "; {} failed: {}",
+                failed.len(),
+                failures.join(", ")
+            ));
+        }
+        summary
+    }
+
+    pub fn failed(&self) -> usize {
+        self.reports
+            .iter()
+            .filter(|report| report.result.is_err())
+            .count()
+    }
+}
+
+/// Sync a doc set like [`sync_doc`], also reporting on the sync, which is logged
+pub async fn sync_doc_reported(
+    storage: &Storage,
+    http: &HttpClient,
+    slug: &str,
+    max_download: Option<u64>,
+) -> (
+    Result<Option<IndexDiff>, Box<dyn std::error::Error>>,
+    SyncReport,
+) {
+    let started = Instant::now();
+    let mut bytes = 0;
+    let result = download_doc(storage, http, slug, max_download, &mut bytes).await;
+    let report = SyncReport {
+        slug: slug.to_owned(),
+        bytes,
+        duration: started.elapsed(),
+        entries: match &result {
+            Ok(_) => load_doc_set(storage, slug).map_or(0, |entries| entries.len()),
+            Err(_) => 0,
+        },
+        result: match &result {
+            Ok(changes) => Ok(changes.clone()),
+            Err(e) => Err(SyncFailure::from(&**e)),
+        },
+    };
+    report.log();
+    (result, report)
+}
+
+/// Download and install a doc set for [`sync_doc`], adding the bytes downloaded to `bytes`
+async fn download_doc(
+    storage: &Storage,
+    http: &HttpClient,
+    slug: &str,
+    max_download: Option<u64>,
+    bytes: &mut u64,
+) -> Result<Option<IndexDiff>, Box<dyn std::error::Error>> {
+    trace!("Syncing doc set {slug}");
+
+    let registry = registry(storage, http).await?;
+    let entry = registry
+        .iter()
+        .find(|entry| entry.slug == slug)
+        .ok_or_else(|| {
+            let known: Vec<String> = registry.iter().map(|entry| entry.slug.clone()).collect();
+            UnknownDocSet(match slug_hint(slug, &known) {
+                Some(hint) => format!("devdocs has no doc set '{slug}', {hint}"),
+                None => format!("devdocs has no doc set '{slug}'"),
+            })
+        })?;
+
+    // Both files are downloaded before either is written, so stopping the download midway,
+    // e.g. by dropping this future, leaves no partial files and any installed version intact
+    let archives = storage.archive_dir();
+    let kept = archives
+        .as_deref()
+        .and_then(|archives| kept_archive(archives, entry));
+    let reinstalling = kept.is_some();
+    let (db, index) = match kept {
+        Some(kept) => {
+            trace!("Reinstalling {slug} from its kept archive");
+            kept
+        }
+        None => {
+            let db = download(
+                http,
+                &format!("{DEVDOCS_DOCUMENTS}/{slug}/db.json"),
+                max_download,
+            )
+            .await?;
+            *bytes += db.len() as u64;
+            let index = download(
+                http,
+                &format!("{DEVDOCS_DOCUMENTS}/{slug}/index.json"),
+                max_download,
+            )
+            .await?;
+            *bytes += index.len() as u64;
+            (db, index)
+        }
+    };
+
+    // The content is kept compressed, as served if the server already compressed it
+    let db = if db.starts_with(&GZIP_MAGIC) {
+        db
+    } else {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&db)?;
+        encoder.finish()?
+    };
+    if let Some(archives) = archives.as_deref().filter(|_| !reinstalling) {
+        if let Err(e) = keep_archive(archives, entry, &db, &index) {
+            warn!("Could not keep the archive of {slug}: {e}");
+        }
+    }
+    install(storage, entry, &db, &index)
+}
+
+/// Write a doc set's gzipped content and its index into its directory, replacing any
+/// previous version, and return the entries the update added and removed
+fn install(
+    storage: &Storage,
+    entry: &RegistryEntry,
+    db: &[u8],
+    index: &[u8],
+) -> Result<Option<IndexDiff>, Box<dyn std::error::Error>> {
+    let slug = entry.slug.as_str();
+    let dir = storage.doc_set_dir(slug);
+    write_atomic(&dir.join(COMPRESSED_DB), db)?;
+    // An uncompressed copy from before would be read in its place, and a full-text index
+    // would describe the old content
+    for stale in [DB, FULL_TEXT_INDEX] {
+        match std::fs::remove_file(dir.join(stale)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+            _ => {}
+        }
+    }
+    // The index is written last, as a doc set only counts as installed once it has one
+    let index_path = dir.join("index.json");
+    if index_path.is_file() {
+        std::fs::copy(&index_path, dir.join(PREVIOUS_INDEX))?;
+    }
+    write_atomic(&index_path, index)?;
+    write_manifest(storage, &Manifest::from(entry))?;
+    // Without abstracts the doc set is still usable, its results only show less
+    if let Err(e) = write_abstracts(storage, slug) {
+        warn!("Could not work out the abstracts of {slug}: {e}");
+    }
+    if let Err(e) = record_indexed(storage, slug) {
+        warn!("Could not record what the index of {slug} was built from: {e}");
+    }
+
+    index_changes(storage, slug)
+}
+
+/// Sync several doc sets like [`sync_doc`], at most `concurrency` at a time, calling `synced`
+/// with the report on each as it finishes, and sum them up
+pub async fn sync_many(
+    storage: &Storage,
+    http: &HttpClient,
+    slugs: &[String],
+    max_download: Option<u64>,
+    concurrency: usize,
+    mut synced: impl FnMut(&SyncReport),
+) -> SyncSummary {
+    let started = Instant::now();
+    let mut summary = SyncSummary::default();
+    // Fetched once up front, rather than by every sync at once when the cache is stale
+    if let Err(e) = registry(storage, http).await {
+        for slug in slugs {
+            let report = SyncReport {
+                slug: slug.clone(),
+                bytes: 0,
+                duration: started.elapsed(),
+                entries: 0,
+                result: Err(SyncFailure::from(&*e)),
+            };
+            report.log();
+            synced(&report);
+            summary.reports.push(report);
+        }
+        summary.elapsed = started.elapsed();
+        return summary;
+    }
+    let permits = Arc::new(Semaphore::new(concurrency.max(1)));
+    let mut syncs = JoinSet::new();
+    for slug in slugs {
+        let (storage, http, slug) = (storage.clone(), http.clone(), slug.clone());
+        let permits = Arc::clone(&permits);
+        syncs.spawn(async move {
+            let _permit = permits.acquire_owned().await;
+            sync_doc_reported(&storage, &http, &slug, max_download)
+                .await
+                .1
+        });
+    }
+    while let Some(done) = syncs.join_next().await {
+        match done {
+            Ok(report) => {
+                synced(&report);
+                summary.reports.push(report);
+            }
+            Err(e) => warn!("A doc set sync stopped unexpectedly: {e}"),
+        }
+    }
+    summary.elapsed = started.elapsed();
+    summary
+}
+
+/// Where a doc set's `index.json` is kept from before its last update
+const PREVIOUS_INDEX: &str = "index.previous.json";
+
+/// Entries an update added to or removed from a doc set, by path
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IndexDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+impl IndexDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+
+    /// A one-line summary, such as "12 new entries, 3 removed"
+    pub fn summary(&self) -> String {
+        if self.is_empty() {
+            return "no entries added or removed".to_owned();
+        }
+        let entries = if self.added.len() == 1 {
+            "entry"
+        } else {
+            "entries"
+        };
+        format!(
+            "{} new {entries}, {} removed",
+            self.added.len(),
+            self.removed.len()
+        )
+    }
+}
+
+/// What changed in a doc set's index with its last update, or `None` if it has not been
+/// updated since it was first installed
+pub fn index_changes(
+    storage: &Storage,
+    slug: &str,
+) -> Result<Option<IndexDiff>, Box<dyn std::error::Error>> {
+    let previous_path = storage.doc_set_dir(slug).join(PREVIOUS_INDEX);
+    if !previous_path.is_file() {
+        return Ok(None);
+    }
+    let paths = |entries: Vec<IndexEntry>| -> BTreeSet<String> {
+        entries.into_iter().map(|entry| entry.path).collect()
+    };
+    let previous = paths(load_index_file(&previous_path, slug)?);
+    let current = paths(load_doc_set(storage, slug)?);
+
+    Ok(Some(IndexDiff {
+        added: current.difference(&previous).cloned().collect(),
+        removed: previous.difference(&current).cloned().collect(),
+    }))
+}
+
+/// Install a devdocs-format doc set from a local directory, such as internally generated docs
+///
+/// The directory must contain an `index.json` and a `db.json`, which are copied into the docs
+/// directory under `slug`. Adding a local doc set again replaces it, but a doc set downloaded
+/// from devdocs is never overwritten.
+///
+/// Slugs are lowercase, like devdocs', since scopes typed in a query are matched lowercased.
+pub fn add_local(
+    storage: &Storage,
+    dir: &Path,
+    slug: &str,
+) -> Result<Manifest, Box<dyn std::error::Error>> {
+    let valid_slug = !slug.is_empty()
+        && slug.chars().all(|c| {
+            c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '~' | '.' | '_' | '-')
+        });
+    if !valid_slug {
+        let lowercase = slug.to_ascii_lowercase();
+        if lowercase != slug && !lowercase.is_empty() {
+            return Err(format!("'{slug}' is not a valid doc set slug, try '{lowercase}'").into());
+        }
+        return Err(format!("'{slug}' is not a valid doc set slug").into());
+    }
+    if read_manifest(storage, slug).is_some_and(|manifest| !manifest.local) {
+        return Err(format!("'{slug}' is already installed from devdocs").into());
+    }
+
+    for file in ["index.json", "db.json"] {
+        let path = dir.join(file);
+        let value: Value = serde_json::from_reader(BufReader::new(
+            File::open(&path).map_err(|e| format!("{}: {e}", path.display()))?,
+        ))
+        .map_err(|e| format!("{}: {e}", path.display()))?;
+        let valid = match file {
+            "index.json" => value.get("entries").is_some_and(Value::is_array),
+            _ => value.is_object(),
+        };
+        if !valid {
+            return Err(format!("{} is not a devdocs {file}", path.display()).into());
+        }
+    }
+
+    let target = storage.doc_set_dir(slug);
+    std::fs::create_dir_all(&target)?;
+    match std::fs::remove_file(target.join(FULL_TEXT_INDEX)) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+        _ => {}
+    }
+    for file in ["db.json", "index.json"] {
+        let tmp = target.join(format!("{file}.tmp"));
+        std::fs::copy(dir.join(file), &tmp)?;
+        std::fs::rename(&tmp, target.join(file))?;
+    }
+
+    let manifest = Manifest {
+        slug: slug.to_owned(),
+        name: slug.to_owned(),
+        version: String::new(),
+        release: String::new(),
+        mtime: unix_now(),
+        local: true,
+        about: About::default(),
+    };
+    write_manifest(storage, &manifest)?;
+    if let Err(e) = write_abstracts(storage, slug) {
+        warn!("Could not work out the abstracts of {slug}: {e}");
+    }
+    if let Err(e) = record_indexed(storage, slug) {
+        warn!("Could not record what the index of {slug} was built from: {e}");
+    }
+    Ok(manifest)
+}
+
+/// Read the HTML content of an entry from a doc set's `db.json`, or `None` if the doc set has
+/// no such entry
+///
+/// Entry paths may carry a `#fragment`, which is ignored when looking up the content.
+pub fn read_entry(
+    storage: &Storage,
+    slug: &str,
+    path: &str,
+) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    trace!("Reading entry {slug}/{path}");
+
+    Ok(DocDb::open(&storage.doc_set_dir(slug))?.get(path))
+}
+
+/// Build the full-text index of an installed doc set from its content, returning the number
+/// of entries indexed
+///
+/// The index takes about as much disk space as the doc set's compressed content, so it is
+/// only built when the config asks for full-text search.
+pub fn index_full_text(storage: &Storage, slug: &str) -> Result<usize, Box<dyn std::error::Error>> {
+    trace!("Building the full-text index of {slug}");
+
+    let index = FullTextIndex::build(&DocDb::open(&storage.doc_set_dir(slug))?);
+    index.save(&storage.doc_set_dir(slug))?;
+    Ok(index.len())
+}
+
+/// Where a doc set keeps the abstract of each of its pages, next to its content
+const ABSTRACTS: &str = "abstracts.json";
+
+/// Characters an abstract is cut to
+const ABSTRACT_CHARS: usize = 100;
+
+/// Work out the abstract of each page of an installed doc set from its content and save
+/// them next to it, returning how many pages have one
+///
+/// A page's abstract is the first sentence of its first paragraph. Pages without prose, e.g.
+/// those that are all code, have none.
+pub fn write_abstracts(storage: &Storage, slug: &str) -> Result<usize, Box<dyn std::error::Error>> {
+    trace!("Working out the abstracts of {slug}");
+
+    let db = DocDb::open(&storage.doc_set_dir(slug))?;
+    let options = RenderOptions::default();
+    let abstracts: serde_json::Map<String, Value> = db
+        .pages()
+        .filter_map(|(path, html)| {
+            let paragraph = html_to_text(html, &options).first_paragraph?;
+            Some((path.to_owned(), Value::from(abstract_of(&paragraph))))
+        })
+        .collect();
+    let count = abstracts.len();
+    write_versioned(
+        &storage.doc_set_dir(slug).join(ABSTRACTS),
+        Value::Object(abstracts),
+    )?;
+    Ok(count)
+}
+
+/// The abstracts of a doc set's pages by path, empty for doc sets synced before abstracts
+/// were worked out
+pub fn read_abstracts(storage: &Storage, slug: &str) -> HashMap<String, String> {
+    let path = storage.doc_set_dir(slug).join(ABSTRACTS);
+    let Some(Value::Object(abstracts)) = read_versioned(&path, MIGRATIONS).ok().flatten() else {
+        return HashMap::new();
+    };
+    abstracts
+        .into_iter()
+        .filter_map(|(path, text)| Some((path, text.as_str()?.to_owned())))
+        .collect()
+}
+
+/// The first sentence of a paragraph, cut short if it is long
+///
+/// A sentence ends at a `.`, `!` or `?` followed by a space and a capital letter, so
+/// abbreviations like `e.g.` don't end it.
+fn abstract_of(paragraph: &str) -> String {
+    let end = paragraph
+        .char_indices()
+        .find(|&(i, c)| {
+            matches!(c, '.' | '!' | '?')
+                && paragraph[i + 1..].starts_with(' ')
+                && paragraph[i + 2..].starts_with(char::is_uppercase)
+        })
+        .map_or(paragraph.len(), |(i, _)| i + 1);
+    let sentence = &paragraph[..end];
+    if sentence.chars().count() <= ABSTRACT_CHARS {
+        return sentence.to_owned();
+    }
+    let cut: String = sentence.chars().take(ABSTRACT_CHARS - 1).collect();
+    format!("{}…", cut.trim_end())
+}
+
+/// Read the full-text index of an installed doc set, or `None` if it has none
+pub fn full_text_index(
+    storage: &Storage,
+    slug: &str,
+) -> Result<Option<FullTextIndex>, Box<dyn std::error::Error>> {
+    FullTextIndex::load(&storage.doc_set_dir(slug))
+}
+
+/// What a doc set's abstracts and full-text index were built from, to tell when its files
+/// changed since
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct IndexStamp {
+    /// Registry `mtime` from the doc set's manifest, 0 without one
+    mtime: u64,
+    /// Size and modification time, in milliseconds, of its `index.json` and its content
+    files: [(u64, u64); 2],
+}
+
+impl IndexStamp {
+    /// The stamp of an installed doc set as its files are now
+    fn of(storage: &Storage, slug: &str) -> Self {
+        let dir = storage.doc_set_dir(slug);
+        let content =
+            DbFile::find(&dir).map_or_else(|| dir.join(COMPRESSED_DB), |db| db.path().to_owned());
+        let file = |path: &Path| {
+            std::fs::metadata(path).map_or((0, 0), |metadata| {
+                let modified = metadata
+                    .modified()
+                    .ok()
+                    .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+                    .map_or(0, |modified| modified.as_millis() as u64);
+                (metadata.len(), modified)
+            })
+        };
+        Self {
+            mtime: read_manifest(storage, slug).map_or(0, |manifest| manifest.mtime),
+            files: [file(&dir.join("index.json")), file(&content)],
+        }
+    }
+
+    fn to_json(self) -> Value {
+        json!({ "mtime": self.mtime, "files": self.files })
+    }
+
+    fn from_json(value: &Value) -> Option<Self> {
+        let file = |file: &Value| Some((file.get(0)?.as_u64()?, file.get(1)?.as_u64()?));
+        let files = value.get("files")?.as_array()?;
+        Some(Self {
+            mtime: value.get("mtime")?.as_u64()?,
+            files: [file(files.first()?)?, file(files.get(1)?)?],
+        })
+    }
+}
+
+/// Held while the record of what the index was built from is read and written, as doc sets
+/// synced at the same time each update it
+static INDEX_RECORD: Mutex<()> = Mutex::new(());
+
+/// The stamps of the doc sets the index was built from by slug, or `None` before any were
+/// recorded
+fn read_index_record(
+    storage: &Storage,
+) -> Result<Option<HashMap<String, IndexStamp>>, Box<dyn std::error::Error>> {
+    let Some(value) = read_versioned(&storage.index_record_path(), MIGRATIONS)? else {
+        return Ok(None);
+    };
+    let stamps = value
+        .as_object()
+        .into_iter()
+        .flatten()
+        .filter_map(|(slug, stamp)| Some((slug.clone(), IndexStamp::from_json(stamp)?)))
+        .collect();
+    Ok(Some(stamps))
+}
+
+/// Change the record of what the index was built from
+///
+/// Without a record, e.g. on the first run of a version that keeps one, the index is taken
+/// to be up to date and a record of every installed doc set is started.
+fn update_index_record(
+    storage: &Storage,
+    update: impl FnOnce(&mut HashMap<String, IndexStamp>),
+) -> Result<(), Box<dyn std::error::Error>> {
+    let _lock = INDEX_RECORD.lock().unwrap_or_else(|e| e.into_inner());
+    let mut stamps = read_index_record(storage)?.unwrap_or_else(|| {
+        installed_doc_sets(storage)
+            .into_iter()
+            .map(|slug| {
+                let stamp = IndexStamp::of(storage, &slug);
+                (slug, stamp)
+            })
+            .collect()
+    });
+    update(&mut stamps);
+    let record: serde_json::Map<String, Value> = stamps
+        .into_iter()
+        .map(|(slug, stamp)| (slug, stamp.to_json()))
+        .collect();
+    write_versioned(&storage.index_record_path(), Value::Object(record))
+}
+
+/// Record that a doc set's abstracts and full-text index were built from its files as they
+/// are now
+pub fn record_indexed(storage: &Storage, slug: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let stamp = IndexStamp::of(storage, slug);
+    update_index_record(storage, |stamps| {
+        stamps.insert(slug.to_owned(), stamp);
+    })
+}
+
+/// Doc sets whose files differ from what the index was built from
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StaleIndex {
+    /// Installed doc sets that changed, or were installed without the index being updated
+    pub changed: Vec<String>,
+    /// Doc sets the index was built from that are no longer installed
+    pub removed: Vec<String>,
+}
+
+impl StaleIndex {
+    pub fn is_empty(&self) -> bool {
+        self.changed.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Compare the installed doc sets and their manifests and files with what the index was
+/// built from, starting a record of them if there is none
+pub fn check_index(storage: &Storage) -> Result<StaleIndex, Box<dyn std::error::Error>> {
+    let Some(recorded) = read_index_record(storage)? else {
+        update_index_record(storage, |_| {})?;
+        return Ok(StaleIndex::default());
+    };
+    let installed = installed_doc_sets(storage);
+    let changed = installed
+        .iter()
+        .filter(|slug| recorded.get(*slug) != Some(&IndexStamp::of(storage, slug)))
+        .cloned()
+        .collect();
+    let mut removed: Vec<String> = recorded
+        .into_keys()
+        .filter(|slug| !installed.contains(slug))
+        .collect();
+    removed.sort();
+    Ok(StaleIndex { changed, removed })
+}
+
+/// Build a doc set's abstracts again from its content, and its full-text index if it has one
+/// or `full_text` asks for one, then record what they were built from
+///
+/// The doc set is recorded even if building fails, so broken content isn't rebuilt again on
+/// every start.
+pub fn rebuild_index(
+    storage: &Storage,
+    slug: &str,
+    full_text: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    trace!("Rebuilding the index of {slug}");
+
+    let has_full_text = storage.doc_set_dir(slug).join(FULL_TEXT_INDEX).is_file();
+    let built = write_abstracts(storage, slug).and_then(|_| {
+        if full_text || has_full_text {
+            index_full_text(storage, slug)?;
+        }
+        Ok(())
+    });
+    record_indexed(storage, slug)?;
+    built
+}
+
+/// Drop doc sets that are no longer installed from the record of what the index was built
+/// from
+pub fn forget_indexed(
+    storage: &Storage,
+    slugs: &[String],
+) -> Result<(), Box<dyn std::error::Error>> {
+    update_index_record(storage, |stamps| {
+        for slug in slugs {
+            stamps.remove(slug);
+        }
+    })
+}
+
+/// Bytes a doc set takes up on disk, counting every file in its directory
+pub fn doc_set_size(storage: &Storage, slug: &str) -> std::io::Result<u64> {
+    let mut size = 0;
+    for file in std::fs::read_dir(storage.doc_set_dir(slug))? {
+        let metadata = file?.metadata()?;
+        if metadata.is_file() {
+            size += metadata.len();
+        }
+    }
+    Ok(size)
+}
+
+/// Outcome of checking that a doc set's index and content agree
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VerifyReport {
+    /// Number of entries in `index.json`
+    pub entries: usize,
+    /// Paths listed in `index.json` that have no content in `db.json`, sorted
+    pub missing: Vec<String>,
+}
+
+impl VerifyReport {
+    pub fn is_ok(&self) -> bool {
+        self.missing.is_empty()
+    }
+}
+
+/// Check that every entry in a doc set's index has content in its `db.json`
+///
+/// The two files are downloaded separately, so an interrupted or partial sync can leave them
+/// out of step.
+pub fn verify(storage: &Storage, slug: &str) -> Result<VerifyReport, Box<dyn std::error::Error>> {
+    let entries = load_doc_set(storage, slug)?;
+    let db = DocDb::open(&storage.doc_set_dir(slug))?;
+
+    let mut missing: Vec<String> = entries
+        .iter()
+        .map(|entry| split_fragment(&entry.path).0)
+        .filter(|path| !db.contains(path))
+        .map(str::to_owned)
+        .collect();
+    missing.sort();
+    missing.dedup();
+
+    Ok(VerifyReport {
+        entries: entries.len(),
+        missing,
+    })
+}
+
+/// Something an interrupted sync left behind in the docs directory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Leftover {
+    /// A doc set directory lacking some of the files a doc set needs
+    IncompleteDocSet {
+        slug: String,
+        missing: Vec<&'static str>,
+    },
+    /// A temporary or partially downloaded file
+    StrayFile(PathBuf),
+}
+
+impl fmt::Display for Leftover {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Leftover::IncompleteDocSet { slug, missing } => {
+                write!(
+                    f,
+                    "{slug}: incomplete doc set, missing {}",
+                    missing.join(" and ")
+                )
+            }
+            Leftover::StrayFile(path) => write!(f, "{}: stray partial file", path.display()),
+        }
+    }
+}
+
+/// Find incomplete doc sets and stray partial files in the docs directory
+pub fn find_leftovers(storage: &Storage) -> std::io::Result<Vec<Leftover>> {
+    let docs_dir = storage.docs_dir();
+    if !docs_dir.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut leftovers = Vec::new();
+    let mut dirs: Vec<_> = std::fs::read_dir(docs_dir)?.collect::<Result<_, _>>()?;
+    dirs.sort_by_key(|dir| dir.file_name());
+    for dir in dirs {
+        let path = dir.path();
+        if is_partial(&path) {
+            leftovers.push(Leftover::StrayFile(path));
+            continue;
+        }
+        if !path.is_dir() {
+            continue;
+        }
+
+        let mut missing = Vec::new();
+        if !path.join("index.json").is_file() {
+            missing.push("index.json");
+        }
+        if DbFile::find(&path).is_none() {
+            missing.push(DB);
+        }
+        if !missing.is_empty() {
+            leftovers.push(Leftover::IncompleteDocSet {
+                slug: dir.file_name().to_string_lossy().into_owned(),
+                missing,
+            });
+            continue;
+        }
+        let mut files: Vec<PathBuf> = std::fs::read_dir(&path)?
+            .map(|file| file.map(|file| file.path()))
+            .collect::<Result<_, _>>()?;
+        files.sort();
+        leftovers.extend(
+            files
+                .into_iter()
+                .filter(|file| is_partial(file))
+                .map(Leftover::StrayFile),
+        );
+    }
+    Ok(leftovers)
+}
+
+/// Files written during a download before being renamed into place
+fn is_partial(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension == "tmp" || extension == "partial")
+}
+
+/// Delete what `find_leftovers` found
+pub fn remove_leftovers(storage: &Storage, leftovers: &[Leftover]) -> std::io::Result<()> {
+    for leftover in leftovers {
+        match leftover {
+            Leftover::IncompleteDocSet { slug, .. } => {
+                std::fs::remove_dir_all(storage.doc_set_dir(slug))?
+            }
+            Leftover::StrayFile(path) if path.is_dir() => std::fs::remove_dir_all(path)?,
+            Leftover::StrayFile(path) => std::fs::remove_file(path)?,
+        }
+    }
+    Ok(())
+}
+
+/// Uninstall a doc set, deleting its directory with everything in it
+pub fn remove_doc_set(storage: &Storage, slug: &str) -> std::io::Result<()> {
+    trace!("Removing doc set {slug}");
+    std::fs::remove_dir_all(storage.doc_set_dir(slug))?;
+    if let Err(e) = forget_indexed(storage, &[slug.to_owned()]) {
+        warn!("Could not drop {slug} from the record of the index: {e}");
+    }
+    Ok(())
+}
+
+/// Slugs of the doc sets present in the docs directory, sorted
+pub fn installed_doc_sets(storage: &Storage) -> Vec<String> {
+    let Ok(dirs) = std::fs::read_dir(storage.docs_dir()) else {
+        return Vec::new();
+    };
+
+    let mut slugs: Vec<String> = dirs
+        .filter_map(|dir| dir.ok())
+        .filter(|dir| dir.path().join("index.json").is_file())
+        .filter_map(|dir| dir.file_name().into_string().ok())
+        .collect();
+    slugs.sort();
+    slugs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// A registry URL served by a server answering every request with `503`
+    async fn unavailable_registry() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/docs.json", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut request = [0; 1024];
+                let _ = socket.read(&mut request).await;
+                let response = "HTTP/1.1 503 Service Unavailable\r\nRetry-After: 0\r\n\
+                                Content-Length: 0\r\nConnection: close\r\n\r\n";
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+        url
+    }
+
+    /// A URL of `db.json` on a server answering every request with `size` bytes, saying how
+    /// many only if `content_length`
+    async fn serving(size: usize, content_length: bool) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/rust/db.json", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut request = [0; 1024];
+                let _ = socket.read(&mut request).await;
+                let mut response = "HTTP/1.1 200 OK\r\nConnection: close\r\n".to_owned();
+                if content_length {
+                    response.push_str(&format!("Content-Length: {size}\r\n"));
+                }
+                response.push_str("\r\n");
+                let _ = socket.write_all(response.as_bytes()).await;
+                let _ = socket.write_all(&vec![b'x'; size]).await;
+            }
+        });
+        url
+    }
+
+    #[tokio::test]
+    async fn the_cached_registry_stands_in_while_the_server_is_unavailable() {
+        let dir = TempDir::new("registry-fallback");
+        let storage = Storage::at(&*dir);
+        let http = HttpClient::new(None, 1).unwrap();
+        let url = unavailable_registry().await;
+
+        // Without a cached copy there is nothing to show
+        let error = fetch_registry_or_cached_from(&storage, &http, &url)
+            .await
+            .unwrap_err();
+        assert!(error.is::<ServerUnavailable>());
+
+        let cached = RegistryEntry {
+            name: "Rust".to_owned(),
+            slug: "rust".to_owned(),
+            version: String::new(),
+            release: "1.80.0".to_owned(),
+            mtime: 1,
+            db_size: 2,
+            about: About::default(),
+        };
+        let cache = json!({ "fetched": 1, "docs": [cached.to_json()] });
+        write_versioned(&storage.registry_path(), cache).unwrap();
+        match fetch_registry_or_cached_from(&storage, &http, &url)
+            .await
+            .unwrap()
+        {
+            RegistryFetch::Cached { registry, error } => {
+                assert_eq!(registry.entries, [cached]);
+                assert_eq!(error.status, reqwest::StatusCode::SERVICE_UNAVAILABLE);
+            }
+            RegistryFetch::Fetched(_) => panic!("the registry was fetched"),
+        }
+    }
+
+    /// A devdocs-format doc set in a directory of its own, to add with `add_local`
+    fn local_doc_set(name: &str) -> TempDir {
+        let dir = TempDir::new(name);
+        let index = json!({ "entries": [{ "name": "a", "path": "a", "type": "t" }] });
+        std::fs::write(dir.join("index.json"), index.to_string()).unwrap();
+        std::fs::write(dir.join("db.json"), json!({ "a": "<p>a</p>" }).to_string()).unwrap();
+        dir
+    }
+
+    #[test]
+    fn local_doc_sets_take_lowercase_slugs_only() {
+        let data = TempDir::new("add-local-data");
+        let storage = Storage::at(&*data);
+        let source = local_doc_set("add-local-source");
+
+        let error = add_local(&storage, &source, "MyDocs").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "'MyDocs' is not a valid doc set slug, try 'mydocs'"
+        );
+        assert!(add_local(&storage, &source, "my docs").is_err());
+        assert!(installed_doc_sets(&storage).is_empty());
+
+        add_local(&storage, &source, "mydocs~1.0").unwrap();
+        assert_eq!(installed_doc_sets(&storage), ["mydocs~1.0"]);
+    }
+
+    #[test]
+    fn a_kept_archive_is_reused_only_for_its_release_and_checksum() {
+        let dir = TempDir::new("kept-archives");
+        let mut entry = RegistryEntry {
+            name: "Rust".to_owned(),
+            slug: "rust".to_owned(),
+            version: String::new(),
+            release: "1.80.0".to_owned(),
+            mtime: 1,
+            db_size: 2,
+            about: About::default(),
+        };
+        let old = dir.join(archive_name(&entry));
+        keep_archive(&dir, &entry, b"db", b"index").unwrap();
+        assert_eq!(
+            kept_archive(&dir, &entry),
+            Some((b"db".to_vec(), b"index".to_vec()))
+        );
+
+        // A newer release is downloaded, and the month-old archive is removed once it is kept
+        entry.mtime = 2;
+        assert_eq!(kept_archive(&dir, &entry), None);
+        let month_ago = SystemTime::now() - MAX_ARCHIVE_AGE - Duration::from_secs(60);
+        File::options()
+            .write(true)
+            .open(&old)
+            .unwrap()
+            .set_modified(month_ago)
+            .unwrap();
+        keep_archive(&dir, &entry, b"new db", b"new index").unwrap();
+        assert!(!old.exists());
+        assert!(!checksum_path(&old).exists());
+
+        // An archive that no longer matches its checksum is downloaded again
+        let new = dir.join(archive_name(&entry));
+        let mut bytes = std::fs::read(&new).unwrap();
+        let at = bytes
+            .windows(6)
+            .position(|window| window == b"new db")
+            .unwrap();
+        bytes[at] = b'N';
+        std::fs::write(&new, bytes).unwrap();
+        assert_eq!(kept_archive(&dir, &entry), None);
+    }
+
+    #[test]
+    fn verifying_lists_the_entries_without_content() {
+        let dir = TempDir::new("verify");
+        let storage = Storage::at(&*dir);
+        let rust = storage.doc_set_dir("rust");
+        std::fs::create_dir_all(&rust).unwrap();
+        let index = json!({ "entries": [
+            { "name": "a", "path": "a", "type": "t" },
+            { "name": "b", "path": "b#x", "type": "t" },
+            { "name": "b.y", "path": "b#y", "type": "t" },
+            { "name": "c", "path": "c", "type": "t" },
+        ] });
+        std::fs::write(rust.join("index.json"), index.to_string()).unwrap();
+        std::fs::write(rust.join(DB), json!({ "a": "<p>a</p>" }).to_string()).unwrap();
+
+        let report = verify(&storage, "rust").unwrap();
+        assert_eq!(report.entries, 4);
+        assert_eq!(report.missing, ["b", "c"]);
+        assert!(!report.is_ok());
+
+        let db = json!({ "a": "<p>a</p>", "b": "<p id=\"x\">b</p>", "c": "" });
+        std::fs::write(rust.join(DB), db.to_string()).unwrap();
+        assert!(verify(&storage, "rust").unwrap().is_ok());
+    }
+
+    #[test]
+    fn leftovers_of_interrupted_syncs_are_found_and_removed() {
+        let dir = TempDir::new("leftovers");
+        let storage = Storage::at(&*dir);
+        let docs = storage.docs_dir();
+        // A complete doc set, with a download that never got renamed into place
+        let rust = storage.doc_set_dir("rust");
+        std::fs::create_dir_all(&rust).unwrap();
+        std::fs::write(rust.join("index.json"), r#"{"entries":[]}"#).unwrap();
+        std::fs::write(rust.join(DB), "{}").unwrap();
+        std::fs::write(rust.join("db.json.gz.tmp"), "").unwrap();
+        // A doc set whose content was never written, one with nothing at all, and a
+        // partial file outside any doc set
+        let go = storage.doc_set_dir("go");
+        std::fs::create_dir_all(&go).unwrap();
+        std::fs::write(go.join("index.json"), r#"{"entries":[]}"#).unwrap();
+        std::fs::create_dir_all(storage.doc_set_dir("css")).unwrap();
+        std::fs::write(docs.join("ruby.partial"), "").unwrap();
+
+        let leftovers = find_leftovers(&storage).unwrap();
+        assert_eq!(
+            leftovers,
+            [
+                Leftover::IncompleteDocSet {
+                    slug: "css".to_owned(),
+                    missing: vec!["index.json", DB],
+                },
+                Leftover::IncompleteDocSet {
+                    slug: "go".to_owned(),
+                    missing: vec![DB],
+                },
+                Leftover::StrayFile(docs.join("ruby.partial")),
+                Leftover::StrayFile(rust.join("db.json.gz.tmp")),
+            ]
+        );
+
+        remove_leftovers(&storage, &leftovers).unwrap();
+        assert!(find_leftovers(&storage).unwrap().is_empty());
+        assert_eq!(installed_doc_sets(&storage), ["rust"]);
+        let mut files: Vec<_> = std::fs::read_dir(&rust)
+            .unwrap()
+            .map(|file| file.unwrap().file_name())
+            .collect();
+        files.sort();
+        assert_eq!(files, [DB, "index.json"]);
+    }
+
+    #[tokio::test]
+    async fn downloads_over_the_cap_are_refused() {
+        let http = HttpClient::new(None, 1).unwrap();
+        let too_large = |size| DownloadTooLarge {
+            file: "db.json".to_owned(),
+            size,
+            limit: 1_000,
+        };
+
+        // The reported size is refused before downloading anything
+        let url = serving(5_000, true).await;
+        let error = download(&http, &url, Some(1_000)).await.unwrap_err();
+        assert_eq!(error.downcast_ref(), Some(&too_large(Some(5_000))));
+
+        // Without a reported size, the download stops once it passes the cap
+        let url = serving(5_000, false).await;
+        let error = download(&http, &url, Some(1_000)).await.unwrap_err();
+        assert_eq!(error.downcast_ref(), Some(&too_large(None)));
+
+        assert_eq!(
+            download(&http, &url, Some(5_000)).await.unwrap().len(),
+            5_000
+        );
+        assert_eq!(download(&http, &url, None).await.unwrap().len(), 5_000);
+    }
+
+    #[test]
+    fn installing_keeps_the_content_compressed_and_drops_a_stale_copy() {
+        let dir = TempDir::new("install");
+        let storage = Storage::at(&*dir);
+        let rust = storage.doc_set_dir("rust");
+        std::fs::create_dir_all(&rust).unwrap();
+        let old_index = json!({ "entries": [{ "name": "a", "path": "a", "type": "t" }] });
+        std::fs::write(rust.join("index.json"), old_index.to_string()).unwrap();
+        std::fs::write(rust.join(DB), json!({ "a": "<p>old</p>" }).to_string()).unwrap();
+        std::fs::write(rust.join(FULL_TEXT_INDEX), "{}").unwrap();
+
+        let content = json!({ "a": "<p>new</p>", "b": "<p>b</p>" });
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(content.to_string().as_bytes()).unwrap();
+        let index = json!({ "entries": [
+            { "name": "a", "path": "a", "type": "t" },
+            { "name": "b", "path": "b", "type": "t" },
+        ] });
+        let entry = RegistryEntry {
+            name: "Rust".to_owned(),
+            slug: "rust".to_owned(),
+            version: String::new(),
+            release: "1.80.0".to_owned(),
+            mtime: 2,
+            db_size: 2,
+            about: About::default(),
+        };
+        let diff = install(
+            &storage,
+            &entry,
+            &encoder.finish().unwrap(),
+            index.to_string().as_bytes(),
+        )
+        .unwrap();
+
+        assert!(!rust.join(DB).exists());
+        assert!(!rust.join(FULL_TEXT_INDEX).exists());
+        assert_eq!(
+            DbFile::find(&rust),
+            Some(DbFile::Gzip(rust.join(COMPRESSED_DB)))
+        );
+        let db = DocDb::open(&rust).unwrap();
+        assert_eq!(db.get("a").as_deref(), Some("<p>new</p>"));
+        assert_eq!(db.get("b").as_deref(), Some("<p>b</p>"));
+        assert_eq!(db.len(), 2);
+
+        let previous = std::fs::read_to_string(rust.join(PREVIOUS_INDEX)).unwrap();
+        assert_eq!(previous, old_index.to_string());
+        assert_eq!(diff.unwrap().added, ["b"]);
+        assert_eq!(read_manifest(&storage, "rust").unwrap().mtime, 2);
+    }
+}
@@ -0,0 +1,170 @@
+use std::ffi::OsStr;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// The pager to read entries in: `$PAGER`, or `less -R` if it isn't set
+///
+/// Returns `None` when `PAGER` is set but empty, meaning no pager should be used.
+pub fn pager_command() -> Option<Vec<String>> {
+    let pager = std::env::var("PAGER").unwrap_or_else(|_| "less -R".to_owned());
+    let command: Vec<String> = pager.split_whitespace().map(str::to_owned).collect();
+    (!command.is_empty()).then_some(command)
+}
+
+/// Run a pager on some text, waiting for the user to quit it
+pub fn run_pager(command: &[String], text: &str) -> std::io::Result<()> {
+    let (program, args) = command
+        .split_first()
+        .ok_or_else(|| std::io::Error::other("empty pager command"))?;
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .spawn()?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        // The pager closing its input early, e.g. when quit before reading everything, is fine
+        match stdin.write_all(text.as_bytes()) {
+            Err(e) if e.kind() != std::io::ErrorKind::BrokenPipe => return Err(e),
+            _ => {}
+        }
+    }
+    child.wait()?;
+    Ok(())
+}
+
+/// The editor to open files in: `$VISUAL`, then `$EDITOR`, or `None` if neither is set
+pub fn editor_command() -> Option<Vec<String>> {
+    ["VISUAL", "EDITOR"].iter().find_map(|var| {
+        let editor = std::env::var(var).ok()?;
+        let command: Vec<String> = editor.split_whitespace().map(str::to_owned).collect();
+        (!command.is_empty()).then_some(command)
+    })
+}
+
+/// File extensions for the languages devdocs marks code blocks with, so editors pick the
+/// right syntax highlighting
+const EXTENSIONS: &[(&str, &str)] = &[
+    ("bash", "sh"),
+    ("c", "c"),
+    ("cpp", "cpp"),
+    ("csharp", "cs"),
+    ("css", "css"),
+    ("elixir", "ex"),
+    ("erlang", "erl"),
+    ("go", "go"),
+    ("haskell", "hs"),
+    ("html", "html"),
+    ("java", "java"),
+    ("javascript", "js"),
+    ("js", "js"),
+    ("json", "json"),
+    ("jsx", "jsx"),
+    ("kotlin", "kt"),
+    ("lua", "lua"),
+    ("markup", "html"),
+    ("php", "php"),
+    ("python", "py"),
+    ("ruby", "rb"),
+    ("rust", "rs"),
+    ("scala", "scala"),
+    ("shell", "sh"),
+    ("sql", "sql"),
+    ("swift", "swift"),
+    ("toml", "toml"),
+    ("typescript", "ts"),
+    ("xml", "xml"),
+    ("yaml", "yml"),
+];
+
+/// The file extension for code in a language, preferring the user's `custom` mapping over
+/// the built-in one and falling back to `txt`
+pub fn extension_for<'a>(language: Option<&str>, custom: &'a [(String, String)]) -> &'a str {
+    let Some(language) = language else {
+        return "txt";
+    };
+    custom
+        .iter()
+        .find(|(name, _)| name == language)
+        .map(|(_, extension)| extension.as_str())
+        .or_else(|| {
+            EXTENSIONS
+                .iter()
+                .find(|(name, _)| *name == language)
+                .map(|(_, extension)| *extension)
+        })
+        .unwrap_or("txt")
+}
+
+/// Open `contents` in an editor as a temporary file named after `extension`, waiting for the
+/// editor to exit
+///
+/// The file is removed afterwards, whether or not the editor succeeded.
+pub fn edit_temporary(command: &[String], contents: &str, extension: &str) -> std::io::Result<()> {
+    let (program, args) = command
+        .split_first()
+        .ok_or_else(|| std::io::Error::other("empty editor command"))?;
+    let path = std::env::temp_dir().join(format!(
+        "rtftui-{}-{}.{extension}",
+        std::process::id(),
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis()
+    ));
+    std::fs::write(&path, contents)?;
+    let status = Command::new(program).args(args).arg(&path).status();
+    // Best effort, the operating system cleans the temp directory eventually anyway
+    let _ = std::fs::remove_file(&path);
+
+    let status = status?;
+    if !status.success() {
+        return Err(std::io::Error::other(format!(
+            "the editor exited with {status}"
+        )));
+    }
+    Ok(())
+}
+
+/// What the platform opens files and URLs with
+fn platform_opener() -> Vec<String> {
+    if cfg!(target_os = "macos") {
+        vec!["open".to_owned()]
+    } else if cfg!(windows) {
+        ["cmd", "/c", "start", ""].map(str::to_owned).to_vec()
+    } else {
+        vec!["xdg-open".to_owned()]
+    }
+}
+
+/// Start a program on one argument without waiting for it, keeping it from drawing over
+/// the UI
+fn spawn_detached(command: &[String], arg: &OsStr) -> std::io::Result<()> {
+    let (program, args) = command
+        .split_first()
+        .ok_or_else(|| std::io::Error::other("empty command"))?;
+    Command::new(program)
+        .args(args)
+        .arg(arg)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    Ok(())
+}
+
+/// Open a URL in the browser without waiting for it: `$BROWSER`, or whatever the platform
+/// opens URLs with
+pub fn open_url(url: &str) -> std::io::Result<()> {
+    let browser = std::env::var("BROWSER").unwrap_or_default();
+    let mut command: Vec<String> = browser.split_whitespace().map(str::to_owned).collect();
+    if command.is_empty() {
+        command = platform_opener();
+    }
+    spawn_detached(&command, url.as_ref())
+}
+
+/// Open a directory in the platform's file manager without waiting for it
+pub fn open_dir(dir: &Path) -> std::io::Result<()> {
+    spawn_detached(&platform_opener(), dir.as_os_str())
+}
@@ -0,0 +1,385 @@
+use crate::db::DocDb;
+use crate::html::{html_to_text, RenderOptions};
+use crate::storage::write_atomic;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::BufReader;
+use std::ops::Range;
+use std::path::Path;
+
+/// Where a doc set keeps its full-text index, next to its content
+pub const FULL_TEXT_INDEX: &str = "fulltext.json.gz";
+
+/// The rendered text of a doc set's entries, and which entries each word appears in
+#[derive(Debug, Clone, Default)]
+pub struct FullTextIndex {
+    /// Path and text of each entry
+    documents: Vec<(String, String)>,
+    /// Lowercased words, with the positions in `documents` of the entries they appear in
+    terms: BTreeMap<String, Vec<u32>>,
+}
+
+/// An entry whose text contains every word of a query
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FullTextMatch {
+    /// Path of the entry in the doc set's `db.json`, without a fragment
+    pub path: String,
+    /// How well the entry matched, higher is better
+    pub score: i64,
+    /// The sentence of the entry's text with the best match, empty if it has no text
+    /// around the match
+    pub snippet: String,
+}
+
+impl FullTextMatch {
+    /// The snippet, or else `name` as all there is to show for the match
+    pub fn snippet_or(self, name: &str) -> String {
+        if self.snippet.is_empty() {
+            name.to_owned()
+        } else {
+            self.snippet
+        }
+    }
+}
+
+/// Characters a snippet is cut to
+const SNIPPET_CHARS: usize = 120;
+
+impl FullTextIndex {
+    /// Index the pages of a doc set's content, rendered as they are shown
+    pub fn build(db: &DocDb) -> Self {
+        let mut index = Self::default();
+        let options = RenderOptions::default();
+        for (path, html) in db.pages() {
+            let text = html_to_text(html, &options).plain_text();
+            let id = index.documents.len() as u32;
+            let words: BTreeSet<String> = words(&text).collect();
+            for word in words {
+                index.terms.entry(word).or_default().push(id);
+            }
+            index.documents.push((path.to_owned(), text));
+        }
+        index
+    }
+
+    pub fn len(&self) -> usize {
+        self.documents.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.documents.is_empty()
+    }
+
+    /// Read the index a doc set directory keeps, or `None` if it has none
+    pub fn load(dir: &Path) -> Result<Option<Self>, Box<dyn std::error::Error>> {
+        let path = dir.join(FULL_TEXT_INDEX);
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let value: Value =
+            serde_json::from_reader(BufReader::new(GzDecoder::new(File::open(&path)?)))?;
+        let documents = value
+            .get("documents")
+            .and_then(Value::as_array)
+            .ok_or("the full-text index has no documents")?
+            .iter()
+            .filter_map(|document| {
+                Some((
+                    document.get(0)?.as_str()?.to_owned(),
+                    document.get(1)?.as_str()?.to_owned(),
+                ))
+            })
+            .collect();
+        let terms = value
+            .get("terms")
+            .and_then(Value::as_object)
+            .ok_or("the full-text index has no terms")?
+            .iter()
+            .map(|(term, ids)| {
+                let ids = ids
+                    .as_array()
+                    .map(|ids| {
+                        ids.iter()
+                            .filter_map(|id| Some(id.as_u64()? as u32))
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                (term.clone(), ids)
+            })
+            .collect();
+        Ok(Some(Self { documents, terms }))
+    }
+
+    /// Write the index into a doc set directory, compressed
+    pub fn save(&self, dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let documents: Vec<Value> = self
+            .documents
+            .iter()
+            .map(|(path, text)| json!([path, text]))
+            .collect();
+        let value = json!({ "documents": documents, "terms": self.terms });
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        serde_json::to_writer(&mut encoder, &value)?;
+        write_atomic(&dir.join(FULL_TEXT_INDEX), &encoder.finish()?)?;
+        Ok(())
+    }
+
+    /// Find the entries whose text contains every word of the query, or a word starting
+    /// with it, best match first
+    ///
+    /// Entries containing the query as a phrase score highest, then those mentioning its
+    /// words most often.
+    pub fn search(&self, query: &str, max: usize) -> Vec<FullTextMatch> {
+        let query_words: Vec<String> = words(query).collect();
+        if query_words.is_empty() {
+            return Vec::new();
+        }
+
+        let mut candidates: Option<BTreeSet<u32>> = None;
+        for word in &query_words {
+            let ids: BTreeSet<u32> = self
+                .terms
+                .range(word.clone()..)
+                .take_while(|(term, _)| term.starts_with(word.as_str()))
+                .flat_map(|(_, ids)| ids.iter().copied())
+                .collect();
+            candidates = Some(match candidates {
+                Some(candidates) => candidates.intersection(&ids).copied().collect(),
+                None => ids,
+            });
+        }
+
+        let phrase = query.trim();
+        let mut matches: Vec<FullTextMatch> = candidates
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|id| {
+                let (path, text) = self.documents.get(id as usize)?;
+                let mut score = if find_ignore_case(text, phrase).is_some() {
+                    100
+                } else {
+                    0
+                };
+                for word in &query_words {
+                    score += count_ignore_case(text, word).min(20) as i64;
+                }
+                let at = best_match(text, phrase, &query_words)?;
+                Some(FullTextMatch {
+                    path: path.clone(),
+                    score,
+                    snippet: snippet(text, at),
+                })
+            })
+            .collect();
+        matches.sort_by(|a, b| b.score.cmp(&a.score).then_with(|| a.path.cmp(&b.path)));
+        matches.truncate(max);
+        matches
+    }
+}
+
+/// The lowercased words of some text, splitting at anything but letters and digits
+pub fn words(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+}
+
+/// Where `needle` first appears in `haystack`, ignoring case, as a byte range of `haystack`
+pub fn find_ignore_case(haystack: &str, needle: &str) -> Option<Range<usize>> {
+    let needle: Vec<char> = needle.chars().flat_map(char::to_lowercase).collect();
+    if needle.is_empty() {
+        return None;
+    }
+    haystack.char_indices().find_map(|(start, _)| {
+        let mut wanted = needle.iter();
+        let mut next = wanted.next();
+        for (i, c) in haystack[start..].char_indices() {
+            for c in c.to_lowercase() {
+                match next {
+                    Some(&expected) if expected == c => next = wanted.next(),
+                    _ => return None,
+                }
+            }
+            if next.is_none() {
+                return Some(start..start + i + c.len_utf8());
+            }
+        }
+        None
+    })
+}
+
+fn count_ignore_case(haystack: &str, needle: &str) -> usize {
+    let mut count = 0;
+    let mut rest = haystack;
+    while let Some(range) = find_ignore_case(rest, needle) {
+        count += 1;
+        rest = &rest[range.end..];
+    }
+    count
+}
+
+/// Where in `text` the query matches best: in the first line with the whole phrase, or
+/// else the first of the lines with the most of its words
+fn best_match(text: &str, phrase: &str, words: &[String]) -> Option<Range<usize>> {
+    let mut best: Option<(usize, Range<usize>)> = None;
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        let find = |needle: &str| {
+            find_ignore_case(line, needle).map(|found| offset + found.start..offset + found.end)
+        };
+        if let Some(found) = find(phrase) {
+            return Some(found);
+        }
+        let found: Vec<Range<usize>> = words.iter().filter_map(|word| find(word)).collect();
+        if let Some(first) = found.iter().min_by_key(|found| found.start) {
+            if best.as_ref().is_none_or(|(most, _)| found.len() > *most) {
+                best = Some((found.len(), first.clone()));
+            }
+        }
+        offset += line.len();
+    }
+    best.map(|(_, found)| found)
+}
+
+/// Whether a sentence ends at this byte of `text`, a period, question or exclamation mark
+/// followed by whitespace or the end of the text
+fn ends_sentence(text: &str, i: usize) -> bool {
+    text[i..].starts_with(['.', '!', '?'])
+        && text[i + 1..].chars().next().is_none_or(char::is_whitespace)
+}
+
+/// The sentence of `text` around a match, cut to [`SNIPPET_CHARS`] around the match if it is
+/// longer, with an ellipsis where it was cut
+///
+/// Sentences don't reach across lines, so a heading or list item stands on its own.
+fn snippet(text: &str, at: Range<usize>) -> String {
+    let line_start = text[..at.start].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = text[at.end..].find('\n').map_or(text.len(), |i| at.end + i);
+    let start = text[line_start..at.start]
+        .char_indices()
+        .rev()
+        .find(|&(i, _)| ends_sentence(text, line_start + i))
+        .map_or(line_start, |(i, _)| line_start + i + 1);
+    let end = text[at.end..line_end]
+        .char_indices()
+        .find(|&(i, _)| ends_sentence(text, at.end + i))
+        .map_or(line_end, |(i, _)| at.end + i + 1);
+
+    // Count in characters rather than bytes, so the cuts never split one
+    let sentence: Vec<char> = text[start..end].chars().collect();
+    if sentence.len() <= SNIPPET_CHARS {
+        return sentence.into_iter().collect::<String>().trim().to_owned();
+    }
+    let before = text[start..at.start].chars().count();
+    let matched = text[at.clone()].chars().count();
+    let from = before
+        .saturating_sub(SNIPPET_CHARS.saturating_sub(matched) / 2)
+        .min(sentence.len() - SNIPPET_CHARS);
+    let to = from + SNIPPET_CHARS;
+    let mut snippet = String::new();
+    if from > 0 {
+        snippet.push('…');
+    }
+    snippet.extend(&sentence[from..to]);
+    if to < sentence.len() {
+        snippet.push('…');
+    }
+    snippet.trim().to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A full-text index of pages given as HTML
+    fn index(pages: &[(&str, &str)]) -> FullTextIndex {
+        let pages: serde_json::Map<String, Value> = pages
+            .iter()
+            .map(|&(path, html)| (path.to_owned(), html.into()))
+            .collect();
+        FullTextIndex::build(&DocDb::from_json(Value::Object(pages)).unwrap())
+    }
+
+    fn snippets(index: &FullTextIndex, query: &str) -> Vec<String> {
+        index
+            .search(query, 10)
+            .into_iter()
+            .map(|found| found.snippet)
+            .collect()
+    }
+
+    #[test]
+    fn long_sentences_are_cut_around_the_match() {
+        let sentence = format!("{}needle {}end.", "lorem ".repeat(40), "ipsum ".repeat(40));
+        let long = index(&[("page", &format!("<p>First. {sentence} Last.</p>"))]);
+        let [snippet] = &snippets(&long, "needle")[..] else {
+            panic!("one match");
+        };
+        assert!(
+            snippet.starts_with('…') && snippet.ends_with('…'),
+            "{snippet}"
+        );
+        assert!(snippet.chars().count() <= SNIPPET_CHARS + 2);
+        assert!(snippet.contains("needle"));
+        assert!(!snippet.contains("First") && !snippet.contains("Last"));
+
+        // A short sentence is shown whole
+        let short = index(&[("page", "<p>First. The needle is here. Last.</p>")]);
+        assert_eq!(snippets(&short, "needle"), ["The needle is here."]);
+    }
+
+    #[test]
+    fn snippets_of_multibyte_text_are_cut_between_characters() {
+        let index = index(&[
+            ("de", "<p>Vorher. Die Straße ist lang. Nachher.</p>"),
+            (
+                "ja",
+                &format!("<p>{}天気{}</p>", "東京".repeat(100), "晴れ".repeat(100)),
+            ),
+        ]);
+        assert_eq!(snippets(&index, "STRAßE"), ["Die Straße ist lang."]);
+
+        let [snippet] = &snippets(&index, "東京")[..] else {
+            panic!("one match");
+        };
+        assert!(snippet.chars().count() <= SNIPPET_CHARS + 1);
+        assert!(
+            snippet.starts_with("東京") && snippet.ends_with('…'),
+            "{snippet}"
+        );
+        assert_eq!(find_ignore_case("東京天気", "天気"), Some(6..12));
+    }
+
+    #[test]
+    fn the_phrase_outranks_its_words_apart() {
+        let index = index(&[
+            ("apart", "<p>A map of hash values: hash, hash and hash.</p>"),
+            ("phrase", "<p>Every hash map stores values.</p>"),
+        ]);
+        let found = index.search("hash map", 10);
+        let paths: Vec<&str> = found.iter().map(|found| found.path.as_str()).collect();
+        assert_eq!(paths, ["phrase", "apart"]);
+        assert!(found[0].score > found[1].score);
+        assert_eq!(found[0].snippet, "Every hash map stores values.");
+        assert!(index.search("hash missing", 10).is_empty());
+    }
+
+    #[test]
+    fn a_match_without_text_around_it_shows_the_entry_name() {
+        let found = FullTextMatch {
+            path: "page".to_owned(),
+            score: 1,
+            snippet: String::new(),
+        };
+        assert_eq!(found.clone().snippet_or("HashMap"), "HashMap");
+        let found = FullTextMatch {
+            snippet: "The needle is here.".to_owned(),
+            ..found
+        };
+        assert_eq!(found.snippet_or("HashMap"), "The needle is here.");
+    }
+}
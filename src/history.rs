@@ -0,0 +1,258 @@
+use crate::storage::{read_versioned, write_versioned, Migration};
+use serde_json::{json, Value};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Upgrades of the recently viewed list and the search history; format 2 only stamped them
+const MIGRATIONS: &[Migration] = &[Some];
+
+/// How many entries the recently viewed list remembers
+pub const RECENT_CAPACITY: usize = 50;
+
+/// An entry that was opened in the viewer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecentEntry {
+    pub slug: String,
+    pub path: String,
+    pub title: String,
+    /// Seconds since the Unix epoch at which the entry was last opened
+    pub timestamp: u64,
+}
+
+impl RecentEntry {
+    /// A recent entry stamped with the current time
+    pub fn now(slug: &str, path: &str, title: &str) -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs());
+        Self {
+            slug: slug.to_owned(),
+            path: path.to_owned(),
+            title: title.to_owned(),
+            timestamp,
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        json!({
+            "slug": self.slug,
+            "path": self.path,
+            "title": self.title,
+            "timestamp": self.timestamp,
+        })
+    }
+
+    fn from_json(value: &Value) -> Option<Self> {
+        Some(Self {
+            slug: value.get("slug")?.as_str()?.to_owned(),
+            path: value.get("path")?.as_str()?.to_owned(),
+            title: value.get("title")?.as_str()?.to_owned(),
+            timestamp: value.get("timestamp")?.as_u64()?,
+        })
+    }
+}
+
+/// Recently viewed entries, most recent first, without duplicates
+#[derive(Debug, Clone)]
+pub struct RecentList {
+    entries: Vec<RecentEntry>,
+    capacity: usize,
+}
+
+impl Default for RecentList {
+    fn default() -> Self {
+        Self::new(RECENT_CAPACITY)
+    }
+}
+
+impl RecentList {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: Vec::new(),
+            capacity,
+        }
+    }
+
+    /// Load the list from disk, starting empty if it has never been saved
+    pub fn load(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut list = Self::default();
+        let Some(value) = read_versioned(path, MIGRATIONS)? else {
+            return Ok(list);
+        };
+        if let Some(entries) = value.as_array() {
+            list.entries = entries.iter().filter_map(RecentEntry::from_json).collect();
+            list.entries.truncate(list.capacity);
+        }
+        Ok(list)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let value = Value::Array(self.entries.iter().map(RecentEntry::to_json).collect());
+        write_versioned(path, value)
+    }
+
+    /// Move an entry to the front of the list, dropping the oldest entries past capacity
+    pub fn record(&mut self, entry: RecentEntry) {
+        self.entries
+            .retain(|e| !(e.slug == entry.slug && e.path == entry.path));
+        self.entries.insert(0, entry);
+        self.entries.truncate(self.capacity);
+    }
+
+    pub fn entries(&self) -> &[RecentEntry] {
+        &self.entries
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+/// How many queries the search history remembers
+pub const SEARCH_HISTORY_CAPACITY: usize = 50;
+
+/// Queries that were searched for, most recent first, without duplicates
+#[derive(Debug, Clone, Default)]
+pub struct SearchHistory {
+    queries: Vec<String>,
+}
+
+impl SearchHistory {
+    /// Load the history from disk, starting empty if it has never been saved
+    pub fn load(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut history = Self::default();
+        let Some(value) = read_versioned(path, MIGRATIONS)? else {
+            return Ok(history);
+        };
+        if let Some(queries) = value.as_array() {
+            history.queries = queries
+                .iter()
+                .filter_map(|query| Some(query.as_str()?.to_owned()))
+                .collect();
+            history.queries.truncate(SEARCH_HISTORY_CAPACITY);
+        }
+        Ok(history)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        write_versioned(path, json!(self.queries))
+    }
+
+    /// Move a query to the front of the history, ignoring blank ones
+    pub fn record(&mut self, query: &str) {
+        let query = query.trim();
+        if query.is_empty() {
+            return;
+        }
+        self.queries.retain(|q| q != query);
+        self.queries.insert(0, query.to_owned());
+        self.queries.truncate(SEARCH_HISTORY_CAPACITY);
+    }
+
+    pub fn queries(&self) -> &[String] {
+        &self.queries
+    }
+}
+
+/// The scope last switched to with the scope keys, kept with the history rather than in the
+/// config so the `default_scope` written there stays as it was
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LastScope {
+    /// Doc sets searched, empty meaning all of them
+    pub scope: Vec<String>,
+    /// The config's `default_scope` when the scope was switched, so that changing it in the
+    /// config afterwards takes over again
+    pub configured: Vec<String>,
+}
+
+impl LastScope {
+    /// Load the scope from disk, or `None` if it was never switched
+    pub fn load(path: &Path) -> Result<Option<Self>, Box<dyn std::error::Error>> {
+        let Some(value) = read_versioned(path, MIGRATIONS)? else {
+            return Ok(None);
+        };
+        let slugs = |key: &str| -> Vec<String> {
+            value
+                .get(key)
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .filter_map(|slug| Some(slug.as_str()?.to_owned()))
+                .collect()
+        };
+        Ok(Some(Self {
+            scope: slugs("scope"),
+            configured: slugs("configured"),
+        }))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        write_versioned(
+            path,
+            json!({ "scope": self.scope, "configured": self.configured }),
+        )
+    }
+
+    /// The scope to search given the config's `default_scope`, or `None` if the config
+    /// changed since the scope was switched
+    pub fn scope_for(&self, configured: &[String]) -> Option<&[String]> {
+        (self.configured == configured).then_some(self.scope.as_slice())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(path: &str, timestamp: u64) -> RecentEntry {
+        RecentEntry {
+            slug: "rust".to_owned(),
+            path: path.to_owned(),
+            title: path.to_owned(),
+            timestamp,
+        }
+    }
+
+    fn paths(list: &RecentList) -> Vec<&str> {
+        list.entries()
+            .iter()
+            .map(|entry| entry.path.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn reopening_an_entry_moves_it_to_the_front() {
+        let mut list = RecentList::new(5);
+        for (i, path) in ["a", "b", "c"].into_iter().enumerate() {
+            list.record(entry(path, i as u64));
+        }
+        assert_eq!(paths(&list), ["c", "b", "a"]);
+
+        list.record(entry("a", 3));
+        assert_eq!(paths(&list), ["a", "c", "b"]);
+        assert_eq!(list.entries()[0].timestamp, 3);
+
+        // The same path in another doc set is another entry
+        list.record(RecentEntry {
+            slug: "python".to_owned(),
+            ..entry("b", 4)
+        });
+        assert_eq!(paths(&list), ["b", "a", "c", "b"]);
+    }
+
+    #[test]
+    fn the_oldest_entries_are_dropped_past_the_capacity() {
+        let mut list = RecentList::new(3);
+        for (i, path) in ["a", "b", "c", "d"].into_iter().enumerate() {
+            list.record(entry(path, i as u64));
+        }
+        assert_eq!(paths(&list), ["d", "c", "b"]);
+
+        list.record(entry("b", 4));
+        assert_eq!(paths(&list), ["b", "d", "c"]);
+    }
+}
@@ -0,0 +1,1013 @@
+use crate::theme::CalloutTheme;
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::{Line, Span, Text},
+};
+use std::collections::HashMap;
+use unicode_width::UnicodeWidthChar;
+
+/// An entry's HTML rendered for the terminal
+#[derive(Debug, Clone, Default)]
+pub struct Document {
+    pub text: Text<'static>,
+    /// Line of `text` at which each element with an `id` starts, by id
+    pub anchors: HashMap<String, usize>,
+    pub stats: TextStats,
+    /// The document's `<pre>` blocks, in order
+    pub code_blocks: Vec<CodeBlock>,
+    /// The document's headings, in order
+    pub headings: Vec<Heading>,
+    /// The document's `<img>` tags, in order, each rendered as a placeholder
+    pub images: Vec<Image>,
+    /// The entry is marked deprecated or obsolete before its first section, so the marking
+    /// is about the entry itself rather than something it mentions
+    pub deprecated: bool,
+    /// The text of the first `<h1>`, or else of the `<title>`, if the entry has either
+    pub title: Option<String>,
+    /// The text of the first `<p>` that has any, leaving out code, or `None` for entries
+    /// without prose
+    pub first_paragraph: Option<String>,
+}
+
+/// A heading of a document, from `<h1>` to `<h6>`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Heading {
+    /// 1 for `<h1>` through 6 for `<h6>`
+    pub level: u8,
+    pub title: String,
+    /// Line of the document's text the heading is on
+    pub line: usize,
+}
+
+/// An image of a document, which the terminal shows as a placeholder with its alt text
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Image {
+    /// Line of the document's text the placeholder is on
+    pub line: usize,
+    /// The image's `src` as written, empty if it has none
+    pub src: String,
+    /// Its `alt` text, if it has any
+    pub alt: Option<String>,
+}
+
+/// A code block of a document
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CodeBlock {
+    /// First line of the document's text the block covers
+    pub start_line: usize,
+    /// Line after the block's last line
+    pub end_line: usize,
+    /// Language the block is marked up as, e.g. `rust`, if any
+    pub language: Option<String>,
+    /// The code as written, without tab expansion
+    pub code: String,
+}
+
+/// How much there is to read in a document
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TextStats {
+    /// Words of prose, not counting code blocks
+    pub words: usize,
+    /// Non-blank lines of code blocks
+    pub code_lines: usize,
+}
+
+impl TextStats {
+    /// Typical reading speed for technical prose
+    pub const WORDS_PER_MINUTE: usize = 200;
+
+    /// Estimated minutes it takes to read the prose, at least one
+    pub fn reading_minutes(&self) -> usize {
+        self.words.div_ceil(Self::WORDS_PER_MINUTE).max(1)
+    }
+}
+
+impl Document {
+    /// The rendered text without any styling
+    pub fn plain_text(&self) -> String {
+        self.plain_lines(0..self.text.lines.len())
+    }
+
+    /// Some of the rendered lines without any styling, each ending in a newline
+    pub fn plain_lines(&self, lines: std::ops::Range<usize>) -> String {
+        let mut plain = String::new();
+        for line in &self.text.lines[lines] {
+            for span in &line.spans {
+                plain.push_str(&span.content);
+            }
+            plain.push('\n');
+        }
+        plain
+    }
+
+    /// The lines of the section a heading starts, up to the next heading of the same or a
+    /// higher level
+    pub fn section(&self, heading: &Heading) -> std::ops::Range<usize> {
+        let end = self
+            .headings
+            .iter()
+            .find(|other| other.line > heading.line && other.level <= heading.level)
+            .map_or(self.text.lines.len(), |other| other.line);
+        heading.line..end
+    }
+
+    /// The first code block that ends after `line`, i.e. the one at or below it
+    pub fn code_block_at(&self, line: usize) -> Option<&CodeBlock> {
+        self.code_blocks.iter().find(|block| block.end_line > line)
+    }
+
+    /// The first image on `line` or below it
+    pub fn image_at(&self, line: usize) -> Option<&Image> {
+        self.images.iter().find(|image| image.line >= line)
+    }
+
+    /// Every code block of the document in order, as Markdown fenced code blocks marked
+    /// with their language, separated by blank lines
+    pub fn fenced_code(&self) -> String {
+        self.code_blocks
+            .iter()
+            .map(|block| {
+                // A fence has to be longer than any run of backticks in the code
+                let longest = block
+                    .code
+                    .split(|c| c != '`')
+                    .map(str::len)
+                    .max()
+                    .unwrap_or_default();
+                let fence = "`".repeat(longest.max(2) + 1);
+                let language = block.language.as_deref().unwrap_or_default();
+                format!(
+                    "{fence}{language}\n{}\n{fence}\n",
+                    block.code.trim_end_matches('\n')
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// The rendered text with its styling as ANSI escape sequences, e.g. for `less -R`
+    pub fn ansi_text(&self) -> String {
+        let mut ansi = String::new();
+        for line in &self.text.lines {
+            for span in &line.spans {
+                let codes = sgr_codes(span.style);
+                if codes.is_empty() {
+                    ansi.push_str(&span.content);
+                } else {
+                    ansi.push_str(&format!("\x1b[{}m{}\x1b[0m", codes.join(";"), span.content));
+                }
+            }
+            ansi.push('\n');
+        }
+        ansi
+    }
+}
+
+/// Select Graphic Rendition parameters that apply a style
+fn sgr_codes(style: Style) -> Vec<String> {
+    let mut codes = Vec::new();
+    for (modifier, code) in [
+        (Modifier::BOLD, "1"),
+        (Modifier::DIM, "2"),
+        (Modifier::ITALIC, "3"),
+        (Modifier::UNDERLINED, "4"),
+        (Modifier::REVERSED, "7"),
+        (Modifier::CROSSED_OUT, "9"),
+    ] {
+        if style.add_modifier.contains(modifier) {
+            codes.push(code.to_owned());
+        }
+    }
+    if let Some(fg) = style.fg.and_then(|color| sgr_color(color, false)) {
+        codes.push(fg);
+    }
+    if let Some(bg) = style.bg.and_then(|color| sgr_color(color, true)) {
+        codes.push(bg);
+    }
+    codes
+}
+
+fn sgr_color(color: Color, background: bool) -> Option<String> {
+    let offset = if background { 10 } else { 0 };
+    let basic = |code: u8| Some((code + offset).to_string());
+    match color {
+        Color::Reset => None,
+        Color::Black => basic(30),
+        Color::Red => basic(31),
+        Color::Green => basic(32),
+        Color::Yellow => basic(33),
+        Color::Blue => basic(34),
+        Color::Magenta => basic(35),
+        Color::Cyan => basic(36),
+        Color::Gray => basic(37),
+        Color::DarkGray => basic(90),
+        Color::LightRed => basic(91),
+        Color::LightGreen => basic(92),
+        Color::LightYellow => basic(93),
+        Color::LightBlue => basic(94),
+        Color::LightMagenta => basic(95),
+        Color::LightCyan => basic(96),
+        Color::White => basic(97),
+        Color::Rgb(r, g, b) => Some(format!("{};2;{r};{g};{b}", 38 + offset)),
+        Color::Indexed(i) => Some(format!("{};5;{i}", 38 + offset)),
+    }
+}
+
+/// Settings that affect how entries are rendered
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenderOptions {
+    /// Columns between tab stops in code blocks
+    pub tab_width: usize,
+    pub callouts: CalloutTheme,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self {
+            tab_width: 4,
+            callouts: CalloutTheme::default(),
+        }
+    }
+}
+
+/// The kinds of callout boxes devdocs sets apart from the surrounding text
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CalloutKind {
+    Note,
+    Tip,
+    Warning,
+    Deprecated,
+}
+
+impl CalloutKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            CalloutKind::Note => "ℹ Note",
+            CalloutKind::Tip => "✓ Tip",
+            CalloutKind::Warning => "⚠ Warning",
+            CalloutKind::Deprecated => "✖ Deprecated",
+        }
+    }
+
+    /// The kind of callout an element's `class` attribute marks it as, if any
+    ///
+    /// Doc sets name these differently, e.g. `_note`, `notecard warning` or
+    /// `admonition caution`. The most severe kind named wins, and a generic callout class such
+    /// as `admonition` without a recognized kind counts as a note.
+    pub fn from_classes(classes: &str) -> Option<Self> {
+        let mut kind = None;
+        for class in classes.split_whitespace() {
+            let class = class.trim_start_matches('_').to_ascii_lowercase();
+            let named = match class.as_str() {
+                "deprecated" | "obsolete" => CalloutKind::Deprecated,
+                "warning" | "caution" | "danger" | "important" | "attention" => {
+                    CalloutKind::Warning
+                }
+                "tip" | "hint" => CalloutKind::Tip,
+                "note" | "notice" | "notecard" | "admonition" | "callout" | "info" => {
+                    CalloutKind::Note
+                }
+                _ => continue,
+            };
+            kind = kind.max(Some(named));
+        }
+        kind
+    }
+
+    fn color(self, theme: &CalloutTheme) -> Color {
+        match self {
+            CalloutKind::Note => theme.note,
+            CalloutKind::Tip => theme.tip,
+            CalloutKind::Warning => theme.warning,
+            CalloutKind::Deprecated => theme.deprecated,
+        }
+    }
+}
+
+/// Render a devdocs HTML fragment as styled terminal text
+///
+/// This is not a general purpose HTML parser; it understands the small set of tags devdocs
+/// emits for its entries and renders everything else as plain text.
+pub fn html_to_text(html: &str, options: &RenderOptions) -> Document {
+    let mut renderer = Renderer {
+        options: options.clone(),
+        ..Renderer::default()
+    };
+    let mut rest = html;
+
+    while let Some(start) = rest.find('<') {
+        renderer.text(&rest[..start]);
+        rest = &rest[start..];
+
+        if rest.starts_with("<!--") {
+            rest = rest.find("-->").map_or("", |end| &rest[end + 3..]);
+            continue;
+        }
+        let Some(end) = rest.find('>') else {
+            break;
+        };
+        let tag = &rest[1..end];
+        rest = &rest[end + 1..];
+
+        let closing = tag.starts_with('/');
+        let name = tag
+            .trim_start_matches('/')
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+
+        // The contents of these are never meant to be displayed
+        if !closing && name == "title" {
+            let end = rest.find("</title").unwrap_or(rest.len());
+            renderer
+                .page_title
+                .get_or_insert_with(|| collapse_whitespace(&decode_entities(&rest[..end])));
+            rest = &rest[end..];
+            continue;
+        }
+        if !closing && (name == "script" || name == "style") {
+            let close = format!("</{name}");
+            rest = rest.find(&close).map_or("", |i| &rest[i..]);
+            continue;
+        }
+
+        if closing {
+            renderer.close(&name);
+        } else {
+            renderer.open(&name);
+            if name == "h2" {
+                renderer.past_intro = true;
+            }
+            let classes = attribute(tag, "class");
+            if !renderer.past_intro
+                && classes.as_deref().and_then(CalloutKind::from_classes)
+                    == Some(CalloutKind::Deprecated)
+            {
+                renderer.deprecated = true;
+            }
+            if renderer.options.callouts.enabled
+                && matches!(
+                    name.as_str(),
+                    "div" | "p" | "aside" | "section" | "blockquote"
+                )
+            {
+                if let Some(kind) = classes.as_deref().and_then(CalloutKind::from_classes) {
+                    renderer.open_callout(&name, kind);
+                }
+            }
+            if name == "img" {
+                renderer.image(
+                    attribute(tag, "src").unwrap_or_default(),
+                    attribute(tag, "alt"),
+                );
+            }
+            if name == "pre" {
+                if let Some(block) = renderer.code.as_mut().filter(|b| b.language.is_none()) {
+                    block.language = code_language(tag);
+                }
+            }
+            let id = attribute(tag, "id").or_else(|| match name.as_str() {
+                "a" => attribute(tag, "name"),
+                _ => None,
+            });
+            if let Some(id) = id {
+                renderer.anchors.entry(id).or_insert(renderer.lines.len());
+            }
+        }
+    }
+    renderer.text(rest);
+
+    renderer.finish()
+}
+
+#[derive(Default)]
+struct Renderer {
+    options: RenderOptions,
+    lines: Vec<Line<'static>>,
+    spans: Vec<Span<'static>>,
+    /// Open tags that changed the style, innermost last
+    styles: Vec<(String, Style)>,
+    /// Depth of nested `<pre>` blocks, inside which whitespace is preserved
+    pre_depth: usize,
+    list_depth: usize,
+    /// A collapsed run of whitespace is waiting to be written before the next word
+    pending_space: bool,
+    anchors: HashMap<String, usize>,
+    stats: TextStats,
+    code_blocks: Vec<CodeBlock>,
+    /// The outermost `<pre>` block being rendered
+    code: Option<CodeBlock>,
+    /// Callouts being rendered, innermost last
+    callouts: Vec<OpenCallout>,
+    headings: Vec<Heading>,
+    images: Vec<Image>,
+    /// The heading being rendered
+    heading: Option<Heading>,
+    /// The first `<h2>`, which ends the entry's introduction, has been seen
+    past_intro: bool,
+    deprecated: bool,
+    /// Text of the `<title>`, if there is one
+    page_title: Option<String>,
+    first_paragraph: Option<String>,
+    /// Text of the `<p>` being rendered, until one with text is found
+    paragraph_text: Option<String>,
+}
+
+/// Border drawn in front of every line of a callout
+const CALLOUT_BORDER: &str = "│ ";
+
+struct OpenCallout {
+    /// Tag of the element the callout is, which ends it when closed
+    tag: String,
+    /// Elements with the same tag opened inside the callout and not closed yet
+    nested: usize,
+    color: Color,
+    /// Line of the callout's label, which its first paragraph follows without a gap
+    label_line: usize,
+}
+
+impl Renderer {
+    fn style(&self) -> Style {
+        self.styles
+            .iter()
+            .fold(Style::default(), |style, (_, s)| style.patch(*s))
+    }
+
+    fn push(&mut self, content: String) {
+        if !content.is_empty() {
+            if self.spans.is_empty() {
+                self.push_borders();
+            }
+            let style = self.style();
+            self.spans.push(Span::styled(content, style));
+        }
+    }
+
+    /// Write an image as `[image: <alt text>]`, falling back to its file name for images
+    /// without alt text and to `[image]` for those without either
+    fn image(&mut self, src: String, alt: Option<String>) {
+        let alt = alt
+            .map(|alt| collapse_whitespace(&alt))
+            .filter(|alt| !alt.is_empty());
+        let file_name = (!src.starts_with("data:"))
+            .then(|| {
+                let path = src.split(['?', '#']).next().unwrap_or_default();
+                path.rsplit('/').next().unwrap_or_default()
+            })
+            .filter(|name| !name.is_empty());
+        let placeholder = match alt.as_deref().or(file_name) {
+            Some(label) => format!("[image: {label}]"),
+            None => "[image]".to_owned(),
+        };
+        if self.pending_space && !self.spans.is_empty() {
+            self.push(" ".to_owned());
+        }
+        self.pending_space = false;
+        self.open_style(
+            "img",
+            Style::default()
+                .fg(Color::Magenta)
+                .add_modifier(Modifier::UNDERLINED),
+        );
+        self.push(placeholder);
+        self.close_style("img");
+        self.images.push(Image {
+            line: self.lines.len(),
+            src,
+            alt,
+        });
+    }
+
+    fn text(&mut self, raw: &str) {
+        if raw.is_empty() {
+            return;
+        }
+        let decoded = decode_entities(raw);
+
+        if self.pre_depth > 0 {
+            if let Some(block) = self.code.as_mut() {
+                block.code.push_str(&decoded);
+            }
+            let mut segments = decoded.split('\n');
+            if let Some(first) = segments.next() {
+                let expanded = self.expand_tabs(first);
+                self.push(expanded);
+            }
+            for segment in segments {
+                self.break_line();
+                let expanded = self.expand_tabs(segment);
+                self.push(expanded);
+            }
+            return;
+        }
+
+        if let Some(heading) = self.heading.as_mut() {
+            heading.title.push_str(&decoded);
+        }
+        if let Some(paragraph) = self.paragraph_text.as_mut() {
+            paragraph.push_str(&decoded);
+        }
+
+        // Text split by inline tags, like `Hash<b>Map</b>`, continues the word before it
+        let continues_word = !decoded.starts_with(char::is_whitespace)
+            && !self.pending_space
+            && self
+                .spans
+                .last()
+                .is_some_and(|span| !span.content.ends_with(char::is_whitespace));
+        self.stats.words += decoded.split_whitespace().count() - usize::from(continues_word);
+
+        let mut content = String::new();
+        if decoded.starts_with(char::is_whitespace) {
+            self.pending_space = true;
+        }
+        for word in decoded.split_whitespace() {
+            if self.pending_space && !(self.spans.is_empty() && content.is_empty()) {
+                content.push(' ');
+            }
+            content.push_str(word);
+            self.pending_space = true;
+        }
+        self.pending_space = decoded.ends_with(char::is_whitespace);
+        self.push(content);
+    }
+
+    /// Replace tabs with spaces up to the next tab stop, counting from the start of the line
+    fn expand_tabs(&self, segment: &str) -> String {
+        if !segment.contains('\t') {
+            return segment.to_owned();
+        }
+        let tab_width = self.options.tab_width.max(1);
+        let mut column: usize = self.spans.iter().map(Span::width).sum();
+        let mut expanded = String::with_capacity(segment.len());
+        for c in segment.chars() {
+            if c == '\t' {
+                let spaces = tab_width - column % tab_width;
+                expanded.extend(std::iter::repeat_n(' ', spaces));
+                column += spaces;
+            } else {
+                expanded.push(c);
+                column += c.width().unwrap_or_default();
+            }
+        }
+        expanded
+    }
+
+    /// Start a line inside callouts with their borders
+    fn push_borders(&mut self) {
+        for callout in &self.callouts {
+            self.spans.push(Span::styled(
+                CALLOUT_BORDER,
+                Style::default().fg(callout.color),
+            ));
+        }
+    }
+
+    /// Whether a line has nothing on it but callout borders
+    fn is_blank(line: &Line) -> bool {
+        line.spans.iter().all(|span| span.content == CALLOUT_BORDER)
+    }
+
+    fn open_callout(&mut self, tag: &str, kind: CalloutKind) {
+        let color = kind.color(&self.options.callouts);
+        self.paragraph();
+        self.callouts.push(OpenCallout {
+            tag: tag.to_owned(),
+            nested: 0,
+            color,
+            label_line: self.lines.len(),
+        });
+        self.push_borders();
+        self.spans.push(Span::styled(
+            kind.label(),
+            Style::default().fg(color).add_modifier(Modifier::BOLD),
+        ));
+        self.break_line();
+    }
+
+    /// End the innermost callout if `tag` closes it, dropping the blank lines it ends with
+    ///
+    /// Returns whether it ended.
+    fn close_callout(&mut self, tag: &str) -> bool {
+        let Some(callout) = self
+            .callouts
+            .last_mut()
+            .filter(|callout| callout.tag == tag)
+        else {
+            return false;
+        };
+        if callout.nested > 0 {
+            callout.nested -= 1;
+            return false;
+        }
+        self.line_start();
+        while self
+            .lines
+            .last()
+            .is_some_and(|line| line.width() > 0 && Self::is_blank(line))
+        {
+            self.lines.pop();
+        }
+        self.callouts.pop();
+        true
+    }
+
+    fn break_line(&mut self) {
+        let spans = std::mem::take(&mut self.spans);
+        self.lines.push(Line::from(spans));
+        self.pending_space = false;
+    }
+
+    /// Make sure the next text starts on a fresh line
+    fn line_start(&mut self) {
+        if !self.spans.is_empty() {
+            self.break_line();
+        }
+        self.pending_space = false;
+    }
+
+    /// Make sure the next text is separated from the previous block by an empty line
+    fn paragraph(&mut self) {
+        self.line_start();
+        let after_label = self
+            .callouts
+            .last()
+            .is_some_and(|callout| callout.label_line + 1 == self.lines.len());
+        if !after_label && self.lines.last().is_some_and(|line| !Self::is_blank(line)) {
+            self.push_borders();
+            self.break_line();
+        }
+    }
+
+    fn open_style(&mut self, name: &str, style: Style) {
+        self.styles.push((name.to_owned(), style));
+    }
+
+    fn close_style(&mut self, name: &str) {
+        if let Some(i) = self.styles.iter().rposition(|(open, _)| open == name) {
+            self.styles.truncate(i);
+        }
+    }
+
+    fn open(&mut self, name: &str) {
+        if let Some(callout) = self
+            .callouts
+            .last_mut()
+            .filter(|callout| callout.tag == name)
+        {
+            callout.nested += 1;
+        }
+        if name == "p" && self.first_paragraph.is_none() {
+            self.paragraph_text = Some(String::new());
+        }
+        match name {
+            "p" | "table" | "blockquote" | "dl" | "figure" => self.paragraph(),
+            "div" | "section" | "tr" | "dt" | "details" | "summary" => self.line_start(),
+            "ul" | "ol" => {
+                if self.list_depth == 0 {
+                    self.paragraph();
+                }
+                self.list_depth += 1;
+            }
+            "li" => {
+                self.line_start();
+                let indent = "  ".repeat(self.list_depth.saturating_sub(1));
+                self.push(format!("{indent}• "));
+            }
+            "dd" => {
+                self.line_start();
+                self.push("    ".to_owned());
+            }
+            "td" | "th" if !self.spans.is_empty() => self.push("  ".to_owned()),
+            "br" => self.break_line(),
+            "hr" => {
+                self.paragraph();
+                self.push("─".repeat(40));
+                self.paragraph();
+            }
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                self.paragraph();
+                self.heading = Some(Heading {
+                    level: name[1..].parse().unwrap_or(1),
+                    title: String::new(),
+                    line: self.lines.len(),
+                });
+                let style = Style::default().add_modifier(Modifier::BOLD);
+                let style = if name == "h1" || name == "h2" {
+                    style.add_modifier(Modifier::UNDERLINED)
+                } else {
+                    style
+                };
+                self.open_style(name, style);
+            }
+            "pre" => {
+                self.paragraph();
+                if self.pre_depth == 0 {
+                    self.code = Some(CodeBlock {
+                        start_line: self.lines.len(),
+                        ..CodeBlock::default()
+                    });
+                }
+                self.pre_depth += 1;
+                self.open_style(name, Style::default().fg(Color::Cyan));
+            }
+            "code" => self.open_style(name, Style::default().fg(Color::Cyan)),
+            "strong" | "b" => self.open_style(name, Style::default().add_modifier(Modifier::BOLD)),
+            "em" | "i" => self.open_style(name, Style::default().add_modifier(Modifier::ITALIC)),
+            "a" => self.open_style(name, Style::default().add_modifier(Modifier::UNDERLINED)),
+            _ => {}
+        }
+    }
+
+    fn close(&mut self, name: &str) {
+        if name == "p" {
+            let text = self
+                .paragraph_text
+                .take()
+                .map(|text| collapse_whitespace(&text));
+            self.first_paragraph = self
+                .first_paragraph
+                .take()
+                .or(text.filter(|text| !text.is_empty()));
+        }
+        match name {
+            "p" | "table" | "blockquote" | "dl" | "figure" => self.paragraph(),
+            "div" | "section" | "tr" | "dt" | "dd" | "li" | "details" | "summary" => {
+                self.line_start()
+            }
+            "ul" | "ol" => {
+                self.list_depth = self.list_depth.saturating_sub(1);
+                if self.list_depth == 0 {
+                    self.paragraph();
+                }
+            }
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                self.close_style(name);
+                if let Some(mut heading) = self.heading.take() {
+                    heading.title = collapse_whitespace(&heading.title);
+                    if !heading.title.is_empty() {
+                        self.headings.push(heading);
+                    }
+                }
+                self.paragraph();
+            }
+            "pre" => {
+                self.close_style(name);
+                self.pre_depth = self.pre_depth.saturating_sub(1);
+                self.line_start();
+                if self.pre_depth == 0 {
+                    if let Some(mut block) = self.code.take() {
+                        block.end_line = self.lines.len();
+                        block.code = block.code.trim_matches('\n').to_owned();
+                        block.code.push('\n');
+                        // Counted once the block is whole, as highlighting splits its lines
+                        // into many chunks of text
+                        self.stats.code_lines += block
+                            .code
+                            .lines()
+                            .filter(|line| !line.trim().is_empty())
+                            .count();
+                        self.code_blocks.push(block);
+                    }
+                }
+                self.paragraph();
+            }
+            "code" | "strong" | "b" | "em" | "i" | "a" => self.close_style(name),
+            _ => {}
+        }
+        if self.close_callout(name) {
+            self.paragraph();
+        }
+    }
+
+    fn finish(mut self) -> Document {
+        self.line_start();
+        while self.lines.last().is_some_and(|line| line.width() == 0) {
+            self.lines.pop();
+        }
+        let title = self
+            .headings
+            .iter()
+            .find(|heading| heading.level == 1)
+            .map(|heading| heading.title.clone())
+            .or(self.page_title)
+            .filter(|title| !title.is_empty());
+        Document {
+            text: Text::from(self.lines),
+            anchors: self.anchors,
+            stats: self.stats,
+            code_blocks: self.code_blocks,
+            headings: self.headings,
+            images: self.images,
+            deprecated: self.deprecated,
+            title,
+            first_paragraph: self.first_paragraph,
+        }
+    }
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// The language a `<pre>` start tag marks its code as, from `data-language` or a
+/// `language-*` class
+fn code_language(tag: &str) -> Option<String> {
+    let language = attribute(tag, "data-language").or_else(|| {
+        attribute(tag, "class")?
+            .split_whitespace()
+            .find_map(|class| class.strip_prefix("language-").map(str::to_owned))
+    })?;
+    let language = language.trim().to_lowercase();
+    (!language.is_empty()).then_some(language)
+}
+
+/// The decoded value of an attribute in the raw contents of a start tag
+fn attribute(tag: &str, name: &str) -> Option<String> {
+    let mut rest = tag.split_once(char::is_whitespace)?.1;
+    loop {
+        rest = rest.trim_start();
+        let key_end = rest.find(|c: char| c == '=' || c == '>' || c.is_whitespace())?;
+        let key = &rest[..key_end];
+        rest = rest[key_end..].trim_start();
+
+        let value = match rest.strip_prefix('=') {
+            Some(value) => {
+                let value = value.trim_start();
+                let (value, remainder) = match value.chars().next() {
+                    Some(quote @ ('"' | '\'')) => value[1..].split_once(quote)?,
+                    _ => value.split_at(value.find(char::is_whitespace).unwrap_or(value.len())),
+                };
+                rest = remainder;
+                value
+            }
+            None => "",
+        };
+        if key.eq_ignore_ascii_case(name) {
+            return Some(decode_entities(value));
+        }
+        if key.is_empty() || rest.is_empty() {
+            return None;
+        }
+    }
+}
+
+/// Replace the HTML character references devdocs commonly uses with the characters they name
+fn decode_entities(raw: &str) -> String {
+    let mut decoded = String::with_capacity(raw.len());
+    let mut rest = raw;
+
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let entity = rest
+            .find(';')
+            .filter(|&end| end <= 10)
+            .and_then(|end| Some((decode_entity(&rest[1..end])?, end)));
+        match entity {
+            Some((c, end)) => {
+                decoded.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+
+    decoded
+}
+
+fn decode_entity(name: &str) -> Option<char> {
+    if let Some(number) = name.strip_prefix('#') {
+        let code = match number.strip_prefix(['x', 'X']) {
+            Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+            None => number.parse().ok()?,
+        };
+        return char::from_u32(code);
+    }
+    let c = match name {
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" => ' ',
+        "hellip" => '…',
+        "mdash" => '—',
+        "ndash" => '–',
+        "lsquo" => '‘',
+        "rsquo" => '’',
+        "ldquo" => '“',
+        "rdquo" => '”',
+        "laquo" => '«',
+        "raquo" => '»',
+        "copy" => '©',
+        "reg" => '®',
+        "trade" => '™',
+        "times" => '×',
+        "larr" => '←',
+        "rarr" => '→',
+        "uarr" => '↑',
+        "darr" => '↓',
+        "le" => '≤',
+        "ge" => '≥',
+        "ne" => '≠',
+        _ => return None,
+    };
+    Some(c)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A code block as rendered with tab stops every `tab_width` columns
+    fn code(html: &str, tab_width: usize) -> String {
+        let options = RenderOptions {
+            tab_width,
+            ..RenderOptions::default()
+        };
+        html_to_text(&format!("<pre>{html}</pre>"), &options).plain_text()
+    }
+
+    #[test]
+    fn tabs_reach_the_next_tab_stop_from_the_start_of_the_line() {
+        assert_eq!(
+            code("\tx\na\tx\nabc\tx\nabcd\tx", 4),
+            "    x\na   x\nabc x\nabcd    x\n"
+        );
+        assert_eq!(code("a\tb\tc", 4), "a   b   c\n");
+        // What comes before the tab counts as well when it is styled apart
+        assert_eq!(code("<b>ab</b>\tc", 4), "ab  c\n");
+    }
+
+    #[test]
+    fn a_tab_width_of_one_makes_every_tab_a_space() {
+        assert_eq!(code("a\tbc\t\td", 1), "a bc  d\n");
+        assert_eq!(code("a\tb", 0), "a b\n");
+    }
+
+    #[test]
+    fn wide_characters_take_two_columns_before_a_tab() {
+        assert_eq!(code("日\tx", 4), "日  x\n");
+        assert_eq!(code("日本\tx", 4), "日本    x\n");
+        assert_eq!(code("a日\tx", 4), "a日 x\n");
+    }
+
+    fn title(html: &str) -> Option<String> {
+        html_to_text(html, &RenderOptions::default()).title
+    }
+
+    #[test]
+    fn the_first_h1_is_the_title() {
+        assert_eq!(
+            title("<title>std::collections - Rust</title><h2>Intro</h2><h1>HashMap</h1><h1>Other</h1>"),
+            Some("HashMap".to_owned())
+        );
+        assert_eq!(
+            title("<h1><code>Vec</code>::push</h1>"),
+            Some("Vec::push".to_owned())
+        );
+    }
+
+    #[test]
+    fn the_page_title_stands_in_without_an_h1() {
+        assert_eq!(
+            title("<head><title>dict - Python</title></head><h2>dict</h2>"),
+            Some("dict - Python".to_owned())
+        );
+        assert_eq!(
+            title("<title>Page</title><h1> </h1>"),
+            Some("Page".to_owned())
+        );
+        assert_eq!(title("<h2>Only a section</h2><p>text</p>"), None);
+        assert_eq!(title("<title></title><p>text</p>"), None);
+    }
+
+    #[test]
+    fn titles_are_decoded_and_their_whitespace_collapsed() {
+        assert_eq!(
+            title("<h1>\n  Option&lt;T&gt;   &amp;\n  Result </h1>"),
+            Some("Option<T> & Result".to_owned())
+        );
+        assert_eq!(
+            title("<title> Lists &#8212;\n\tPython </title>"),
+            Some("Lists \u{2014} Python".to_owned())
+        );
+    }
+
+    #[test]
+    fn highlighted_code_lines_are_counted_once() {
+        let html = "<p>Some words here</p>\
+                    <pre>let <span>x</span> = <span>1</span>;\n\n  <span>foo</span>();\n</pre>\
+                    <pre>bar</pre>";
+        let stats = html_to_text(html, &RenderOptions::default()).stats;
+        assert_eq!(stats.code_lines, 3);
+        assert_eq!(stats.words, 3);
+    }
+}
@@ -0,0 +1,379 @@
+use crate::{docset_manager, path::split_fragment, storage::Storage};
+use log::{trace, warn};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+/// A single searchable entry of a doc set, as listed in its `index.json`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexEntry {
+    /// Slug of the doc set the entry belongs to
+    pub slug: String,
+    pub name: String,
+    /// Path of the entry's content in the doc set's `db.json`, possibly with a `#fragment`
+    pub path: String,
+    pub type_name: String,
+    /// The last part of the name, if it is qualified, matched against queries as well
+    pub short_name: Option<String>,
+}
+
+/// The last part of a qualified name: `map` of `Iterator::map`, or `at()` of
+/// `Array.prototype.at()`
+///
+/// Separators inside parentheses, as in `f(a.b)`, don't count, and names that aren't
+/// qualified have none.
+pub fn short_name(name: &str) -> Option<String> {
+    let head = name.find('(').map_or(name, |i| &name[..i]);
+    let start = ["::", ".", "#", "->"]
+        .iter()
+        .filter_map(|separator| head.rfind(separator).map(|i| i + separator.len()))
+        .max()?;
+    let short = name[start..].trim();
+    (!short.is_empty()).then(|| short.to_owned())
+}
+
+impl IndexEntry {
+    /// Whether the entry's name or type says it is deprecated, e.g. `escape() (deprecated)`
+    /// or a type named `Deprecated features`
+    ///
+    /// Only whole words count, so names like `deprecatedAttribute` don't.
+    pub fn looks_deprecated(&self) -> bool {
+        let marked = |text: &str| {
+            text.split(|c: char| !c.is_alphanumeric()).any(|word| {
+                word.eq_ignore_ascii_case("deprecated") || word.eq_ignore_ascii_case("obsolete")
+            })
+        };
+        marked(&self.name) || marked(&self.type_name)
+    }
+
+    /// The module path the entry sits in, from the segments of its path, e.g.
+    /// `std::collections::struct.HashMap` for `std/collections/struct.HashMap#method.entry`
+    ///
+    /// The page itself only counts when the entry is a part of it, and `None` is returned when
+    /// the name already says as much or there is no module path.
+    pub fn namespace(&self) -> Option<String> {
+        let (page, fragment) = split_fragment(&self.path);
+        let mut segments: Vec<&str> = page
+            .split('/')
+            .filter(|segment| !segment.is_empty() && *segment != "index")
+            .collect();
+        if fragment.is_none() {
+            segments.pop();
+        }
+        let namespace = segments.join("::");
+        if namespace.is_empty() || self.name.contains(&namespace) {
+            return None;
+        }
+        Some(namespace)
+    }
+}
+
+/// A category of a doc set's entries, as listed in the `types` of its `index.json`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DocType {
+    pub name: String,
+    /// Number of entries of this type
+    pub count: usize,
+}
+
+/// The entries of one doc set and the types they are sorted into, as its `index.json` lists
+/// them
+///
+/// devdocs writes `index.json` as `{"entries": [{"name", "path", "type"}, ...], "types":
+/// [{"name", "count", "slug"}, ...]}`. Entries without a name or path are skipped, and an
+/// entry without a type gets an empty one.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DocSetIndex {
+    pub slug: String,
+    pub entries: Vec<IndexEntry>,
+    /// Types in the order `index.json` lists them
+    ///
+    /// Doc sets without a `types` array get their types from the entries themselves, sorted
+    /// by name. Entries without a type aren't counted under any.
+    pub types: Vec<DocType>,
+}
+
+impl DocSetIndex {
+    /// Read the `index.json` of an installed doc set
+    pub fn load(storage: &Storage, slug: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        trace!("Loading index of {slug}");
+        Self::read(&storage.doc_set_dir(slug).join("index.json"), slug)
+    }
+
+    /// Read an `index.json` at any path, attributing its entries to `slug`
+    pub fn read(index_path: &Path, slug: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let reader = BufReader::new(File::open(index_path)?);
+        Self::from_json(slug, &serde_json::from_reader(reader)?)
+    }
+
+    pub fn from_json(slug: &str, index: &Value) -> Result<Self, Box<dyn std::error::Error>> {
+        let entries: Vec<IndexEntry> = index
+            .get("entries")
+            .and_then(Value::as_array)
+            .ok_or("index.json has no entries")?
+            .iter()
+            .filter_map(|entry| {
+                let name = entry.get("name")?.as_str()?;
+                Some(IndexEntry {
+                    slug: slug.to_owned(),
+                    name: name.to_owned(),
+                    path: entry.get("path")?.as_str()?.to_owned(),
+                    type_name: entry
+                        .get("type")
+                        .and_then(Value::as_str)
+                        .unwrap_or_default()
+                        .to_owned(),
+                    short_name: short_name(name),
+                })
+            })
+            .collect();
+
+        let types = match index.get("types").and_then(Value::as_array) {
+            Some(types) => types
+                .iter()
+                .filter_map(|doc_type| {
+                    Some(DocType {
+                        name: doc_type.get("name")?.as_str()?.to_owned(),
+                        count: doc_type
+                            .get("count")
+                            .and_then(Value::as_u64)
+                            .unwrap_or_default() as usize,
+                    })
+                })
+                .collect(),
+            None => {
+                let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+                for entry in entries.iter().filter(|entry| !entry.type_name.is_empty()) {
+                    *counts.entry(&entry.type_name).or_default() += 1;
+                }
+                counts
+                    .into_iter()
+                    .map(|(name, count)| DocType {
+                        name: name.to_owned(),
+                        count,
+                    })
+                    .collect()
+            }
+        };
+
+        Ok(Self {
+            slug: slug.to_owned(),
+            entries,
+            types,
+        })
+    }
+}
+
+/// Entries of every installed doc set, the one list of entries everything else searches and
+/// browses
+#[derive(Debug, Clone, Default)]
+pub struct Index {
+    pub entries: Vec<IndexEntry>,
+    /// Types of each doc set's entries, by slug
+    types: HashMap<String, Vec<DocType>>,
+    /// Abstracts of the pages of each doc set, by slug and then path
+    abstracts: HashMap<String, HashMap<String, String>>,
+}
+
+impl Index {
+    /// Load the indexes of all installed doc sets, skipping any that can't be read
+    pub fn load(storage: &Storage) -> Self {
+        let mut index = Self::default();
+        for slug in docset_manager::installed_doc_sets(storage) {
+            match DocSetIndex::load(storage, &slug) {
+                Ok(doc_set) => index.add(doc_set),
+                Err(e) => {
+                    warn!("Could not load the index of {slug}: {e}");
+                    continue;
+                }
+            }
+            let doc_abstracts = docset_manager::read_abstracts(storage, &slug);
+            if !doc_abstracts.is_empty() {
+                index.abstracts.insert(slug, doc_abstracts);
+            }
+        }
+        index
+    }
+
+    /// Add the entries of a doc set, in place of any it had
+    pub fn add(&mut self, doc_set: DocSetIndex) {
+        self.entries.retain(|entry| entry.slug != doc_set.slug);
+        self.entries.extend(doc_set.entries);
+        self.types.insert(doc_set.slug, doc_set.types);
+    }
+
+    /// The entries of a doc set, in the order its `index.json` lists them
+    pub fn entries_of<'a>(&'a self, slug: &'a str) -> impl Iterator<Item = &'a IndexEntry> {
+        self.entries.iter().filter(move |entry| entry.slug == slug)
+    }
+
+    /// The entry of a doc set at a path, `#fragment` included
+    pub fn find(&self, slug: &str, path: &str) -> Option<&IndexEntry> {
+        self.entries
+            .iter()
+            .find(|entry| entry.slug == slug && entry.path == path)
+    }
+
+    /// The types a doc set sorts its entries into, none if it isn't installed
+    pub fn types_of(&self, slug: &str) -> &[DocType] {
+        self.types.get(slug).map_or(&[], Vec::as_slice)
+    }
+
+    /// The first sentence of an entry, if it is a page of its own and has prose
+    ///
+    /// Entries for a part of a page, like a method, have none, as the page's first sentence
+    /// is not about them.
+    pub fn abstract_of(&self, entry: &IndexEntry) -> Option<&str> {
+        if split_fragment(&entry.path).1.is_some() {
+            return None;
+        }
+        self.abstracts
+            .get(&entry.slug)?
+            .get(&entry.path)
+            .map(String::as_str)
+    }
+}
+
+/// Read the entries of a doc set's `index.json`
+pub fn load_doc_set(
+    storage: &Storage,
+    slug: &str,
+) -> Result<Vec<IndexEntry>, Box<dyn std::error::Error>> {
+    DocSetIndex::load(storage, slug).map(|doc_set| doc_set.entries)
+}
+
+/// Read the entries of an `index.json` at any path, attributing them to `slug`
+pub fn load_index_file(
+    index_path: &Path,
+    slug: &str,
+) -> Result<Vec<IndexEntry>, Box<dyn std::error::Error>> {
+    DocSetIndex::read(index_path, slug).map(|doc_set| doc_set.entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// Trimmed from the `index.json` devdocs serves for `javascript`
+    fn fixture() -> Value {
+        json!({
+            "entries": [
+                {"name": "Array", "path": "global_objects/array", "type": "Array"},
+                {
+                    "name": "Array.prototype.at()",
+                    "path": "global_objects/array/at",
+                    "type": "Array"
+                },
+                {
+                    "name": "async function",
+                    "path": "statements/async_function",
+                    "type": "Statements"
+                },
+                {
+                    "name": "Lexical grammar: Keywords",
+                    "path": "lexical_grammar#keywords",
+                    "type": "Miscellaneous"
+                },
+                {"name": "No path", "type": "Array"},
+            ],
+            "types": [
+                {"name": "Array", "count": 2, "slug": "array"},
+                {"name": "Miscellaneous", "count": 1, "slug": "miscellaneous"},
+                {"name": "Statements", "count": 1, "slug": "statements"},
+            ]
+        })
+    }
+
+    #[test]
+    fn qualified_names_have_short_names() {
+        assert_eq!(short_name("Iterator::map").as_deref(), Some("map"));
+        assert_eq!(short_name("Array.prototype.at()").as_deref(), Some("at()"));
+        assert_eq!(short_name("Element#click").as_deref(), Some("click"));
+        assert_eq!(short_name("Closure->bind").as_deref(), Some("bind"));
+        assert_eq!(short_name("std::f64::consts::PI").as_deref(), Some("PI"));
+        assert_eq!(short_name("parse(a.b)"), None);
+        assert_eq!(short_name("Lexical grammar: Keywords"), None);
+        assert_eq!(short_name("etc."), None);
+    }
+
+    #[test]
+    fn a_devdocs_index_is_read() {
+        let doc_set = DocSetIndex::from_json("javascript", &fixture()).unwrap();
+        assert_eq!(doc_set.slug, "javascript");
+        assert_eq!(doc_set.entries.len(), 4);
+        assert_eq!(
+            doc_set.entries[3],
+            IndexEntry {
+                slug: "javascript".to_owned(),
+                name: "Lexical grammar: Keywords".to_owned(),
+                path: "lexical_grammar#keywords".to_owned(),
+                type_name: "Miscellaneous".to_owned(),
+                short_name: None,
+            }
+        );
+        let types: Vec<(&str, usize)> = doc_set
+            .types
+            .iter()
+            .map(|doc_type| (doc_type.name.as_str(), doc_type.count))
+            .collect();
+        assert_eq!(
+            types,
+            [("Array", 2), ("Miscellaneous", 1), ("Statements", 1)]
+        );
+    }
+
+    #[test]
+    fn types_come_from_the_entries_without_a_types_array() {
+        let mut index = fixture();
+        index.as_object_mut().unwrap().remove("types");
+        index["entries"][2].as_object_mut().unwrap().remove("type");
+        let doc_set = DocSetIndex::from_json("javascript", &index).unwrap();
+        assert_eq!(doc_set.entries[2].type_name, "");
+        let types: Vec<(&str, usize)> = doc_set
+            .types
+            .iter()
+            .map(|doc_type| (doc_type.name.as_str(), doc_type.count))
+            .collect();
+        assert_eq!(types, [("Array", 2), ("Miscellaneous", 1)]);
+    }
+
+    #[test]
+    fn an_index_without_entries_is_an_error() {
+        assert!(DocSetIndex::from_json("x", &json!({"types": []})).is_err());
+        assert!(DocSetIndex::from_json("x", &json!({"entries": {}})).is_err());
+    }
+
+    #[test]
+    fn the_index_is_queried_by_doc_set() {
+        let mut index = Index::default();
+        index.add(DocSetIndex::from_json("javascript", &fixture()).unwrap());
+        index.add(DocSetIndex {
+            slug: "css".to_owned(),
+            entries: vec![IndexEntry {
+                slug: "css".to_owned(),
+                name: "color".to_owned(),
+                path: "color".to_owned(),
+                type_name: String::new(),
+                short_name: None,
+            }],
+            types: Vec::new(),
+        });
+        assert_eq!(index.entries.len(), 5);
+        assert_eq!(index.entries_of("javascript").count(), 4);
+        assert_eq!(
+            index
+                .find("javascript", "global_objects/array/at")
+                .map(|e| &*e.name),
+            Some("Array.prototype.at()")
+        );
+        assert_eq!(index.find("css", "global_objects/array"), None);
+        assert_eq!(index.types_of("javascript").len(), 3);
+        assert!(index.types_of("ruby").is_empty());
+        // Adding a doc set again replaces its entries
+        index.add(DocSetIndex::from_json("javascript", &fixture()).unwrap());
+        assert_eq!(index.entries.len(), 5);
+    }
+}
@@ -0,0 +1,209 @@
+use std::time::{Duration, Instant};
+
+/// Placeholder for the configured leader key in binding notation, as in `<leader>b`
+pub const LEADER: &str = "<leader>";
+
+/// Multi-key sequences such as `gg` or `<leader>b`, each bound to an action
+#[derive(Debug, Clone)]
+pub struct Keymap<A> {
+    leader: char,
+    bindings: Vec<(Vec<char>, A)>,
+}
+
+impl<A: Copy> Keymap<A> {
+    /// A keymap of bindings written in the notation `gg` or `<leader>b`
+    pub fn new(leader: char, bindings: &[(&str, A)]) -> Self {
+        let bindings = bindings
+            .iter()
+            .map(|&(notation, action)| {
+                let keys = notation
+                    .replace(LEADER, &leader.to_string())
+                    .chars()
+                    .collect();
+                (keys, action)
+            })
+            .collect();
+        Self { leader, bindings }
+    }
+
+    /// The action bound to exactly these keys
+    pub fn action(&self, keys: &[char]) -> Option<A> {
+        self.bindings
+            .iter()
+            .find(|(sequence, _)| sequence == keys)
+            .map(|&(_, action)| action)
+    }
+
+    /// Whether a longer sequence starts with these keys, so more keys may follow
+    pub fn is_prefix(&self, keys: &[char]) -> bool {
+        self.bindings
+            .iter()
+            .any(|(sequence, _)| sequence.len() > keys.len() && sequence.starts_with(keys))
+    }
+
+    /// Keys as they are written in bindings, showing the leader as `<leader>`
+    pub fn notation(&self, keys: &[char]) -> String {
+        keys.iter()
+            .map(|&key| {
+                if key == self.leader {
+                    LEADER.to_owned()
+                } else {
+                    key.to_string()
+                }
+            })
+            .collect()
+    }
+}
+
+/// What the keys typed so far amount to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Resolution<A> {
+    /// They start a sequence, so wait for the next key
+    Pending,
+    /// They complete the sequence bound to this action
+    Action(A),
+    /// They aren't bound to anything, so handle each of them as a key of its own
+    Fallback(Vec<char>),
+}
+
+/// Keys typed towards a sequence that isn't complete yet
+#[derive(Debug, Clone, Default)]
+pub struct PendingKeys {
+    keys: Vec<char>,
+    /// When the last key was typed
+    since: Option<Instant>,
+}
+
+impl PendingKeys {
+    pub fn keys(&self) -> &[char] {
+        &self.keys
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Add a typed key and resolve the keys so far
+    ///
+    /// When the keys are bound themselves but also start a longer sequence, as `g` would
+    /// with both `g` and `gg` bound, this waits for the next key or the timeout.
+    pub fn push<A: Copy>(&mut self, keymap: &Keymap<A>, key: char, now: Instant) -> Resolution<A> {
+        self.keys.push(key);
+        if keymap.is_prefix(&self.keys) {
+            self.since = Some(now);
+            return Resolution::Pending;
+        }
+        let keys = self.take();
+        match keymap.action(&keys) {
+            Some(action) => Resolution::Action(action),
+            None => Resolution::Fallback(keys),
+        }
+    }
+
+    /// Give up waiting for more keys once `timeout` passed since the last one, resolving to
+    /// what the keys typed so far are bound to
+    pub fn expire<A: Copy>(
+        &mut self,
+        keymap: &Keymap<A>,
+        now: Instant,
+        timeout: Duration,
+    ) -> Option<Resolution<A>> {
+        let since = self.since?;
+        if now.duration_since(since) < timeout {
+            return None;
+        }
+        Some(self.flush(keymap))
+    }
+
+    /// Stop waiting for more keys right away, e.g. because a key that can't be part of a
+    /// sequence was typed
+    pub fn flush<A: Copy>(&mut self, keymap: &Keymap<A>) -> Resolution<A> {
+        let keys = self.take();
+        match keymap.action(&keys) {
+            Some(action) => Resolution::Action(action),
+            None => Resolution::Fallback(keys),
+        }
+    }
+
+    fn take(&mut self) -> Vec<char> {
+        self.since = None;
+        std::mem::take(&mut self.keys)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TIMEOUT: Duration = Duration::from_millis(500);
+
+    fn keymap() -> Keymap<&'static str> {
+        Keymap::new(
+            ' ',
+            &[("g", "down"), ("gg", "top"), ("<leader>b", "buffers")],
+        )
+    }
+
+    #[test]
+    fn a_key_starting_a_longer_sequence_waits_for_the_next() {
+        let (keymap, now) = (keymap(), Instant::now());
+        let mut pending = PendingKeys::default();
+        assert_eq!(pending.push(&keymap, 'g', now), Resolution::Pending);
+        assert_eq!(pending.keys(), ['g']);
+        assert_eq!(pending.expire(&keymap, now, TIMEOUT), None);
+        assert_eq!(pending.push(&keymap, 'g', now), Resolution::Action("top"));
+        assert!(pending.is_empty());
+    }
+
+    #[test]
+    fn a_pending_key_resolves_to_its_own_binding_once_the_timeout_passes() {
+        let (keymap, now) = (keymap(), Instant::now());
+        let mut pending = PendingKeys::default();
+        pending.push(&keymap, 'g', now);
+        let soon = now + TIMEOUT / 2;
+        assert_eq!(pending.expire(&keymap, soon, TIMEOUT), None);
+        let later = now + TIMEOUT;
+        assert_eq!(
+            pending.expire(&keymap, later, TIMEOUT),
+            Some(Resolution::Action("down"))
+        );
+        assert!(pending.is_empty());
+        assert_eq!(pending.expire(&keymap, later, TIMEOUT), None);
+    }
+
+    #[test]
+    fn keys_that_complete_no_sequence_fall_back_to_each_key() {
+        let (keymap, now) = (keymap(), Instant::now());
+        let mut pending = PendingKeys::default();
+        pending.push(&keymap, 'g', now);
+        assert_eq!(
+            pending.push(&keymap, 'x', now),
+            Resolution::Fallback(vec!['g', 'x'])
+        );
+        assert!(pending.is_empty());
+
+        pending.push(&keymap, ' ', now);
+        assert_eq!(pending.flush(&keymap), Resolution::Fallback(vec![' ']));
+        pending.push(&keymap, 'g', now);
+        assert_eq!(pending.flush(&keymap), Resolution::Action("down"));
+    }
+
+    #[test]
+    fn the_leader_stands_in_for_the_configured_key() {
+        let (keymap, now) = (keymap(), Instant::now());
+        assert_eq!(keymap.action(&[' ', 'b']), Some("buffers"));
+        assert_eq!(keymap.notation(&[' ', 'b']), "<leader>b");
+        assert_eq!(keymap.notation(&['g', 'g']), "gg");
+
+        let mut pending = PendingKeys::default();
+        assert_eq!(pending.push(&keymap, ' ', now), Resolution::Pending);
+        assert_eq!(
+            pending.push(&keymap, 'b', now),
+            Resolution::Action("buffers")
+        );
+
+        let comma = Keymap::new(',', &[("<leader>b", "buffers")]);
+        assert_eq!(comma.action(&[',', 'b']), Some("buffers"));
+        assert_eq!(comma.action(&[' ', 'b']), None);
+    }
+}
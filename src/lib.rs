@@ -1,7 +1,35 @@
+pub mod clipboard;
+pub mod config;
+pub mod db;
+pub mod diff;
+pub mod docset_manager;
+pub mod external;
+pub mod fulltext;
+pub mod history;
+pub mod html;
+pub mod index;
+pub mod keymap;
+pub mod link;
+pub mod net;
+pub mod notification;
+pub mod output;
+pub mod path;
+pub mod query;
+pub mod search;
+pub mod stats;
+pub mod storage;
+pub mod theme;
+pub mod toml;
+
+#[cfg(test)]
+mod testing;
+
 use directories::BaseDirs;
-use log::trace;
-use reqwest;
-use std::io::Cursor;
+use log::{trace, warn};
+use std::collections::HashSet;
+use std::fs::{File, OpenOptions};
+use std::io::{Cursor, Read, Seek, Write};
+use std::path::Path;
 
 const DEVDOCS_GIT: &str = "https://github.com/freeCodeCamp/devdocs/archive/refs/heads/main.zip";
 
@@ -20,7 +48,251 @@ pub async fn sync_repo() -> Result<(), Box<dyn std::error::Error>> {
 
     // Extract the zip file to the data directory
     std::fs::create_dir_all(&local_storage)?;
-    archive.extract(&local_storage)?;
+    extract_replacing(&mut archive, &local_storage)?;
+
+    Ok(())
+}
+
+/// Directory of the data directory archives are extracted into before they replace what
+/// an earlier sync extracted
+const EXTRACT_STAGING: &str = ".extracting";
+
+/// Extract an archive into a directory all or nothing, replacing the files and directories
+/// at its top level that an earlier extraction left
+///
+/// The archive is extracted into [`EXTRACT_STAGING`] first, and only once all of it is
+/// there are its top-level entries swapped into place, so what was there before is kept if
+/// extraction fails. If it fails as the archive is corrupt, e.g. truncated, the staging
+/// directory is removed. If it fails otherwise, e.g. as the disk is full, or the run is
+/// interrupted, the staging directory and its progress file are left for the next run to
+/// resume.
+fn extract_replacing<R: Read + Seek>(
+    archive: &mut zip::ZipArchive<R>,
+    dir: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let staging = dir.join(EXTRACT_STAGING);
+    std::fs::create_dir_all(&staging)?;
+    if let Err(e) = extract_resumable(archive, &staging) {
+        if is_corrupt_archive(&*e) {
+            if let Err(e) = std::fs::remove_dir_all(&staging) {
+                warn!("Could not remove {}: {e}", staging.display());
+            }
+        }
+        return Err(e);
+    }
+    for extracted in std::fs::read_dir(&staging)? {
+        let extracted = extracted?;
+        swap_into_place(&extracted.path(), &dir.join(extracted.file_name()))?;
+    }
+    std::fs::remove_dir(&staging)?;
+    Ok(())
+}
+
+/// Whether extraction failed on the archive itself rather than on writing what it holds
+///
+/// A file whose content doesn't match its checksum, or can't be decompressed, fails to read
+/// with [`std::io::ErrorKind::InvalidData`].
+fn is_corrupt_archive(error: &(dyn std::error::Error + 'static)) -> bool {
+    if let Some(error) = error.downcast_ref::<zip::result::ZipError>() {
+        return match error {
+            zip::result::ZipError::Io(error) => error.kind() == std::io::ErrorKind::InvalidData,
+            _ => true,
+        };
+    }
+    error
+        .downcast_ref::<std::io::Error>()
+        .is_some_and(|error| error.kind() == std::io::ErrorKind::InvalidData)
+}
+
+/// Move a file or directory to `target`, removing what was there only once the new one
+/// is in its place, and putting the old one back if it can't be
+fn swap_into_place(path: &Path, target: &Path) -> std::io::Result<()> {
+    let Some(name) = target.file_name() else {
+        return Err(std::io::Error::other("nothing to replace"));
+    };
+    let mut old_name = std::ffi::OsString::from(".old-");
+    old_name.push(name);
+    let old = target.with_file_name(old_name);
+    remove_path(&old)?;
+    let replacing = target.symlink_metadata().is_ok();
+    if replacing {
+        std::fs::rename(target, &old)?;
+    }
+    if let Err(e) = std::fs::rename(path, target) {
+        if replacing {
+            std::fs::rename(&old, target)?;
+        }
+        return Err(e);
+    }
+    remove_path(&old)
+}
+
+/// Remove a file or a directory with everything in it, if there is one
+fn remove_path(path: &Path) -> std::io::Result<()> {
+    match path.symlink_metadata() {
+        Ok(metadata) if metadata.is_dir() => std::fs::remove_dir_all(path),
+        Ok(_) => std::fs::remove_file(path),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Sidecar file listing the archive entries extracted so far
+const EXTRACT_PROGRESS: &str = ".extract-progress";
 
+/// Extract an archive into a directory, skipping what an interrupted earlier run already
+/// extracted
+///
+/// Each file is recorded in a progress file once it is fully written. A rerun skips recorded
+/// files that still have the size the archive gives them, and the progress file is removed
+/// once everything is extracted.
+fn extract_resumable<R: Read + Seek>(
+    archive: &mut zip::ZipArchive<R>,
+    dir: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let progress_path = dir.join(EXTRACT_PROGRESS);
+    let done: HashSet<String> = std::fs::read_to_string(&progress_path)
+        .map(|progress| progress.lines().map(str::to_owned).collect())
+        .unwrap_or_default();
+    let mut progress = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&progress_path)?;
+
+    let mut skipped = 0;
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i)?;
+        let Some(relative) = file.enclosed_name() else {
+            continue;
+        };
+        let path = dir.join(relative);
+        if file.is_dir() {
+            std::fs::create_dir_all(&path)?;
+            continue;
+        }
+        let unchanged = done.contains(file.name())
+            && std::fs::metadata(&path).is_ok_and(|metadata| metadata.len() == file.size());
+        if unchanged {
+            skipped += 1;
+            continue;
+        }
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::io::copy(&mut file, &mut File::create(&path)?)?;
+        writeln!(progress, "{}", file.name())?;
+    }
+    if skipped > 0 {
+        trace!("Skipped {skipped} files extracted by an earlier run");
+    }
+
+    drop(progress);
+    std::fs::remove_file(&progress_path)?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+    use zip::write::SimpleFileOptions;
+    use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+    /// A zip of `devdocs-main/` holding these files, uncompressed
+    fn archive(files: &[(&str, &str)]) -> Vec<u8> {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        let options = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+        for (name, content) in files {
+            zip.start_file(format!("devdocs-main/{name}"), options)
+                .unwrap();
+            zip.write_all(content.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn a_failed_extraction_keeps_the_previous_one() {
+        let dir = TempDir::new("extract");
+        let good = archive(&[("README.md", "old"), ("lib/app.rb", "old")]);
+        extract_replacing(&mut ZipArchive::new(Cursor::new(good)).unwrap(), &dir).unwrap();
+        let readme = dir.join("devdocs-main").join("README.md");
+        assert_eq!(std::fs::read_to_string(&readme).unwrap(), "old");
+
+        // The second file's content no longer matches its checksum, so extraction fails
+        // after the first one is written
+        let mut broken = archive(&[("README.md", "new"), ("lib/app.rb", "corrupted")]);
+        let at = broken
+            .windows(9)
+            .position(|window| window == b"corrupted")
+            .unwrap();
+        broken[at] = b'C';
+        let result = extract_replacing(&mut ZipArchive::new(Cursor::new(broken)).unwrap(), &dir);
+        assert!(result.is_err());
+        assert_eq!(std::fs::read_to_string(&readme).unwrap(), "old");
+        assert!(!dir.join(EXTRACT_STAGING).exists());
+
+        let newer = archive(&[("README.md", "new")]);
+        extract_replacing(&mut ZipArchive::new(Cursor::new(newer)).unwrap(), &dir).unwrap();
+        assert_eq!(std::fs::read_to_string(&readme).unwrap(), "new");
+        assert!(!dir.join("devdocs-main").join("lib").exists());
+        let names: Vec<_> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert_eq!(names, ["devdocs-main"]);
+    }
+
+    #[test]
+    fn a_rerun_skips_the_files_an_earlier_run_extracted() {
+        let dir = TempDir::new("extract-resume");
+        let files = [("README.md", "new"), ("lib/app.rb", "new")];
+        let zip = || ZipArchive::new(Cursor::new(archive(&files))).unwrap();
+
+        // An earlier run was stopped after writing only the first file, given content of the
+        // same size so whether it is written again shows
+        let readme = dir.join("devdocs-main").join("README.md");
+        std::fs::create_dir_all(readme.parent().unwrap()).unwrap();
+        std::fs::write(&readme, "old").unwrap();
+        std::fs::write(dir.join(EXTRACT_PROGRESS), "devdocs-main/README.md\n").unwrap();
+
+        extract_resumable(&mut zip(), &dir).unwrap();
+        assert_eq!(std::fs::read_to_string(&readme).unwrap(), "old");
+        let app = dir.join("devdocs-main").join("lib").join("app.rb");
+        assert_eq!(std::fs::read_to_string(app).unwrap(), "new");
+        assert!(!dir.join(EXTRACT_PROGRESS).exists());
+
+        // Without a progress file nothing counts as extracted
+        extract_resumable(&mut zip(), &dir).unwrap();
+        assert_eq!(std::fs::read_to_string(&readme).unwrap(), "new");
+    }
+
+    #[test]
+    fn a_failed_write_leaves_the_extraction_to_resume() {
+        let dir = TempDir::new("extract-keep-staging");
+        let files = [("README.md", "new"), ("lib/app.rb", "new")];
+        let zip = || ZipArchive::new(Cursor::new(archive(&files))).unwrap();
+
+        // A directory where the second file goes stops it from being written
+        let staging = dir.join(EXTRACT_STAGING).join("devdocs-main");
+        let blocked = staging.join("lib").join("app.rb");
+        std::fs::create_dir_all(&blocked).unwrap();
+        assert!(extract_replacing(&mut zip(), &dir).is_err());
+        assert!(!dir.join("devdocs-main").exists());
+        assert_eq!(
+            std::fs::read_to_string(staging.join("README.md")).unwrap(),
+            "new"
+        );
+        let progress = dir.join(EXTRACT_STAGING).join(EXTRACT_PROGRESS);
+        assert_eq!(
+            std::fs::read_to_string(&progress).unwrap(),
+            "devdocs-main/README.md\n"
+        );
+
+        std::fs::remove_dir(&blocked).unwrap();
+        extract_replacing(&mut zip(), &dir).unwrap();
+        let app = dir.join("devdocs-main").join("lib").join("app.rb");
+        assert_eq!(std::fs::read_to_string(app).unwrap(), "new");
+        assert!(!dir.join(EXTRACT_STAGING).exists());
+    }
+}
@@ -0,0 +1,172 @@
+use crate::path::{canonical_url, normalize};
+use crate::search::SearchResult;
+use percent_encoding::percent_decode_str;
+use url::Url;
+
+/// An entry of a doc set as addressed by a devdocs.io URL
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DevdocsLink {
+    pub slug: String,
+    /// Path of the entry within the doc set, without the fragment
+    pub path: String,
+    /// Element of the entry to scroll to
+    pub fragment: Option<String>,
+}
+
+impl DevdocsLink {
+    /// The entry path as found in a doc set's index, including the fragment
+    pub fn entry_path(&self) -> String {
+        match &self.fragment {
+            Some(fragment) => format!("{}#{fragment}", self.path),
+            None => self.path.clone(),
+        }
+    }
+}
+
+/// Parse a URL of the form `https://devdocs.io/<slug>/<path>#<fragment>`
+pub fn parse_devdocs_url(input: &str) -> Result<DevdocsLink, Box<dyn std::error::Error>> {
+    let input = input.trim();
+    let url = match input.strip_prefix("devdocs.io/") {
+        Some(rest) => Url::parse(&format!("https://devdocs.io/{rest}")),
+        None => Url::parse(input),
+    }
+    .map_err(|e| format!("'{input}' is not a URL: {e}"))?;
+
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(format!("unsupported URL scheme '{}'", url.scheme()).into());
+    }
+    match url.host_str() {
+        Some("devdocs.io" | "www.devdocs.io") => {}
+        Some(host) => return Err(format!("'{host}' is not devdocs.io").into()),
+        None => return Err("the URL has no host".into()),
+    }
+
+    let segments: Vec<String> = url
+        .path_segments()
+        .into_iter()
+        .flatten()
+        .filter(|segment| !segment.is_empty())
+        .map(|segment| percent_decode_str(segment).decode_utf8_lossy().into_owned())
+        .collect();
+    let [slug, path @ ..] = segments.as_slice() else {
+        return Err("the URL does not name a doc set".into());
+    };
+    let path = normalize(&path.join("/")).unwrap_or_default();
+    if path.is_empty() {
+        return Err(format!("the URL names the '{slug}' doc set but no entry in it").into());
+    }
+
+    Ok(DevdocsLink {
+        slug: slug.clone(),
+        path,
+        fragment: url
+            .fragment()
+            .filter(|fragment| !fragment.is_empty())
+            .map(|fragment| {
+                percent_decode_str(fragment)
+                    .decode_utf8_lossy()
+                    .into_owned()
+            }),
+    })
+}
+
+/// Whether some input, such as a search query, looks like it is meant to be a devdocs URL
+pub fn looks_like_devdocs_url(input: &str) -> bool {
+    let input = input.trim();
+    ["https://devdocs.io/", "http://devdocs.io/", "devdocs.io/"]
+        .iter()
+        .any(|prefix| input.starts_with(prefix))
+}
+
+/// Ways of referring to an entry when sharing it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShareFormat {
+    /// `slug/path`
+    Path,
+    /// The full devdocs.io URL
+    Url,
+    /// A Markdown link titled with the entry's name
+    Markdown,
+}
+
+impl ShareFormat {
+    pub const ALL: [ShareFormat; 3] = [ShareFormat::Path, ShareFormat::Url, ShareFormat::Markdown];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ShareFormat::Path => "path",
+            ShareFormat::Url => "URL",
+            ShareFormat::Markdown => "Markdown link",
+        }
+    }
+
+    pub fn format(self, slug: &str, path: &str, title: &str) -> String {
+        match self {
+            ShareFormat::Path => format!("{slug}/{path}"),
+            ShareFormat::Url => canonical_url(slug, path),
+            ShareFormat::Markdown => {
+                let title = title.replace('[', "\\[").replace(']', "\\]");
+                format!("[{title}]({})", canonical_url(slug, path))
+            }
+        }
+    }
+}
+
+/// Ways of writing a list of search results out to share them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListFormat {
+    /// A bulleted list of Markdown links
+    Markdown,
+    /// One entry per line, its name then its URL
+    Text,
+}
+
+impl ListFormat {
+    /// Name used for the format on the command line
+    pub fn name(self) -> &'static str {
+        match self {
+            ListFormat::Markdown => "markdown",
+            ListFormat::Text => "text",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "markdown" => Some(ListFormat::Markdown),
+            "text" => Some(ListFormat::Text),
+            _ => None,
+        }
+    }
+
+    /// The results of a query, under a header line naming the query and the doc sets it
+    /// searched, all of them when `scope` is empty
+    pub fn format(self, query: &str, scope: &[String], results: &[SearchResult]) -> String {
+        let doc_sets = if scope.is_empty() {
+            "all doc sets".to_owned()
+        } else {
+            scope.join(", ")
+        };
+        let mut list = match self {
+            ListFormat::Markdown => format!("Results for `{}` in {doc_sets}\n\n", query.trim()),
+            ListFormat::Text => format!("Results for \"{}\" in {doc_sets}\n\n", query.trim()),
+        };
+        for result in results {
+            let entry = &result.entry;
+            let line = match self {
+                ListFormat::Markdown => format!(
+                    "- {} ({})",
+                    ShareFormat::Markdown.format(&entry.slug, &entry.path, &entry.name),
+                    entry.slug
+                ),
+                ListFormat::Text => format!(
+                    "{}  {}",
+                    entry.name,
+                    canonical_url(&entry.slug, &entry.path)
+                ),
+            };
+            list.push_str(&line);
+            list.push('\n');
+        }
+        list
+    }
+}
@@ -0,0 +1,64 @@
+use std::collections::VecDeque;
+
+/// How much a notification matters, which decides its color
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Info,
+    Success,
+    Warning,
+    Error,
+}
+
+/// A message shown briefly over the UI, e.g. the outcome of an action
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Notification {
+    pub severity: Severity,
+    pub message: String,
+    /// Tick the notification was pushed at
+    pub since: u64,
+}
+
+/// Notifications waiting to be dismissed, oldest first
+#[derive(Debug, Clone, Default)]
+pub struct Notifications {
+    queue: VecDeque<Notification>,
+}
+
+impl Notifications {
+    /// Most notifications kept at once, beyond which the oldest are dropped
+    pub const CAPACITY: usize = 16;
+
+    pub fn push(&mut self, severity: Severity, message: impl Into<String>, tick: u64) {
+        self.queue.push_back(Notification {
+            severity,
+            message: message.into(),
+            since: tick,
+        });
+        if self.queue.len() > Self::CAPACITY {
+            self.queue.pop_front();
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    /// Drop the notifications pushed at least `lifetime` ticks before `tick`, returning
+    /// whether there were any
+    pub fn expire(&mut self, tick: u64, lifetime: u64) -> bool {
+        let before = self.queue.len();
+        self.queue
+            .retain(|notification| tick.wrapping_sub(notification.since) < lifetime);
+        self.queue.len() != before
+    }
+
+    /// Drop the oldest notification, returning whether there was one
+    pub fn dismiss(&mut self) -> bool {
+        self.queue.pop_front().is_some()
+    }
+
+    /// The `max` most recent notifications, oldest first
+    pub fn visible(&self, max: usize) -> impl Iterator<Item = &Notification> {
+        self.queue.iter().skip(self.queue.len().saturating_sub(max))
+    }
+}
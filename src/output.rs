@@ -0,0 +1,345 @@
+//! JSON shapes printed by the command line commands when given `--json`
+//!
+//! Each command prints a single JSON object to stdout. Errors are printed to stderr as
+//! `{"error": "<message>"}` and the process exits with a nonzero status. Fields are only ever
+//! added to these shapes, never renamed or removed, so scripts can rely on them.
+
+use crate::docset_manager::{Leftover, Manifest, VerifyReport};
+use crate::path::canonical_url;
+use crate::search::SearchResult;
+use serde_json::{json, Value};
+use std::path::PathBuf;
+
+/// `{"error": "<message>"}`
+pub fn error(message: &str) -> Value {
+    json!({ "error": message })
+}
+
+/// `search`: `{"query", "results": [{"name", "slug", "path", "type", "score", "url"}]}`,
+/// best match first, where results of a full-text search also have a `snippet`
+pub fn search_results(query: &str, results: &[SearchResult]) -> Value {
+    let results: Vec<Value> = results
+        .iter()
+        .map(|result| {
+            let mut value = json!({
+                "name": result.entry.name,
+                "slug": result.entry.slug,
+                "path": result.entry.path,
+                "type": result.entry.type_name,
+                "score": result.score,
+                "url": canonical_url(&result.entry.slug, &result.entry.path),
+            });
+            if let Some(snippet) = &result.snippet {
+                value["snippet"] = snippet.as_str().into();
+            }
+            value
+        })
+        .collect();
+    json!({ "query": query, "results": results })
+}
+
+/// A rendered entry
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntryOutput {
+    pub slug: String,
+    /// Path of the entry, possibly with a `#fragment`
+    pub path: String,
+    /// Line of `text` the fragment points at, if the path has one that was found
+    pub anchor_line: Option<usize>,
+    /// The entry rendered as plain text
+    pub text: String,
+}
+
+impl EntryOutput {
+    /// `get` and `open`: `{"slug", "path", "url", "anchor_line", "text"}`, where
+    /// `anchor_line` is `null` without a fragment
+    pub fn to_json(&self) -> Value {
+        json!({
+            "slug": self.slug,
+            "path": self.path,
+            "url": canonical_url(&self.slug, &self.path),
+            "anchor_line": self.anchor_line,
+            "text": self.text,
+        })
+    }
+}
+
+/// `docs list`: `{"doc_sets": [{"slug", "name", "version", "release", "local",
+/// "entries"}]}`
+///
+/// Doc sets without a manifest have their slug as name and empty versions.
+pub fn doc_set_list(doc_sets: &[(String, Option<Manifest>, usize)]) -> Value {
+    let doc_sets: Vec<Value> = doc_sets
+        .iter()
+        .map(|(slug, manifest, entries)| {
+            json!({
+                "slug": slug,
+                "name": manifest.as_ref().map_or(slug.as_str(), |m| m.name.as_str()),
+                "version": manifest.as_ref().map_or("", |m| m.version.as_str()),
+                "release": manifest.as_ref().map_or("", |m| m.release.as_str()),
+                "local": manifest.as_ref().is_some_and(|m| m.local),
+                "entries": entries,
+            })
+        })
+        .collect();
+    json!({ "doc_sets": doc_sets })
+}
+
+/// `docs size`: `{"doc_sets": [{"slug", "bytes"}], "total_bytes"}`
+pub fn doc_set_sizes(sizes: &[(String, u64)]) -> Value {
+    let doc_sets: Vec<Value> = sizes
+        .iter()
+        .map(|(slug, bytes)| json!({ "slug": slug, "bytes": bytes }))
+        .collect();
+    let total: u64 = sizes.iter().map(|(_, bytes)| bytes).sum();
+    json!({ "doc_sets": doc_sets, "total_bytes": total })
+}
+
+/// `verify`: `{"doc_sets": [{"slug", "entries", "missing": ["<path>"]}]}`
+pub fn verify_reports(reports: &[(String, VerifyReport)]) -> Value {
+    let doc_sets: Vec<Value> = reports
+        .iter()
+        .map(|(slug, report)| {
+            json!({
+                "slug": slug,
+                "entries": report.entries,
+                "missing": report.missing,
+            })
+        })
+        .collect();
+    json!({ "doc_sets": doc_sets })
+}
+
+/// `docs clean`: `{"leftovers": [{"kind", "path", "missing"}]}`
+///
+/// `kind` is `incomplete_doc_set`, with `path` the doc set's slug and `missing` the files it
+/// lacks, or `stray_file`, with an empty `missing`. As JSON output can't ask for
+/// confirmation, the leftovers are only listed, not removed.
+pub fn leftovers(leftovers: &[Leftover]) -> Value {
+    let leftovers: Vec<Value> = leftovers
+        .iter()
+        .map(|leftover| match leftover {
+            Leftover::IncompleteDocSet { slug, missing } => json!({
+                "kind": "incomplete_doc_set",
+                "path": slug,
+                "missing": missing,
+            }),
+            Leftover::StrayFile(path) => json!({
+                "kind": "stray_file",
+                "path": path.display().to_string(),
+                "missing": [],
+            }),
+        })
+        .collect();
+    json!({ "leftovers": leftovers })
+}
+
+/// `docs add`: `{"added": [{"slug", "entries"}]}`
+pub fn added(added: &[(String, usize)]) -> Value {
+    let added: Vec<Value> = added
+        .iter()
+        .map(|(slug, entries)| json!({ "slug": slug, "entries": entries }))
+        .collect();
+    json!({ "added": added })
+}
+
+/// `docs index`: `{"indexed": [{"slug", "entries"}]}`
+pub fn full_text_indexed(indexed: &[(String, usize)]) -> Value {
+    let indexed: Vec<Value> = indexed
+        .iter()
+        .map(|(slug, entries)| json!({ "slug": slug, "entries": entries }))
+        .collect();
+    json!({ "indexed": indexed })
+}
+
+/// `index rebuild`: `{"rebuilt": [slug]}`
+pub fn index_rebuilt(rebuilt: &[String]) -> Value {
+    json!({ "rebuilt": rebuilt })
+}
+
+/// `docs add-local`: `{"slug", "entries"}`
+pub fn added_local(slug: &str, entries: usize) -> Value {
+    json!({ "slug": slug, "entries": entries })
+}
+
+/// `paths`: `{"paths": [{"name", "path", "exists"}]}`, where `name` is `data`, `docs` or
+/// `config`
+pub fn paths(paths: &[(&str, PathBuf)]) -> Value {
+    let paths: Vec<Value> = paths
+        .iter()
+        .map(|(name, path)| {
+            json!({
+                "name": name,
+                "path": path.display().to_string(),
+                "exists": path.exists(),
+            })
+        })
+        .collect();
+    json!({ "paths": paths })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::docset_manager::About;
+    use crate::index::{short_name, IndexEntry};
+
+    fn result(slug: &str, name: &str, path: &str, snippet: Option<&str>) -> SearchResult {
+        SearchResult {
+            entry: IndexEntry {
+                slug: slug.to_owned(),
+                name: name.to_owned(),
+                path: path.to_owned(),
+                type_name: "Method".to_owned(),
+                short_name: short_name(name),
+            },
+            score: 80,
+            other_versions: Vec::new(),
+            snippet: snippet.map(str::to_owned),
+        }
+    }
+
+    #[test]
+    fn search_results_keep_their_json_shape() {
+        let results = [
+            result(
+                "rust",
+                "HashMap::insert",
+                "std/collections/struct.hashmap#method.insert",
+                None,
+            ),
+            result(
+                "python~3.12",
+                "dict",
+                "library/stdtypes#dict",
+                Some("…mapping objects…"),
+            ),
+        ];
+        assert_eq!(
+            search_results("insert", &results).to_string(),
+            concat!(
+                r#"{"query":"insert","results":["#,
+                r#"{"name":"HashMap::insert","path":"std/collections/struct.hashmap#method.insert","#,
+                r#""score":80,"slug":"rust","type":"Method","#,
+                r#""url":"https://devdocs.io/rust/std/collections/struct.hashmap#method.insert"},"#,
+                r#"{"name":"dict","path":"library/stdtypes#dict","score":80,"slug":"python~3.12","#,
+                r#""snippet":"…mapping objects…","type":"Method","#,
+                r#""url":"https://devdocs.io/python~3.12/library/stdtypes#dict"}]}"#,
+            )
+        );
+        assert_eq!(
+            search_results("nothing", &[]).to_string(),
+            r#"{"query":"nothing","results":[]}"#
+        );
+        assert_eq!(
+            error("no doc set 'rust' is installed").to_string(),
+            r#"{"error":"no doc set 'rust' is installed"}"#
+        );
+    }
+
+    #[test]
+    fn entries_keep_their_json_shape() {
+        let mut entry = EntryOutput {
+            slug: "rust".to_owned(),
+            path: "std/index#modules".to_owned(),
+            anchor_line: Some(3),
+            text: "Modules\n".to_owned(),
+        };
+        assert_eq!(
+            entry.to_json().to_string(),
+            concat!(
+                r#"{"anchor_line":3,"path":"std/index#modules","slug":"rust","text":"Modules\n","#,
+                r#""url":"https://devdocs.io/rust/std/index#modules"}"#,
+            )
+        );
+        entry.path = "std/index".to_owned();
+        entry.anchor_line = None;
+        assert_eq!(
+            entry.to_json().to_string(),
+            concat!(
+                r#"{"anchor_line":null,"path":"std/index","slug":"rust","text":"Modules\n","#,
+                r#""url":"https://devdocs.io/rust/std/index"}"#,
+            )
+        );
+    }
+
+    #[test]
+    fn doc_set_commands_keep_their_json_shape() {
+        let manifest = Manifest {
+            slug: "python~3.12".to_owned(),
+            name: "Python".to_owned(),
+            version: "3.12".to_owned(),
+            release: "3.12.4".to_owned(),
+            mtime: 1,
+            local: false,
+            about: About::default(),
+        };
+        let doc_sets = [
+            ("python~3.12".to_owned(), Some(manifest), 10),
+            ("mydocs".to_owned(), None, 2),
+        ];
+        assert_eq!(
+            doc_set_list(&doc_sets).to_string(),
+            concat!(
+                r#"{"doc_sets":["#,
+                r#"{"entries":10,"local":false,"name":"Python","release":"3.12.4","#,
+                r#""slug":"python~3.12","version":"3.12"},"#,
+                r#"{"entries":2,"local":false,"name":"mydocs","release":"","slug":"mydocs","#,
+                r#""version":""}]}"#,
+            )
+        );
+        let sizes = [("rust".to_owned(), 1000), ("go".to_owned(), 24)];
+        assert_eq!(
+            doc_set_sizes(&sizes).to_string(),
+            r#"{"doc_sets":[{"bytes":1000,"slug":"rust"},{"bytes":24,"slug":"go"}],"total_bytes":1024}"#
+        );
+        let rust = [("rust".to_owned(), 3)];
+        assert_eq!(
+            added(&rust).to_string(),
+            r#"{"added":[{"entries":3,"slug":"rust"}]}"#
+        );
+        assert_eq!(
+            full_text_indexed(&rust).to_string(),
+            r#"{"indexed":[{"entries":3,"slug":"rust"}]}"#
+        );
+        assert_eq!(
+            index_rebuilt(&["rust".to_owned()]).to_string(),
+            r#"{"rebuilt":["rust"]}"#
+        );
+        assert_eq!(
+            added_local("mydocs", 2).to_string(),
+            r#"{"entries":2,"slug":"mydocs"}"#
+        );
+    }
+
+    #[test]
+    fn maintenance_commands_keep_their_json_shape() {
+        let report = VerifyReport {
+            entries: 3,
+            missing: vec!["b".to_owned()],
+        };
+        assert_eq!(
+            verify_reports(&[("rust".to_owned(), report)]).to_string(),
+            r#"{"doc_sets":[{"entries":3,"missing":["b"],"slug":"rust"}]}"#
+        );
+        let found = [
+            Leftover::IncompleteDocSet {
+                slug: "go".to_owned(),
+                missing: vec!["db.json"],
+            },
+            Leftover::StrayFile(PathBuf::from("/data/docs/rust/db.json.gz.tmp")),
+        ];
+        assert_eq!(
+            leftovers(&found).to_string(),
+            concat!(
+                r#"{"leftovers":["#,
+                r#"{"kind":"incomplete_doc_set","missing":["db.json"],"path":"go"},"#,
+                r#"{"kind":"stray_file","missing":[],"path":"/data/docs/rust/db.json.gz.tmp"}]}"#,
+            )
+        );
+        assert_eq!(
+            paths(&[("data", PathBuf::from("/nonexistent/rtftui"))]).to_string(),
+            r#"{"paths":[{"exists":false,"name":"data","path":"/nonexistent/rtftui"}]}"#
+        );
+    }
+}
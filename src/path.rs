@@ -0,0 +1,244 @@
+use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
+
+/// Characters escaped in the path of a devdocs.io URL, besides control characters
+const PATH: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'<')
+    .add(b'>')
+    .add(b'`')
+    .add(b'?')
+    .add(b'#')
+    .add(b'{')
+    .add(b'}');
+
+/// Characters escaped in the fragment of a devdocs.io URL, besides control characters
+const FRAGMENT: &AsciiSet = &CONTROLS.add(b' ').add(b'"').add(b'<').add(b'>').add(b'`');
+
+/// Split an entry path into its page and the element of the page it points at, if any
+///
+/// An empty fragment, as in `page#`, counts as none.
+pub fn split_fragment(path: &str) -> (&str, Option<&str>) {
+    match path.split_once('#') {
+        Some((page, fragment)) if !fragment.is_empty() => (page, Some(fragment)),
+        Some((page, _)) => (page, None),
+        None => (path, None),
+    }
+}
+
+/// An entry path in the form doc set indexes use: segments joined by single slashes, with
+/// no leading or trailing slash, `.` or `..` segments or query string
+///
+/// Returns `None` if `..` climbs out of the doc set.
+pub fn normalize(path: &str) -> Option<String> {
+    let (page, fragment) = split_fragment(path);
+    let page = page.split_once('?').map_or(page, |(page, _)| page);
+    let mut segments: Vec<&str> = Vec::new();
+    for segment in page.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop()?;
+            }
+            segment => segments.push(segment),
+        }
+    }
+    let page = segments.join("/");
+    Some(match fragment {
+        Some(fragment) => format!("{page}#{fragment}"),
+        None => page,
+    })
+}
+
+/// The entry path an `href` found in the entry at `base` points to, or `None` if it points
+/// outside the doc set, e.g. to another site or above the doc set's root
+///
+/// Like on devdocs.io, an absolute `href` is relative to the doc set's root rather than the
+/// site's.
+pub fn resolve_relative(base: &str, href: &str) -> Option<String> {
+    let href = href.trim();
+    let has_scheme = href
+        .split_once(':')
+        .is_some_and(|(scheme, _)| !scheme.is_empty() && !scheme.contains(['/', '#', '?']));
+    if has_scheme || href.starts_with("//") {
+        return None;
+    }
+    let (page, _) = split_fragment(base);
+    if href.is_empty() || href.starts_with('?') {
+        return normalize(page);
+    }
+    if href.starts_with('#') {
+        return normalize(&format!("{page}{href}"));
+    }
+    if href.starts_with('/') {
+        return normalize(href);
+    }
+    match page.rsplit_once('/') {
+        Some((dir, _)) => normalize(&format!("{dir}/{href}")),
+        None => normalize(href),
+    }
+}
+
+/// The devdocs.io URL of an entry, whose path may include a `#fragment`
+///
+/// The path is normalized and escaped as needed, so the same entry always gets the same URL.
+pub fn canonical_url(slug: &str, path: &str) -> String {
+    let path = normalize(path).unwrap_or_else(|| path.to_owned());
+    let (page, fragment) = split_fragment(&path);
+    let mut url = format!(
+        "https://devdocs.io/{slug}/{}",
+        utf8_percent_encode(page, PATH)
+    );
+    if let Some(fragment) = fragment {
+        url.push('#');
+        url.extend(utf8_percent_encode(fragment, FRAGMENT));
+    }
+    url
+}
+
+/// The address to open an image of the entry at `base` at, or `None` for images embedded in
+/// the entry as `data:` URLs or without a `src`
+///
+/// Images are mostly on other sites; one relative to the entry is looked for next to the
+/// entry on devdocs.io.
+pub fn image_url(slug: &str, base: &str, src: &str) -> Option<String> {
+    let src = src.trim();
+    if let Some(rest) = src.strip_prefix("//") {
+        return Some(format!("https://{rest}"));
+    }
+    match src.split_once(':') {
+        Some((scheme, _)) if scheme.eq_ignore_ascii_case("data") => None,
+        Some((scheme, _))
+            if scheme.eq_ignore_ascii_case("http") || scheme.eq_ignore_ascii_case("https") =>
+        {
+            Some(src.to_owned())
+        }
+        _ if src.is_empty() => None,
+        _ => resolve_relative(base, src).map(|path| canonical_url(slug, &path)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fragments_are_split_off() {
+        assert_eq!(split_fragment("std/vec"), ("std/vec", None));
+        assert_eq!(
+            split_fragment("std/vec#method.push"),
+            ("std/vec", Some("method.push"))
+        );
+        assert_eq!(split_fragment("std/vec#"), ("std/vec", None));
+        assert_eq!(split_fragment("#top"), ("", Some("top")));
+        assert_eq!(split_fragment("a#b#c"), ("a", Some("b#c")));
+    }
+
+    #[test]
+    fn paths_are_normalized() {
+        assert_eq!(normalize("std/vec").as_deref(), Some("std/vec"));
+        assert_eq!(normalize("/std//vec/").as_deref(), Some("std/vec"));
+        assert_eq!(normalize("std/./vec").as_deref(), Some("std/vec"));
+        assert_eq!(normalize("std/io/../vec").as_deref(), Some("std/vec"));
+        assert_eq!(normalize("std/vec?search=push").as_deref(), Some("std/vec"));
+        assert_eq!(
+            normalize("std/vec?x=1#method.push").as_deref(),
+            Some("std/vec#method.push")
+        );
+        assert_eq!(normalize("std/vec#").as_deref(), Some("std/vec"));
+        assert_eq!(normalize("").as_deref(), Some(""));
+    }
+
+    #[test]
+    fn climbing_above_the_root_fails() {
+        assert_eq!(normalize(".."), None);
+        assert_eq!(normalize("std/../../vec"), None);
+        assert_eq!(resolve_relative("std/vec", "../../core"), None);
+        assert_eq!(resolve_relative("vec", "../core"), None);
+    }
+
+    #[test]
+    fn hrefs_resolve_against_the_page_they_are_in() {
+        let base = "std/collections/struct.HashMap#method.entry";
+        assert_eq!(
+            resolve_relative(base, "struct.BTreeMap").as_deref(),
+            Some("std/collections/struct.BTreeMap")
+        );
+        assert_eq!(
+            resolve_relative(base, "./hash_map/enum.Entry#variants").as_deref(),
+            Some("std/collections/hash_map/enum.Entry#variants")
+        );
+        assert_eq!(
+            resolve_relative(base, "../vec/struct.Vec").as_deref(),
+            Some("std/vec/struct.Vec")
+        );
+        assert_eq!(
+            resolve_relative(base, "#method.insert").as_deref(),
+            Some("std/collections/struct.HashMap#method.insert")
+        );
+        assert_eq!(
+            resolve_relative(base, "").as_deref(),
+            Some("std/collections/struct.HashMap")
+        );
+        assert_eq!(
+            resolve_relative(base, "?q=1").as_deref(),
+            Some("std/collections/struct.HashMap")
+        );
+        assert_eq!(
+            resolve_relative(base, "/core/index").as_deref(),
+            Some("core/index")
+        );
+        assert_eq!(resolve_relative("index", "other").as_deref(), Some("other"));
+    }
+
+    #[test]
+    fn hrefs_to_other_sites_do_not_resolve() {
+        let base = "std/vec";
+        assert_eq!(resolve_relative(base, "https://www.rust-lang.org/"), None);
+        assert_eq!(resolve_relative(base, "mailto:someone@example.com"), None);
+        assert_eq!(resolve_relative(base, "//example.com/x"), None);
+        assert_eq!(
+            resolve_relative(base, "page#a:b").as_deref(),
+            Some("std/page#a:b")
+        );
+    }
+
+    #[test]
+    fn images_open_where_they_are() {
+        let base = "std/vec/struct.Vec";
+        assert_eq!(
+            image_url("rust", base, "https://example.com/a.png").as_deref(),
+            Some("https://example.com/a.png")
+        );
+        assert_eq!(
+            image_url("rust", base, "//example.com/a.png").as_deref(),
+            Some("https://example.com/a.png")
+        );
+        assert_eq!(
+            image_url("rust", base, "../images/a.png").as_deref(),
+            Some("https://devdocs.io/rust/std/images/a.png")
+        );
+        assert_eq!(image_url("rust", base, "data:image/png;base64,AAAA"), None);
+        assert_eq!(image_url("rust", base, ""), None);
+    }
+
+    #[test]
+    fn urls_are_canonical() {
+        assert_eq!(
+            canonical_url("rust", "std/vec/struct.Vec"),
+            "https://devdocs.io/rust/std/vec/struct.Vec"
+        );
+        assert_eq!(
+            canonical_url("rust", "/std//vec/../vec/struct.Vec/#method.push"),
+            "https://devdocs.io/rust/std/vec/struct.Vec#method.push"
+        );
+        assert_eq!(
+            canonical_url("css", "a b?c"),
+            "https://devdocs.io/css/a%20b"
+        );
+        assert_eq!(
+            canonical_url("dom", "x#a b"),
+            "https://devdocs.io/dom/x#a%20b"
+        );
+    }
+}
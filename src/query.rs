@@ -0,0 +1,388 @@
+use crate::search::resolve_scope;
+
+/// Split a `slug:` scope prefix off a query
+///
+/// `rust:hashmap` is scoped to `rust`, while `std::vec` is an unscoped query for a path and
+/// `type:function` starts with an operator of [`Query`].
+pub fn split_scope(query: &str) -> (Option<&str>, &str) {
+    if let Some((prefix, rest)) = query.split_once(':') {
+        let is_slug = !prefix.is_empty()
+            && !OPERATORS
+                .iter()
+                .any(|operator| prefix.eq_ignore_ascii_case(operator))
+            && prefix
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '~' | '.' | '_' | '-'));
+        if is_slug && !rest.starts_with(':') {
+            return (Some(prefix), rest);
+        }
+    }
+    (None, query)
+}
+
+/// Names of the operators a query can have, as in `type:function`
+const OPERATORS: &[&str] = &["type", "in", "exact"];
+
+/// Whether the case of letters counts when matching a query
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum CaseMode {
+    /// `hashmap` matches `HashMap`, and so does `HASHMAP`
+    #[default]
+    Insensitive,
+    /// Case counts only once the query has a capital letter
+    Smart,
+    /// `HashMap` matches nothing but `HashMap`
+    Sensitive,
+}
+
+impl CaseMode {
+    pub const ALL: [CaseMode; 3] = [CaseMode::Insensitive, CaseMode::Smart, CaseMode::Sensitive];
+
+    /// Name used for the mode in the config file
+    pub fn name(self) -> &'static str {
+        match self {
+            CaseMode::Insensitive => "insensitive",
+            CaseMode::Smart => "smart",
+            CaseMode::Sensitive => "sensitive",
+        }
+    }
+
+    /// How the mode is described in the UI
+    pub fn label(self) -> &'static str {
+        match self {
+            CaseMode::Insensitive => "ignoring case",
+            CaseMode::Smart => "smart case",
+            CaseMode::Sensitive => "case sensitive",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|mode| mode.name() == name)
+    }
+
+    /// The mode after this one, wrapping around
+    pub fn next(self) -> Self {
+        let i = Self::ALL.iter().position(|&mode| mode == self).unwrap_or(0);
+        Self::ALL[(i + 1) % Self::ALL.len()]
+    }
+}
+
+/// A query split into the text matched against entry names and what narrows the entries
+/// down
+///
+/// `rust:` searches only that doc set or family, `in:rust,python` searches several,
+/// `type:function` keeps entries of a type, and `"exact phrase"` or `exact:phrase` keeps
+/// entries whose names contain the phrase. Operators can be combined with each other and
+/// with the text. Whether case counts is left for the caller to set, e.g. from a toggle in
+/// the UI.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Query {
+    /// The words that aren't operators, joined by single spaces
+    pub text: String,
+    /// Doc set or family named by a `slug:` prefix, as written, which takes precedence over
+    /// `in:`
+    pub prefix: Option<String>,
+    /// Doc sets or families to search, from `in:`, as written
+    pub scopes: Vec<String>,
+    /// Lowercased parts of the entry types to keep, from `type:`
+    pub types: Vec<String>,
+    /// Phrases entry names have to contain, from quotes or `exact:`
+    pub phrases: Vec<String>,
+    pub case: CaseMode,
+    /// Most results to return, or `None` for the default, left for the caller to set like
+    /// the case mode
+    pub limit: Option<usize>,
+    /// What is wrong with the operators, which are left out rather than failing the search
+    pub errors: Vec<String>,
+}
+
+/// Parse what was typed in the search box, or given to `search`, into a query
+pub fn parse_query(input: &str) -> Query {
+    let (prefix, rest) = split_scope(input);
+    let mut query = Query {
+        prefix: prefix.map(str::to_owned),
+        ..Query::default()
+    };
+    let mut words = Vec::new();
+    for token in tokens(rest) {
+        let (operator, value) = match token.split_once(':') {
+            Some((operator, value)) if !token.starts_with('"') => {
+                (operator.to_ascii_lowercase(), value)
+            }
+            _ => (String::new(), token),
+        };
+        let values = || {
+            value
+                .split(',')
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+        };
+        match operator.as_str() {
+            "type" => {
+                query
+                    .types
+                    .extend(values().map(|value| value.to_lowercase()));
+                if values().next().is_none() {
+                    query
+                        .errors
+                        .push("type: needs an entry type, like type:function".to_owned());
+                }
+            }
+            "in" => {
+                query.scopes.extend(values().map(str::to_owned));
+                if values().next().is_none() {
+                    query
+                        .errors
+                        .push("in: needs doc sets, like in:rust,python".to_owned());
+                }
+            }
+            "exact" => match query.phrase(value) {
+                Some(phrase) => query.phrases.push(phrase),
+                None => query.errors.push("exact: needs text to match".to_owned()),
+            },
+            _ if token.starts_with('"') => {
+                if let Some(phrase) = query.phrase(token) {
+                    query.phrases.push(phrase);
+                }
+            }
+            _ => words.push(token),
+        }
+    }
+    query.text = words.join(" ");
+    query
+}
+
+impl Query {
+    /// A query of plain text, with anything looking like an operator matched as text
+    pub fn text(text: &str) -> Self {
+        Self {
+            text: text.split_whitespace().collect::<Vec<_>>().join(" "),
+            ..Self::default()
+        }
+    }
+
+    /// The text of a phrase, quoted or not, noting a quote left open
+    fn phrase(&mut self, phrase: &str) -> Option<String> {
+        let phrase = match phrase.strip_prefix('"') {
+            Some(quoted) => match quoted.strip_suffix('"') {
+                Some(quoted) => quoted,
+                None => {
+                    self.errors.push("a quote is not closed".to_owned());
+                    quoted
+                }
+            },
+            None => phrase,
+        };
+        let phrase = phrase.split_whitespace().collect::<Vec<_>>().join(" ");
+        (!phrase.is_empty()).then_some(phrase)
+    }
+
+    /// Whether there is nothing to search for, as without operators that keep entries
+    /// there would be every entry to list
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty() && self.phrases.is_empty() && self.types.is_empty()
+    }
+
+    /// Whether the case of letters counts, going by the case mode and, in smart case, by
+    /// whether the text or a phrase has a capital letter
+    pub fn case_sensitive(&self) -> bool {
+        match self.case {
+            CaseMode::Insensitive => false,
+            CaseMode::Sensitive => true,
+            CaseMode::Smart => std::iter::once(&self.text)
+                .chain(&self.phrases)
+                .any(|text| text.chars().any(char::is_uppercase)),
+        }
+    }
+
+    /// The doc sets or families the prefix or else `in:` names, resolved against the
+    /// installed doc sets, empty if the query names none
+    ///
+    /// The first one no installed doc set falls within is returned as the error.
+    pub fn resolve_scopes(&self, installed: &[String]) -> Result<Vec<String>, String> {
+        let scopes = match &self.prefix {
+            Some(prefix) => std::slice::from_ref(prefix),
+            None => self.scopes.as_slice(),
+        };
+        scopes
+            .iter()
+            .map(|scope| resolve_scope(scope, installed).ok_or_else(|| scope.clone()))
+            .collect()
+    }
+
+    /// The words searched in entry content, the phrases among them
+    pub fn full_text(&self) -> String {
+        let mut words = vec![self.text.as_str()];
+        words.extend(self.phrases.iter().map(String::as_str));
+        words.retain(|word| !word.is_empty());
+        words.join(" ")
+    }
+}
+
+/// The whitespace-separated tokens of a query, keeping quoted text together, including a
+/// quote after an operator as in `exact:"a b"`
+fn tokens(input: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut start = None;
+    let mut quoted = false;
+    for (i, c) in input.char_indices() {
+        match c {
+            '"' => {
+                start.get_or_insert(i);
+                quoted = !quoted;
+            }
+            c if c.is_whitespace() && !quoted => {
+                if let Some(start) = start.take() {
+                    tokens.push(&input[start..i]);
+                }
+            }
+            _ => {
+                start.get_or_insert(i);
+            }
+        }
+    }
+    if let Some(start) = start {
+        tokens.push(&input[start..]);
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_queries_are_all_text() {
+        let query = parse_query("  hash   map ");
+        assert_eq!(query.text, "hash map");
+        assert_eq!(query.prefix, None);
+        assert!(query.types.is_empty() && query.scopes.is_empty() && query.phrases.is_empty());
+        assert!(query.errors.is_empty());
+        assert_eq!(parse_query("std::vec").text, "std::vec");
+        assert_eq!(parse_query("::new").text, "::new");
+        assert!(parse_query("   ").is_empty());
+        assert_eq!(
+            Query::text("type:x  in:y"),
+            Query {
+                text: "type:x in:y".to_owned(),
+                ..Query::default()
+            }
+        );
+    }
+
+    #[test]
+    fn a_slug_prefix_scopes_the_query() {
+        let query = parse_query("rust:hash map");
+        assert_eq!(query.prefix.as_deref(), Some("rust"));
+        assert_eq!(query.text, "hash map");
+        assert_eq!(
+            parse_query("python~3.12:dict").prefix.as_deref(),
+            Some("python~3.12")
+        );
+        assert_eq!(split_scope("rust:"), (Some("rust"), ""));
+        assert_eq!(split_scope("std::vec"), (None, "std::vec"));
+        assert_eq!(split_scope("a b:c"), (None, "a b:c"));
+        assert_eq!(split_scope("type:method ins"), (None, "type:method ins"));
+        assert_eq!(split_scope("In:rust x"), (None, "In:rust x"));
+    }
+
+    #[test]
+    fn type_lists_entry_types() {
+        let query = parse_query("insert type:Method,function");
+        assert_eq!(query.text, "insert");
+        assert_eq!(query.types, ["method", "function"]);
+        assert_eq!(
+            parse_query("TYPE:struct type:trait").types,
+            ["struct", "trait"]
+        );
+        assert_eq!(parse_query("type:,struct,").types, ["struct"]);
+    }
+
+    #[test]
+    fn in_lists_doc_sets() {
+        let query = parse_query("in:rust,python insert");
+        assert_eq!(query.text, "insert");
+        assert_eq!(query.scopes, ["rust", "python"]);
+        let installed = vec!["rust".to_owned(), "javascript".to_owned()];
+        assert_eq!(
+            parse_query("in:js").resolve_scopes(&installed),
+            Ok(vec!["javascript".to_owned()])
+        );
+        assert_eq!(
+            parse_query("in:rust,go").resolve_scopes(&installed),
+            Err("go".to_owned())
+        );
+        assert_eq!(parse_query("x").resolve_scopes(&installed), Ok(Vec::new()));
+        // A prefix wins over in:, even one that names nothing installed
+        assert_eq!(
+            parse_query("rust:in:go x").resolve_scopes(&installed),
+            Ok(vec!["rust".to_owned()])
+        );
+    }
+
+    #[test]
+    fn phrases_keep_their_words_together() {
+        let query = parse_query(r#""hash  maps" explained"#);
+        assert_eq!(query.phrases, ["hash maps"]);
+        assert_eq!(query.text, "explained");
+        assert_eq!(
+            parse_query("exact:Map.prototype").phrases,
+            ["Map.prototype"]
+        );
+        assert_eq!(
+            parse_query(r#"exact:"maps explained" x"#).phrases,
+            ["maps explained"]
+        );
+        assert_eq!(parse_query(r#"a"b c"d"#).text, r#"a"b c"d"#);
+        assert!(parse_query(r#""" x"#).phrases.is_empty());
+        assert_eq!(parse_query(r#""hash" maps "#).full_text(), "maps hash");
+    }
+
+    #[test]
+    fn operators_combine() {
+        let query = parse_query(r#"rust:in:python type:method "hashmap" ins"#);
+        assert_eq!(query.prefix.as_deref(), Some("rust"));
+        assert_eq!(query.scopes, ["python"]);
+        assert_eq!(query.types, ["method"]);
+        assert_eq!(query.phrases, ["hashmap"]);
+        assert_eq!(query.text, "ins");
+        assert!(query.errors.is_empty());
+        assert!(!parse_query("type:method").is_empty());
+        assert!(parse_query("in:rust").is_empty());
+    }
+
+    #[test]
+    fn misused_operators_are_reported() {
+        let query = parse_query("type: in:, exact: insert");
+        assert_eq!(query.text, "insert");
+        assert_eq!(
+            query.errors,
+            [
+                "type: needs an entry type, like type:function",
+                "in: needs doc sets, like in:rust,python",
+                "exact: needs text to match",
+            ]
+        );
+        let query = parse_query(r#"map "hash ma"#);
+        assert_eq!(query.text, "map");
+        assert_eq!(query.phrases, ["hash ma"]);
+        assert_eq!(query.errors, ["a quote is not closed"]);
+        assert!(parse_query(r#""" x"#).errors.is_empty());
+    }
+
+    #[test]
+    fn smart_case_counts_case_once_there_is_a_capital() {
+        let with = |input: &str, case: CaseMode| Query {
+            case,
+            ..parse_query(input)
+        };
+        assert!(!with("HashMap", CaseMode::Insensitive).case_sensitive());
+        assert!(with("hashmap", CaseMode::Sensitive).case_sensitive());
+        assert!(!with("hashmap", CaseMode::Smart).case_sensitive());
+        assert!(with("hashMap", CaseMode::Smart).case_sensitive());
+        assert!(with(r#"x "Map" type:Struct"#, CaseMode::Smart).case_sensitive());
+        assert!(!with("x type:Struct", CaseMode::Smart).case_sensitive());
+        assert_eq!(CaseMode::Sensitive.next(), CaseMode::Insensitive);
+        assert_eq!(CaseMode::from_name("smart"), Some(CaseMode::Smart));
+    }
+}
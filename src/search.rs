@@ -0,0 +1,1037 @@
+use crate::docset_manager::doc_family;
+use crate::index::{Index, IndexEntry};
+use crate::query::Query;
+use regex::{Regex, RegexBuilder};
+use std::cmp::{Ordering, Reverse};
+use std::collections::{HashMap, HashSet};
+
+/// Upper bound on the number of results a search returns, unless the query sets its own
+pub const MAX_RESULTS: usize = 200;
+
+/// An index entry that matched a query
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchResult {
+    pub entry: IndexEntry,
+    /// How well the entry matched, higher is better
+    pub score: i64,
+    /// Slugs of the other versions of the doc family that have the same entry, once merged
+    /// into this result
+    pub other_versions: Vec<String>,
+    /// Text around the match, for results of a full-text search
+    pub snippet: Option<String>,
+}
+
+/// Common short names for doc families, mapped to their devdocs slugs
+const SCOPE_ALIASES: &[(&str, &str)] = &[
+    ("js", "javascript"),
+    ("ts", "typescript"),
+    ("py", "python"),
+    ("rb", "ruby"),
+    ("rs", "rust"),
+    ("golang", "go"),
+    ("k8s", "kubernetes"),
+    ("pg", "postgresql"),
+    ("sh", "bash"),
+];
+
+/// Resolve the scope named by a `slug:` prefix against the installed doc sets
+///
+/// Matching ignores case and accepts common aliases such as `js` for `javascript`. Returns
+/// `None` if no installed doc set falls within the scope.
+pub fn resolve_scope(prefix: &str, installed: &[String]) -> Option<String> {
+    let scope = expand_alias(prefix);
+    installed
+        .iter()
+        .any(|slug| in_scope(&slug.to_lowercase(), &scope))
+        .then_some(scope)
+}
+
+/// The lowercased doc family a short name such as `js` stands for, or the name itself
+pub fn expand_alias(name: &str) -> String {
+    let name = name.to_lowercase();
+    SCOPE_ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == name)
+        .map_or(name, |(_, slug)| (*slug).to_owned())
+}
+
+/// Whether an installed doc set slug falls within a scope
+///
+/// A scope names either an exact slug or a doc family, so `python` covers `python~3.12`.
+pub fn in_scope(slug: &str, scope: &str) -> bool {
+    slug == scope
+        || slug
+            .strip_prefix(scope)
+            .is_some_and(|version| version.starts_with('~'))
+}
+
+/// A hint for a doc set slug that names nothing known: the closest known slug if one is
+/// close enough to be a typo of it, or else a few of the known slugs
+///
+/// `known` lists the slugs to choose from, e.g. those of the registry or the installed doc
+/// sets. Returns `None` if there are none.
+pub fn slug_hint(unknown: &str, known: &[String]) -> Option<String> {
+    if let Some(closest) = closest_slug(unknown, known) {
+        return Some(format!("did you mean '{closest}'?"));
+    }
+    let mut known: Vec<&str> = known.iter().map(String::as_str).collect();
+    known.sort_unstable();
+    known.dedup();
+    match known.len() {
+        0 => None,
+        count if count <= SLUGS_LISTED => Some(format!("available: {}", known.join(", "))),
+        count => Some(format!(
+            "available: {} and {} more",
+            known[..SLUGS_LISTED].join(", "),
+            count - SLUGS_LISTED
+        )),
+    }
+}
+
+/// Known slugs listed when none is close to an unknown one
+const SLUGS_LISTED: usize = 5;
+
+/// The known slug or doc family an unknown slug is most likely a typo of
+///
+/// Candidates at most a third of their length in edits away are close enough, and the
+/// fuzzy matcher breaks ties between equally close ones.
+pub fn closest_slug<'a>(unknown: &str, known: &'a [String]) -> Option<&'a str> {
+    let unknown = unknown.to_lowercase();
+    known
+        .iter()
+        .flat_map(|slug| [slug.as_str(), doc_family(slug)])
+        .filter_map(|candidate| {
+            let distance = edit_distance(&unknown, &candidate.to_lowercase());
+            let limit = (candidate.chars().count() / 3).max(1);
+            (distance <= limit).then(|| {
+                let score = fuzzy_score(&unknown, candidate).unwrap_or(i64::MIN);
+                ((distance, Reverse(score), candidate.len()), candidate)
+            })
+        })
+        .min()
+        .map(|(_, candidate)| candidate)
+}
+
+/// Edits turning one string into the other, counting swapped neighbours as one edit
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut rows = vec![(0..=b.len()).collect::<Vec<usize>>()];
+    for i in 1..=a.len() {
+        let mut row = vec![i; b.len() + 1];
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            row[j] = (rows[i - 1][j] + 1)
+                .min(row[j - 1] + 1)
+                .min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                row[j] = row[j].min(rows[i - 2][j - 2] + 1);
+            }
+        }
+        rows.push(row);
+    }
+    rows[a.len()][b.len()]
+}
+
+/// A way of matching entry names against a query's text
+///
+/// Every backend narrows the entries down by the query's operators the same way; they only
+/// differ in how the text matches and how matches rank.
+pub trait Searcher: Send + Sync {
+    /// Search the index for entries whose name matches the query's text and that pass its
+    /// operators, best match first
+    fn search(&self, query: &Query, index: &Index) -> Vec<SearchResult> {
+        self.search_in(query, index, &[])
+    }
+
+    /// [`Searcher::search`] within a scope besides the query's own, where an empty `scope`
+    /// searches every doc set in the index
+    fn search_in(&self, query: &Query, index: &Index, scope: &[String]) -> Vec<SearchResult> {
+        self.search_until(query, index, scope, &|| false)
+            .unwrap_or_default()
+    }
+
+    /// [`Searcher::search_in`], giving up with `None` as soon as `cancelled` returns true,
+    /// e.g. because a newer query superseded this one
+    fn search_until(
+        &self,
+        query: &Query,
+        index: &Index,
+        scope: &[String],
+        cancelled: &dyn Fn() -> bool,
+    ) -> Option<Vec<SearchResult>>;
+
+    /// Why the query's text can't be searched for, e.g. because it isn't a valid regex
+    fn check(&self, query: &Query) -> Result<(), String> {
+        let _ = query;
+        Ok(())
+    }
+}
+
+/// Matches names that have the query's characters in order, the default
+///
+/// A query of operators alone ranks the entries by their first phrase, if they have one.
+pub struct Fuzzy;
+
+impl Searcher for Fuzzy {
+    fn search_until(
+        &self,
+        query: &Query,
+        index: &Index,
+        scope: &[String],
+        cancelled: &dyn Fn() -> bool,
+    ) -> Option<Vec<SearchResult>> {
+        let text = ranked_text(query);
+        let sensitive = query.case_sensitive();
+        collect(query, index, scope, cancelled, |name| {
+            if sensitive && !in_order(text, name) {
+                return None;
+            }
+            fuzzy_score(text, name)
+        })
+    }
+}
+
+/// Matches names that contain the query's text as it is
+pub struct Substring;
+
+impl Searcher for Substring {
+    fn search_until(
+        &self,
+        query: &Query,
+        index: &Index,
+        scope: &[String],
+        cancelled: &dyn Fn() -> bool,
+    ) -> Option<Vec<SearchResult>> {
+        let sensitive = query.case_sensitive();
+        let fold = |text: &str| {
+            if sensitive {
+                text.to_owned()
+            } else {
+                text.to_lowercase()
+            }
+        };
+        let text = fold(ranked_text(query));
+        collect(query, index, scope, cancelled, |name| {
+            let name = fold(name);
+            let start = name.find(&text)?;
+            let bonus = if name == text {
+                100
+            } else if start == 0 {
+                50
+            } else {
+                25
+            };
+            Some(bonus - start.min(20) as i64)
+        })
+    }
+}
+
+/// Matches names against the query's text as a regular expression
+pub struct RegexSearcher;
+
+impl RegexSearcher {
+    /// The query's text as a regular expression, `None` if it has no text
+    fn regex(query: &Query) -> Result<Option<Regex>, String> {
+        if query.text.is_empty() {
+            return Ok(None);
+        }
+        RegexBuilder::new(&query.text)
+            .case_insensitive(!query.case_sensitive())
+            .build()
+            .map(Some)
+            .map_err(|e| match e {
+                regex::Error::Syntax(_) => "invalid regex".to_owned(),
+                e => format!("invalid regex: {e}"),
+            })
+    }
+}
+
+impl Searcher for RegexSearcher {
+    fn search_until(
+        &self,
+        query: &Query,
+        index: &Index,
+        scope: &[String],
+        cancelled: &dyn Fn() -> bool,
+    ) -> Option<Vec<SearchResult>> {
+        let Ok(regex) = Self::regex(query) else {
+            return Some(Vec::new());
+        };
+        collect(query, index, scope, cancelled, |name| match &regex {
+            // Earlier matches in shorter names first, like fuzzy matches
+            Some(regex) => regex
+                .find(name)
+                .map(|found| -((found.start() + name.len()) as i64)),
+            None => Some(0),
+        })
+    }
+
+    fn check(&self, query: &Query) -> Result<(), String> {
+        Self::regex(query).map(|_| ())
+    }
+}
+
+/// The search backends users can pick from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Matcher {
+    #[default]
+    Fuzzy,
+    Substring,
+    Regex,
+}
+
+impl Matcher {
+    pub const ALL: [Matcher; 3] = [Matcher::Fuzzy, Matcher::Substring, Matcher::Regex];
+
+    /// Name used for the backend in the config file, which is also how the UI shows it
+    pub fn name(self) -> &'static str {
+        match self {
+            Matcher::Fuzzy => "fuzzy",
+            Matcher::Substring => "substring",
+            Matcher::Regex => "regex",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|matcher| matcher.name() == name)
+    }
+
+    /// The backend after this one, wrapping around
+    pub fn next(self) -> Self {
+        let i = Self::ALL
+            .iter()
+            .position(|&matcher| matcher == self)
+            .unwrap_or(0);
+        Self::ALL[(i + 1) % Self::ALL.len()]
+    }
+
+    pub fn searcher(self) -> &'static dyn Searcher {
+        match self {
+            Matcher::Fuzzy => &Fuzzy,
+            Matcher::Substring => &Substring,
+            Matcher::Regex => &RegexSearcher,
+        }
+    }
+}
+
+/// Entries searched between checks whether a search was cancelled
+const CANCEL_CHECK_ENTRIES: usize = 4096;
+
+/// How much lower a match of an entry's short name scores than the same match of its full
+/// name, so that `map` lists `Iterator::map` high but below an entry named `map`
+const SHORT_NAME_PENALTY: i64 = 25;
+
+/// The entries in scope that pass the query's operators and whose names `score`, best
+/// first, or `None` if the search was cancelled
+///
+/// An entry scores the better of its full name and its short name, less
+/// [`SHORT_NAME_PENALTY`].
+fn collect(
+    query: &Query,
+    index: &Index,
+    scope: &[String],
+    cancelled: &dyn Fn() -> bool,
+    score: impl Fn(&str) -> Option<i64>,
+) -> Option<Vec<SearchResult>> {
+    if query.is_empty() {
+        return Some(Vec::new());
+    }
+    let mut results = Vec::new();
+    for chunk in index.entries.chunks(CANCEL_CHECK_ENTRIES) {
+        if cancelled() {
+            return None;
+        }
+        results.extend(
+            chunk
+                .iter()
+                .filter(|entry| scope.is_empty() || scope.iter().any(|s| in_scope(&entry.slug, s)))
+                .filter(|entry| admits(query, entry))
+                .filter_map(|entry| {
+                    let short = entry.short_name.as_deref().and_then(&score);
+                    let score = [score(&entry.name), short.map(|s| s - SHORT_NAME_PENALTY)]
+                        .into_iter()
+                        .flatten()
+                        .max()?;
+                    Some(SearchResult {
+                        score,
+                        entry: entry.clone(),
+                        other_versions: Vec::new(),
+                        snippet: None,
+                    })
+                }),
+        );
+    }
+
+    sort_results(&mut results, SortMode::Relevance, &[]);
+    results.truncate(query.limit.unwrap_or(MAX_RESULTS));
+    Some(results)
+}
+
+/// The text results rank by: the query's text, or its first phrase if it has only phrases
+fn ranked_text(query: &Query) -> &str {
+    match query.phrases.first() {
+        Some(phrase) if query.text.is_empty() => phrase,
+        _ => &query.text,
+    }
+}
+
+/// Whether an entry passes a query's scope, `type:` and phrases, leaving its text aside
+fn admits(query: &Query, entry: &IndexEntry) -> bool {
+    let contains = |phrase: &String| {
+        if query.case_sensitive() {
+            entry.name.contains(phrase.as_str())
+        } else {
+            entry.name.to_lowercase().contains(&phrase.to_lowercase())
+        }
+    };
+    let scopes = match &query.prefix {
+        Some(prefix) => std::slice::from_ref(prefix),
+        None => query.scopes.as_slice(),
+    };
+    admits_type(query, &entry.type_name)
+        && (scopes.is_empty()
+            || scopes
+                .iter()
+                .any(|scope| in_scope(&entry.slug, &expand_alias(scope))))
+        && query.phrases.iter().all(contains)
+}
+
+/// Whether an entry type is one a query's `type:` keeps, as any type is without `type:`
+pub fn admits_type(query: &Query, type_name: &str) -> bool {
+    let type_name = type_name.to_lowercase();
+    query.types.is_empty() || query.types.iter().any(|t| type_name.contains(t.as_str()))
+}
+
+/// Whether the characters of `text` appear in `name` in the same order, case included
+fn in_order(text: &str, name: &str) -> bool {
+    let mut name = name.chars();
+    text.chars().all(|c| name.any(|n| n == c))
+}
+
+/// Orders search results can be listed in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum SortMode {
+    /// Best match first
+    #[default]
+    Relevance,
+    /// Alphabetically by entry name
+    Name,
+    /// Grouped by doc set, best match first within each
+    DocSet,
+    /// Grouped by entry type, best match first within each
+    Type,
+}
+
+impl SortMode {
+    pub const ALL: [SortMode; 4] = [
+        SortMode::Relevance,
+        SortMode::Name,
+        SortMode::DocSet,
+        SortMode::Type,
+    ];
+
+    /// Name used for the mode in the config file
+    pub fn name(self) -> &'static str {
+        match self {
+            SortMode::Relevance => "relevance",
+            SortMode::Name => "name",
+            SortMode::DocSet => "doc_set",
+            SortMode::Type => "type",
+        }
+    }
+
+    /// How the mode is described in the UI
+    pub fn label(self) -> &'static str {
+        match self {
+            SortMode::Relevance => "relevance",
+            SortMode::Name => "name",
+            SortMode::DocSet => "doc set",
+            SortMode::Type => "type",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|mode| mode.name() == name)
+    }
+
+    /// The mode after this one, wrapping around
+    pub fn next(self) -> Self {
+        let i = Self::ALL
+            .iter()
+            .position(|&mode| mode == self)
+            .unwrap_or_default();
+        Self::ALL[(i + 1) % Self::ALL.len()]
+    }
+}
+
+/// Reorder results without matching them again
+///
+/// Results that match equally well are ordered by `priority`, a list of preferred doc sets
+/// (or families), ahead of doc sets it doesn't list. Every mode breaks remaining ties down
+/// to the entry path, so the order is always deterministic.
+pub fn sort_results(results: &mut [SearchResult], mode: SortMode, priority: &[String]) {
+    let rank = |slug: &str| {
+        priority
+            .iter()
+            .position(|scope| in_scope(slug, scope))
+            .unwrap_or(priority.len())
+    };
+    let relevance = |result: &SearchResult| {
+        (
+            Reverse(result.score),
+            rank(&result.entry.slug),
+            result.entry.name.len(),
+            result.entry.name.clone(),
+            result.entry.slug.clone(),
+            result.entry.path.clone(),
+        )
+    };
+    match mode {
+        SortMode::Relevance => results.sort_by_cached_key(relevance),
+        SortMode::Name => results.sort_by_cached_key(|result| {
+            (
+                result.entry.name.to_lowercase(),
+                result.entry.slug.clone(),
+                result.entry.path.clone(),
+            )
+        }),
+        SortMode::DocSet => results.sort_by_cached_key(|result| {
+            (
+                rank(&result.entry.slug),
+                result.entry.slug.clone(),
+                relevance(result),
+            )
+        }),
+        SortMode::Type => {
+            results.sort_by_cached_key(|result| (result.entry.type_name.clone(), relevance(result)))
+        }
+    }
+}
+
+/// Keep one result for an entry that several versions of a doc family have, from the newest
+/// of them, where the first of them was
+///
+/// Entries are the same when they have the same name and path. The merged result lists the
+/// versions it stands in for.
+pub fn merge_versions(results: &mut Vec<SearchResult>) {
+    let mut kept: HashMap<(String, String, String), usize> = HashMap::new();
+    let mut merged: Vec<SearchResult> = Vec::with_capacity(results.len());
+    for result in results.drain(..) {
+        let key = (
+            doc_family(&result.entry.slug).to_owned(),
+            result.entry.name.clone(),
+            result.entry.path.clone(),
+        );
+        let Some(&i) = kept.get(&key) else {
+            kept.insert(key, merged.len());
+            merged.push(result);
+            continue;
+        };
+        let existing = &mut merged[i];
+        if compare_versions(&result.entry.slug, &existing.entry.slug) == Ordering::Greater {
+            let older = std::mem::replace(&mut existing.entry, result.entry);
+            existing.other_versions.push(older.slug);
+        } else {
+            existing.other_versions.push(result.entry.slug);
+        }
+    }
+    *results = merged;
+}
+
+/// Order the slugs of two versions of a doc family, e.g. `python~3.9` before `python~3.12`
+///
+/// Numbers in versions compare as numbers, and a slug without a version, which devdocs uses
+/// for the latest one, comes after every versioned one.
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    fn parts(slug: &str) -> Option<Vec<(u64, String)>> {
+        let (_, version) = slug.split_once('~')?;
+        Some(
+            version
+                .split(['.', '_', '-'])
+                .map(|part| {
+                    let digits =
+                        part.len() - part.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+                    (
+                        part[..digits].parse().unwrap_or_default(),
+                        part[digits..].to_owned(),
+                    )
+                })
+                .collect(),
+        )
+    }
+    match (parts(a), parts(b)) {
+        (None, None) => Ordering::Equal,
+        (None, Some(_)) => Ordering::Greater,
+        (Some(_), None) => Ordering::Less,
+        (Some(a), Some(b)) => a.cmp(&b),
+    }
+}
+
+/// A row of the result list
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResultRow {
+    /// The result at this position of the results
+    Result(usize),
+    /// Stands in for the results of a doc set beyond its group's cap
+    More { slug: String, hidden: usize },
+}
+
+/// Lay out results sorted by doc set so that each doc set shows at most `max_per_group` of
+/// them, unless it is in `expanded`
+///
+/// A `max_per_group` of 0 shows every result.
+pub fn group_rows(
+    results: &[SearchResult],
+    max_per_group: usize,
+    expanded: &HashSet<String>,
+) -> Vec<ResultRow> {
+    let mut rows = Vec::with_capacity(results.len());
+    let mut start = 0;
+    for group in results.chunk_by(|a, b| a.entry.slug == b.entry.slug) {
+        let slug = &group[0].entry.slug;
+        let shown = if max_per_group == 0 || expanded.contains(slug) {
+            group.len()
+        } else {
+            group.len().min(max_per_group)
+        };
+        rows.extend((start..start + shown).map(ResultRow::Result));
+        if shown < group.len() {
+            rows.push(ResultRow::More {
+                slug: slug.clone(),
+                hidden: group.len() - shown,
+            });
+        }
+        start += group.len();
+    }
+    rows
+}
+
+/// Score how well `query` matches `candidate`, or `None` if it doesn't match at all
+///
+/// Every query character must appear in the candidate in order, ignoring case. Matches score
+/// higher when they are exact, a prefix or a substring, and when matched characters are
+/// consecutive or start words.
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<i64> {
+    let query: Vec<char> = query.chars().flat_map(char::to_lowercase).collect();
+    let original: Vec<char> = candidate.chars().collect();
+    let lower: Vec<char> = candidate.chars().flat_map(char::to_lowercase).collect();
+    if query.is_empty() {
+        return Some(0);
+    }
+    // Lowercasing can change the length of some characters, which would misalign the two
+    let original = if original.len() == lower.len() {
+        original
+    } else {
+        lower.clone()
+    };
+
+    let mut score = 0;
+    let mut query_pos = 0;
+    let mut previous_match: Option<usize> = None;
+    let mut first_match = None;
+
+    for (i, &c) in lower.iter().enumerate() {
+        if query_pos == query.len() {
+            break;
+        }
+        if c != query[query_pos] {
+            continue;
+        }
+
+        score += 1;
+        if previous_match.is_some_and(|previous| previous + 1 == i) {
+            score += 5;
+        }
+        let word_start = i == 0
+            || !original[i - 1].is_alphanumeric()
+            || (original[i - 1].is_lowercase() && original[i].is_uppercase());
+        if word_start {
+            score += 8;
+        }
+
+        first_match.get_or_insert(i);
+        previous_match = Some(i);
+        query_pos += 1;
+    }
+    if query_pos < query.len() {
+        return None;
+    }
+
+    let query: String = query.into_iter().collect();
+    let lower: String = lower.into_iter().collect();
+    if lower == query {
+        score += 100;
+    } else if lower.starts_with(&query) {
+        score += 50;
+    } else if lower.contains(&query) {
+        score += 25;
+    }
+    // Prefer matches that start early
+    score -= first_match.unwrap_or_default().min(20) as i64;
+
+    Some(score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index::short_name;
+    use crate::query::{parse_query, CaseMode};
+
+    fn index() -> Index {
+        let entry = |slug: &str, name: &str, type_name: &str| IndexEntry {
+            slug: slug.to_owned(),
+            name: name.to_owned(),
+            path: name.to_lowercase().replace(' ', "-"),
+            type_name: type_name.to_owned(),
+            short_name: short_name(name),
+        };
+        let mut index = Index::default();
+        index.entries = vec![
+            entry("rust", "HashMap::insert", "Method"),
+            entry("rust", "HashMap", "Struct"),
+            entry("python~3.12", "dict.insert", "Built-in Functions"),
+            entry("python~3.12", "Hash maps explained", "Tutorial"),
+            entry("javascript", "Map.prototype.set", "Function"),
+        ];
+        index
+    }
+
+    fn names(query: &Query) -> Vec<String> {
+        names_with(&Fuzzy, query)
+    }
+
+    fn names_with(searcher: &dyn Searcher, query: &Query) -> Vec<String> {
+        let mut names: Vec<String> = searcher
+            .search(query, &index())
+            .into_iter()
+            .map(|result| result.entry.name)
+            .collect();
+        names.sort_unstable();
+        names
+    }
+
+    #[test]
+    fn each_sort_mode_orders_results_the_same_whatever_order_they_came_in() {
+        let scores = [50, 80, 50, 80, 10];
+        let results: Vec<SearchResult> = index()
+            .entries
+            .into_iter()
+            .zip(scores)
+            .map(|(entry, score)| SearchResult {
+                entry,
+                score,
+                other_versions: Vec::new(),
+                snippet: None,
+            })
+            .collect();
+        let sorted = |mode: SortMode, results: &[SearchResult]| {
+            let mut results = results.to_vec();
+            sort_results(&mut results, mode, &[]);
+            results
+                .into_iter()
+                .map(|result| result.entry.name)
+                .collect::<Vec<_>>()
+        };
+        let expected = [
+            (
+                SortMode::Relevance,
+                [
+                    "HashMap",
+                    "Hash maps explained",
+                    "dict.insert",
+                    "HashMap::insert",
+                    "Map.prototype.set",
+                ],
+            ),
+            (
+                SortMode::Name,
+                [
+                    "dict.insert",
+                    "Hash maps explained",
+                    "HashMap",
+                    "HashMap::insert",
+                    "Map.prototype.set",
+                ],
+            ),
+            (
+                SortMode::DocSet,
+                [
+                    "Map.prototype.set",
+                    "Hash maps explained",
+                    "dict.insert",
+                    "HashMap",
+                    "HashMap::insert",
+                ],
+            ),
+            (
+                SortMode::Type,
+                [
+                    "dict.insert",
+                    "Map.prototype.set",
+                    "HashMap::insert",
+                    "HashMap",
+                    "Hash maps explained",
+                ],
+            ),
+        ];
+        let mut reversed = results.clone();
+        reversed.reverse();
+        let mut rotated = results.clone();
+        rotated.rotate_left(2);
+        for (mode, names) in expected {
+            for results in [&results, &reversed, &rotated] {
+                assert_eq!(sorted(mode, results), names, "sorting by {}", mode.name());
+            }
+        }
+    }
+
+    #[test]
+    fn type_keeps_entries_of_a_type() {
+        assert_eq!(
+            names(&parse_query("insert type:Method,function")),
+            ["HashMap::insert", "dict.insert"]
+        );
+        assert_eq!(names(&parse_query("type:struct")), ["HashMap"]);
+    }
+
+    #[test]
+    fn in_searches_several_doc_sets() {
+        assert_eq!(
+            names(&parse_query("in:rust,python insert")),
+            ["HashMap::insert", "dict.insert"]
+        );
+        assert_eq!(names(&parse_query("in:js map")), ["Map.prototype.set"]);
+    }
+
+    #[test]
+    fn phrases_match_exactly() {
+        assert_eq!(
+            names(&parse_query(r#""hash maps" explained"#)),
+            ["Hash maps explained"]
+        );
+        // Fuzzily, `hsmap` matches every hash map, but as a phrase it matches none
+        assert_eq!(
+            names(&parse_query("hsmap")),
+            ["Hash maps explained", "HashMap", "HashMap::insert"]
+        );
+        assert!(names(&parse_query("exact:hsmap")).is_empty());
+        assert_eq!(
+            names(&parse_query("exact:hashmap")),
+            ["HashMap", "HashMap::insert"]
+        );
+    }
+
+    #[test]
+    fn operators_combine() {
+        let query = parse_query(r#"in:rust type:method "hashmap" ins"#);
+        assert_eq!(names(&query), ["HashMap::insert"]);
+        assert!(Fuzzy
+            .search_in(&query, &index(), &["javascript".to_owned()])
+            .is_empty());
+        assert!(names(&parse_query("in:rust")).is_empty());
+    }
+
+    #[test]
+    fn case_counts_when_the_query_says_so() {
+        let with = |input: &str, case: CaseMode| Query {
+            case,
+            ..parse_query(input)
+        };
+        assert_eq!(
+            names(&with("hash", CaseMode::Insensitive)),
+            ["Hash maps explained", "HashMap", "HashMap::insert"]
+        );
+        assert!(names(&with("hashmap", CaseMode::Sensitive)).is_empty());
+        assert_eq!(
+            names(&with("HM", CaseMode::Sensitive)),
+            ["HashMap", "HashMap::insert"]
+        );
+        assert_eq!(
+            names(&with("Hash m", CaseMode::Smart)),
+            ["Hash maps explained"]
+        );
+        assert!(names(&with(r#""MAP""#, CaseMode::Smart)).is_empty());
+    }
+
+    #[test]
+    fn short_names_match_below_full_names() {
+        let entry = |name: &str| IndexEntry {
+            slug: "rust".to_owned(),
+            name: name.to_owned(),
+            path: name.to_lowercase(),
+            type_name: "Method".to_owned(),
+            short_name: short_name(name),
+        };
+        let mut index = Index::default();
+        index.entries = [
+            "mapping",
+            "Iterator::map",
+            "map",
+            "HashMap",
+            "Option::map_or",
+        ]
+        .map(entry)
+        .to_vec();
+        for searcher in [&Fuzzy as &dyn Searcher, &Substring] {
+            let names: Vec<String> = searcher
+                .search(&parse_query("map"), &index)
+                .into_iter()
+                .map(|result| result.entry.name)
+                .collect();
+            assert_eq!(names[..3], ["map", "Iterator::map", "mapping"]);
+        }
+
+        // A qualified query still finds the full name best
+        let results = Fuzzy.search(&parse_query("iterator::map"), &index);
+        assert_eq!(results[0].entry.name, "Iterator::map");
+    }
+
+    #[test]
+    fn substrings_match_as_typed() {
+        assert_eq!(
+            names_with(&Substring, &parse_query("map")),
+            [
+                "Hash maps explained",
+                "HashMap",
+                "HashMap::insert",
+                "Map.prototype.set"
+            ]
+        );
+        assert!(names_with(&Substring, &parse_query("hsmap")).is_empty());
+        let results = Substring.search(&parse_query("hashmap"), &index());
+        assert_eq!(results[0].entry.name, "HashMap");
+        let sensitive = Query {
+            case: CaseMode::Sensitive,
+            ..parse_query("Map.")
+        };
+        assert_eq!(names_with(&Substring, &sensitive), ["Map.prototype.set"]);
+    }
+
+    #[test]
+    fn regexes_match_anywhere_in_names() {
+        assert_eq!(
+            names_with(&RegexSearcher, &parse_query(r"insert$")),
+            ["HashMap::insert", "dict.insert"]
+        );
+        assert_eq!(
+            names_with(&RegexSearcher, &parse_query(r"^map\.")),
+            ["Map.prototype.set"]
+        );
+        assert_eq!(
+            names_with(&RegexSearcher, &parse_query("^h.*p$ type:struct")),
+            ["HashMap"]
+        );
+        assert!(RegexSearcher.check(&parse_query("(")).is_err());
+        assert!(names_with(&RegexSearcher, &parse_query("(")).is_empty());
+        assert_eq!(Fuzzy.check(&parse_query("(")), Ok(()));
+    }
+
+    #[test]
+    fn every_backend_narrows_entries_down_the_same_way() {
+        for matcher in Matcher::ALL {
+            let searcher = matcher.searcher();
+            assert_eq!(
+                names_with(searcher, &parse_query("in:python type:function insert")),
+                ["dict.insert"],
+                "{}",
+                matcher.name()
+            );
+            assert_eq!(
+                names_with(searcher, &parse_query("rust:in:python hashmap")),
+                ["HashMap", "HashMap::insert"],
+                "{}",
+                matcher.name()
+            );
+            assert!(searcher.search(&parse_query(""), &index()).is_empty());
+        }
+        assert_eq!(Matcher::default(), Matcher::Fuzzy);
+        assert_eq!(Matcher::from_name("regex"), Some(Matcher::Regex));
+    }
+
+    #[test]
+    fn prefixes_resolve_to_installed_doc_sets_whatever_their_case() {
+        let installed: Vec<String> = ["javascript", "rust", "python~3.12"]
+            .map(str::to_owned)
+            .to_vec();
+        assert_eq!(expand_alias("JS"), "javascript");
+        assert_eq!(expand_alias("Rust"), "rust");
+        assert_eq!(expand_alias("Nope"), "nope");
+
+        let resolve = |prefix| resolve_scope(prefix, &installed);
+        assert_eq!(resolve("JS"), Some("javascript".to_owned()));
+        assert_eq!(resolve("Rust"), Some("rust".to_owned()));
+        assert_eq!(resolve("PY"), Some("python".to_owned()));
+        assert_eq!(resolve("Nope"), None);
+        // An alias of a doc set that isn't installed resolves to nothing either
+        assert_eq!(resolve("ts"), None);
+
+        assert_eq!(
+            parse_query("JS:map").resolve_scopes(&installed),
+            Ok(vec!["javascript".to_owned()])
+        );
+        assert_eq!(
+            parse_query("Rust:insert").resolve_scopes(&installed),
+            Ok(vec!["rust".to_owned()])
+        );
+        assert_eq!(
+            parse_query("Nope:insert").resolve_scopes(&installed),
+            Err("Nope".to_owned())
+        );
+    }
+
+    #[test]
+    fn equally_good_results_follow_the_doc_set_order() {
+        let result = |slug: &str, score| SearchResult {
+            entry: IndexEntry {
+                slug: slug.to_owned(),
+                name: "insert".to_owned(),
+                path: "insert".to_owned(),
+                type_name: "Method".to_owned(),
+                short_name: short_name("insert"),
+            },
+            score,
+            other_versions: Vec::new(),
+            snippet: None,
+        };
+        let slugs = |mode, priority: &[&str], results: &[SearchResult]| {
+            let priority: Vec<String> = priority.iter().map(|&slug| slug.to_owned()).collect();
+            let mut results = results.to_vec();
+            sort_results(&mut results, mode, &priority);
+            results
+                .into_iter()
+                .map(|result| result.entry.slug)
+                .collect::<Vec<_>>()
+        };
+        let results = [
+            result("rust", 50),
+            result("javascript", 50),
+            result("python~3.12", 50),
+            result("go", 50),
+        ];
+
+        // A family covers its versions, and unlisted doc sets come after the listed ones
+        let order = ["python", "javascript"];
+        assert_eq!(
+            slugs(SortMode::Relevance, &order, &results),
+            ["python~3.12", "javascript", "go", "rust"]
+        );
+        assert_eq!(
+            slugs(SortMode::Relevance, &["rust"], &results),
+            ["rust", "go", "javascript", "python~3.12"]
+        );
+        assert_eq!(
+            slugs(SortMode::DocSet, &order, &results),
+            ["python~3.12", "javascript", "go", "rust"]
+        );
+
+        // A better match still comes first
+        let mut results = results.to_vec();
+        results[0].score = 80;
+        assert_eq!(
+            slugs(SortMode::Relevance, &order, &results),
+            ["rust", "python~3.12", "javascript", "go"]
+        );
+    }
+}
@@ -0,0 +1,150 @@
+use crate::storage::{read_versioned, write_versioned, Migration};
+use serde_json::{json, Value};
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Upgrades of the usage stats; they were stamped from the start
+const MIGRATIONS: &[Migration] = &[Some];
+
+/// How often an entry was opened
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntryCount {
+    pub slug: String,
+    pub path: String,
+    /// Title of the entry when it was last opened
+    pub title: String,
+    pub count: u64,
+}
+
+impl EntryCount {
+    fn to_json(&self) -> Value {
+        json!({
+            "slug": self.slug,
+            "path": self.path,
+            "title": self.title,
+            "count": self.count,
+        })
+    }
+
+    fn from_json(value: &Value) -> Option<Self> {
+        Some(Self {
+            slug: value.get("slug")?.as_str()?.to_owned(),
+            path: value.get("path")?.as_str()?.to_owned(),
+            title: value.get("title")?.as_str()?.to_owned(),
+            count: value.get("count")?.as_u64()?,
+        })
+    }
+}
+
+/// How much each entry and doc set was used, kept on this machine only
+#[derive(Debug, Clone, Default)]
+pub struct UsageStats {
+    /// Entries by slug and path
+    entries: BTreeMap<(String, String), EntryCount>,
+    /// Entries opened per doc set, by slug
+    doc_sets: BTreeMap<String, u64>,
+    searches: u64,
+}
+
+impl UsageStats {
+    /// Load the stats from disk, starting from zero if they have never been saved
+    pub fn load(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut stats = Self::default();
+        let Some(value) = read_versioned(path, MIGRATIONS)? else {
+            return Ok(stats);
+        };
+        if let Some(entries) = value.get("entries").and_then(Value::as_array) {
+            stats.entries = entries
+                .iter()
+                .filter_map(EntryCount::from_json)
+                .map(|entry| ((entry.slug.clone(), entry.path.clone()), entry))
+                .collect();
+        }
+        if let Some(doc_sets) = value.get("doc_sets").and_then(Value::as_object) {
+            stats.doc_sets = doc_sets
+                .iter()
+                .filter_map(|(slug, count)| Some((slug.clone(), count.as_u64()?)))
+                .collect();
+        }
+        stats.searches = value
+            .get("searches")
+            .and_then(Value::as_u64)
+            .unwrap_or_default();
+        Ok(stats)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let entries: Vec<Value> = self.entries.values().map(EntryCount::to_json).collect();
+        write_versioned(
+            path,
+            json!({
+                "entries": entries,
+                "doc_sets": self.doc_sets,
+                "searches": self.searches,
+            }),
+        )
+    }
+
+    /// Count an entry as opened once more
+    pub fn record_open(&mut self, slug: &str, path: &str, title: &str) {
+        let entry = self
+            .entries
+            .entry((slug.to_owned(), path.to_owned()))
+            .or_insert_with(|| EntryCount {
+                slug: slug.to_owned(),
+                path: path.to_owned(),
+                title: String::new(),
+                count: 0,
+            });
+        entry.title = title.to_owned();
+        entry.count += 1;
+        *self.doc_sets.entry(slug.to_owned()).or_default() += 1;
+    }
+
+    pub fn record_search(&mut self) {
+        self.searches += 1;
+    }
+
+    /// The `max` most opened entries, most opened first
+    pub fn top_entries(&self, max: usize) -> Vec<&EntryCount> {
+        let mut entries: Vec<&EntryCount> = self.entries.values().collect();
+        entries.sort_by_key(|entry| Reverse(entry.count));
+        entries.truncate(max);
+        entries
+    }
+
+    /// The `max` doc sets whose entries were opened most, with how often, most opened first
+    pub fn top_doc_sets(&self, max: usize) -> Vec<(&str, u64)> {
+        let mut doc_sets: Vec<(&str, u64)> = self
+            .doc_sets
+            .iter()
+            .map(|(slug, &count)| (slug.as_str(), count))
+            .collect();
+        doc_sets.sort_by_key(|&(_, count)| Reverse(count));
+        doc_sets.truncate(max);
+        doc_sets
+    }
+
+    /// Every entry opened since the stats were last cleared
+    pub fn entries(&self) -> impl Iterator<Item = &EntryCount> {
+        self.entries.values()
+    }
+
+    /// Entries opened in all, counting every time
+    pub fn opens(&self) -> u64 {
+        self.doc_sets.values().sum()
+    }
+
+    pub fn searches(&self) -> u64 {
+        self.searches
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty() && self.searches == 0
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+}
@@ -0,0 +1,270 @@
+use directories::BaseDirs;
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+
+/// Locations of everything rtftui keeps on disk
+#[derive(Debug, Clone)]
+pub struct Storage {
+    root: PathBuf,
+    /// Where the recently viewed entries are kept, if not in `root`
+    recent: Option<PathBuf>,
+    /// Where past queries are kept, if not in `root`
+    search_history: Option<PathBuf>,
+    /// Whether downloaded doc sets are kept in `root` to be reinstalled from
+    keep_archives: bool,
+}
+
+impl Storage {
+    /// Storage rooted in the platform's local data directory
+    pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
+        let dirs = BaseDirs::new().ok_or("could not determine the home directory")?;
+        Ok(Self::at(dirs.data_local_dir().join("rtftui")))
+    }
+
+    /// Storage rooted at an arbitrary directory
+    pub fn at(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            recent: None,
+            search_history: None,
+            keep_archives: false,
+        }
+    }
+
+    /// Keep the history files at the given paths, e.g. in a dotfiles repository, rather than
+    /// in the data directory
+    pub fn with_history_files(
+        mut self,
+        recent: Option<PathBuf>,
+        search_history: Option<PathBuf>,
+    ) -> Self {
+        self.recent = recent;
+        self.search_history = search_history;
+        self
+    }
+
+    /// Keep the files of every downloaded doc set, so reinstalling the same release needs no
+    /// download
+    pub fn with_kept_archives(mut self, keep: bool) -> Self {
+        self.keep_archives = keep;
+        self
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Directory holding one subdirectory per installed doc set
+    pub fn docs_dir(&self) -> PathBuf {
+        self.root.join("docs")
+    }
+
+    /// Directory of a single doc set, containing its `index.json` and its `db.json`, which
+    /// downloaded doc sets keep compressed as `db.json.gz`
+    pub fn doc_set_dir(&self, slug: &str) -> PathBuf {
+        self.docs_dir().join(slug)
+    }
+
+    pub fn recent_path(&self) -> PathBuf {
+        self.recent
+            .clone()
+            .unwrap_or_else(|| self.root.join("recent.json"))
+    }
+
+    /// How often entries and doc sets were used, which never leaves this machine
+    pub fn stats_path(&self) -> PathBuf {
+        self.root.join("stats.json")
+    }
+
+    pub fn search_history_path(&self) -> PathBuf {
+        self.search_history
+            .clone()
+            .unwrap_or_else(|| self.root.join("searches.json"))
+    }
+
+    /// The scope last switched to with the scope keys
+    pub fn last_scope_path(&self) -> PathBuf {
+        self.root.join("scope.json")
+    }
+
+    /// What the abstracts and full-text indexes of the installed doc sets were built from
+    pub fn index_record_path(&self) -> PathBuf {
+        self.root.join("indexed.json")
+    }
+
+    /// Where downloaded doc sets are kept, if they are
+    pub fn archive_dir(&self) -> Option<PathBuf> {
+        self.keep_archives.then(|| self.root.join("archives"))
+    }
+
+    /// The devdocs registry as last fetched
+    pub fn registry_path(&self) -> PathBuf {
+        self.root.join("registry.json")
+    }
+}
+
+/// Write a file by writing a sibling temporary file and renaming it into place,
+/// so readers never observe a half-written file
+///
+/// A symlink is written through, replacing the file it points to rather than the link.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let resolved;
+    let path = if path.is_symlink() {
+        // A relative target is relative to the link's directory; joining keeps absolute ones
+        resolved = path
+            .parent()
+            .unwrap_or(Path::new(""))
+            .join(std::fs::read_link(path)?);
+        &resolved
+    } else {
+        path
+    };
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    std::fs::write(&tmp, contents)?;
+    std::fs::rename(&tmp, path)
+}
+
+/// Version of the layout of the files rtftui writes, stamped into each of them so a newer
+/// rtftui can upgrade what an older one wrote
+///
+/// Files from before formats were stamped count as format 1. Bump this whenever a file's
+/// contents change shape, adding a migration from the previous format to every file.
+pub const DATA_FORMAT: u64 = 2;
+
+/// Upgrades a file's data from one format to the next, or `None` if it can't
+pub type Migration = fn(Value) -> Option<Value>;
+
+/// Read a file written by [`write_versioned`], or by a version of rtftui before formats
+/// were stamped, upgrading its data to [`DATA_FORMAT`]
+///
+/// `migrations` upgrade the data from format 1 to 2, from 2 to 3 and so on. A file that
+/// can't be read as any format this version knows, e.g. one a newer version of rtftui
+/// wrote, is moved aside with a warning and read as missing, so it is neither misread nor
+/// overwritten. Missing files are `None`.
+pub fn read_versioned(
+    path: &Path,
+    migrations: &[Migration],
+) -> Result<Option<Value>, Box<dyn std::error::Error>> {
+    debug_assert_eq!(migrations.len() as u64, DATA_FORMAT - 1);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let Ok(value) = serde_json::from_slice::<Value>(&std::fs::read(path)?) else {
+        return set_aside(path, "it is not valid JSON");
+    };
+    let (format, mut data) = match value {
+        Value::Object(mut object)
+            if object.contains_key("format") && object.contains_key("data") =>
+        {
+            let format = object
+                .get("format")
+                .and_then(Value::as_u64)
+                .unwrap_or_default();
+            (format, object.remove("data").unwrap_or_default())
+        }
+        value => (1, value),
+    };
+    if format == 0 || format > DATA_FORMAT {
+        return set_aside(
+            path,
+            &format!("its format {format} is not one this version of rtftui knows"),
+        );
+    }
+    for migration in &migrations[format as usize - 1..] {
+        let Some(upgraded) = migration(data) else {
+            return set_aside(path, &format!("it can't be upgraded from format {format}"));
+        };
+        data = upgraded;
+    }
+    Ok(Some(data))
+}
+
+/// Move a file that can't be read out of the way, keeping it for a later version of rtftui
+/// or for recovering it by hand
+fn set_aside(path: &Path, reason: &str) -> Result<Option<Value>, Box<dyn std::error::Error>> {
+    let mut aside = path.as_os_str().to_owned();
+    aside.push(".incompatible");
+    std::fs::rename(path, &aside)?;
+    log::warn!(
+        "Starting over without {}, as {reason}; it was kept as {}",
+        path.display(),
+        Path::new(&aside).display()
+    );
+    Ok(None)
+}
+
+/// Write data stamped with the current [`DATA_FORMAT`], atomically
+pub fn write_versioned(path: &Path, data: Value) -> Result<(), Box<dyn std::error::Error>> {
+    let value = json!({ "format": DATA_FORMAT, "data": data });
+    write_atomic(path, &serde_json::to_vec_pretty(&value)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::{RecentEntry, RecentList};
+    use crate::testing::TempDir;
+
+    #[test]
+    fn a_format_1_recent_list_is_upgraded() {
+        let dir = TempDir::new("migrate");
+        let path = dir.join("recent.json");
+        let v1 = json!([
+            { "slug": "rust", "path": "std/index", "title": "std", "timestamp": 2 },
+            { "slug": "react", "path": "hooks", "title": "Hooks", "timestamp": 1 },
+        ]);
+        std::fs::write(&path, v1.to_string()).unwrap();
+
+        let list = RecentList::load(&path).unwrap();
+        assert_eq!(
+            list.entries()
+                .iter()
+                .map(|entry| entry.slug.as_str())
+                .collect::<Vec<_>>(),
+            ["rust", "react"]
+        );
+
+        list.save(&path).unwrap();
+        let saved: Value = serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+        assert_eq!(saved["format"], DATA_FORMAT);
+        assert_eq!(saved["data"], v1);
+        assert_eq!(RecentList::load(&path).unwrap().entries(), list.entries());
+    }
+
+    #[test]
+    fn a_newer_format_is_set_aside() {
+        let dir = TempDir::new("newer");
+        let path = dir.join("recent.json");
+        let newer = json!({ "format": DATA_FORMAT + 1, "data": { "entries": [] } });
+        std::fs::write(&path, newer.to_string()).unwrap();
+
+        let mut list = RecentList::load(&path).unwrap();
+        assert!(list.is_empty());
+        list.record(RecentEntry::now("rust", "std/index", "std"));
+        list.save(&path).unwrap();
+        let aside = std::fs::read(dir.join("recent.json.incompatible")).unwrap();
+        assert_eq!(serde_json::from_slice::<Value>(&aside).unwrap(), newer);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn a_symlinked_file_is_written_through_the_link() {
+        let dir = TempDir::new("symlink");
+        let target = dir.join("dotfiles").join("recent.json");
+        std::fs::create_dir_all(target.parent().unwrap()).unwrap();
+        let link = dir.join("recent.json");
+        std::os::unix::fs::symlink(Path::new("dotfiles").join("recent.json"), &link).unwrap();
+
+        let storage = Storage::at(&*dir).with_history_files(Some(link.clone()), None);
+        let mut list = RecentList::default();
+        list.record(RecentEntry::now("rust", "std/index", "std"));
+        list.save(&storage.recent_path()).unwrap();
+        assert!(link.is_symlink());
+        assert_eq!(RecentList::load(&target).unwrap().entries(), list.entries());
+    }
+}
@@ -0,0 +1,38 @@
+//! Helpers shared by the tests of the library and of the app, which includes this file too
+
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+
+/// An empty directory of a test's own, removed with everything in it when dropped
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    /// A directory named after the test, and the process, so tests running at the same time
+    /// never share one
+    pub fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("rtftui-test-{}-{name}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        Self(dir)
+    }
+}
+
+impl Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for TempDir {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
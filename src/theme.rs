@@ -0,0 +1,421 @@
+use crate::notification::Severity;
+use ratatui::{
+    buffer::Buffer,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+};
+
+/// Looks the app comes with, which the theme tables of the config adjust
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BuiltInTheme {
+    #[default]
+    Default,
+    /// Bright colors and no dim or gray text, with selections marked by more than color
+    HighContrast,
+}
+
+impl BuiltInTheme {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "default" => Some(Self::Default),
+            "high_contrast" => Some(Self::HighContrast),
+            _ => None,
+        }
+    }
+}
+
+/// Make a drawn frame high contrast: gray and dim text in the terminal's own foreground
+/// color, and reversed text, which marks selections, bold and underlined as well
+pub fn apply_high_contrast(buffer: &mut Buffer) {
+    for cell in &mut buffer.content {
+        if matches!(cell.fg, Color::DarkGray | Color::Gray) {
+            cell.fg = Color::Reset;
+        }
+        if matches!(cell.bg, Color::DarkGray | Color::Gray) {
+            cell.bg = Color::Reset;
+        }
+        cell.modifier.remove(Modifier::DIM);
+        if cell.modifier.contains(Modifier::REVERSED) {
+            cell.modifier.insert(Modifier::BOLD | Modifier::UNDERLINED);
+        }
+    }
+}
+
+/// How many colors the terminal can display
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ColorSupport {
+    /// Colors are disabled, e.g. through `NO_COLOR`
+    None,
+    /// The 16 basic ANSI colors
+    Basic,
+    Ansi256,
+    TrueColor,
+}
+
+impl ColorSupport {
+    /// Detect what the terminal supports from the environment
+    pub fn detect() -> Self {
+        Self::from_env(|name| std::env::var(name).ok())
+    }
+
+    /// Detect color support from environment variables looked up through `var`
+    pub fn from_env(var: impl Fn(&str) -> Option<String>) -> Self {
+        // https://no-color.org: any non-empty value disables color
+        if var("NO_COLOR").is_some_and(|value| !value.is_empty()) {
+            return Self::None;
+        }
+        if var("COLORTERM").is_some_and(|value| value == "truecolor" || value == "24bit") {
+            return Self::TrueColor;
+        }
+        if var("TERM").is_some_and(|term| term.contains("256color")) {
+            return Self::Ansi256;
+        }
+        Self::Basic
+    }
+}
+
+/// Whether the terminal is likely to blink text rather than ignore blinking or show it some
+/// other way, going by environment variables looked up through `var`
+///
+/// The Linux console shows blinking text with a bright background instead, and some
+/// terminals, such as VS Code's, never blink.
+pub fn blink_supported(var: impl Fn(&str) -> Option<String>) -> bool {
+    let term = var("TERM").unwrap_or_default();
+    if term.is_empty() || term == "dumb" || term == "linux" {
+        return false;
+    }
+    var("TERM_PROGRAM").is_none_or(|program| program != "vscode")
+}
+
+/// Color each character of `text` along a horizontal gradient from `start` to `end`
+///
+/// `width` is the width the gradient is spread over, so lines of different lengths in a block
+/// of text share the same colors per column.
+pub fn gradient_line(text: &str, width: usize, start: Color, end: Color) -> Line<'static> {
+    let (Color::Rgb(r1, g1, b1), Color::Rgb(r2, g2, b2)) = (start, end) else {
+        return Line::styled(text.to_owned(), Style::default().fg(start));
+    };
+    let steps = width.saturating_sub(1).max(1) as f32;
+    let mix = |a: u8, b: u8, t: f32| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+
+    let spans: Vec<Span> = text
+        .chars()
+        .enumerate()
+        .map(|(x, c)| {
+            let t = (x as f32 / steps).min(1.0);
+            let color = Color::Rgb(mix(r1, r2, t), mix(g1, g2, t), mix(b1, b2, t));
+            Span::styled(c.to_string(), Style::default().fg(color))
+        })
+        .collect();
+    Line::from(spans)
+}
+
+/// Kinds of entry recognised from their type, with a Nerd Font glyph and an ASCII fallback
+///
+/// Types are matched by containing the kind, in this order, so `Methods` is a method.
+const TYPE_GLYPHS: &[(&str, &str, &str)] = &[
+    ("method", "\u{ea8c}", "m"),
+    ("function", "\u{ea8c}", "f"),
+    ("macro", "\u{eb66}", "!"),
+    ("trait", "\u{eb61}", "T"),
+    ("interface", "\u{eb61}", "I"),
+    ("class", "\u{eb5b}", "C"),
+    ("struct", "\u{ea91}", "S"),
+    ("enum", "\u{ea95}", "E"),
+    ("module", "\u{ea8b}", "M"),
+    ("namespace", "\u{ea8b}", "N"),
+    ("constant", "\u{eb5d}", "K"),
+    ("propert", "\u{eb65}", "p"),
+    ("attribute", "\u{eb65}", "@"),
+    ("event", "\u{ea86}", "e"),
+    ("keyword", "\u{eb62}", "k"),
+    ("variable", "\u{ea88}", "v"),
+    ("type", "\u{ea92}", "t"),
+];
+
+/// Glyphs shown before search results to tell their entry type apart at a glance
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TypeGlyphs {
+    /// When disabled, results only show their type as a text tag
+    pub enabled: bool,
+    /// Use the Nerd Font glyphs rather than the ASCII fallbacks
+    pub use_nerd_font: bool,
+    /// Glyphs set by the theme, by kind, taking precedence over the built-in ones
+    pub custom: Vec<(String, String)>,
+}
+
+impl TypeGlyphs {
+    /// The glyph for an entry of type `type_name`, if glyphs are enabled
+    pub fn glyph(&self, type_name: &str) -> Option<&str> {
+        if !self.enabled {
+            return None;
+        }
+        let type_name = type_name.to_lowercase();
+        let custom = self
+            .custom
+            .iter()
+            .find(|(kind, _)| type_name.contains(kind.as_str()))
+            .map(|(_, glyph)| glyph.as_str());
+        let built_in = || {
+            TYPE_GLYPHS
+                .iter()
+                .find(|(kind, _, _)| type_name.contains(kind))
+                .map(|&(_, nerd, ascii)| if self.use_nerd_font { nerd } else { ascii })
+        };
+        Some(
+            custom
+                .or_else(built_in)
+                .unwrap_or(if self.use_nerd_font { "\u{eb63}" } else { "-" }),
+        )
+    }
+}
+
+/// Colors of notifications by severity, from the `[notifications]` table
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NotificationTheme {
+    pub info: Color,
+    pub success: Color,
+    pub warning: Color,
+    pub error: Color,
+}
+
+impl NotificationTheme {
+    pub fn color(&self, severity: Severity) -> Color {
+        match severity {
+            Severity::Info => self.info,
+            Severity::Success => self.success,
+            Severity::Warning => self.warning,
+            Severity::Error => self.error,
+        }
+    }
+}
+
+impl Default for NotificationTheme {
+    fn default() -> Self {
+        Self {
+            info: Color::Cyan,
+            success: Color::Green,
+            warning: Color::Yellow,
+            error: Color::Red,
+        }
+    }
+}
+
+impl NotificationTheme {
+    pub fn high_contrast() -> Self {
+        Self {
+            info: Color::LightCyan,
+            success: Color::LightGreen,
+            warning: Color::LightYellow,
+            error: Color::LightRed,
+        }
+    }
+}
+
+/// Colors of the lines that differ between two versions of an entry, from the `[diff]` table
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiffTheme {
+    pub added: Color,
+    pub removed: Color,
+}
+
+impl Default for DiffTheme {
+    fn default() -> Self {
+        Self {
+            added: Color::Green,
+            removed: Color::Red,
+        }
+    }
+}
+
+impl DiffTheme {
+    pub fn high_contrast() -> Self {
+        Self {
+            added: Color::LightGreen,
+            removed: Color::LightRed,
+        }
+    }
+}
+
+/// Colors of the callout boxes devdocs marks notes and warnings with, from the `[callouts]`
+/// table
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CalloutTheme {
+    /// Render callouts as boxes at all, rather than as plain text
+    pub enabled: bool,
+    pub note: Color,
+    pub tip: Color,
+    pub warning: Color,
+    pub deprecated: Color,
+}
+
+impl Default for CalloutTheme {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            note: Color::Blue,
+            tip: Color::Green,
+            warning: Color::Yellow,
+            deprecated: Color::Red,
+        }
+    }
+}
+
+impl CalloutTheme {
+    pub fn high_contrast() -> Self {
+        Self {
+            enabled: true,
+            note: Color::LightBlue,
+            tip: Color::LightGreen,
+            warning: Color::LightYellow,
+            deprecated: Color::LightRed,
+        }
+    }
+}
+
+/// How the selected row of a list and the focused panel stand out, from the `[selection]`
+/// table
+///
+/// The selected row is never marked by color alone: without a background of its own it is
+/// reversed, and with one it is bold or has the indicator bar too.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SelectionTheme {
+    /// Background of the selected row, or `None` to reverse its colors
+    pub background: Option<Color>,
+    pub bold: bool,
+    /// Mark the selected row with a bar at its left edge
+    pub indicator: bool,
+    /// Border color of the focused panel, which is drawn thick as well
+    pub border: Color,
+}
+
+impl Default for SelectionTheme {
+    fn default() -> Self {
+        Self {
+            background: None,
+            bold: false,
+            indicator: false,
+            border: Color::Cyan,
+        }
+    }
+}
+
+impl SelectionTheme {
+    pub fn high_contrast() -> Self {
+        Self {
+            background: None,
+            bold: true,
+            indicator: true,
+            border: Color::LightCyan,
+        }
+    }
+
+    /// The style of the selected row, reversed rather than colored where colors are disabled
+    pub fn style(&self, colors: ColorSupport) -> Style {
+        let style = match self.background {
+            Some(background) if colors != ColorSupport::None => Style::default().bg(background),
+            _ => Style::default().add_modifier(Modifier::REVERSED),
+        };
+        if self.bold {
+            style.add_modifier(Modifier::BOLD)
+        } else {
+            style
+        }
+    }
+
+    /// What the selected row starts with: the indicator bar, or `>` where colors are disabled
+    /// and the bar might not show
+    pub fn symbol(&self, colors: ColorSupport) -> Option<&'static str> {
+        let colored_only = self.background.is_some() && !self.bold && colors != ColorSupport::None;
+        match colors {
+            _ if !self.indicator && !colored_only => None,
+            ColorSupport::None => Some("> "),
+            _ => Some("▎ "),
+        }
+    }
+
+    /// The border style of the focused panel
+    pub fn border_style(&self, colors: ColorSupport) -> Style {
+        match colors {
+            ColorSupport::None => Style::default().add_modifier(Modifier::BOLD),
+            _ => Style::default().fg(self.border),
+        }
+    }
+}
+
+/// Colors doc sets are told apart by, picked from their name: of medium brightness, so they
+/// read on dark and light backgrounds alike
+const DOC_SET_PALETTE: &[Color] = &[
+    Color::Indexed(38),
+    Color::Indexed(71),
+    Color::Indexed(136),
+    Color::Indexed(170),
+    Color::Indexed(68),
+    Color::Indexed(173),
+    Color::Indexed(72),
+    Color::Indexed(140),
+    Color::Indexed(179),
+    Color::Indexed(74),
+    Color::Indexed(107),
+    Color::Indexed(174),
+];
+
+/// [`DOC_SET_PALETTE`] for terminals with only the basic colors, leaving out red, which
+/// marks errors, and the grays
+const BASIC_DOC_SET_PALETTE: &[Color] = &[
+    Color::Cyan,
+    Color::Green,
+    Color::Yellow,
+    Color::Magenta,
+    Color::Blue,
+    Color::LightCyan,
+];
+
+/// The color each doc set's name is shown in, from the `[doc_set_colors]` table
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DocSetColors {
+    /// Colors set by slug or doc family, taking precedence over the picked ones
+    pub custom: Vec<(String, Color)>,
+}
+
+impl DocSetColors {
+    /// The color of a doc set, or `None` where colors are disabled
+    ///
+    /// A color set for the slug wins over one set for its family. Otherwise the color is
+    /// picked by hashing the family, so every version of a doc set shares it and it stays
+    /// the same from one run to the next.
+    pub fn color(&self, slug: &str, colors: ColorSupport) -> Option<Color> {
+        if colors == ColorSupport::None {
+            return None;
+        }
+        let family = crate::docset_manager::doc_family(slug);
+        let custom = |name: &str| {
+            self.custom
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|&(_, color)| color)
+        };
+        if let Some(color) = custom(slug).or_else(|| custom(family)) {
+            return Some(color);
+        }
+        // FNV-1a, which unlike the standard library's hasher is the same on every build
+        let hash = family
+            .bytes()
+            .fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+                (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+            });
+        let palette = match colors {
+            ColorSupport::Basic => BASIC_DOC_SET_PALETTE,
+            _ => DOC_SET_PALETTE,
+        };
+        Some(palette[(hash % palette.len() as u64) as usize])
+    }
+
+    /// The style a doc set's name is shown in
+    pub fn style(&self, slug: &str, colors: ColorSupport) -> Style {
+        match self.color(slug, colors) {
+            Some(color) => Style::default().fg(color),
+            None => Style::default(),
+        }
+    }
+}
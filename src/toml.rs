@@ -0,0 +1,565 @@
+//! A reader for the subset of TOML used by rtftui's configuration files
+//!
+//! Supported: comments, `[table]` headers, bare/quoted/dotted keys, basic and literal strings,
+//! integers, floats, booleans, arrays and inline tables. Arrays of tables are not supported.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+pub type Table = BTreeMap<String, Value>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    String(String),
+    Integer(i64),
+    Float(f64),
+    Boolean(bool),
+    Array(Vec<Value>),
+    Table(Table),
+}
+
+impl Value {
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_integer(&self) -> Option<i64> {
+        match self {
+            Value::Integer(i) => Some(*i),
+            _ => None,
+        }
+    }
+
+    pub fn as_float(&self) -> Option<f64> {
+        match self {
+            Value::Float(f) => Some(*f),
+            Value::Integer(i) => Some(*i as f64),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Boolean(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match self {
+            Value::Array(a) => Some(a),
+            _ => None,
+        }
+    }
+
+    pub fn as_table(&self) -> Option<&Table> {
+        match self {
+            Value::Table(t) => Some(t),
+            _ => None,
+        }
+    }
+
+    /// A string, or an array of strings, as a list of strings
+    pub fn as_str_list(&self) -> Option<Vec<String>> {
+        match self {
+            Value::String(s) => Some(vec![s.clone()]),
+            Value::Array(a) => a.iter().map(|v| v.as_str().map(str::to_owned)).collect(),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Parse a TOML document into its root table
+pub fn parse(source: &str) -> Result<Table, ParseError> {
+    let mut parser = Parser {
+        chars: source.chars().collect(),
+        pos: 0,
+        line: 1,
+    };
+    parser.document()
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+}
+
+impl Parser {
+    fn error<T>(&self, message: impl Into<String>) -> Result<T, ParseError> {
+        Err(ParseError {
+            line: self.line,
+            message: message.into(),
+        })
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        if c == '\n' {
+            self.line += 1;
+        }
+        Some(c)
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        if self.peek() == Some(expected) {
+            self.bump();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), ParseError> {
+        if self.eat(expected) {
+            Ok(())
+        } else {
+            self.error(format!("expected '{expected}'"))
+        }
+    }
+
+    /// Skip spaces and tabs on the current line
+    fn skip_inline_space(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t')) {
+            self.bump();
+        }
+    }
+
+    fn skip_comment(&mut self) {
+        if self.peek() == Some('#') {
+            while !matches!(self.peek(), None | Some('\n')) {
+                self.bump();
+            }
+        }
+    }
+
+    /// Skip whitespace, newlines and comments, as allowed between array elements
+    fn skip_blank(&mut self) {
+        loop {
+            match self.peek() {
+                Some(' ' | '\t' | '\r' | '\n') => {
+                    self.bump();
+                }
+                Some('#') => self.skip_comment(),
+                _ => return,
+            }
+        }
+    }
+
+    /// Consume the rest of a line, which may only contain a comment
+    fn end_of_line(&mut self) -> Result<(), ParseError> {
+        self.skip_inline_space();
+        self.skip_comment();
+        match self.peek() {
+            None => Ok(()),
+            Some('\n') => {
+                self.bump();
+                Ok(())
+            }
+            Some('\r') => {
+                self.bump();
+                self.expect('\n')
+            }
+            Some(c) => self.error(format!("unexpected '{c}' after value")),
+        }
+    }
+
+    fn document(&mut self) -> Result<Table, ParseError> {
+        let mut root = Table::new();
+        let mut current: Vec<String> = Vec::new();
+
+        loop {
+            self.skip_blank();
+            match self.peek() {
+                None => return Ok(root),
+                Some('[') => {
+                    self.bump();
+                    if self.peek() == Some('[') {
+                        return self.error("arrays of tables are not supported");
+                    }
+                    self.skip_inline_space();
+                    current = self.key()?;
+                    self.skip_inline_space();
+                    self.expect(']')?;
+                    table_at(&mut root, &current).map_err(|message| ParseError {
+                        line: self.line,
+                        message,
+                    })?;
+                    self.end_of_line()?;
+                }
+                Some(_) => {
+                    let line = self.line;
+                    let (key, value) = self.key_value()?;
+                    let table = table_at(&mut root, &current)
+                        .map_err(|message| ParseError { line, message })?;
+                    insert(table, &key, value).map_err(|message| ParseError { line, message })?;
+                    self.end_of_line()?;
+                }
+            }
+        }
+    }
+
+    fn key_value(&mut self) -> Result<(Vec<String>, Value), ParseError> {
+        let key = self.key()?;
+        self.skip_inline_space();
+        self.expect('=')?;
+        self.skip_inline_space();
+        let value = self.value()?;
+        Ok((key, value))
+    }
+
+    /// A possibly dotted key, split into its parts
+    fn key(&mut self) -> Result<Vec<String>, ParseError> {
+        let mut parts = vec![self.simple_key()?];
+        loop {
+            self.skip_inline_space();
+            if !self.eat('.') {
+                return Ok(parts);
+            }
+            self.skip_inline_space();
+            parts.push(self.simple_key()?);
+        }
+    }
+
+    fn simple_key(&mut self) -> Result<String, ParseError> {
+        match self.peek() {
+            Some('"') => self.basic_string(),
+            Some('\'') => self.literal_string(),
+            _ => {
+                let mut key = String::new();
+                while let Some(c) = self
+                    .peek()
+                    .filter(|c| c.is_ascii_alphanumeric() || *c == '_' || *c == '-')
+                {
+                    key.push(c);
+                    self.bump();
+                }
+                if key.is_empty() {
+                    return self.error("expected a key");
+                }
+                Ok(key)
+            }
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, ParseError> {
+        match self.peek() {
+            Some('"') => self.basic_string().map(Value::String),
+            Some('\'') => self.literal_string().map(Value::String),
+            Some('[') => self.array(),
+            Some('{') => self.inline_table(),
+            Some('t' | 'f') => {
+                let word = self.word();
+                match word.as_str() {
+                    "true" => Ok(Value::Boolean(true)),
+                    "false" => Ok(Value::Boolean(false)),
+                    _ => self.error(format!("invalid value '{word}'")),
+                }
+            }
+            Some(c) if c.is_ascii_digit() || c == '-' || c == '+' => self.number(),
+            Some(c) => self.error(format!("unexpected '{c}'")),
+            None => self.error("expected a value"),
+        }
+    }
+
+    fn word(&mut self) -> String {
+        let mut word = String::new();
+        while let Some(c) = self
+            .peek()
+            .filter(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '+' | '.'))
+        {
+            word.push(c);
+            self.bump();
+        }
+        word
+    }
+
+    fn number(&mut self) -> Result<Value, ParseError> {
+        let word = self.word().replace('_', "");
+        if let Ok(i) = word.parse::<i64>() {
+            return Ok(Value::Integer(i));
+        }
+        match word.parse::<f64>() {
+            Ok(f) => Ok(Value::Float(f)),
+            Err(_) => self.error(format!("invalid number '{word}'")),
+        }
+    }
+
+    fn basic_string(&mut self) -> Result<String, ParseError> {
+        self.expect('"')?;
+        let mut s = String::new();
+        loop {
+            match self.bump() {
+                None | Some('\n') => return self.error("unterminated string"),
+                Some('"') => return Ok(s),
+                Some('\\') => {
+                    let escaped = match self.bump() {
+                        Some('n') => '\n',
+                        Some('t') => '\t',
+                        Some('r') => '\r',
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('u') => self.unicode_escape(4)?,
+                        Some('U') => self.unicode_escape(8)?,
+                        _ => return self.error("invalid escape sequence"),
+                    };
+                    s.push(escaped);
+                }
+                Some(c) => s.push(c),
+            }
+        }
+    }
+
+    fn unicode_escape(&mut self, digits: usize) -> Result<char, ParseError> {
+        let hex: String = (0..digits).filter_map(|_| self.bump()).collect();
+        u32::from_str_radix(&hex, 16)
+            .ok()
+            .and_then(char::from_u32)
+            .map_or_else(|| self.error("invalid unicode escape"), Ok)
+    }
+
+    fn literal_string(&mut self) -> Result<String, ParseError> {
+        self.expect('\'')?;
+        let mut s = String::new();
+        loop {
+            match self.bump() {
+                None | Some('\n') => return self.error("unterminated string"),
+                Some('\'') => return Ok(s),
+                Some(c) => s.push(c),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Value, ParseError> {
+        self.expect('[')?;
+        let mut items = Vec::new();
+        loop {
+            self.skip_blank();
+            if self.eat(']') {
+                return Ok(Value::Array(items));
+            }
+            items.push(self.value()?);
+            self.skip_blank();
+            if !self.eat(',') {
+                self.skip_blank();
+                self.expect(']')?;
+                return Ok(Value::Array(items));
+            }
+        }
+    }
+
+    fn inline_table(&mut self) -> Result<Value, ParseError> {
+        self.expect('{')?;
+        let mut table = Table::new();
+        self.skip_inline_space();
+        if self.eat('}') {
+            return Ok(Value::Table(table));
+        }
+        loop {
+            self.skip_inline_space();
+            let (key, value) = self.key_value()?;
+            insert(&mut table, &key, value).map_err(|message| ParseError {
+                line: self.line,
+                message,
+            })?;
+            self.skip_inline_space();
+            if self.eat('}') {
+                return Ok(Value::Table(table));
+            }
+            self.expect(',')?;
+        }
+    }
+}
+
+/// Set a top-level key in a TOML document, keeping the rest of it (including comments) intact
+///
+/// `literal` must already be a valid TOML value, such as `"name"` or `true`. An existing
+/// assignment to the key is replaced, every line of it where its value spans several, as
+/// arrays can, otherwise the key is added before the first table.
+pub fn set_top_level(source: &str, key: &str, literal: &str) -> String {
+    let assignment = format!("{key} = {literal}");
+    let mut lines: Vec<String> = source.lines().map(str::to_owned).collect();
+
+    let mut first_table = lines.len();
+    let mut existing = None;
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i].trim_start();
+        if line.starts_with('[') {
+            first_table = i;
+            break;
+        }
+        let end = statement_end(&lines, i);
+        let assigns_key = line
+            .strip_prefix(key)
+            .is_some_and(|rest| rest.trim_start().starts_with('='));
+        if assigns_key && existing.is_none() {
+            existing = Some(i..=end);
+        }
+        i = end + 1;
+    }
+    match existing {
+        Some(span) => {
+            lines.splice(span, [assignment]);
+        }
+        None => lines.insert(first_table, assignment),
+    }
+
+    let mut updated = lines.join("\n");
+    updated.push('\n');
+    updated
+}
+
+/// The last line of the statement starting at line `start`: the line its brackets and
+/// braces are all closed on, outside of strings and comments
+fn statement_end(lines: &[String], start: usize) -> usize {
+    let mut depth = 0_i32;
+    for (i, line) in lines.iter().enumerate().skip(start) {
+        // Strings can't span lines in the subset of TOML supported
+        let mut string = None;
+        let mut escaped = false;
+        for c in line.chars() {
+            match string {
+                Some('"') if escaped => escaped = false,
+                Some('"') if c == '\\' => escaped = true,
+                Some(quote) if c == quote => string = None,
+                Some(_) => {}
+                None => match c {
+                    '"' | '\'' => string = Some(c),
+                    '#' => break,
+                    '[' | '{' => depth += 1,
+                    ']' | '}' => depth -= 1,
+                    _ => {}
+                },
+            }
+        }
+        if depth <= 0 {
+            return i;
+        }
+    }
+    lines.len() - 1
+}
+
+/// A string as a TOML basic string literal, e.g. for `set_top_level`
+pub fn quote(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04X}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// The table at a dotted path, creating intermediate tables as needed
+fn table_at<'a>(root: &'a mut Table, path: &[String]) -> Result<&'a mut Table, String> {
+    let mut table = root;
+    for part in path {
+        let entry = table
+            .entry(part.clone())
+            .or_insert_with(|| Value::Table(Table::new()));
+        table = match entry {
+            Value::Table(t) => t,
+            _ => return Err(format!("'{part}' is not a table")),
+        };
+    }
+    Ok(table)
+}
+
+fn insert(table: &mut Table, key: &[String], value: Value) -> Result<(), String> {
+    let (last, parents) = key.split_last().ok_or("empty key")?;
+    let table = table_at(table, parents)?;
+    if table.contains_key(last) {
+        return Err(format!("duplicate key '{last}'"));
+    }
+    table.insert(last.clone(), value);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_missing_key_is_added_before_the_first_table() {
+        let source = "# settings\nwrap = true\n\n[selection]\nbold = true\n";
+        let updated = set_top_level(source, "theme", "\"high_contrast\"");
+        assert_eq!(
+            updated,
+            "# settings\nwrap = true\n\ntheme = \"high_contrast\"\n[selection]\nbold = true\n"
+        );
+        assert_eq!(
+            set_top_level("", "wrap", "false"),
+            "wrap = false\n",
+            "an empty document gets the key alone"
+        );
+    }
+
+    #[test]
+    fn a_single_line_value_is_replaced_in_place() {
+        let source = "wrap = true # the default\nwrap_width = 80\n[banner]\nwrap = 1\n";
+        let updated = set_top_level(source, "wrap", "false");
+        assert_eq!(
+            updated,
+            "wrap = false\nwrap_width = 80\n[banner]\nwrap = 1\n"
+        );
+        let table = parse(&updated).unwrap();
+        assert_eq!(table["wrap"], Value::Boolean(false));
+        assert_eq!(table["wrap_width"], Value::Integer(80));
+    }
+
+    #[test]
+    fn a_multi_line_value_is_replaced_whole() {
+        let source = "\
+default_scope = [
+    \"rust\", # the one I use most
+    \"python]\",
+    [\"nested\"],
+]
+wrap = true
+
+[selection]
+bold = true
+";
+        let updated = set_top_level(source, "default_scope", "[\"go\"]");
+        assert_eq!(
+            updated,
+            "default_scope = [\"go\"]\nwrap = true\n\n[selection]\nbold = true\n"
+        );
+        let table = parse(&updated).unwrap();
+        assert_eq!(
+            table["default_scope"],
+            Value::Array(vec![Value::String("go".to_owned())])
+        );
+        assert_eq!(table["wrap"], Value::Boolean(true));
+    }
+}